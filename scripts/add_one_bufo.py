@@ -17,6 +17,7 @@ import base64
 import hashlib
 import os
 import sys
+import time
 from io import BytesIO
 from pathlib import Path
 
@@ -27,6 +28,23 @@ from dotenv import load_dotenv
 load_dotenv(Path(__file__).parent.parent / ".env")
 
 
+def full_text_search_config() -> dict | bool:
+    """BM25 index options for the name fields.
+
+    turbopuffer analyzes queries with the attribute's own tokenizer, so setting
+    BM25_STEMMING=true here (and re-indexing) is what makes "jump" match "jumping".
+    """
+    if os.getenv("BM25_STEMMING", "false").lower() == "true":
+        return {"stemming": True}
+    return True
+
+
+def output_dimension_option() -> dict:
+    """Truncated vector size, matching the server's VOYAGE_OUTPUT_DIMENSION."""
+    dimension = os.getenv("VOYAGE_OUTPUT_DIMENSION", "").strip()
+    return {"output_dimension": int(dimension)} if dimension else {}
+
+
 async def embed_image(client: httpx.AsyncClient, image_path: Path, api_key: str) -> list[float] | None:
     """Generate embedding for an image using Voyage AI"""
     try:
@@ -68,6 +86,7 @@ async def embed_image(client: httpx.AsyncClient, image_path: Path, api_key: str)
                 "inputs": [{"content": content}],
                 "model": "voyage-multimodal-3",
                 "input_type": "document",
+                **output_dimension_option(),
             },
             timeout=60.0,
         )
@@ -100,10 +119,12 @@ async def upload_to_turbopuffer(filename: str, embedding: list[float], api_key:
                     "url": [url],
                     "name": [name],
                     "filename": [filename],
+                    # unix seconds; searches with `since` filter on it
+                    "added_at": [int(time.time())],
                 },
                 "schema": {
-                    "name": {"type": "string", "full_text_search": True},
-                    "filename": {"type": "string", "full_text_search": True},
+                    "name": {"type": "string", "full_text_search": full_text_search_config()},
+                    "filename": {"type": "string", "full_text_search": full_text_search_config()},
                 },
             },
             timeout=30.0,
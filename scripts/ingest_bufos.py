@@ -35,6 +35,23 @@ console = Console()
 load_dotenv(Path(__file__).parent.parent / ".env")
 
 
+def full_text_search_config() -> dict | bool:
+    """BM25 index options for the name fields.
+
+    turbopuffer analyzes queries with the attribute's own tokenizer, so setting
+    BM25_STEMMING=true here (and re-indexing) is what makes "jump" match "jumping".
+    """
+    if os.getenv("BM25_STEMMING", "false").lower() == "true":
+        return {"stemming": True}
+    return True
+
+
+def output_dimension_option() -> dict:
+    """Truncated vector size, matching the server's VOYAGE_OUTPUT_DIMENSION."""
+    dimension = os.getenv("VOYAGE_OUTPUT_DIMENSION", "").strip()
+    return {"output_dimension": int(dimension)} if dimension else {}
+
+
 async def fetch_bufo_urls() -> set[str]:
     """Fetch all unique bufo URLs from bufo.zone"""
     console.print("[cyan]fetching bufo list from bufo.zone...[/cyan]")
@@ -172,6 +189,7 @@ async def embed_image(client: httpx.AsyncClient, image_path: Path, api_key: str,
                     "inputs": [{"content": content}],
                     "model": "voyage-multimodal-3",
                     "input_type": "document",
+                    **output_dimension_option(),
                 },
                 timeout=60.0,
             )
@@ -276,11 +294,11 @@ async def upload_to_turbopuffer(
                 "schema": {
                     "name": {
                         "type": "string",
-                        "full_text_search": True,
+                        "full_text_search": full_text_search_config(),
                     },
                     "filename": {
                         "type": "string",
-                        "full_text_search": True,
+                        "full_text_search": full_text_search_config(),
                     },
                 },
             },
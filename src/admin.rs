@@ -0,0 +1,333 @@
+//! operator endpoints behind `ADMIN_TOKEN`
+//!
+//! admin endpoints only exist when `ADMIN_TOKEN` is set (they're a 404 otherwise) and
+//! require it as `Authorization: Bearer <token>`.
+
+use crate::breaker::BreakerStats;
+use crate::cache::CacheStats;
+use crate::config::Config;
+use crate::filter::ContentFilter;
+use crate::providers::{Embedder, VectorStore};
+use crate::reload::CurrentConfig;
+use crate::scoring::FusionConfig;
+use crate::search::{build_embedder, build_store, default_top_k};
+use crate::state::AppState;
+use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
+use serde::Serialize;
+
+/// whether an `Authorization` header value carries the admin token
+///
+/// compares in constant time so the token can't be guessed byte by byte.
+fn is_authorized(authorization: Option<&str>, admin_token: &str) -> bool {
+    let Some(presented) = authorization.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+
+    presented.len() == admin_token.len()
+        && presented
+            .bytes()
+            .zip(admin_token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// `Ok` for an authorized admin request, otherwise the response to send instead
+pub fn require_admin(req: &HttpRequest, config: &Config) -> Result<(), HttpResponse> {
+    let Some(admin_token) = config.admin_token.as_deref() else {
+        return Err(HttpResponse::NotFound().finish());
+    };
+
+    let authorization = req
+        .headers()
+        .get("authorization")
+        .and_then(|h| h.to_str().ok());
+    if is_authorized(authorization, admin_token) {
+        Ok(())
+    } else {
+        Err(HttpResponse::Unauthorized()
+            .insert_header(("www-authenticate", "Bearer"))
+            .body("missing or invalid admin token"))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FusionDefaults {
+    pub alpha: f32,
+    pub top_k: usize,
+    pub min_score: f32,
+    pub keyword_normalization: String,
+    pub similarity_curve: String,
+    pub keyword_fields: String,
+    pub position_boost: f32,
+    pub coverage_weight: f32,
+    pub exact_match_boost: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingCacheReport {
+    #[serde(flatten)]
+    pub stats: CacheStats,
+    /// hits / (hits + misses); 0 before the first lookup
+    pub hit_rate: f64,
+}
+
+impl From<CacheStats> for EmbeddingCacheReport {
+    fn from(stats: CacheStats) -> Self {
+        let lookups = stats.hits + stats.misses;
+        Self {
+            stats,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                stats.hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    pub namespace: String,
+    pub search_namespaces: Vec<String>,
+    pub embedding_model: &'static str,
+    /// truncated voyage dimension; absent means the model's full size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dimension: Option<usize>,
+    /// openai model fused in when the ensemble is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ensemble_model: Option<String>,
+    pub fusion: FusionDefaults,
+    /// turbopuffer's approximate row count; absent when it couldn't be fetched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approx_row_count: Option<u64>,
+    pub uptime_secs: u64,
+    pub embedding_cache: EmbeddingCacheReport,
+    /// the primary embedder's circuit breaker
+    pub circuit_breaker: BreakerStats,
+}
+
+/// GET /api/stats handler: what this deployment is pointed at
+pub async fn stats(
+    req: HttpRequest,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if let Err(response) = require_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    let store = build_store(&config, &config.turbopuffer_namespace);
+    Ok(HttpResponse::Ok().json(stats_report(&config, &state, &store).await))
+}
+
+/// the stats for `config`, with the row count `store` reports
+async fn stats_report<V: VectorStore>(
+    config: &Config,
+    state: &AppState,
+    store: &V,
+) -> StatsResponse {
+    let approx_row_count = match store.approx_row_count().await {
+        Ok(count) => count,
+        Err(e) => {
+            let error = e.to_string();
+            logfire::warn!(
+                "failed to fetch namespace size",
+                namespace = &config.turbopuffer_namespace,
+                error = &error
+            );
+            None
+        }
+    };
+
+    let ensemble_model = config
+        .openai_namespace
+        .as_ref()
+        .and(config.openai_api_key.as_ref())
+        .map(|_| config.openai_embedding_model.clone());
+
+    StatsResponse {
+        namespace: config.turbopuffer_namespace.clone(),
+        search_namespaces: config.search_namespaces.clone(),
+        embedding_model: build_embedder(config, state).name(),
+        output_dimension: config.voyage_output_dimension,
+        ensemble_model,
+        fusion: FusionDefaults {
+            alpha: config.default_alpha,
+            top_k: default_top_k(),
+            min_score: FusionConfig::default().min_score,
+            keyword_normalization: config.keyword_normalization.to_string(),
+            similarity_curve: config.similarity_curve.to_string(),
+            keyword_fields: config.keyword_fields.to_string(),
+            position_boost: config.bm25_position_boost,
+            coverage_weight: config.bm25_coverage_weight,
+            exact_match_boost: config.exact_match_boost,
+        },
+        approx_row_count,
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        embedding_cache: state.embedding_cache.stats().into(),
+        circuit_breaker: state.embedding_breaker.stats(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FiltersResponse {
+    /// whether searches that don't set `family_friendly` get the blocklist
+    pub default_family_friendly: bool,
+    /// names hidden in family-friendly mode (substring matches)
+    pub blocklist: Vec<&'static str>,
+}
+
+/// GET /api/filters handler: what the content filter hides by default
+///
+/// exclude/include patterns only come from requests, so there are none to list here.
+pub async fn filters(req: HttpRequest, config: CurrentConfig) -> ActixResult<HttpResponse> {
+    if let Err(response) = require_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    let filter = ContentFilter::new(config.default_family_friendly, None, None);
+    Ok(HttpResponse::Ok().json(FiltersResponse {
+        default_family_friendly: config.default_family_friendly,
+        blocklist: filter.blocklist().to_vec(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{QueryOptions, SearchResult, VectorSearchError};
+    use actix_web::test::{self};
+    use actix_web::App;
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(!is_authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!is_authorized(Some("Bearer s3creT"), "s3cret"));
+        assert!(!is_authorized(Some("s3cret"), "s3cret"));
+        assert!(!is_authorized(None, "s3cret"));
+    }
+
+    #[actix_web::test]
+    async fn test_filters_lists_the_blocklist() {
+        let mut config = Config::for_tests();
+        config.admin_token = Some("s3cret".to_string());
+        let state = AppState::new(&config).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .route("/filters", web::get().to(filters)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/filters").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/filters")
+            .insert_header(("authorization", "Bearer s3cret"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let blocklist = body["blocklist"].as_array().unwrap();
+        assert!(blocklist.contains(&serde_json::json!("bufo-juicy")));
+        assert!(blocklist.contains(&serde_json::json!("tsa-bufo-gropes-you")));
+        assert_eq!(
+            body["default_family_friendly"],
+            config.default_family_friendly
+        );
+    }
+
+    /// a store with a fixed row count and no documents
+    struct SizedStore(u64);
+
+    impl VectorStore for SizedStore {
+        async fn search_by_vector(
+            &self,
+            _embedding: &[f32],
+            _top_k: usize,
+            _options: &QueryOptions,
+        ) -> Result<Vec<SearchResult>, VectorSearchError> {
+            Ok(Vec::new())
+        }
+
+        async fn search_by_keyword(
+            &self,
+            _query: &str,
+            _top_k: usize,
+            _options: &QueryOptions,
+        ) -> Result<Vec<SearchResult>, VectorSearchError> {
+            Ok(Vec::new())
+        }
+
+        async fn search_by_keyword_field(
+            &self,
+            _field: &str,
+            _query: &str,
+            _top_k: usize,
+            _options: &QueryOptions,
+        ) -> Result<Vec<SearchResult>, VectorSearchError> {
+            Ok(Vec::new())
+        }
+
+        async fn get_by_id(&self, _id: &str) -> Result<Option<SearchResult>, VectorSearchError> {
+            Ok(None)
+        }
+
+        async fn list_all(&self, _limit: usize) -> Result<Vec<SearchResult>, VectorSearchError> {
+            Ok(Vec::new())
+        }
+
+        async fn approx_row_count(&self) -> Result<Option<u64>, VectorSearchError> {
+            Ok(Some(self.0))
+        }
+
+        fn name(&self) -> &'static str {
+            "sized"
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_stats_reports_cache_and_open_breaker() {
+        let config = Config::for_tests();
+        let state = AppState::new(&config).unwrap();
+
+        state.embedding_cache.insert("happy".to_string(), vec![1.0]);
+        state.embedding_cache.get("happy");
+        state.embedding_cache.get("sad");
+        state.embedding_breaker.force_open();
+
+        let report = stats_report(&config, &state, &SizedStore(42)).await;
+        let body = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(body["approx_row_count"], 42);
+        assert_eq!(
+            body["fusion"]["alpha"],
+            serde_json::json!(config.default_alpha)
+        );
+        assert_eq!(body["embedding_cache"]["entries"], 1);
+        assert_eq!(body["embedding_cache"]["hits"], 1);
+        assert_eq!(body["embedding_cache"]["misses"], 1);
+        assert_eq!(body["embedding_cache"]["hit_rate"], 0.5);
+        assert_eq!(body["circuit_breaker"]["state"], "open");
+        assert_eq!(body["circuit_breaker"]["times_opened"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_stats_requires_the_admin_token() {
+        let mut config = Config::for_tests();
+        config.admin_token = Some("s3cret".to_string());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::new(&config).unwrap()))
+                .route("/stats", web::get().to(stats)),
+        )
+        .await;
+
+        // rejected before anything reaches turbopuffer
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -0,0 +1,197 @@
+//! tuned alpha overrides
+//!
+//! `ALPHA_POLICY_PATH` points at a json file of alphas found during tuning:
+//!
+//! ```json
+//! {
+//!   "queries": { "bufo party": 0.2 },
+//!   "categories": [{ "name": "feelings", "terms": ["happy", "sad"], "alpha": 0.9 }]
+//! }
+//! ```
+//!
+//! a request without its own `alpha` uses the exact query's alpha (matched after
+//! normalization), else the first category sharing a term with the query, else
+//! `DEFAULT_ALPHA`. an explicit `alpha` always wins. `/api/admin/reload` re-reads the
+//! file.
+
+use crate::rewrite::{Normalize, QueryRewriter};
+use crate::tokenize::tokenize;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    queries: HashMap<String, f32>,
+    #[serde(default)]
+    categories: Vec<CategoryEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoryEntry {
+    name: String,
+    terms: Vec<String>,
+    alpha: f32,
+}
+
+#[derive(Debug)]
+struct Category {
+    name: String,
+    terms: HashSet<String>,
+    alpha: f32,
+}
+
+/// what decided a search's alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaSource<'a> {
+    /// the request's own `alpha`
+    Request,
+    /// the policy's entry for the exact query
+    Query,
+    /// the named policy category
+    Category(&'a str),
+    Default,
+}
+
+impl AlphaSource<'_> {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlphaSource::Request => "request",
+            AlphaSource::Query => "query",
+            AlphaSource::Category(_) => "category",
+            AlphaSource::Default => "default",
+        }
+    }
+}
+
+/// per-query and per-category alphas (empty when no policy file is configured)
+#[derive(Debug, Default)]
+pub struct AlphaPolicy {
+    queries: HashMap<String, f32>,
+    categories: Vec<Category>,
+}
+
+fn check_alpha(alpha: f32, entry: &str) -> anyhow::Result<f32> {
+    if !(0.0..=1.0).contains(&alpha) {
+        anyhow::bail!("alpha for {} must be between 0 and 1, got {}", entry, alpha);
+    }
+    Ok(alpha)
+}
+
+impl AlphaPolicy {
+    /// read the policy at `path`, or an empty one when there's none
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read ALPHA_POLICY_PATH {}", path))?;
+        Self::parse(&raw).with_context(|| format!("invalid alpha policy in {}", path))
+    }
+
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        let file: PolicyFile = serde_json::from_str(raw)?;
+        let queries = file
+            .queries
+            .into_iter()
+            .map(|(query, alpha)| {
+                let alpha = check_alpha(alpha, &format!("query {:?}", query))?;
+                Ok((Normalize.rewrite(&query), alpha))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let categories = file
+            .categories
+            .into_iter()
+            .map(|entry| {
+                let alpha = check_alpha(entry.alpha, &format!("category {:?}", entry.name))?;
+                let terms = entry.terms.iter().flat_map(|term| tokenize(term)).collect();
+                Ok(Category {
+                    name: entry.name,
+                    terms,
+                    alpha,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            queries,
+            categories,
+        })
+    }
+
+    /// the alpha for `query`, and what decided it; `default` when nothing matches
+    pub fn decide(
+        &self,
+        requested: Option<f32>,
+        query: &str,
+        default: f32,
+    ) -> (f32, AlphaSource<'_>) {
+        if let Some(alpha) = requested {
+            return (alpha, AlphaSource::Request);
+        }
+        if let Some(&alpha) = self.queries.get(&Normalize.rewrite(query)) {
+            return (alpha, AlphaSource::Query);
+        }
+        let terms = tokenize(query);
+        let category = self
+            .categories
+            .iter()
+            .find(|category| terms.iter().any(|term| category.terms.contains(term)));
+        match category {
+            Some(category) => (category.alpha, AlphaSource::Category(&category.name)),
+            None => (default, AlphaSource::Default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"{
+        "queries": { "Bufo Party": 0.2 },
+        "categories": [
+            { "name": "feelings", "terms": ["happy", "sad"], "alpha": 0.9 },
+            { "name": "moods", "terms": ["happy"], "alpha": 0.5 }
+        ]
+    }"#;
+
+    #[test]
+    fn test_decide_checks_request_then_query_then_category() {
+        let policy = AlphaPolicy::parse(POLICY).unwrap();
+
+        assert_eq!(
+            policy.decide(Some(0.4), "bufo party", 0.7),
+            (0.4, AlphaSource::Request)
+        );
+        assert_eq!(
+            policy.decide(None, "  bufo   PARTY ", 0.7),
+            (0.2, AlphaSource::Query)
+        );
+        // the first matching category wins
+        assert_eq!(
+            policy.decide(None, "very happy bufo", 0.7),
+            (0.9, AlphaSource::Category("feelings"))
+        );
+        assert_eq!(
+            policy.decide(None, "bufo party hat", 0.7),
+            (0.7, AlphaSource::Default)
+        );
+        assert_eq!(
+            AlphaPolicy::default().decide(None, "happy", 0.4),
+            (0.4, AlphaSource::Default)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_out_of_range_alphas() {
+        assert!(AlphaPolicy::parse(r#"{ "queries": { "happy": 1.5 } }"#).is_err());
+        let err = AlphaPolicy::parse(
+            r#"{ "categories": [{ "name": "feelings", "terms": [], "alpha": -1 }] }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("feelings"), "{}", err);
+        assert!(AlphaPolicy::parse("{}").is_ok());
+        assert!(AlphaPolicy::load(None).unwrap().queries.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! chunked batch embedding
+//!
+//! a provider request can only carry so many inputs (`VOYAGE_MAX_BATCH` for voyage), so
+//! large batches are split into chunks of `EMBED_BATCH_SIZE` texts, sent at most
+//! `EMBED_BATCH_CONCURRENCY` at a time, and reassembled in input order. when chunks
+//! fail, the error names the inputs they covered so callers can retry just those.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+use futures::future::join_all;
+use std::ops::Range;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+
+/// a chunk whose request failed
+#[derive(Debug)]
+pub struct ChunkFailure {
+    /// indices into the submitted texts
+    pub inputs: Range<usize>,
+    pub error: EmbeddingError,
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "{} of {total} inputs failed to embed (first failure, inputs {:?}: {})",
+    .failures.iter().map(|f| f.inputs.len()).sum::<usize>(),
+    .failures[0].inputs,
+    .failures[0].error
+)]
+pub struct BatchEmbedError {
+    pub total: usize,
+    /// failed chunks, in input order (never empty)
+    pub failures: Vec<ChunkFailure>,
+}
+
+impl BatchEmbedError {
+    /// indices of every input that didn't get an embedding
+    pub fn failed_inputs(&self) -> Vec<usize> {
+        self.failures
+            .iter()
+            .flat_map(|failure| failure.inputs.clone())
+            .collect()
+    }
+}
+
+/// embed `texts` in chunks of `chunk_size`, at most `concurrency` chunks in flight
+///
+/// embeddings come back in the order of `texts`. every chunk runs even after one
+/// fails, so the error lists all the failed inputs.
+pub async fn embed_chunked<E: Embedder>(
+    embedder: &E,
+    texts: &[String],
+    input_type: InputType,
+    chunk_size: usize,
+    concurrency: usize,
+) -> Result<Vec<Vec<f32>>, BatchEmbedError> {
+    let chunk_size = chunk_size.max(1);
+    let permits = Semaphore::new(concurrency.max(1));
+
+    let chunks = texts.chunks(chunk_size).enumerate().map(|(i, chunk)| {
+        let permits = &permits;
+        async move {
+            let _permit = permits.acquire().await.expect("semaphore is never closed");
+            let start = i * chunk_size;
+            let inputs = start..start + chunk.len();
+            let result = embedder.embed_batch(chunk, input_type).await;
+            (inputs, result)
+        }
+    });
+
+    let mut embeddings = Vec::with_capacity(texts.len());
+    let mut failures = Vec::new();
+    for (inputs, result) in join_all(chunks).await {
+        match result {
+            Ok(chunk) => embeddings.extend(chunk),
+            Err(error) => failures.push(ChunkFailure { inputs, error }),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(embeddings)
+    } else {
+        Err(BatchEmbedError {
+            total: texts.len(),
+            failures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// embeds each text as its number, recording chunk sizes and peak concurrency;
+    /// chunks containing `fail_on` fail
+    #[derive(Clone, Default)]
+    struct RecordingEmbedder {
+        chunks: Arc<Mutex<Vec<usize>>>,
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl Embedder for RecordingEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(vec![text.parse().unwrap()])
+        }
+
+        async fn embed_batch(
+            &self,
+            texts: &[String],
+            input_type: InputType,
+        ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            self.chunks.lock().unwrap().push(texts.len());
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+
+            // later chunks finish first, so ordering can't come from completion order
+            let first: u64 = texts[0].parse().unwrap();
+            tokio::time::sleep(Duration::from_millis(50 - first.min(49))).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if self
+                .fail_on
+                .is_some_and(|bad| texts.iter().any(|t| t == bad))
+            {
+                return Err(EmbeddingError::EmptyResponse);
+            }
+            let mut embeddings = Vec::new();
+            for text in texts {
+                embeddings.push(self.embed_as(text, input_type).await?);
+            }
+            Ok(embeddings)
+        }
+
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+    }
+
+    fn texts(n: usize) -> Vec<String> {
+        (0..n).map(|i| i.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_reassembled_in_order() {
+        let embedder = RecordingEmbedder::default();
+        let embeddings = embed_chunked(&embedder, &texts(10), InputType::Document, 3, 2)
+            .await
+            .unwrap();
+
+        let expected: Vec<Vec<f32>> = (0..10).map(|i| vec![i as f32]).collect();
+        assert_eq!(embeddings, expected);
+
+        let mut chunks = embedder.chunks.lock().unwrap().clone();
+        chunks.sort();
+        assert_eq!(chunks, vec![1, 3, 3, 3]);
+        assert_eq!(embedder.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_chunks_name_their_inputs() {
+        let embedder = RecordingEmbedder {
+            fail_on: Some("4"),
+            ..RecordingEmbedder::default()
+        };
+        let err = embed_chunked(&embedder, &texts(7), InputType::Query, 3, 4)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.total, 7);
+        assert_eq!(err.failed_inputs(), vec![3, 4, 5]);
+        assert!(err.to_string().contains("3 of 7"), "{}", err);
+        // the other chunks still ran
+        assert_eq!(embedder.chunks.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_empty_batch() {
+        let embedder = RecordingEmbedder::default();
+        let embeddings = embed_chunked(&embedder, &[], InputType::Query, 3, 2)
+            .await
+            .unwrap();
+        assert!(embeddings.is_empty());
+        assert!(embedder.chunks.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! bufos suppressed by id
+//!
+//! the name blocklist can't catch a bufo whose name is innocuous, so `BLOCKED_IDS_PATH`
+//! names a file of ids (one per line, `#` for comments) that are never returned, e.g.
+//! after a takedown request. blocked candidates are dropped right after fusion, before
+//! any `BufoResult` is built, and looking a blocked bufo up by id is a 404.
+
+use crate::search::FusedCandidate;
+use anyhow::Context;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// ids that are never returned (empty when no file is configured)
+#[derive(Debug, Default)]
+pub struct BlockedIds {
+    ids: HashSet<String>,
+    digest: u64,
+}
+
+impl BlockedIds {
+    /// read the ids at `path`, or none when there's no path
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read BLOCKED_IDS_PATH {}", path))?;
+        Ok(Self::parse(&raw))
+    }
+
+    pub fn parse(raw: &str) -> Self {
+        let ids: HashSet<String> = raw
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let digest = if ids.is_empty() {
+            0
+        } else {
+            let mut sorted: Vec<&String> = ids.iter().collect();
+            sorted.sort();
+            let mut hasher = DefaultHasher::new();
+            sorted.hash(&mut hasher);
+            hasher.finish()
+        };
+        Self { ids, digest }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// a hash of the ids (0 when there are none), stable across restarts
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// drop blocked candidates, returning how many there were
+    pub fn remove_blocked(&self, candidates: &mut Vec<FusedCandidate>) -> usize {
+        if self.ids.is_empty() {
+            return 0;
+        }
+        let before = candidates.len();
+        candidates.retain(|(id, _, _)| !self.ids.contains(id));
+        before - candidates.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn candidate(id: &str, score: f32, name: &str) -> FusedCandidate {
+        let attrs = HashMap::from([("name".to_string(), name.to_string())]);
+        (id.to_string(), score, attrs)
+    }
+
+    #[test]
+    fn test_blocked_id_is_removed_even_when_ranked_first() {
+        let blocked = BlockedIds::parse("# takedowns\n  bufo-1  \n\nbufo-9\n");
+
+        // the top candidate has a perfectly innocuous name
+        let mut candidates = vec![
+            candidate("bufo-1", 0.99, "bufo-happy"),
+            candidate("bufo-2", 0.5, "bufo-sad"),
+            candidate("# takedowns", 0.4, "bufo-comment"),
+        ];
+        assert_eq!(blocked.remove_blocked(&mut candidates), 1);
+        let ids: Vec<&str> = candidates.iter().map(|(id, _, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["bufo-2", "# takedowns"]);
+
+        let mut candidates = vec![candidate("bufo-1", 0.99, "bufo-happy")];
+        assert_eq!(
+            BlockedIds::load(None)
+                .unwrap()
+                .remove_blocked(&mut candidates),
+            0
+        );
+        assert!(BlockedIds::load(Some("/nonexistent/blocked.txt")).is_err());
+    }
+
+    #[test]
+    fn test_digest_ignores_order_and_comments() {
+        let a = BlockedIds::parse("bufo-1\nbufo-2\n");
+        let b = BlockedIds::parse("# takedowns\nbufo-2\n\n  bufo-1\n");
+        assert_eq!(a.digest(), b.digest());
+        assert_ne!(a.digest(), BlockedIds::parse("bufo-1\n").digest());
+        assert_eq!(BlockedIds::parse("# nothing yet\n").digest(), 0);
+        assert_eq!(BlockedIds::default().digest(), 0);
+    }
+}
@@ -0,0 +1,325 @@
+//! circuit breaker for the embedding provider
+//!
+//! after `EMBEDDING_BREAKER_THRESHOLD` consecutive upstream failures the breaker opens,
+//! and for `EMBEDDING_BREAKER_COOLDOWN_SECS` embedding calls fail immediately instead of
+//! each waiting out a timeout; searches fall back to keyword results as for any other
+//! embedding failure. once the cooldown passes, one trial request is let through
+//! (half-open): success closes the breaker, failure opens it again.
+//!
+//! only transport errors, 429s and 5xxs count as failures. a 4xx is the request's
+//! fault, not the provider's, and says nothing about whether it's up.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// where the breaker is in its cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BreakerState {
+    /// calls go through
+    Closed,
+    /// calls fail fast until the cooldown passes
+    Open,
+    /// the cooldown passed; the next call is a trial
+    HalfOpen,
+}
+
+/// point-in-time breaker counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BreakerStats {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    /// times the breaker went from closed or half-open to open
+    pub times_opened: u64,
+}
+
+#[derive(Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    total_failures: u64,
+    times_opened: u64,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+/// shared breaker state (cheap to clone)
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Mutex<BreakerInner>>,
+    /// consecutive failures that open the breaker (0 disables it)
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    /// a threshold of 0 disables the breaker
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(BreakerInner::default())),
+            threshold,
+            cooldown,
+        }
+    }
+
+    /// a breaker that never opens
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn state_of(&self, inner: &BreakerInner) -> BreakerState {
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// whether a call may go through now; in half-open, only the one trial call may
+    fn allow(&self) -> bool {
+        if self.threshold == 0 {
+            return true;
+        }
+
+        let mut inner = self.lock();
+        match self.state_of(&inner) {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if inner.trial_in_flight => false,
+            BreakerState::HalfOpen => {
+                inner.trial_in_flight = true;
+                true
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        let mut inner = self.lock();
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.trial_in_flight = false;
+    }
+
+    /// count a failure, returning whether it opened the breaker
+    fn record_failure(&self) -> bool {
+        let mut inner = self.lock();
+        inner.consecutive_failures += 1;
+        inner.total_failures += 1;
+        if self.threshold == 0 {
+            return false;
+        }
+
+        let opens = inner.trial_in_flight || inner.consecutive_failures == self.threshold;
+        if opens {
+            inner.times_opened += 1;
+            inner.opened_at = Some(Instant::now());
+            inner.trial_in_flight = false;
+        }
+        opens
+    }
+
+    /// open the breaker as if the threshold had just been reached
+    #[cfg(test)]
+    pub fn force_open(&self) {
+        let mut inner = self.lock();
+        inner.times_opened += 1;
+        inner.opened_at = Some(Instant::now());
+    }
+
+    pub fn stats(&self) -> BreakerStats {
+        let inner = self.lock();
+        BreakerStats {
+            state: self.state_of(&inner),
+            consecutive_failures: inner.consecutive_failures,
+            total_failures: inner.total_failures,
+            times_opened: inner.times_opened,
+        }
+    }
+}
+
+/// whether `error` says the provider is struggling (rather than the request being bad)
+fn is_upstream_failure(error: &EmbeddingError) -> bool {
+    match error {
+        EmbeddingError::Request(_) => true,
+        EmbeddingError::Api { status, .. } => *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+/// an embedder that stops calling the wrapped one while its breaker is open
+#[derive(Clone)]
+pub struct BreakerEmbedder<E> {
+    inner: E,
+    breaker: CircuitBreaker,
+}
+
+impl<E: Embedder> BreakerEmbedder<E> {
+    pub fn new(inner: E, breaker: CircuitBreaker) -> Self {
+        Self { inner, breaker }
+    }
+
+    /// run `call` if the breaker allows it, recording how it went
+    async fn guarded<T>(
+        &self,
+        call: impl std::future::Future<Output = Result<T, EmbeddingError>>,
+    ) -> Result<T, EmbeddingError> {
+        if !self.breaker.allow() {
+            return Err(EmbeddingError::CircuitOpen {
+                model: self.inner.name(),
+            });
+        }
+
+        let result = call.await;
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(e) if is_upstream_failure(e) => {
+                if self.breaker.record_failure() {
+                    let error = e.to_string();
+                    logfire::warn!(
+                        "embedding circuit breaker open",
+                        model = self.inner.name(),
+                        error = &error
+                    );
+                }
+            }
+            // the provider answered, so it's up
+            Err(_) => self.breaker.record_success(),
+        }
+        result
+    }
+}
+
+impl<E: Embedder> Embedder for BreakerEmbedder<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        self.guarded(self.inner.embed_as(text, input_type)).await
+    }
+
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        self.guarded(self.inner.embed_batch(texts, input_type))
+            .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// fails with `status` while `failing` is set
+    #[derive(Clone)]
+    struct Flaky {
+        calls: Arc<AtomicUsize>,
+        failing: Arc<Mutex<Option<u16>>>,
+    }
+
+    impl Flaky {
+        fn failing_with(status: u16) -> Self {
+            Self {
+                calls: Arc::new(AtomicUsize::new(0)),
+                failing: Arc::new(Mutex::new(Some(status))),
+            }
+        }
+
+        fn recover(&self) {
+            *self.failing.lock().unwrap() = None;
+        }
+    }
+
+    impl Embedder for Flaky {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match *self.failing.lock().unwrap() {
+                Some(status) => Err(EmbeddingError::Api {
+                    status,
+                    body: String::new(),
+                }),
+                None => Ok(vec![1.0]),
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_and_fails_fast() {
+        let inner = Flaky::failing_with(503);
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let embedder = BreakerEmbedder::new(inner.clone(), breaker.clone());
+
+        assert!(embedder.embed("happy").await.is_err());
+        assert_eq!(breaker.stats().state, BreakerState::Closed);
+        assert!(embedder.embed("happy").await.is_err());
+        assert_eq!(breaker.stats().state, BreakerState::Open);
+
+        assert!(matches!(
+            embedder.embed("happy").await,
+            Err(EmbeddingError::CircuitOpen { model: "flaky" })
+        ));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+
+        let stats = breaker.stats();
+        assert_eq!(stats.consecutive_failures, 2);
+        assert_eq!(stats.total_failures, 2);
+        assert_eq!(stats.times_opened, 1);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_trial_closes_or_reopens() {
+        let inner = Flaky::failing_with(500);
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        let embedder = BreakerEmbedder::new(inner.clone(), breaker.clone());
+
+        assert!(embedder.embed("happy").await.is_err());
+        // a zero cooldown is over as soon as it starts
+        assert_eq!(breaker.stats().state, BreakerState::HalfOpen);
+
+        assert!(embedder.embed("happy").await.is_err());
+        assert_eq!(breaker.stats().times_opened, 2);
+
+        inner.recover();
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![1.0]);
+        let stats = breaker.stats();
+        assert_eq!(stats.state, BreakerState::Closed);
+        assert_eq!(stats.consecutive_failures, 0);
+        assert_eq!(stats.total_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_and_disabled_breaker_never_open() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let embedder = BreakerEmbedder::new(Flaky::failing_with(400), breaker.clone());
+        assert!(embedder.embed("happy").await.is_err());
+        assert!(embedder.embed("happy").await.is_err());
+        assert_eq!(breaker.stats().state, BreakerState::Closed);
+
+        let inner = Flaky::failing_with(503);
+        let disabled = CircuitBreaker::disabled();
+        let embedder = BreakerEmbedder::new(inner.clone(), disabled.clone());
+        for _ in 0..3 {
+            assert!(embedder.embed("happy").await.is_err());
+        }
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(disabled.stats().state, BreakerState::Closed);
+        assert_eq!(disabled.stats().total_failures, 3);
+    }
+}
@@ -0,0 +1,302 @@
+//! in-memory query embedding cache
+//!
+//! embeddings are deterministic for a given model and text, so repeated queries skip the
+//! embedding api entirely. the cache is bounded and evicts the oldest entry first; keys
+//! include the embedder name and input type, so vectors are never shared across models
+//! or between query and document embeddings.
+//!
+//! with `QUERY_NORMALIZATION` on, text is normalized (`rewrite::Normalize`) before it's
+//! looked up or embedded, so "Happy", "happy" and " happy " share one entry. the inner
+//! embedder is then always sent the normalized text, which keeps a cached vector
+//! identical to a freshly embedded one. with it off, text is cached and embedded as given.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+use crate::rewrite::{Normalize, QueryRewriter};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<String, Vec<f32>>,
+    order: VecDeque<String>,
+    hits: u64,
+    misses: u64,
+}
+
+/// point-in-time cache counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// shared, bounded embedding cache (cheap to clone)
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    inner: Arc<Mutex<CacheInner>>,
+    capacity: usize,
+}
+
+impl EmbeddingCache {
+    /// a capacity of 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(CacheInner::default())),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<f32>> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let found = inner.entries.get(key).cloned();
+        if found.is_some() {
+            inner.hits += 1;
+        } else {
+            inner.misses += 1;
+        }
+        found
+    }
+
+    pub fn insert(&self, key: String, embedding: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.entries.contains_key(&key) {
+            return;
+        }
+        while inner.entries.len() >= self.capacity {
+            match inner.order.pop_front() {
+                Some(oldest) => {
+                    inner.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        inner.order.push_back(key.clone());
+        inner.entries.insert(key, embedding);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            entries: inner.entries.len(),
+            capacity: self.capacity,
+            hits: inner.hits,
+            misses: inner.misses,
+        }
+    }
+}
+
+/// an embedder that checks the shared cache before calling the wrapped one
+#[derive(Clone)]
+pub struct CachingEmbedder<E> {
+    inner: E,
+    cache: EmbeddingCache,
+    /// normalize text before caching and embedding it (`QUERY_NORMALIZATION`)
+    normalize: bool,
+}
+
+impl<E: Embedder> CachingEmbedder<E> {
+    pub fn new(inner: E, cache: EmbeddingCache, normalize: bool) -> Self {
+        Self {
+            inner,
+            cache,
+            normalize,
+        }
+    }
+
+    fn prepare(&self, text: &str) -> String {
+        if self.normalize {
+            Normalize.rewrite(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// `text` must already be normalized
+    fn key(&self, text: &str, input_type: InputType) -> String {
+        format!("{}:{}:{}", self.inner.name(), input_type.as_str(), text)
+    }
+}
+
+impl<E: Embedder> Embedder for CachingEmbedder<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        let text = self.prepare(text);
+        let key = self.key(&text, input_type);
+        if let Some(embedding) = self.cache.get(&key) {
+            return Ok(embedding);
+        }
+
+        let embedding = self.inner.embed_as(&text, input_type).await?;
+        self.cache.insert(key, embedding.clone());
+        Ok(embedding)
+    }
+
+    /// cached texts are served from the cache; the rest go to the inner embedder together
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let texts: Vec<String> = texts.iter().map(|text| self.prepare(text)).collect();
+        let mut embeddings: Vec<Option<Vec<f32>>> = texts
+            .iter()
+            .map(|text| self.cache.get(&self.key(text, input_type)))
+            .collect();
+        let missing: Vec<usize> = (0..texts.len())
+            .filter(|&i| embeddings[i].is_none())
+            .collect();
+
+        if !missing.is_empty() {
+            let uncached: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let fetched = self.inner.embed_batch(&uncached, input_type).await?;
+            for (i, embedding) in missing.into_iter().zip(fetched) {
+                self.cache
+                    .insert(self.key(&texts[i], input_type), embedding.clone());
+                embeddings[i] = Some(embedding);
+            }
+        }
+
+        embeddings
+            .into_iter()
+            .map(|embedding| embedding.ok_or(EmbeddingError::EmptyResponse))
+            .collect()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct CountingEmbedder {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Embedder for CountingEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![text.len() as f32])
+        }
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    /// records the text of every call
+    #[derive(Clone, Default)]
+    struct RecordingEmbedder {
+        texts: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Embedder for RecordingEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.texts.lock().unwrap().push(text.to_string());
+            Ok(vec![text.len() as f32])
+        }
+
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest() {
+        let cache = EmbeddingCache::new(2);
+        cache.insert("a".into(), vec![1.0]);
+        cache.insert("b".into(), vec![2.0]);
+        cache.insert("c".into(), vec![3.0]);
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(vec![2.0]));
+        assert_eq!(cache.get("c"), Some(vec![3.0]));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() {
+        let cache = EmbeddingCache::new(0);
+        cache.insert("a".into(), vec![1.0]);
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[tokio::test]
+    async fn test_caching_embedder_reuses_embeddings() {
+        let inner = CountingEmbedder::default();
+        let embedder = CachingEmbedder::new(inner.clone(), EmbeddingCache::new(10), true);
+
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![5.0]);
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![5.0]);
+        assert_eq!(embedder.embed("sad").await.unwrap(), vec![3.0]);
+
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+        let stats = embedder.cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_batch_embeds_only_uncached_texts() {
+        let inner = CountingEmbedder::default();
+        let embedder = CachingEmbedder::new(inner.clone(), EmbeddingCache::new(10), true);
+        embedder.embed("happy").await.unwrap();
+
+        let texts = vec!["sad".to_string(), "happy".to_string(), "angry".to_string()];
+        let embeddings = embedder
+            .embed_batch(&texts, InputType::Query)
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![3.0], vec![5.0], vec![5.0]]);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_case_and_whitespace_variants_share_an_entry() {
+        let inner = RecordingEmbedder::default();
+        let embedder = CachingEmbedder::new(inner.clone(), EmbeddingCache::new(10), true);
+
+        let first = embedder.embed("Happy").await.unwrap();
+        assert_eq!(embedder.embed("happy").await.unwrap(), first);
+        assert_eq!(embedder.embed("  happy ").await.unwrap(), first);
+        assert_eq!(embedder.embed("HAPPY\tbufo").await.unwrap(), vec![10.0]);
+        assert_eq!(embedder.embed("happy bufo").await.unwrap(), vec![10.0]);
+
+        // only the normalized text reaches the embedding api
+        assert_eq!(*inner.texts.lock().unwrap(), vec!["happy", "happy bufo"]);
+        let stats = embedder.cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_text_is_embedded_as_given_without_normalization() {
+        let inner = RecordingEmbedder::default();
+        let embedder = CachingEmbedder::new(inner.clone(), EmbeddingCache::new(10), false);
+
+        embedder.embed("Happy").await.unwrap();
+        embedder.embed("happy").await.unwrap();
+        embedder.embed("Happy").await.unwrap();
+        embedder
+            .embed_batch(&[" Happy ".to_string()], InputType::Query)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *inner.texts.lock().unwrap(),
+            vec!["Happy", "happy", " Happy "]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! query cluster labels
+//!
+//! `CLUSTER_CENTROIDS_PATH` points at a json file of centroids precomputed over the
+//! corpus, each with a label:
+//!
+//! ```json
+//! { "clusters": [{ "label": "reactions", "centroid": [0.12, -0.03, ...] }] }
+//! ```
+//!
+//! a debug search reports the label of the centroid nearest its query embedding as
+//! `query_cluster`. the embedding is already there, so labeling costs no model call.
+
+use anyhow::Context;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct CentroidFile {
+    clusters: Vec<Cluster>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cluster {
+    label: String,
+    centroid: Vec<f32>,
+}
+
+/// labeled centroids (empty when no file is configured)
+#[derive(Debug, Default)]
+pub struct Clusterer {
+    clusters: Vec<Cluster>,
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// cosine similarity of `a` and `b`; `None` for mismatched or zero-length vectors
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    let norms = dot(a, a).sqrt() * dot(b, b).sqrt();
+    (a.len() == b.len() && norms > 0.0).then(|| dot(a, b) / norms)
+}
+
+/// the label of the centroid most cosine-similar to `query`, first wins on ties
+///
+/// centroids of another dimension than `query` are never nearest.
+pub fn nearest_centroid<'a>(
+    query: &[f32],
+    centroids: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+) -> Option<&'a str> {
+    centroids
+        .into_iter()
+        .filter_map(|(label, centroid)| Some((label, cosine_similarity(query, centroid)?)))
+        .fold(
+            None,
+            |best: Option<(&str, f32)>, (label, similarity)| match best {
+                Some((_, best_similarity)) if best_similarity >= similarity => best,
+                _ => Some((label, similarity)),
+            },
+        )
+        .map(|(label, _)| label)
+}
+
+impl Clusterer {
+    /// read the centroids at `path`, or none when there's no path
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read CLUSTER_CENTROIDS_PATH {}", path))?;
+        Self::parse(&raw).with_context(|| format!("invalid cluster centroids in {}", path))
+    }
+
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        let file: CentroidFile = serde_json::from_str(raw)?;
+        let dimension = file.clusters.first().map_or(0, |c| c.centroid.len());
+        for cluster in &file.clusters {
+            if cluster.label.trim().is_empty() {
+                anyhow::bail!("cluster labels must not be empty");
+            }
+            if cluster.centroid.is_empty() || cluster.centroid.len() != dimension {
+                anyhow::bail!(
+                    "centroid for {} has {} dimensions, expected {}",
+                    cluster.label,
+                    cluster.centroid.len(),
+                    dimension
+                );
+            }
+        }
+        Ok(Self {
+            clusters: file.clusters,
+        })
+    }
+
+    /// the nearest cluster's label, or `None` without centroids of the query's dimension
+    pub fn label(&self, query_embedding: &[f32]) -> Option<&str> {
+        nearest_centroid(
+            query_embedding,
+            self.clusters
+                .iter()
+                .map(|c| (c.label.as_str(), c.centroid.as_slice())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_centroid_by_cosine() {
+        let centroids: [(&str, &[f32]); 3] = [
+            ("reactions", &[1.0, 0.0]),
+            ("animals", &[0.0, 1.0]),
+            ("food", &[-1.0, 0.0]),
+        ];
+
+        // direction matters, not length
+        assert_eq!(nearest_centroid(&[10.0, 2.0], centroids), Some("reactions"));
+        assert_eq!(nearest_centroid(&[0.1, 0.3], centroids), Some("animals"));
+        // equally near: the first listed wins
+        assert_eq!(nearest_centroid(&[1.0, 1.0], centroids), Some("reactions"));
+
+        assert_eq!(nearest_centroid(&[0.0, 0.0], centroids), None);
+        assert_eq!(nearest_centroid(&[1.0, 0.0, 0.0], centroids), None);
+        assert_eq!(nearest_centroid(&[1.0, 0.0], []), None);
+    }
+
+    #[test]
+    fn test_centroid_file_is_validated() {
+        let clusterer = Clusterer::parse(
+            r#"{ "clusters": [
+                { "label": "reactions", "centroid": [1.0, 0.0] },
+                { "label": "animals", "centroid": [0.0, 1.0] }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(clusterer.label(&[0.2, 0.9]), Some("animals"));
+        assert_eq!(clusterer.label(&[0.2, 0.9, 0.1]), None);
+
+        assert!(Clusterer::parse(
+            r#"{ "clusters": [{ "label": "a", "centroid": [1.0] }, { "label": "b", "centroid": [1.0, 0.0] }] }"#
+        )
+        .is_err());
+        assert!(
+            Clusterer::parse(r#"{ "clusters": [{ "label": " ", "centroid": [1.0] }] }"#).is_err()
+        );
+        assert!(Clusterer::parse(r#"{ "clusters": [{ "label": "a", "centroid": [] }] }"#).is_err());
+        assert_eq!(Clusterer::load(None).unwrap().label(&[1.0]), None);
+        assert!(Clusterer::load(Some("/nonexistent/centroids.json")).is_err());
+    }
+}
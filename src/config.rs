@@ -1,5 +1,18 @@
+use crate::display::NameCase;
+use crate::embedding;
+use crate::https::HttpsUrls;
+use crate::models::ModelRoutes;
+use crate::negotiate::{ImageFormat, ImageRewrite};
+use crate::postprocess::{self, Step};
+use crate::response_cache::CacheBackend;
+use crate::scoring::{AttributeMerge, KeywordFields, KeywordNormalization, SimilarityCurve};
+use crate::thumbnail::ThumbnailTransform;
+use crate::tokenize;
+use crate::turbopuffer::{Consistency, HyphenMode};
 use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::env;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -7,23 +20,687 @@ pub struct Config {
     pub port: u16,
     pub turbopuffer_api_key: String,
     pub turbopuffer_namespace: String,
+    /// namespaces a request may search via `namespaces` (always includes the default)
+    pub search_namespaces: Vec<String>,
+    /// searched when a single-namespace search finds fewer than `min_results` (or none)
+    pub fallback_namespace: Option<String>,
+    /// query read consistency; `None` leaves turbopuffer's default
+    pub turbopuffer_consistency: Option<Consistency>,
+    /// drop vector neighbours beyond this cosine distance (0-2); `None` keeps all top_k
+    pub vector_max_distance: Option<f32>,
+    /// limit on each turbopuffer request; `None` waits indefinitely
+    pub turbopuffer_timeout: Option<Duration>,
     pub voyage_api_key: String,
+    /// truncated voyage embedding size; `None` uses the model's full 1024
+    pub voyage_output_dimension: Option<usize>,
+    /// re-send voyage requests still pending after this long; `None` disables hedging
+    pub voyage_hedge_delay: Option<Duration>,
+    /// limit on each voyage request (per hedged attempt); `None` waits indefinitely
+    pub voyage_timeout: Option<Duration>,
+    /// times an empty voyage response is retried before failing the embedding
+    pub voyage_empty_retries: u32,
+    /// onnx model directory used instead of voyage when `EMBEDDING_PROVIDER=local`
+    #[cfg_attr(not(feature = "local-embeddings"), allow(dead_code))]
+    pub local_model_dir: Option<String>,
+    /// fail startup unless the local model's vectors have this many dimensions
+    #[cfg_attr(not(feature = "local-embeddings"), allow(dead_code))]
+    pub local_embedding_dimension: Option<usize>,
+    /// reject query embeddings of any other size (the namespace's vector dimension)
+    pub expected_embedding_dim: Option<usize>,
+    /// optional openai embedder searched alongside voyage as an ensemble member
+    pub openai_api_key: Option<String>,
+    pub openai_embedding_model: String,
+    /// namespace indexed with `openai_embedding_model` vectors
+    pub openai_namespace: Option<String>,
+    /// share of the semantic weight given to openai when the ensemble is enabled
+    pub openai_ensemble_weight: f32,
+    /// models a request may pick with `model`, each routed to its own namespace
+    pub embedding_models: ModelRoutes,
+    /// family-friendly mode used when a request doesn't specify one
+    pub default_family_friendly: bool,
+    /// alpha for requests without one that the alpha policy doesn't cover
+    pub default_alpha: f32,
+    /// pass `last_as_prefix` to BM25 so partial words match
+    pub bm25_prefix_match: bool,
+    /// whether hyphens in keyword queries are kept or split into spaces
+    pub bm25_hyphen_mode: HyphenMode,
+    /// keyword query terms shorter than this many characters are dropped (1 keeps all)
+    pub bm25_min_term_length: usize,
+    /// keyword score boost for names where the query matches early (0 = off)
+    pub bm25_position_boost: f32,
+    /// how much keyword scores are scaled by the share of query terms in the name (0 = off)
+    pub bm25_coverage_weight: f32,
+    /// added to the fused score of bufos whose name is exactly the query (0 = off)
+    pub exact_match_boost: f32,
+    /// largest score bonus `prev_order` gives a previously returned result (0 = off)
+    pub sticky_hysteresis: f32,
+    /// most candidates fetched from each upstream search (over-fetch is `top_k * 5`)
+    pub candidate_cap: usize,
+    /// how BM25 scores are normalized before fusion (`max`, `softmax:<t>`, `power:<p>`)
+    pub keyword_normalization: KeywordNormalization,
+    /// cosine distance → semantic score mapping (`linear` or `sigmoid:<k>:<d0>`)
+    pub similarity_curve: SimilarityCurve,
+    /// BM25 fields and weights combined into the keyword score (`name:1,tags:0.5`)
+    pub keyword_fields: KeywordFields,
+    /// attributes for an id found by both searches (`fields` or `richer`)
+    pub attribute_merge: AttributeMerge,
+    /// how often the "did you mean" vocabulary is reloaded from the namespace
+    pub vocabulary_refresh_secs: u64,
+    /// `max-age` for cacheable GET search responses
+    pub search_cache_max_age_secs: u64,
+    /// how long rendered GET search responses stay in the response cache (0 disables it)
+    pub response_cache_ttl_secs: u64,
+    /// where the response cache keeps entries
+    pub cache_backend: CacheBackend,
+    /// redis for `CACHE_BACKEND=redis`
+    pub redis_url: Option<String>,
+    /// directory served under `/static` (and checked for an index.html override)
+    pub static_dir: String,
+    /// record search text in logs and spans; when false it's replaced by a hash
+    pub log_queries: bool,
+    /// trim, collapse whitespace in and lowercase queries before searching
+    pub query_normalization: bool,
+    /// runs of 3+ identical characters in queries are cut to this length (`None` = off)
+    pub query_collapse_repeats: Option<usize>,
+    /// detect the query language and log it
+    pub language_detection: bool,
+    /// prefix reliably non-english queries with their language before embedding
+    pub language_hints: bool,
+    /// drop `embedding_stopwords` from the text sent to the embedders (not from BM25)
+    pub remove_embedding_stopwords: bool,
+    /// stopwords dropped before embedding; defaults to `tokenize::STOPWORDS`
+    pub embedding_stopwords: Vec<String>,
+    /// share of the query embedding taken from the spaced form of hyphenated queries (0-1)
+    pub filename_blend_weight: f32,
+    /// embed long semantic texts in overlapping chunks and mean-pool the vectors
+    pub long_query_pooling: bool,
+    /// characters past which `long_query_pooling` chunks a semantic text
+    pub long_query_threshold: usize,
+    /// post-processing steps run after the content filter, in order
+    pub post_processors: Vec<Step>,
+    /// cap on results returned to the client, applied after ranking; `None` means top_k
+    pub max_returned_results: Option<usize>,
+    /// cap on a serialized search response; results are dropped to fit (`None` disables)
+    pub max_response_bytes: Option<usize>,
+    /// most exclude (or include) patterns one request may send
+    pub max_filter_patterns: usize,
+    /// derives `thumbnail_url` from `url` for bufos without a stored thumbnail
+    pub thumbnail_transform: Option<ThumbnailTransform>,
+    /// capitalization of `display_name` for `pretty_names` requests
+    pub display_name_case: NameCase,
+    /// upgrade or drop `http://` result urls (off returns them as stored)
+    pub https_urls: HttpsUrls,
+    /// rewrites `url` to webp/avif for clients that accept them; `None` leaves urls as-is
+    pub image_rewrite: Option<ImageRewrite>,
+    /// max query embeddings kept in memory (0 disables the cache)
+    pub embedding_cache_size: usize,
+    /// consecutive embedding failures that open the circuit breaker (0 disables it)
+    pub embedding_breaker_threshold: u32,
+    /// how long an open breaker fails embedding calls before a trial request
+    pub embedding_breaker_cooldown: Duration,
+    /// past queries kept for autocompletion (0 disables; requires `log_queries`)
+    pub query_history_size: usize,
+    /// newline-separated queries embedded at startup to warm the cache
+    pub popular_queries_path: Option<String>,
+    /// json file of tuned alphas by query and category, read at startup
+    pub alpha_policy_path: Option<String>,
+    /// newline-separated bufo ids never returned by search, read at startup
+    pub blocked_ids_path: Option<String>,
+    /// json file of labeled centroids for debug `query_cluster`, read at startup
+    pub cluster_centroids_path: Option<String>,
+    /// max concurrent embedding requests while preloading
+    pub preload_concurrency: usize,
+    /// texts per embedding request in batch embeds (at most voyage's per-request limit)
+    pub embed_batch_size: usize,
+    /// batch embedding requests in flight at once
+    pub embed_batch_concurrency: usize,
+    /// searches allowed in flight before new ones get a 503 (0 = unlimited)
+    pub max_concurrent_searches: usize,
+    /// searches taking longer than this many milliseconds are logged at warn level
+    pub slow_query_ms: Option<u64>,
+    /// idle time before a keep-alive connection is closed (0 disables keep-alive)
+    pub keep_alive_secs: u64,
+    /// time a client has to send its request headers (0 disables the timeout)
+    pub client_timeout_secs: u64,
+    /// also accept cleartext HTTP/2 (h2c, prior knowledge) on the listening port
+    pub enable_http2: bool,
+    /// bearer token for admin endpoints (e.g. `/api/embed`); unset disables them
+    pub admin_token: Option<String>,
+    /// `X-Api-Key` values rate limited per key instead of per ip
+    pub api_keys: HashSet<String>,
+    /// `/api` requests per minute for each anonymous client ip
+    pub rate_limit_per_minute: u32,
+    /// `/api` requests per minute for each known api key
+    pub authenticated_rate_limit_per_minute: u32,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
+        Self::from_vars(|key| env::var(key).ok())
+    }
+
+    /// parse the settings `lookup` returns for each variable name (`None` when unset)
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |key: &str| lookup(key).ok_or(env::VarError::NotPresent);
+
+        let openai_ensemble_weight: f32 = var("OPENAI_ENSEMBLE_WEIGHT")
+            .unwrap_or_else(|_| "0.5".to_string())
+            .parse()
+            .context("failed to parse OPENAI_ENSEMBLE_WEIGHT")?;
+        if !(0.0..=1.0).contains(&openai_ensemble_weight) {
+            anyhow::bail!("OPENAI_ENSEMBLE_WEIGHT must be between 0 and 1");
+        }
+
+        let openai_api_key = var("OPENAI_API_KEY").ok();
+        let embedding_models = var("EMBEDDING_MODELS")
+            .unwrap_or_default()
+            .parse::<ModelRoutes>()
+            .map_err(|e| anyhow::anyhow!("failed to parse EMBEDDING_MODELS: {}", e))?;
+        if embedding_models.needs_openai() && openai_api_key.is_none() {
+            anyhow::bail!("EMBEDDING_MODELS lists an openai model but OPENAI_API_KEY isn't set");
+        }
+
+        let post_processors = postprocess::parse_steps(
+            &var("POST_PROCESSORS").unwrap_or_else(|_| "boost".to_string()),
+        )
+        .map_err(|e| anyhow::anyhow!("failed to parse POST_PROCESSORS: {}", e))?;
+
+        let keyword_normalization = var("KEYWORD_NORMALIZATION")
+            .unwrap_or_else(|_| "max".to_string())
+            .parse::<KeywordNormalization>()
+            .map_err(|e| anyhow::anyhow!("failed to parse KEYWORD_NORMALIZATION: {}", e))?;
+
+        let attribute_merge = var("ATTRIBUTE_MERGE")
+            .unwrap_or_else(|_| "fields".to_string())
+            .parse::<AttributeMerge>()
+            .map_err(|e| anyhow::anyhow!("failed to parse ATTRIBUTE_MERGE: {}", e))?;
+
+        let display_name_case = var("DISPLAY_NAME_CASE")
+            .unwrap_or_else(|_| "sentence".to_string())
+            .parse::<NameCase>()
+            .map_err(|e| anyhow::anyhow!("failed to parse DISPLAY_NAME_CASE: {}", e))?;
+
+        let max_filter_patterns: usize = var("MAX_FILTER_PATTERNS")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse()
+            .context("failed to parse MAX_FILTER_PATTERNS")?;
+        if max_filter_patterns == 0 {
+            anyhow::bail!("MAX_FILTER_PATTERNS must be at least 1");
+        }
+
+        let https_urls = var("HTTPS_URLS")
+            .unwrap_or_else(|_| "off".to_string())
+            .parse::<HttpsUrls>()
+            .map_err(|e| anyhow::anyhow!("failed to parse HTTPS_URLS: {}", e))?;
+
+        let cache_backend = var("CACHE_BACKEND")
+            .unwrap_or_else(|_| "memory".to_string())
+            .parse::<CacheBackend>()
+            .map_err(|e| anyhow::anyhow!("failed to parse CACHE_BACKEND: {}", e))?;
+
+        let similarity_curve = var("SIMILARITY_CURVE")
+            .unwrap_or_else(|_| "linear".to_string())
+            .parse::<SimilarityCurve>()
+            .map_err(|e| anyhow::anyhow!("failed to parse SIMILARITY_CURVE: {}", e))?;
+
+        let keyword_fields = var("KEYWORD_FIELDS")
+            .unwrap_or_else(|_| "name".to_string())
+            .parse::<KeywordFields>()
+            .map_err(|e| anyhow::anyhow!("failed to parse KEYWORD_FIELDS: {}", e))?;
+
+        let bm25_hyphen_mode = var("BM25_HYPHEN_MODE")
+            .unwrap_or_else(|_| "keep".to_string())
+            .parse::<HyphenMode>()
+            .map_err(|e| anyhow::anyhow!("failed to parse BM25_HYPHEN_MODE: {}", e))?;
+
+        let turbopuffer_consistency = var("TURBOPUFFER_CONSISTENCY")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| v.parse::<Consistency>())
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("failed to parse TURBOPUFFER_CONSISTENCY: {}", e))?;
+
+        let voyage_output_dimension = var("VOYAGE_OUTPUT_DIMENSION")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| {
+                v.trim()
+                    .parse::<usize>()
+                    .context("failed to parse VOYAGE_OUTPUT_DIMENSION")
+            })
+            .transpose()?
+            .map(embedding::validate_output_dimension)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("invalid VOYAGE_OUTPUT_DIMENSION: {}", e))?;
+
+        let local_model_dir = match var("EMBEDDING_PROVIDER")
+            .unwrap_or_else(|_| "voyage".to_string())
+            .trim()
+        {
+            "voyage" => None,
+            "local" if cfg!(feature = "local-embeddings") => Some(
+                var("LOCAL_MODEL_DIR")
+                    .context("LOCAL_MODEL_DIR must be set when EMBEDDING_PROVIDER=local")?,
+            ),
+            "local" => anyhow::bail!(
+                "EMBEDDING_PROVIDER=local needs a build with --features local-embeddings"
+            ),
+            other => anyhow::bail!(
+                "failed to parse EMBEDDING_PROVIDER: expected voyage or local, got {:?}",
+                other
+            ),
+        };
+        let local_embedding_dimension = var("LOCAL_EMBEDDING_DIMENSION")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| {
+                v.trim()
+                    .parse::<usize>()
+                    .context("failed to parse LOCAL_EMBEDDING_DIMENSION")
+            })
+            .transpose()?;
+
+        let expected_embedding_dim = var("EXPECTED_EMBEDDING_DIM")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| {
+                v.trim()
+                    .parse::<usize>()
+                    .context("failed to parse EXPECTED_EMBEDDING_DIM")
+            })
+            .transpose()?;
+        let configured_dimension = match local_model_dir {
+            Some(_) => local_embedding_dimension.map(|d| ("LOCAL_EMBEDDING_DIMENSION", d)),
+            None => voyage_output_dimension.map(|d| ("VOYAGE_OUTPUT_DIMENSION", d)),
+        };
+        if let (Some(expected), Some((name, configured))) =
+            (expected_embedding_dim, configured_dimension)
+        {
+            anyhow::ensure!(
+                expected == configured,
+                "EXPECTED_EMBEDDING_DIM is {} but {} is {}",
+                expected,
+                name,
+                configured
+            );
+        }
+
+        let thumbnail_transform = var("THUMBNAIL_URL_PATTERN")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|pattern| {
+                let replacement = var("THUMBNAIL_URL_REPLACEMENT")
+                    .context("THUMBNAIL_URL_REPLACEMENT must be set with THUMBNAIL_URL_PATTERN")?;
+                ThumbnailTransform::new(&pattern, replacement)
+                    .context("failed to parse THUMBNAIL_URL_PATTERN")
+            })
+            .transpose()?;
+
+        let image_formats = var("IMAGE_FORMATS")
+            .unwrap_or_else(|_| "avif,webp".to_string())
+            .split(',')
+            .filter(|f| !f.trim().is_empty())
+            .map(|f| f.parse::<ImageFormat>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!("failed to parse IMAGE_FORMATS: {}", e))?;
+        let image_rewrite = var("IMAGE_URL_PATTERN")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|pattern| {
+                let replacement = var("IMAGE_URL_REPLACEMENT")
+                    .context("IMAGE_URL_REPLACEMENT must be set with IMAGE_URL_PATTERN")?;
+                ImageRewrite::new(&pattern, replacement, image_formats)
+                    .context("failed to parse IMAGE_URL_PATTERN")
+            })
+            .transpose()?;
+
+        let exact_match_boost: f32 = var("EXACT_MATCH_BOOST")
+            .unwrap_or_else(|_| "1.0".to_string())
+            .parse()
+            .context("failed to parse EXACT_MATCH_BOOST")?;
+        if !exact_match_boost.is_finite() || exact_match_boost < 0.0 {
+            anyhow::bail!("EXACT_MATCH_BOOST must be 0 or more");
+        }
+
+        let default_alpha: f32 = var("DEFAULT_ALPHA")
+            .unwrap_or_else(|_| "0.7".to_string())
+            .parse()
+            .context("failed to parse DEFAULT_ALPHA")?;
+        if !(0.0..=1.0).contains(&default_alpha) {
+            anyhow::bail!("DEFAULT_ALPHA must be between 0 and 1");
+        }
+
+        let rate_limit_per_minute: u32 = var("RATE_LIMIT_PER_MINUTE")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .context("failed to parse RATE_LIMIT_PER_MINUTE")?;
+        let authenticated_rate_limit_per_minute: u32 = var("AUTHENTICATED_RATE_LIMIT_PER_MINUTE")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .context("failed to parse AUTHENTICATED_RATE_LIMIT_PER_MINUTE")?;
+        if rate_limit_per_minute == 0 || authenticated_rate_limit_per_minute == 0 {
+            anyhow::bail!("rate limits must be at least 1 request per minute");
+        }
+
+        let sticky_hysteresis: f32 = var("STICKY_HYSTERESIS")
+            .unwrap_or_else(|_| "0.05".to_string())
+            .parse()
+            .context("failed to parse STICKY_HYSTERESIS")?;
+        if !sticky_hysteresis.is_finite() || sticky_hysteresis < 0.0 {
+            anyhow::bail!("STICKY_HYSTERESIS must be 0 or more");
+        }
+
+        let candidate_cap: usize = var("CANDIDATE_CAP")
+            .unwrap_or_else(|_| "200".to_string())
+            .parse()
+            .context("failed to parse CANDIDATE_CAP")?;
+        if candidate_cap == 0 {
+            anyhow::bail!("CANDIDATE_CAP must be at least 1");
+        }
+
+        let bm25_coverage_weight: f32 = var("BM25_COVERAGE_WEIGHT")
+            .unwrap_or_else(|_| "0.0".to_string())
+            .parse()
+            .context("failed to parse BM25_COVERAGE_WEIGHT")?;
+        if !(0.0..=1.0).contains(&bm25_coverage_weight) {
+            anyhow::bail!("BM25_COVERAGE_WEIGHT must be between 0 and 1");
+        }
+
+        let embed_batch_size: usize = var("EMBED_BATCH_SIZE")
+            .unwrap_or_else(|_| embedding::VOYAGE_MAX_BATCH.to_string())
+            .parse()
+            .context("failed to parse EMBED_BATCH_SIZE")?;
+        if !(1..=embedding::VOYAGE_MAX_BATCH).contains(&embed_batch_size) {
+            anyhow::bail!(
+                "EMBED_BATCH_SIZE must be between 1 and {}",
+                embedding::VOYAGE_MAX_BATCH
+            );
+        }
+
+        let filename_blend_weight: f32 = var("FILENAME_BLEND_WEIGHT")
+            .unwrap_or_else(|_| "0.0".to_string())
+            .parse()
+            .context("failed to parse FILENAME_BLEND_WEIGHT")?;
+        if !(0.0..=1.0).contains(&filename_blend_weight) {
+            anyhow::bail!("FILENAME_BLEND_WEIGHT must be between 0 and 1");
+        }
+
+        let long_query_threshold: usize = var("LONG_QUERY_THRESHOLD")
+            .unwrap_or_else(|_| "512".to_string())
+            .parse()
+            .context("failed to parse LONG_QUERY_THRESHOLD")?;
+        if long_query_threshold == 0 {
+            anyhow::bail!("LONG_QUERY_THRESHOLD must be at least 1");
+        }
+
+        let bm25_min_term_length: usize = var("BM25_MIN_TERM_LENGTH")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()
+            .context("failed to parse BM25_MIN_TERM_LENGTH")?;
+        if bm25_min_term_length == 0 {
+            anyhow::bail!("BM25_MIN_TERM_LENGTH must be at least 1");
+        }
+
+        let vocabulary_refresh_secs: u64 = var("VOCABULARY_REFRESH_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse()
+            .context("failed to parse VOCABULARY_REFRESH_SECS")?;
+        if vocabulary_refresh_secs == 0 {
+            anyhow::bail!("VOCABULARY_REFRESH_SECS must be at least 1");
+        }
+
+        let vector_max_distance = var("VECTOR_MAX_DISTANCE")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| {
+                v.trim()
+                    .parse::<f32>()
+                    .context("failed to parse VECTOR_MAX_DISTANCE")
+            })
+            .transpose()?;
+        if let Some(max) = vector_max_distance {
+            if !(0.0..=2.0).contains(&max) {
+                anyhow::bail!("VECTOR_MAX_DISTANCE must be between 0 and 2");
+            }
+        }
+
+        let turbopuffer_namespace =
+            var("TURBOPUFFER_NAMESPACE").unwrap_or_else(|_| "bufos".to_string());
+        let mut search_namespaces: Vec<String> = var("SEARCH_NAMESPACES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|ns| !ns.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !search_namespaces.contains(&turbopuffer_namespace) {
+            search_namespaces.insert(0, turbopuffer_namespace.clone());
+        }
+
         Ok(Config {
-            host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-            port: env::var("PORT")
+            host: var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+            port: var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .context("failed to parse PORT")?,
-            turbopuffer_api_key: env::var("TURBOPUFFER_API_KEY")
+            turbopuffer_api_key: var("TURBOPUFFER_API_KEY")
                 .context("TURBOPUFFER_API_KEY must be set")?,
-            turbopuffer_namespace: env::var("TURBOPUFFER_NAMESPACE")
-                .unwrap_or_else(|_| "bufos".to_string()),
-            voyage_api_key: env::var("VOYAGE_API_TOKEN")
-                .context("VOYAGE_API_TOKEN must be set")?,
+            turbopuffer_namespace,
+            search_namespaces,
+            fallback_namespace: var("FALLBACK_NAMESPACE").ok().filter(|ns| !ns.is_empty()),
+            turbopuffer_consistency,
+            vector_max_distance,
+            turbopuffer_timeout: match var("TURBOPUFFER_TIMEOUT_MS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse::<u64>()
+                .context("failed to parse TURBOPUFFER_TIMEOUT_MS")?
+            {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            // voyage isn't called when a local model embeds queries
+            voyage_api_key: match var("VOYAGE_API_TOKEN") {
+                Ok(key) => key,
+                Err(_) if local_model_dir.is_some() => String::new(),
+                Err(_) => anyhow::bail!("VOYAGE_API_TOKEN must be set"),
+            },
+            voyage_output_dimension,
+            voyage_hedge_delay: var("VOYAGE_HEDGE_DELAY_MS")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.trim().parse::<u64>())
+                .transpose()
+                .context("failed to parse VOYAGE_HEDGE_DELAY_MS")?
+                .map(Duration::from_millis),
+            voyage_timeout: match var("VOYAGE_TIMEOUT_MS")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse::<u64>()
+                .context("failed to parse VOYAGE_TIMEOUT_MS")?
+            {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            voyage_empty_retries: var("VOYAGE_EMPTY_RETRIES")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .context("failed to parse VOYAGE_EMPTY_RETRIES")?,
+            local_model_dir,
+            local_embedding_dimension,
+            expected_embedding_dim,
+            openai_api_key,
+            openai_embedding_model: var("OPENAI_EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+            openai_namespace: var("OPENAI_NAMESPACE").ok(),
+            openai_ensemble_weight,
+            embedding_models,
+            default_family_friendly: var("DEFAULT_FAMILY_FRIENDLY")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("failed to parse DEFAULT_FAMILY_FRIENDLY")?,
+            default_alpha,
+            bm25_prefix_match: var("BM25_PREFIX_MATCH")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse BM25_PREFIX_MATCH")?,
+            bm25_hyphen_mode,
+            bm25_min_term_length,
+            bm25_position_boost: var("BM25_POSITION_BOOST")
+                .unwrap_or_else(|_| "0.0".to_string())
+                .parse()
+                .context("failed to parse BM25_POSITION_BOOST")?,
+            bm25_coverage_weight,
+            exact_match_boost,
+            sticky_hysteresis,
+            candidate_cap,
+            keyword_normalization,
+            similarity_curve,
+            keyword_fields,
+            attribute_merge,
+            vocabulary_refresh_secs,
+            search_cache_max_age_secs: var("SEARCH_CACHE_MAX_AGE_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .context("failed to parse SEARCH_CACHE_MAX_AGE_SECS")?,
+            response_cache_ttl_secs: var("RESPONSE_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("failed to parse RESPONSE_CACHE_TTL_SECS")?,
+            cache_backend,
+            redis_url: var("REDIS_URL").ok(),
+            static_dir: var("STATIC_DIR").unwrap_or_else(|_| "./static".to_string()),
+            log_queries: var("LOG_QUERIES")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("failed to parse LOG_QUERIES")?,
+            query_normalization: var("QUERY_NORMALIZATION")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse QUERY_NORMALIZATION")?,
+            query_collapse_repeats: match var("QUERY_COLLAPSE_REPEATS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse::<usize>()
+                .context("failed to parse QUERY_COLLAPSE_REPEATS")?
+            {
+                0 => None,
+                keep => Some(keep),
+            },
+            language_detection: var("LANGUAGE_DETECTION")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("failed to parse LANGUAGE_DETECTION")?,
+            language_hints: var("LANGUAGE_HINTS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse LANGUAGE_HINTS")?,
+            remove_embedding_stopwords: var("REMOVE_EMBEDDING_STOPWORDS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse REMOVE_EMBEDDING_STOPWORDS")?,
+            embedding_stopwords: var("EMBEDDING_STOPWORDS")
+                .map(|list| {
+                    list.split(',')
+                        .map(str::trim)
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_else(|_| tokenize::STOPWORDS.iter().map(|s| s.to_string()).collect()),
+            filename_blend_weight,
+            long_query_pooling: var("LONG_QUERY_POOLING")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse LONG_QUERY_POOLING")?,
+            long_query_threshold,
+            post_processors,
+            max_returned_results: var("MAX_RETURNED_RESULTS")
+                .ok()
+                .map(|v| v.parse())
+                .transpose()
+                .context("failed to parse MAX_RETURNED_RESULTS")?,
+            max_response_bytes: var("MAX_RESPONSE_BYTES")
+                .ok()
+                .map(|v| v.parse())
+                .transpose()
+                .context("failed to parse MAX_RESPONSE_BYTES")?,
+            max_filter_patterns,
+            thumbnail_transform,
+            display_name_case,
+            https_urls,
+            image_rewrite,
+            embedding_cache_size: var("EMBEDDING_CACHE_SIZE")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .context("failed to parse EMBEDDING_CACHE_SIZE")?,
+            embedding_breaker_threshold: var("EMBEDDING_BREAKER_THRESHOLD")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .context("failed to parse EMBEDDING_BREAKER_THRESHOLD")?,
+            embedding_breaker_cooldown: Duration::from_secs(
+                var("EMBEDDING_BREAKER_COOLDOWN_SECS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .context("failed to parse EMBEDDING_BREAKER_COOLDOWN_SECS")?,
+            ),
+            query_history_size: var("QUERY_HISTORY_SIZE")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("failed to parse QUERY_HISTORY_SIZE")?,
+            popular_queries_path: var("POPULAR_QUERIES_PATH").ok(),
+            alpha_policy_path: var("ALPHA_POLICY_PATH").ok(),
+            blocked_ids_path: var("BLOCKED_IDS_PATH").ok(),
+            cluster_centroids_path: var("CLUSTER_CENTROIDS_PATH").ok(),
+            preload_concurrency: var("PRELOAD_CONCURRENCY")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()
+                .context("failed to parse PRELOAD_CONCURRENCY")?,
+            embed_batch_size,
+            embed_batch_concurrency: var("EMBED_BATCH_CONCURRENCY")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()
+                .context("failed to parse EMBED_BATCH_CONCURRENCY")?,
+            max_concurrent_searches: var("MAX_CONCURRENT_SEARCHES")
+                .unwrap_or_else(|_| "32".to_string())
+                .parse()
+                .context("failed to parse MAX_CONCURRENT_SEARCHES")?,
+            slow_query_ms: var("SLOW_QUERY_MS")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.trim().parse::<u64>())
+                .transpose()
+                .context("failed to parse SLOW_QUERY_MS")?,
+            keep_alive_secs: var("KEEP_ALIVE_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .context("failed to parse KEEP_ALIVE_SECS")?,
+            client_timeout_secs: var("CLIENT_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .context("failed to parse CLIENT_TIMEOUT_SECS")?,
+            enable_http2: var("ENABLE_HTTP2")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("failed to parse ENABLE_HTTP2")?,
+            admin_token: var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            api_keys: var("API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+            rate_limit_per_minute,
+            authenticated_rate_limit_per_minute,
+        })
+    }
+
+    /// the defaults, with placeholder api keys and nothing read from the environment
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self::from_vars(|key| match key {
+            "TURBOPUFFER_API_KEY" => Some("tpuf".to_string()),
+            "VOYAGE_API_TOKEN" => Some("voyage".to_string()),
+            _ => None,
         })
+        .unwrap()
     }
 }
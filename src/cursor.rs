@@ -0,0 +1,130 @@
+//! score cursors for stable pagination
+//!
+//! a cursor is the fused score and id of the last result on a page, base64-encoded
+//! (url-safe, unpadded). the next page keeps only candidates that rank below it in the
+//! usual order (score descending, ties by id), so bufos added or removed between
+//! requests don't shift later pages the way an offset would. the score is stored as
+//! its exact bits, so ties compare the same way on every page.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::cmp::Ordering;
+use thiserror::Error;
+
+/// format tag, so cursors from a future layout are rejected rather than misread
+const VERSION: &str = "v1";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CursorError {
+    #[error("cursor is not valid base64")]
+    Encoding,
+    #[error("cursor is malformed or from an older version; restart from the first page")]
+    Format,
+}
+
+/// the position after which the next page starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub score: f32,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn new(score: f32, id: impl Into<String>) -> Self {
+        Self {
+            score,
+            id: id.into(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{:08x}:{}",
+            VERSION,
+            self.score.to_bits(),
+            self.id
+        ))
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self, CursorError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim())
+            .map_err(|_| CursorError::Encoding)?;
+        let text = String::from_utf8(bytes).map_err(|_| CursorError::Format)?;
+
+        let mut parts = text.splitn(3, ':');
+        let (Some(VERSION), Some(bits), Some(id)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(CursorError::Format);
+        };
+        let score = u32::from_str_radix(bits, 16)
+            .map(f32::from_bits)
+            .map_err(|_| CursorError::Format)?;
+        if !score.is_finite() || id.is_empty() {
+            return Err(CursorError::Format);
+        }
+        Ok(Self::new(score, id))
+    }
+
+    /// whether a candidate with `score` and `id` ranks after this cursor
+    pub fn precedes(&self, score: f32, id: &str) -> bool {
+        match score.partial_cmp(&self.score) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => id > self.id.as_str(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for cursor in [
+            Cursor::new(0.8123456, "bufo-happy"),
+            Cursor::new(-0.25, "id:with:colons"),
+            Cursor::new(0.0, "ü"),
+        ] {
+            let encoded = cursor.encode();
+            assert!(!encoded.contains(['+', '/', '=']), "{}", encoded);
+            assert_eq!(Cursor::decode(&encoded), Ok(cursor));
+        }
+    }
+
+    #[test]
+    fn test_invalid_cursors() {
+        assert_eq!(Cursor::decode("not base64!"), Err(CursorError::Encoding));
+
+        let encode = |raw: &str| URL_SAFE_NO_PAD.encode(raw);
+        for raw in [
+            "v1:3f000000",
+            "v0:3f000000:a",
+            "v1:zz:a",
+            "v1:7fc00000:a",
+            "v1:3f000000:",
+        ] {
+            assert_eq!(
+                Cursor::decode(&encode(raw)),
+                Err(CursorError::Format),
+                "{}",
+                raw
+            );
+        }
+        assert_eq!(
+            Cursor::decode(&URL_SAFE_NO_PAD.encode([0xff, 0xfe])),
+            Err(CursorError::Format)
+        );
+    }
+
+    #[test]
+    fn test_precedes_follows_ranking_order() {
+        let cursor = Cursor::new(0.5, "m");
+        assert!(cursor.precedes(0.4, "a"));
+        assert!(cursor.precedes(0.5, "n"));
+        assert!(!cursor.precedes(0.5, "m"));
+        assert!(!cursor.precedes(0.5, "a"));
+        assert!(!cursor.precedes(0.6, "z"));
+    }
+}
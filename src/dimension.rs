@@ -0,0 +1,194 @@
+//! query embedding dimension check
+//!
+//! a model whose vectors don't match the namespace's (a 1024-dim model against a
+//! 1536-dim index) otherwise only shows up as a turbopuffer error. with
+//! `EXPECTED_EMBEDDING_DIM` set, the primary embedder's vectors are checked before they
+//! reach the store (or the cache), and a mismatch fails with both sizes named. the
+//! first successful embedding's dimension is logged either way.
+//!
+//! without an expected size, a provider silently changing a model's default dimension
+//! would desync query vectors from the stored ones. `DimensionTracker` remembers the
+//! last query embedding's size and warns (and counts `embedding.dimension_drift`) when
+//! the next one differs.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+use opentelemetry::metrics::Counter;
+use opentelemetry::KeyValue;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+fn drift_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("find-bufo")
+            .u64_counter("embedding.dimension_drift")
+            .with_description("query embeddings whose size differed from the previous one")
+            .init()
+    })
+}
+
+/// the last query embedding dimension seen in this process (cheap to clone)
+#[derive(Debug, Clone, Default)]
+pub struct DimensionTracker {
+    /// 0 until the first embedding
+    last: Arc<AtomicUsize>,
+}
+
+impl DimensionTracker {
+    /// record `dimension`, returning the previous one when it differs
+    pub fn observe(&self, dimension: usize) -> Option<usize> {
+        let previous = self.last.swap(dimension, Ordering::Relaxed);
+        (previous != 0 && previous != dimension).then_some(previous)
+    }
+
+    /// record `model`'s latest query embedding size, reporting a change
+    pub fn check(&self, model: &'static str, dimension: usize) {
+        if let Some(previous) = self.observe(dimension) {
+            logfire::warn!(
+                "embedding dimension changed",
+                model = model,
+                previous = previous as i64,
+                dimension = dimension as i64
+            );
+            drift_counter().add(1, &[KeyValue::new("model", model)]);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn last(&self) -> Option<usize> {
+        Some(self.last.load(Ordering::Relaxed)).filter(|&d| d != 0)
+    }
+}
+
+/// an embedder whose vectors must have `expected` dimensions
+#[derive(Clone)]
+pub struct DimensionChecked<E> {
+    inner: E,
+    /// `None` skips the check
+    expected: Option<usize>,
+    /// dimension of the first successful embedding, shared so it's logged once
+    detected: Option<Arc<OnceLock<usize>>>,
+}
+
+impl<E: Embedder> DimensionChecked<E> {
+    pub fn new(inner: E, expected: Option<usize>, detected: Arc<OnceLock<usize>>) -> Self {
+        Self {
+            inner,
+            expected,
+            detected: Some(detected),
+        }
+    }
+
+    /// pass vectors through as-is (ensemble members, whose namespaces have their own size)
+    pub fn unchecked(inner: E) -> Self {
+        Self {
+            inner,
+            expected: None,
+            detected: None,
+        }
+    }
+
+    /// pass `embedding` through, or fail on a mismatch; logs the first dimension seen
+    fn check(&self, embedding: Vec<f32>) -> Result<Vec<f32>, EmbeddingError> {
+        if let Some(expected) = self.expected.filter(|&e| e != embedding.len()) {
+            return Err(EmbeddingError::DimensionMismatch {
+                model: self.inner.name(),
+                expected,
+                actual: embedding.len(),
+            });
+        }
+        if let Some(detected) = &self.detected {
+            if detected.set(embedding.len()).is_ok() {
+                logfire::info!(
+                    "embedding dimension detected",
+                    model = self.inner.name(),
+                    dimension = embedding.len() as i64
+                );
+            }
+        }
+        Ok(embedding)
+    }
+}
+
+impl<E: Embedder> Embedder for DimensionChecked<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        let embedding = self.inner.embed_as(text, input_type).await?;
+        self.check(embedding)
+    }
+
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let embeddings = self.inner.embed_batch(texts, input_type).await?;
+        embeddings.into_iter().map(|e| self.check(e)).collect()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedEmbedder(usize);
+
+    impl Embedder for FixedEmbedder {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(vec![0.5; self.0])
+        }
+
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mismatch_names_both_sizes() {
+        let detected = Arc::new(OnceLock::new());
+        let embedder = DimensionChecked::new(FixedEmbedder(1024), Some(1536), detected.clone());
+
+        let err = embedder.embed("happy").await.unwrap_err();
+        assert!(matches!(
+            err,
+            EmbeddingError::DimensionMismatch {
+                expected: 1536,
+                actual: 1024,
+                ..
+            }
+        ));
+        let message = err.to_string();
+        assert!(message.contains("1024") && message.contains("1536"), "{}", message);
+        // a rejected vector isn't recorded as the detected dimension
+        assert_eq!(detected.get(), None);
+    }
+
+    #[test]
+    fn test_tracker_reports_changes_only() {
+        let tracker = DimensionTracker::default();
+        assert_eq!(tracker.last(), None);
+        assert_eq!(tracker.observe(1024), None);
+        assert_eq!(tracker.observe(1024), None);
+        assert_eq!(tracker.observe(512), Some(1024));
+        assert_eq!(tracker.observe(512), None);
+        assert_eq!(tracker.last(), Some(512));
+    }
+
+    #[tokio::test]
+    async fn test_matching_dimension_is_recorded() {
+        let detected = Arc::new(OnceLock::new());
+        let embedder = DimensionChecked::new(FixedEmbedder(1024), Some(1024), detected.clone());
+        assert_eq!(embedder.embed("happy").await.unwrap().len(), 1024);
+        assert_eq!(detected.get(), Some(&1024));
+
+        let unchecked = DimensionChecked::unchecked(FixedEmbedder(3));
+        assert_eq!(unchecked.embed("happy").await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,112 @@
+//! human-readable display names
+//!
+//! bufo names are filenames like `bufo-jumping-on-the-bed.gif`. with `pretty_names`, each
+//! result also gets a `display_name` with the `bufo-` prefix and extension stripped and
+//! hyphens turned into spaces, cased per `DISPLAY_NAME_CASE` ("Jumping on the bed" in
+//! sentence case, "Jumping On The Bed" in title case). `name` itself is left alone.
+
+use crate::search::BufoResult;
+use std::str::FromStr;
+
+/// how the words of a display name are capitalized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameCase {
+    /// first word capitalized
+    #[default]
+    Sentence,
+    /// every word capitalized
+    Title,
+}
+
+impl FromStr for NameCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "sentence" => Ok(NameCase::Sentence),
+            "title" => Ok(NameCase::Title),
+            other => Err(format!(
+                "unknown name case: {} (use sentence or title)",
+                other
+            )),
+        }
+    }
+}
+
+/// uppercase the first character of `word`
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `name` as a person would write it; falls back to `name` when nothing is left
+pub fn display_name(name: &str, case: NameCase) -> String {
+    let stem = name
+        .rsplit_once('.')
+        .filter(|(stem, ext)| !stem.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map_or(name, |(stem, _)| stem);
+    let stem = stem.strip_prefix("bufo-").unwrap_or(stem);
+
+    let words: Vec<String> = stem
+        .split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .map(|(i, word)| match case {
+            NameCase::Title => capitalized(word),
+            NameCase::Sentence if i == 0 => capitalized(word),
+            NameCase::Sentence => word.to_string(),
+        })
+        .collect();
+    if words.is_empty() {
+        name.to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+/// set `display_name` on each result
+pub fn fill_display_names(results: &mut [BufoResult], case: NameCase) {
+    for result in results {
+        result.display_name = Some(display_name(&result.name, case));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_name_strips_prefix_and_extension() {
+        assert_eq!(
+            display_name("bufo-jumping-on-the-bed", NameCase::Sentence),
+            "Jumping on the bed"
+        );
+        assert_eq!(
+            display_name("bufo-jumping-on-the-bed.gif", NameCase::Title),
+            "Jumping On The Bed"
+        );
+        assert_eq!(display_name("bufo-hmm.png", NameCase::Sentence), "Hmm");
+    }
+
+    #[test]
+    fn test_display_name_without_prefix() {
+        assert_eq!(display_name("happy-bufo", NameCase::Sentence), "Happy bufo");
+        assert_eq!(
+            display_name("just--the_facts", NameCase::Title),
+            "Just The Facts"
+        );
+        assert_eq!(display_name("bufo", NameCase::Sentence), "Bufo");
+        // nothing but the prefix keeps the raw name
+        assert_eq!(display_name("bufo-", NameCase::Sentence), "bufo-");
+    }
+
+    #[test]
+    fn test_name_case_parses() {
+        assert_eq!("title".parse::<NameCase>(), Ok(NameCase::Title));
+        assert_eq!(" sentence ".parse::<NameCase>(), Ok(NameCase::Sentence));
+        assert!("upper".parse::<NameCase>().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! raw query embeddings for internal tools
+//!
+//! `POST /api/embed` returns the configured embedder's vector for a piece of text, so
+//! tools can run their own analysis without holding the voyage key. embeddings cost
+//! money, so this is an admin endpoint (see `admin`). `POST /api/embed/batch` does the
+//! same for many texts at once, chunked per `batch`.
+
+use crate::admin::require_admin;
+use crate::batch::embed_chunked;
+use crate::providers::{Embedder, InputType};
+use crate::reload::CurrentConfig;
+use crate::search::{build_embedder, check_query_text};
+use crate::state::AppState;
+use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct EmbedRequest {
+    pub text: String,
+    /// "query" (the default) or "document", to compare against indexed bufos
+    #[serde(default)]
+    pub input_type: Option<InputType>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbedResponse {
+    pub model: &'static str,
+    pub dimensions: usize,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchEmbedRequest {
+    pub texts: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchEmbedResponse {
+    pub model: &'static str,
+    pub dimensions: usize,
+    /// one per text, in request order
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+/// body of a 502 when some chunks failed
+#[derive(Debug, Serialize)]
+pub struct BatchEmbedFailure {
+    pub error: String,
+    /// indices into `texts` that didn't get an embedding
+    pub failed_inputs: Vec<usize>,
+}
+
+/// POST /api/embed handler
+///
+/// the text is embedded as a search query (the same vector `/api/search` would use),
+/// or with `input_type: "document"` the way the bufos themselves were indexed.
+pub async fn embed(
+    req: HttpRequest,
+    body: web::Json<EmbedRequest>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if let Err(response) = require_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    if let Some(error) = check_query_text("text", &body.text) {
+        return Err(actix_web::error::ErrorBadRequest(error.message));
+    }
+
+    let embedder = build_embedder(&config, &state);
+    let embedding = match body.input_type.unwrap_or(InputType::Query) {
+        InputType::Query => embedder.embed_query(&body.text).await,
+        InputType::Document => embedder.embed_document(&body.text).await,
+    }
+    .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+
+    logfire::info!(
+        "query embedded for api",
+        model = embedder.name(),
+        input_type = body.input_type.unwrap_or(InputType::Query).as_str(),
+        dimensions = embedding.len() as i64
+    );
+
+    Ok(HttpResponse::Ok().json(EmbedResponse {
+        model: embedder.name(),
+        dimensions: embedding.len(),
+        embedding,
+    }))
+}
+
+/// POST /api/embed/batch handler
+///
+/// texts are embedded as search queries, `EMBED_BATCH_SIZE` per provider request. if any
+/// chunk fails the response is a 502 listing the texts that weren't embedded.
+pub async fn embed_batch(
+    req: HttpRequest,
+    body: web::Json<BatchEmbedRequest>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if let Err(response) = require_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    if body.texts.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest("texts must not be empty"));
+    }
+    for (i, text) in body.texts.iter().enumerate() {
+        if let Some(error) = check_query_text("texts", text) {
+            return Err(actix_web::error::ErrorBadRequest(format!(
+                "texts[{}]: {}",
+                i, error.message
+            )));
+        }
+    }
+
+    let embedder = build_embedder(&config, &state);
+    let embeddings = match embed_chunked(
+        &embedder,
+        &body.texts,
+        InputType::Query,
+        config.embed_batch_size,
+        config.embed_batch_concurrency,
+    )
+    .await
+    {
+        Ok(embeddings) => embeddings,
+        Err(e) => {
+            let error = e.to_string();
+            logfire::warn!(
+                "batch embedding failed",
+                model = embedder.name(),
+                total = e.total as i64,
+                failed_count = e.failed_inputs().len() as i64,
+                error = &error
+            );
+            return Ok(HttpResponse::BadGateway().json(BatchEmbedFailure {
+                error,
+                failed_inputs: e.failed_inputs(),
+            }));
+        }
+    };
+
+    let dimensions = embeddings.first().map_or(0, Vec::len);
+    logfire::info!(
+        "batch embedded for api",
+        model = embedder.name(),
+        texts = embeddings.len() as i64,
+        dimensions = dimensions as i64
+    );
+
+    Ok(HttpResponse::Ok().json(BatchEmbedResponse {
+        model: embedder.name(),
+        dimensions,
+        embeddings,
+    }))
+}
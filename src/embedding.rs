@@ -1,20 +1,43 @@
 //! voyage AI embedding implementation
 //!
-//! implements the `Embedder` trait for voyage's multimodal-3 model.
+//! implements the `Embedder` trait for voyage's multimodal-3 model, plus
+//! `EmbeddingProvider` for dispatching across the configured backends.
 
-use crate::providers::{Embedder, EmbeddingError};
+use crate::openai::OpenAiEmbedder;
+use crate::providers::{Embedder, EmbeddingError, InputType};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const VOYAGE_API_URL: &str = "https://api.voyageai.com/v1/multimodalembeddings";
 const VOYAGE_MODEL: &str = "voyage-multimodal-3";
 
+/// most inputs voyage accepts in one multimodal request
+pub const VOYAGE_MAX_BATCH: usize = 1000;
+
+/// matryoshka dimensions voyage can truncate to (the model's native size is 1024)
+pub const SUPPORTED_OUTPUT_DIMENSIONS: &[usize] = &[256, 512, 1024, 2048];
+
+/// check a requested output dimension against the sizes voyage supports
+pub fn validate_output_dimension(dimension: usize) -> Result<usize, String> {
+    if SUPPORTED_OUTPUT_DIMENSIONS.contains(&dimension) {
+        Ok(dimension)
+    } else {
+        Err(format!(
+            "unsupported output dimension {} (expected one of {:?})",
+            dimension, SUPPORTED_OUTPUT_DIMENSIONS
+        ))
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct VoyageRequest {
     inputs: Vec<MultimodalInput>,
     model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     input_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<usize>,
 }
 
 #[derive(Debug, Serialize)]
@@ -40,12 +63,17 @@ struct VoyageEmbeddingData {
 
 /// voyage AI multimodal embedding client
 ///
-/// uses the voyage-multimodal-3 model which produces 1024-dimensional vectors.
-/// designed for early fusion of text and image content.
+/// uses the voyage-multimodal-3 model which produces 1024-dimensional vectors unless
+/// a smaller `output_dimension` is requested. designed for early fusion of text and
+/// image content.
 #[derive(Clone)]
 pub struct VoyageEmbedder {
     client: Client,
     api_key: String,
+    model: &'static str,
+    output_dimension: Option<usize>,
+    timeout: Option<Duration>,
+    url: String,
 }
 
 impl VoyageEmbedder {
@@ -53,29 +81,67 @@ impl VoyageEmbedder {
         Self {
             client: Client::new(),
             api_key,
+            model: VOYAGE_MODEL,
+            output_dimension: None,
+            timeout: None,
+            url: VOYAGE_API_URL.to_string(),
         }
     }
-}
 
-impl Embedder for VoyageEmbedder {
-    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
-        let request = VoyageRequest {
-            inputs: vec![MultimodalInput {
-                content: vec![ContentSegment::Text {
-                    text: text.to_string(),
-                }],
-            }],
-            model: VOYAGE_MODEL.to_string(),
-            input_type: Some("query".to_string()),
-        };
+    /// embed with another multimodal model (see `models::VOYAGE_MODELS`)
+    pub fn with_model(mut self, model: &'static str) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// give up on a request (including reading the response) after `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_url(mut self, url: String) -> Self {
+        self.url = url;
+        self
+    }
+
+    /// request truncated vectors; the namespace must be indexed at the same size
+    pub fn with_output_dimension(mut self, output_dimension: Option<usize>) -> Self {
+        self.output_dimension = output_dimension;
+        self
+    }
+
+    fn request(&self, text: &str, input_type: InputType) -> VoyageRequest {
+        self.batch_request(std::slice::from_ref(&text), input_type)
+    }
 
-        let response = self
+    fn batch_request(&self, texts: &[&str], input_type: InputType) -> VoyageRequest {
+        VoyageRequest {
+            inputs: texts
+                .iter()
+                .map(|text| MultimodalInput {
+                    content: vec![ContentSegment::Text {
+                        text: text.to_string(),
+                    }],
+                })
+                .collect(),
+            model: self.model.to_string(),
+            input_type: Some(input_type.as_str().to_string()),
+            output_dimension: self.output_dimension,
+        }
+    }
+
+    async fn send(&self, request: &VoyageRequest) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut builder = self
             .client
-            .post(VOYAGE_API_URL)
+            .post(&self.url)
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
-            .await?;
+            .json(request);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = builder.send().await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -87,16 +153,210 @@ impl Embedder for VoyageEmbedder {
             EmbeddingError::Other(anyhow::anyhow!("failed to parse response: {}", e))
         })?;
 
-        voyage_response
+        Ok(voyage_response
             .data
             .into_iter()
-            .next()
             .map(|d| d.embedding)
+            .collect())
+    }
+}
+
+impl Embedder for VoyageEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        self.send(&self.request(text, input_type))
+            .await?
+            .into_iter()
+            .next()
             .ok_or(EmbeddingError::EmptyResponse)
     }
 
+    /// one request for all of `texts`; voyage allows up to `VOYAGE_MAX_BATCH` inputs
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let embeddings = self.send(&self.batch_request(&texts, input_type)).await?;
+        match embeddings.len() {
+            0 if !texts.is_empty() => Err(EmbeddingError::EmptyResponse),
+            n if n != texts.len() => Err(EmbeddingError::Other(anyhow::anyhow!(
+                "voyage returned {} embeddings for {} inputs",
+                n,
+                texts.len()
+            ))),
+            _ => Ok(embeddings),
+        }
+    }
+
     fn name(&self) -> &'static str {
-        "voyage-multimodal-3"
+        self.model
     }
 }
 
+/// any of the configured embedding backends
+///
+/// `Embedder` uses `impl Future` returns so it isn't object safe; this enum lets
+/// ensemble members with different backends share one type.
+#[derive(Clone)]
+pub enum EmbeddingProvider {
+    Voyage(VoyageEmbedder),
+    OpenAi(OpenAiEmbedder),
+    #[cfg(feature = "local-embeddings")]
+    Local(crate::local::LocalEmbedder),
+}
+
+impl Embedder for EmbeddingProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        match self {
+            EmbeddingProvider::Voyage(e) => e.embed(text).await,
+            EmbeddingProvider::OpenAi(e) => e.embed(text).await,
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProvider::Local(e) => e.embed(text).await,
+        }
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        match self {
+            EmbeddingProvider::Voyage(e) => e.embed_as(text, input_type).await,
+            EmbeddingProvider::OpenAi(e) => e.embed_as(text, input_type).await,
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProvider::Local(e) => e.embed_as(text, input_type).await,
+        }
+    }
+
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        match self {
+            EmbeddingProvider::Voyage(e) => e.embed_batch(texts, input_type).await,
+            EmbeddingProvider::OpenAi(e) => e.embed_batch(texts, input_type).await,
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProvider::Local(e) => e.embed_batch(texts, input_type).await,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EmbeddingProvider::Voyage(e) => e.name(),
+            EmbeddingProvider::OpenAi(e) => e.name(),
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProvider::Local(e) => e.name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_output_dimension() {
+        assert_eq!(validate_output_dimension(512), Ok(512));
+        assert_eq!(validate_output_dimension(1024), Ok(1024));
+        assert!(validate_output_dimension(300).is_err());
+        assert!(validate_output_dimension(0).is_err());
+    }
+
+    #[test]
+    fn test_output_dimension_omitted_when_unset() {
+        let request = VoyageRequest {
+            inputs: vec![],
+            model: VOYAGE_MODEL.to_string(),
+            input_type: None,
+            output_dimension: None,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("output_dimension").is_none());
+
+        let request = VoyageRequest {
+            output_dimension: Some(512),
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["output_dimension"], 512);
+    }
+
+    #[test]
+    fn test_input_type_serialized_per_role() {
+        let embedder = VoyageEmbedder::new("key".to_string());
+
+        let query = serde_json::to_value(embedder.request("happy", InputType::Query)).unwrap();
+        assert_eq!(query["input_type"], "query");
+
+        let document =
+            serde_json::to_value(embedder.request("happy", InputType::Document)).unwrap();
+        assert_eq!(document["input_type"], "document");
+    }
+
+    #[test]
+    fn test_batch_request_keeps_input_order() {
+        let embedder = VoyageEmbedder::new("key".to_string());
+        let request = serde_json::to_value(
+            embedder.batch_request(&["happy", "sad", "angry"], InputType::Document),
+        )
+        .unwrap();
+
+        let texts: Vec<&str> = request["inputs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|input| input["content"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["happy", "sad", "angry"]);
+    }
+
+    /// a local server that answers every request with `body` after `delay`
+    async fn slow_server(delay: Duration, body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.read(&mut [0u8; 4096]).await;
+                    tokio::time::sleep(delay).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_requests_honor_the_timeout() {
+        let url = slow_server(
+            Duration::from_millis(300),
+            r#"{"data":[{"embedding":[0.5]}]}"#,
+        )
+        .await;
+        let embedder = |timeout| {
+            VoyageEmbedder::new("key".to_string())
+                .with_url(url.clone())
+                .with_timeout(Some(timeout))
+        };
+
+        match embedder(Duration::from_millis(50)).embed("happy").await {
+            Err(EmbeddingError::Request(e)) => assert!(e.is_timeout(), "{}", e),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        let embedding = embedder(Duration::from_secs(5))
+            .embed("happy")
+            .await
+            .unwrap();
+        assert_eq!(embedding, vec![0.5]);
+    }
+}
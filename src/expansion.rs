@@ -0,0 +1,92 @@
+//! pseudo-relevance feedback for keyword search
+//!
+//! with `pseudo_relevance=true`, the names of the top semantic neighbours are mined for
+//! terms the query doesn't already have, and BM25 runs again with those terms appended.
+//! this helps queries whose wording differs from bufo filenames ("glad" finding
+//! "bufo-happy"), at the cost of one more keyword round trip that can only start once
+//! the vector search has finished, so it's off by default.
+
+use crate::tokenize::Tokenizer;
+use std::collections::{HashMap, HashSet};
+
+/// semantic neighbours whose names are mined for terms
+pub const FEEDBACK_RESULTS: usize = 5;
+
+/// most terms appended to the keyword query
+pub const MAX_EXPANSION_TERMS: usize = 4;
+
+/// terms in every name that say nothing about a particular bufo
+const NAME_NOISE: &[&str] = &["bufo", "bufos"];
+
+/// terms from `names` worth adding to `query`, most common first
+///
+/// query terms, stopwords, numbers and "bufo" itself are skipped. terms are ranked by
+/// how many names contain them, ties by first appearance, and capped at `max_terms`.
+pub fn expansion_terms(query: &str, names: &[&str], max_terms: usize) -> Vec<String> {
+    let tokenizer = Tokenizer {
+        remove_stopwords: true,
+        ..Tokenizer::default()
+    };
+    let query_terms: HashSet<String> = tokenizer.tokenize(query).into_iter().collect();
+
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut next = 0;
+    for name in names {
+        let mut seen = HashSet::new();
+        for term in tokenizer.tokenize(name) {
+            if !seen.insert(term.clone())
+                || query_terms.contains(&term)
+                || NAME_NOISE.contains(&term.as_str())
+                || term.chars().all(|c| c.is_ascii_digit())
+            {
+                continue;
+            }
+            let entry = counts.entry(term).or_insert((0, next));
+            entry.0 += 1;
+            next += 1;
+        }
+    }
+
+    let mut ranked: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+    ranked
+        .into_iter()
+        .take(max_terms)
+        .map(|(term, _)| term)
+        .collect()
+}
+
+/// the keyword query with `terms` appended, or `None` when there are none
+pub fn expanded_query(query: &str, terms: &[String]) -> Option<String> {
+    (!terms.is_empty()).then(|| format!("{} {}", query.trim(), terms.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expansion_terms_ranks_shared_terms_first() {
+        let names = [
+            "bufo-happy-dance",
+            "bufo-dance-party",
+            "happy-bufo-jumping-on-the-bed",
+        ];
+        let terms = expansion_terms("happy", &names, 4);
+        assert_eq!(terms, vec!["dance", "party", "jumping", "bed"]);
+    }
+
+    #[test]
+    fn test_expansion_terms_skips_noise_and_caps() {
+        let names = ["bufo-2-of-the-bufos", "bufo-sad", "bufo-cry", "bufo-sob"];
+        assert_eq!(expansion_terms("Sad bufo", &names, 2), vec!["cry", "sob"]);
+        assert!(expansion_terms("happy", &[], 4).is_empty());
+    }
+
+    #[test]
+    fn test_expanded_query() {
+        let terms = vec!["dance".to_string(), "party".to_string()];
+        assert_eq!(expanded_query(" happy ", &terms).as_deref(), Some("happy dance party"));
+        assert_eq!(expanded_query("happy", &[]), None);
+    }
+}
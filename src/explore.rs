@@ -0,0 +1,85 @@
+//! score-weighted sampling for exploratory ("shuffle") searches
+//!
+//! with `explore=<temperature>`, the returned results are drawn without replacement from
+//! the candidates that pass `min_score`, each with probability proportional to
+//! `exp(score / temperature)` among those still undrawn. low temperatures stay close to
+//! the strict ranking; high ones approach a uniform shuffle of the pool. results come
+//! back in draw order.
+
+use rand::Rng;
+
+/// draw up to `k` items, softmax-weighted by `score` at `temperature`
+///
+/// uses the gumbel-top-k trick: perturbing each `score / temperature` with independent
+/// gumbel noise and keeping the `k` largest is the same as drawing one at a time from
+/// the softmax over what's left. `temperature` must be positive.
+pub fn sample_by_score<T, R: Rng + ?Sized>(
+    items: Vec<T>,
+    score: impl Fn(&T) -> f32,
+    k: usize,
+    temperature: f32,
+    rng: &mut R,
+) -> Vec<T> {
+    let mut keyed: Vec<(f64, T)> = items
+        .into_iter()
+        .map(|item| {
+            // gen() is in [0, 1); flipping it keeps ln(u) finite
+            let u: f64 = 1.0 - rng.gen::<f64>();
+            let gumbel = -(-u.ln()).ln();
+            (score(&item) as f64 / temperature as f64 + gumbel, item)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    keyed.into_iter().take(k).map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn draw(scores: &[f32], k: usize, temperature: f32, seed: u64) -> Vec<usize> {
+        let items: Vec<usize> = (0..scores.len()).collect();
+        let mut rng = StdRng::seed_from_u64(seed);
+        sample_by_score(items, |&i| scores[i], k, temperature, &mut rng)
+    }
+
+    #[test]
+    fn test_sampling_is_deterministic_per_seed() {
+        let scores = [0.9, 0.8, 0.5, 0.4, 0.1];
+        assert_eq!(draw(&scores, 3, 0.5, 7), draw(&scores, 3, 0.5, 7));
+
+        let sample = draw(&scores, 3, 0.5, 7);
+        assert_eq!(sample.len(), 3);
+        let mut unique = sample.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 3, "drawn without replacement: {:?}", sample);
+        assert_eq!(draw(&scores, 10, 0.5, 7).len(), scores.len());
+    }
+
+    #[test]
+    fn test_low_temperature_keeps_ranking() {
+        let scores = [0.9, 0.8, 0.5, 0.4, 0.1];
+        for seed in 0..20 {
+            assert_eq!(draw(&scores, 3, 0.001, seed), vec![0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_draws_follow_score_weights() {
+        // at temperature 1, the first draw picks index 0 with p = e^2 / (e^2 + 1) ≈ 0.88
+        let scores = [2.0, 0.0];
+        let firsts = (0..1000)
+            .filter(|&seed| draw(&scores, 1, 1.0, seed) == vec![0])
+            .count();
+        assert!((830..=930).contains(&firsts), "index 0 drawn first {} times", firsts);
+
+        // a high temperature is close to a fair coin
+        let firsts = (0..1000)
+            .filter(|&seed| draw(&scores, 1, 100.0, seed) == vec![0])
+            .count();
+        assert!((430..=570).contains(&firsts), "index 0 drawn first {} times", firsts);
+    }
+}
@@ -0,0 +1,70 @@
+//! tag facet counts for result drill-down
+//!
+//! bufos may carry a `tags` attribute, stored either as a JSON array (which arrives as
+//! its JSON text, e.g. `["animal","reaction"]`) or as a comma-separated string. facets
+//! count how many candidates carry each tag; a bufo counts once per tag.
+
+use std::collections::{HashMap, HashSet};
+
+/// parse a `tags` attribute in either format, dropping blanks and duplicates
+pub fn parse_tags(raw: &str) -> Vec<String> {
+    let raw = raw.trim();
+    let tags: Vec<String> = if raw.starts_with('[') {
+        serde_json::from_str(raw).unwrap_or_default()
+    } else {
+        raw.split(',').map(str::to_string).collect()
+    };
+
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.clone()))
+        .collect()
+}
+
+/// tag → number of items carrying it
+pub fn facet_counts<'a>(tag_lists: impl IntoIterator<Item = &'a [String]>) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for tags in tag_lists {
+        for tag in tags {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags_comma_separated() {
+        assert_eq!(parse_tags("animal, reaction,,animal "), vec!["animal", "reaction"]);
+        assert!(parse_tags("  ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tags_json_array() {
+        assert_eq!(
+            parse_tags(r#"["animal", " reaction", "", "animal"]"#),
+            vec!["animal", "reaction"]
+        );
+        assert!(parse_tags("[]").is_empty());
+        // not an array of strings
+        assert!(parse_tags("[1, 2]").is_empty());
+    }
+
+    #[test]
+    fn test_facet_counts() {
+        let lists = [
+            parse_tags("animal,reaction"),
+            parse_tags(r#"["animal"]"#),
+            parse_tags(""),
+        ];
+        let counts = facet_counts(lists.iter().map(Vec::as_slice));
+
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["animal"], 2);
+        assert_eq!(counts["reaction"], 1);
+    }
+}
@@ -2,7 +2,94 @@
 //!
 //! filters are predicates that can be combined to create complex filtering logic.
 
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+/// compiled size allowed for one exclude/include pattern, in bytes; well under the
+/// regex crate's 10 MiB default, so a request can't make us build huge automata
+pub const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+/// why patterns were rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternProblem {
+    /// not valid regex
+    Invalid,
+    /// valid, but compiles past `PATTERN_SIZE_LIMIT`
+    Oversized,
+}
+
+impl std::fmt::Display for PatternProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PatternProblem::Invalid => "invalid",
+            PatternProblem::Oversized => "oversized",
+        })
+    }
+}
+
+/// exclude/include patterns that failed to compile
+#[derive(Debug, thiserror::Error)]
+#[error("{problem} {field} patterns: {}", .patterns.join(", "))]
+pub struct FilterError {
+    /// "exclude" or "include"
+    pub field: &'static str,
+    pub problem: PatternProblem,
+    pub patterns: Vec<String>,
+}
+
+/// the non-empty patterns in a comma-separated list
+pub fn split_patterns(pattern_str: &str) -> impl Iterator<Item = &str> {
+    pattern_str
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+}
+
+/// `pattern` compiled within the size limit, or `None`
+fn lenient_regex(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .build()
+        .ok()
+}
+
+/// compile comma-separated patterns, collecting the ones that aren't valid regex or
+/// are too large
+///
+/// patterns are unanchored; use `^`/`$` to match the whole bufo name.
+fn compile_patterns(
+    pattern_str: &str,
+    case_insensitive: bool,
+    field: &'static str,
+) -> Result<Vec<Regex>, FilterError> {
+    let mut compiled = Vec::new();
+    let mut invalid = Vec::new();
+    let mut oversized = Vec::new();
+    for pattern in split_patterns(pattern_str) {
+        match RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .size_limit(PATTERN_SIZE_LIMIT)
+            .build()
+        {
+            Ok(regex) => compiled.push(regex),
+            Err(regex::Error::CompiledTooBig(_)) => oversized.push(pattern.to_string()),
+            Err(_) => invalid.push(pattern.to_string()),
+        }
+    }
+
+    let (problem, patterns) = if !invalid.is_empty() {
+        (PatternProblem::Invalid, invalid)
+    } else if !oversized.is_empty() {
+        (PatternProblem::Oversized, oversized)
+    } else {
+        return Ok(compiled);
+    };
+    Err(FilterError {
+        field,
+        problem,
+        patterns,
+    })
+}
 
 /// a single search result that can be filtered
 pub trait Filterable {
@@ -16,6 +103,7 @@ pub trait Filter<T: Filterable>: Send + Sync {
 }
 
 /// filters out inappropriate content based on a blocklist
+#[derive(Clone)]
 struct BlocklistFilter {
     blocklist: Vec<&'static str>,
 }
@@ -40,17 +128,15 @@ impl<T: Filterable> Filter<T> for BlocklistFilter {
 }
 
 /// filters out items matching any of the given regex patterns
+#[derive(Clone)]
 struct ExcludePatternFilter {
     patterns: Vec<Regex>,
 }
 
 impl ExcludePatternFilter {
     fn from_comma_separated(pattern_str: &str) -> Self {
-        let patterns = pattern_str
-            .split(',')
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty())
-            .filter_map(|p| Regex::new(p).ok())
+        let patterns = split_patterns(pattern_str)
+            .filter_map(lenient_regex)
             .collect();
 
         Self { patterns }
@@ -67,7 +153,35 @@ impl<T: Filterable> Filter<T> for ExcludePatternFilter {
     }
 }
 
+/// why the content filter rejected an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    Blocklist,
+    ExcludePattern,
+}
+
+/// rejection counts by reason, aggregated over a candidate list
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RejectionCounts {
+    pub rejected_by_blocklist: usize,
+    pub rejected_by_exclude: usize,
+}
+
+impl RejectionCounts {
+    pub fn record(&mut self, reason: RejectionReason) {
+        match reason {
+            RejectionReason::Blocklist => self.rejected_by_blocklist += 1,
+            RejectionReason::ExcludePattern => self.rejected_by_exclude += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.rejected_by_blocklist + self.rejected_by_exclude
+    }
+}
+
 /// combined filter that handles family-friendly mode and include/exclude patterns
+#[derive(Clone)]
 pub struct ContentFilter {
     family_friendly: bool,
     blocklist: BlocklistFilter,
@@ -86,13 +200,7 @@ impl ContentFilter {
             .unwrap_or_else(ExcludePatternFilter::empty);
 
         let include_patterns: Vec<Regex> = include_str
-            .map(|s| {
-                s.split(',')
-                    .map(|p| p.trim())
-                    .filter(|p| !p.is_empty())
-                    .filter_map(|p| Regex::new(p).ok())
-                    .collect()
-            })
+            .map(|s| split_patterns(s).filter_map(lenient_regex).collect())
             .unwrap_or_default();
 
         Self {
@@ -103,6 +211,35 @@ impl ContentFilter {
         }
     }
 
+    /// strict constructor: invalid patterns are an error instead of being skipped
+    pub fn try_new(
+        family_friendly: bool,
+        exclude_str: Option<&str>,
+        include_str: Option<&str>,
+        case_insensitive: bool,
+    ) -> Result<Self, FilterError> {
+        let exclude = exclude_str
+            .map(|s| compile_patterns(s, case_insensitive, "exclude"))
+            .transpose()?
+            .unwrap_or_default();
+        let include_patterns = include_str
+            .map(|s| compile_patterns(s, case_insensitive, "include"))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            family_friendly,
+            blocklist: BlocklistFilter::inappropriate_bufos(),
+            exclude: ExcludePatternFilter { patterns: exclude },
+            include_patterns,
+        })
+    }
+
+    /// names hidden in family-friendly mode (substring matches)
+    pub fn blocklist(&self) -> &[&'static str] {
+        &self.blocklist.blocklist
+    }
+
     pub fn exclude_pattern_count(&self) -> usize {
         self.exclude.patterns.len()
     }
@@ -115,23 +252,32 @@ impl ContentFilter {
             .collect::<Vec<_>>()
             .join(",")
     }
-}
 
-impl<T: Filterable> Filter<T> for ContentFilter {
-    fn matches(&self, item: &T) -> bool {
+    /// why `item` would be rejected, or `None` when it's kept
+    pub fn check<T: Filterable>(&self, item: &T) -> Option<RejectionReason> {
         // check family-friendly blocklist
         if self.family_friendly && !self.blocklist.matches(item) {
-            return false;
+            return Some(RejectionReason::Blocklist);
         }
 
         // check if explicitly included (overrides exclude)
         let matches_include = self.include_patterns.iter().any(|p| p.is_match(item.name()));
         if matches_include {
-            return true;
+            return None;
         }
 
         // check exclude patterns
-        self.exclude.matches(item)
+        if self.exclude.matches(item) {
+            None
+        } else {
+            Some(RejectionReason::ExcludePattern)
+        }
+    }
+}
+
+impl<T: Filterable> Filter<T> for ContentFilter {
+    fn matches(&self, item: &T) -> bool {
+        self.check(item).is_none()
     }
 }
 
@@ -190,4 +336,74 @@ mod tests {
         assert!(!filter.matches(&excluded));
         assert!(filter.matches(&included));
     }
+
+    #[test]
+    fn test_check_reports_reason() {
+        let filter = ContentFilter::new(true, Some("party"), Some("birthday"));
+        let item = |name: &str| TestItem { name: name.into() };
+
+        assert_eq!(filter.check(&item("bufo-juicy")), Some(RejectionReason::Blocklist));
+        assert_eq!(filter.check(&item("bufo-party")), Some(RejectionReason::ExcludePattern));
+        assert_eq!(filter.check(&item("bufo-birthday-party")), None);
+        assert_eq!(filter.check(&item("bufo-happy")), None);
+    }
+
+    #[test]
+    fn test_rejection_counts() {
+        let mut counts = RejectionCounts::default();
+        counts.record(RejectionReason::Blocklist);
+        counts.record(RejectionReason::ExcludePattern);
+        counts.record(RejectionReason::ExcludePattern);
+
+        assert_eq!(counts.rejected_by_blocklist, 1);
+        assert_eq!(counts.rejected_by_exclude, 2);
+        assert_eq!(counts.total(), 3);
+    }
+
+    #[test]
+    fn test_try_new_is_case_insensitive() {
+        let filter = ContentFilter::try_new(false, Some("PARTY"), None, true).unwrap();
+        assert!(!filter.matches(&TestItem {
+            name: "bufo-party".into()
+        }));
+
+        let filter = ContentFilter::try_new(false, Some("PARTY"), None, false).unwrap();
+        assert!(filter.matches(&TestItem {
+            name: "bufo-party".into()
+        }));
+    }
+
+    #[test]
+    fn test_try_new_anchored_pattern() {
+        let filter = ContentFilter::try_new(false, Some("^bufo-party$"), None, true).unwrap();
+        assert!(!filter.matches(&TestItem {
+            name: "bufo-party".into()
+        }));
+        assert!(filter.matches(&TestItem {
+            name: "bufo-party-time".into()
+        }));
+    }
+
+    #[test]
+    fn test_try_new_reports_invalid_patterns() {
+        let err = ContentFilter::try_new(false, Some("ok, (unclosed, [bad"), None, true)
+            .err()
+            .unwrap();
+        assert_eq!(err.field, "exclude");
+        assert_eq!(err.problem, PatternProblem::Invalid);
+        assert_eq!(err.patterns, vec!["(unclosed", "[bad"]);
+
+        let err = ContentFilter::try_new(false, None, Some("*star"), true).err().unwrap();
+        assert_eq!(err.field, "include");
+    }
+
+    #[test]
+    fn test_try_new_rejects_oversized_patterns() {
+        let err = ContentFilter::try_new(false, Some("party, (\\w{1000}){1000}"), None, true)
+            .err()
+            .unwrap();
+        assert_eq!(err.problem, PatternProblem::Oversized);
+        assert_eq!(err.patterns, vec!["(\\w{1000}){1000}"]);
+        assert!(err.to_string().starts_with("oversized exclude patterns"));
+    }
 }
@@ -0,0 +1,57 @@
+//! image format metadata
+//!
+//! bufos are static pngs/jpegs or animated gifs. a stored `format` attribute wins;
+//! otherwise the file extension of `filename` (or the url) decides. formats that can be
+//! either (webp) or aren't recognized leave `animated` unknown.
+
+/// whether a bufo is animated, from its `format` attribute or file name
+pub fn detect_animated(format: Option<&str>, filename: Option<&str>) -> Option<bool> {
+    format
+        .and_then(animated_format)
+        .or_else(|| filename.and_then(extension).and_then(|ext| animated_format(&ext)))
+}
+
+fn animated_format(format: &str) -> Option<bool> {
+    match format.trim().to_ascii_lowercase().as_str() {
+        "gif" | "apng" | "animated" => Some(true),
+        "png" | "jpg" | "jpeg" | "static" => Some(false),
+        _ => None,
+    }
+}
+
+/// the extension of the last path segment, ignoring any query string or fragment
+fn extension(filename: &str) -> Option<String> {
+    let path = filename.split(['?', '#']).next().unwrap_or(filename);
+    let segment = path.rsplit('/').next().unwrap_or(path);
+    segment
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_animated_from_extension() {
+        assert_eq!(detect_animated(None, Some("bufo-dance.gif")), Some(true));
+        assert_eq!(detect_animated(None, Some("bufo-sad.PNG")), Some(false));
+        assert_eq!(detect_animated(None, Some("bufo.jpeg")), Some(false));
+        assert_eq!(
+            detect_animated(None, Some("https://cdn.example/bufos/bufo-party.gif?v=2")),
+            Some(true)
+        );
+        assert_eq!(detect_animated(None, Some("bufo-maybe.webp")), None);
+        assert_eq!(detect_animated(None, Some("bufo-no-extension")), None);
+        assert_eq!(detect_animated(None, Some("https://cdn.example/v1.2/bufo")), None);
+        assert_eq!(detect_animated(None, None), None);
+    }
+
+    #[test]
+    fn test_format_attribute_wins() {
+        assert_eq!(detect_animated(Some("GIF"), Some("bufo.png")), Some(true));
+        assert_eq!(detect_animated(Some("png"), Some("bufo.gif")), Some(false));
+        // an unrecognized format falls back to the extension
+        assert_eq!(detect_animated(Some("webp"), Some("bufo.gif")), Some(true));
+    }
+}
@@ -0,0 +1,144 @@
+//! hedged embedding requests
+//!
+//! voyage occasionally answers slowly. when a hedge delay is configured
+//! (`VOYAGE_HEDGE_DELAY_MS`), a request that hasn't returned within the delay gets a
+//! second, identical request; whichever returns first wins and the other is dropped
+//! (which cancels it). hedging trades a few duplicate requests for a shorter tail.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+use std::time::Duration;
+
+/// an embedder that re-sends slow requests after `delay`
+#[derive(Clone)]
+pub struct HedgedEmbedder<E> {
+    inner: E,
+    /// `None` disables hedging
+    delay: Option<Duration>,
+}
+
+impl<E: Embedder> HedgedEmbedder<E> {
+    pub fn new(inner: E, delay: Option<Duration>) -> Self {
+        Self { inner, delay }
+    }
+}
+
+impl<E: Embedder> Embedder for HedgedEmbedder<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        let Some(delay) = self.delay else {
+            return self.inner.embed_as(text, input_type).await;
+        };
+
+        let primary = self.inner.embed_as(text, input_type);
+        tokio::pin!(primary);
+
+        tokio::select! {
+            result = &mut primary => return result,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        logfire::info!(
+            "embedding hedge triggered",
+            model = self.inner.name(),
+            delay_ms = delay.as_millis() as i64
+        );
+
+        let hedge = self.inner.embed_as(text, input_type);
+        tokio::pin!(hedge);
+
+        let (result, winner) = tokio::select! {
+            result = &mut primary => (result, "primary"),
+            result = &mut hedge => (result, "hedge"),
+        };
+
+        logfire::info!(
+            "embedding hedge finished",
+            model = self.inner.name(),
+            winner = winner,
+            ok = result.is_ok()
+        );
+
+        result
+    }
+
+    /// batches aren't hedged: a duplicate request would pay for every input twice
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        self.inner.embed_batch(texts, input_type).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// the first call takes `first_latency`, later calls answer immediately; each
+    /// embedding is the call's index so tests can tell which request won
+    #[derive(Clone)]
+    struct SlowFirst {
+        calls: Arc<AtomicUsize>,
+        first_latency: Duration,
+    }
+
+    impl SlowFirst {
+        fn new(first_latency: Duration) -> Self {
+            Self {
+                calls: Arc::new(AtomicUsize::new(0)),
+                first_latency,
+            }
+        }
+    }
+
+    impl Embedder for SlowFirst {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call == 0 {
+                tokio::time::sleep(self.first_latency).await;
+            }
+            Ok(vec![call as f32])
+        }
+
+        fn name(&self) -> &'static str {
+            "slow-first"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fast_response_is_not_hedged() {
+        let inner = SlowFirst::new(Duration::ZERO);
+        let embedder = HedgedEmbedder::new(inner.clone(), Some(Duration::from_millis(200)));
+
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![0.0]);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_response_is_hedged() {
+        let inner = SlowFirst::new(Duration::from_secs(5));
+        let embedder = HedgedEmbedder::new(inner.clone(), Some(Duration::from_millis(10)));
+
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![1.0]);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hedging_disabled() {
+        let inner = SlowFirst::new(Duration::from_millis(20));
+        let embedder = HedgedEmbedder::new(inner.clone(), None);
+
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![0.0]);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,118 @@
+//! past successful queries, ranked by how often they were searched
+//!
+//! feeds query autocompletion in `/api/suggest`. only first-page searches that returned
+//! results are recorded, normalized so "Happy" and " happy " count as one query. the
+//! store is in memory and bounded by `QUERY_HISTORY_SIZE`; when full, the least-searched
+//! query makes room. nothing is recorded unless `LOG_QUERIES` is on, since the stored
+//! text is served back to other clients.
+
+use crate::rewrite::{Normalize, QueryRewriter};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// shared, bounded query frequency store (cheap to clone)
+#[derive(Clone)]
+pub struct QueryHistory {
+    counts: Arc<Mutex<HashMap<String, u64>>>,
+    capacity: usize,
+}
+
+impl QueryHistory {
+    /// a capacity of 0 disables recording
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            counts: Arc::new(Mutex::new(HashMap::new())),
+            capacity,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// count one more search for `query`
+    pub fn record(&self, query: &str) {
+        let query = Normalize.rewrite(query);
+        if !self.is_enabled() || query.is_empty() {
+            return;
+        }
+
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if !counts.contains_key(&query) && counts.len() >= self.capacity {
+            // least searched goes first; ties evict the alphabetically last query
+            let evicted = counts
+                .iter()
+                .min_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(text, _)| text.clone());
+            if let Some(evicted) = evicted {
+                counts.remove(&evicted);
+            }
+        }
+        *counts.entry(query).or_insert(0) += 1;
+    }
+
+    /// recorded queries starting with `prefix`, most searched first
+    pub fn completions(&self, prefix: &str) -> Vec<(String, u64)> {
+        let prefix = Normalize.rewrite(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut matches: Vec<(String, u64)> = counts
+            .iter()
+            .filter(|(text, _)| text.starts_with(&prefix))
+            .map(|(text, count)| (text.clone(), *count))
+            .collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions_rank_by_frequency() {
+        let history = QueryHistory::new(10);
+        history.record("happy bufo");
+        history.record("Happy  Bufo ");
+        history.record("happy dance");
+        history.record("sad bufo");
+
+        assert_eq!(
+            history.completions("HAP"),
+            vec![
+                ("happy bufo".to_string(), 2),
+                ("happy dance".to_string(), 1)
+            ]
+        );
+        assert_eq!(history.completions("sad").len(), 1);
+        assert!(history.completions("  ").is_empty());
+    }
+
+    #[test]
+    fn test_full_history_evicts_least_searched() {
+        let history = QueryHistory::new(2);
+        history.record("party");
+        history.record("party");
+        history.record("pizza");
+        history.record("panic");
+
+        let kept: Vec<String> = history
+            .completions("p")
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(kept, vec!["party", "panic"]);
+    }
+
+    #[test]
+    fn test_zero_capacity_records_nothing() {
+        let history = QueryHistory::new(0);
+        history.record("happy");
+        assert!(!history.is_enabled());
+        assert!(history.completions("happy").is_empty());
+    }
+}
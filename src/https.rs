@@ -0,0 +1,163 @@
+//! https-only result urls
+//!
+//! some stored `url` attributes are `http://`, which browsers flag as mixed content on
+//! a page served over https. `HTTPS_URLS=upgrade` rewrites `http://` urls (and stored
+//! thumbnails) to `https://`, for hosts that serve both; `strict` instead drops results
+//! whose url isn't https, and http thumbnails. off by default, since an upgraded url is
+//! broken on a host without tls.
+
+use crate::postprocess::PostProcessor;
+use crate::search::BufoResult;
+use std::str::FromStr;
+
+/// how non-https result urls are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpsUrls {
+    /// urls are returned as stored
+    #[default]
+    Off,
+    /// `http://` becomes `https://`
+    Upgrade,
+    /// results without an https url are dropped
+    Strict,
+}
+
+impl FromStr for HttpsUrls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "off" => Ok(HttpsUrls::Off),
+            "upgrade" => Ok(HttpsUrls::Upgrade),
+            "strict" => Ok(HttpsUrls::Strict),
+            other => Err(format!(
+                "unknown https mode: {} (use off, upgrade or strict)",
+                other
+            )),
+        }
+    }
+}
+
+fn has_scheme(url: &str, scheme: &str) -> bool {
+    url.get(..scheme.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+}
+
+fn is_https(url: &str) -> bool {
+    has_scheme(url, "https://")
+}
+
+/// `url` with an `http://` scheme swapped for `https://`; anything else is unchanged
+pub fn upgraded(url: &str) -> String {
+    if has_scheme(url, "http://") {
+        format!("https://{}", &url["http://".len()..])
+    } else {
+        url.to_string()
+    }
+}
+
+/// rewrites `http://` urls and thumbnails to `https://`
+pub struct UpgradeHttp;
+
+impl PostProcessor for UpgradeHttp {
+    fn name(&self) -> &'static str {
+        "https-upgrade"
+    }
+
+    fn process(&self, mut results: Vec<BufoResult>) -> Vec<BufoResult> {
+        for result in &mut results {
+            result.url = upgraded(&result.url);
+            result.thumbnail_url = result.thumbnail_url.as_deref().map(upgraded);
+        }
+        results
+    }
+}
+
+/// keeps only results with an https url, clearing non-https thumbnails
+pub struct HttpsOnly;
+
+impl PostProcessor for HttpsOnly {
+    fn name(&self) -> &'static str {
+        "https-only"
+    }
+
+    fn process(&self, results: Vec<BufoResult>) -> Vec<BufoResult> {
+        results
+            .into_iter()
+            .filter(|r| is_https(&r.url))
+            .map(|mut r| {
+                r.thumbnail_url = r.thumbnail_url.filter(|url| is_https(url));
+                r
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn mixed() -> Vec<BufoResult> {
+        [
+            ("1", "https://cdn.example/a.png", None),
+            (
+                "2",
+                "http://cdn.example/b.png",
+                Some("http://cdn.example/b-thumb.png"),
+            ),
+            ("3", "HTTP://cdn.example/c.png", None),
+            (
+                "4",
+                "https://cdn.example/d.png",
+                Some("http://cdn.example/d-thumb.png"),
+            ),
+            ("5", "data:image/png;base64,AAAA", None),
+        ]
+        .into_iter()
+        .map(|(id, url, thumbnail)| {
+            let mut attrs = HashMap::from([("url".to_string(), url.to_string())]);
+            if let Some(thumbnail) = thumbnail {
+                attrs.insert("thumbnail_url".to_string(), thumbnail.to_string());
+            }
+            BufoResult::from_attributes(id.to_string(), 0.5, &attrs)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_upgrade_rewrites_http_urls() {
+        let results = UpgradeHttp.process(mixed());
+        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn.example/a.png",
+                "https://cdn.example/b.png",
+                "https://cdn.example/c.png",
+                "https://cdn.example/d.png",
+                "data:image/png;base64,AAAA",
+            ]
+        );
+        assert_eq!(
+            results[1].thumbnail_url.as_deref(),
+            Some("https://cdn.example/b-thumb.png")
+        );
+    }
+
+    #[test]
+    fn test_strict_drops_non_https_results() {
+        let results = HttpsOnly.process(mixed());
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "4"]);
+        // the result stays, its insecure thumbnail doesn't
+        assert_eq!(results[1].thumbnail_url, None);
+    }
+
+    #[test]
+    fn test_https_urls_parses() {
+        assert_eq!("upgrade".parse::<HttpsUrls>(), Ok(HttpsUrls::Upgrade));
+        assert_eq!(" strict ".parse::<HttpsUrls>(), Ok(HttpsUrls::Strict));
+        assert!("always".parse::<HttpsUrls>().is_err());
+    }
+}
@@ -74,7 +74,7 @@ pub async fn resize_image(query: web::Query<ImageQuery>) -> HttpResponse {
     // try to decode the image
     let img = match ImageReader::new(Cursor::new(&bytes))
         .with_guessed_format()
-        .and_then(|r| r.decode().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)))
+        .and_then(|r| r.decode().map_err(std::io::Error::other))
     {
         Ok(img) => img,
         Err(e) => {
@@ -0,0 +1,79 @@
+//! query language detection
+//!
+//! uses whatlang (trigram based, no model files) to label queries for analytics. when
+//! `LANGUAGE_HINTS` is enabled, reliably non-english queries are prefixed with their
+//! language before embedding; keyword search always sees the original text.
+
+/// a detected query language
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. "eng", "spa"
+    pub code: &'static str,
+    /// english name, e.g. "Spanish"
+    pub name: &'static str,
+    pub confidence: f64,
+    pub reliable: bool,
+}
+
+impl DetectedLanguage {
+    pub fn is_english(&self) -> bool {
+        self.code == "eng"
+    }
+}
+
+/// detect the language of `text`, or `None` when it can't be guessed at all
+pub fn detect(text: &str) -> Option<DetectedLanguage> {
+    whatlang::detect(text).map(|info| DetectedLanguage {
+        code: info.lang().code(),
+        name: info.lang().eng_name(),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    })
+}
+
+/// prefix a reliably non-english query with its language, e.g. "Spanish: sapo feliz"
+pub fn with_language_hint(query: &str, language: Option<&DetectedLanguage>) -> String {
+    match language {
+        Some(lang) if lang.reliable && !lang.is_english() => format!("{}: {}", lang.name, query),
+        _ => query.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(code: &'static str, name: &'static str, reliable: bool) -> DetectedLanguage {
+        DetectedLanguage {
+            code,
+            name,
+            confidence: 0.9,
+            reliable,
+        }
+    }
+
+    #[test]
+    fn test_detect_english_and_spanish() {
+        let english = detect("the happy little frog is jumping on the bed all day long").unwrap();
+        assert!(english.is_english());
+
+        let spanish = detect("la rana feliz está saltando en la cama todo el día").unwrap();
+        assert_eq!(spanish.code, "spa");
+    }
+
+    #[test]
+    fn test_hint_for_reliable_non_english() {
+        let spanish = language("spa", "Spanish", true);
+        assert_eq!(with_language_hint("sapo feliz", Some(&spanish)), "Spanish: sapo feliz");
+    }
+
+    #[test]
+    fn test_no_hint_for_english_or_unreliable() {
+        let english = language("eng", "English", true);
+        let unsure = language("spa", "Spanish", false);
+
+        assert_eq!(with_language_hint("happy", Some(&english)), "happy");
+        assert_eq!(with_language_hint("happy", Some(&unsure)), "happy");
+        assert_eq!(with_language_hint("happy", None), "happy");
+    }
+}
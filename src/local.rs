@@ -0,0 +1,202 @@
+//! local ONNX embedding implementation
+//!
+//! implements the `Embedder` trait by running a sentence-transformer exported to ONNX
+//! in-process with `ort`, so queries never call an embedding api. text only: the
+//! namespace must be indexed with the same model rather than voyage's multimodal
+//! vectors, so this is for text-query deployments. built with
+//! `--features local-embeddings` and selected with `EMBEDDING_PROVIDER=local`.
+//!
+//! the model directory holds `model.onnx` and a huggingface `tokenizer.json`. the
+//! model's first output is mean-pooled over the attention mask when it's per-token
+//! (`[1, tokens, dim]`) and used as-is when already pooled (`[1, dim]`); either way the
+//! vector is L2-normalized.
+
+use crate::providers::{Embedder, EmbeddingError};
+use anyhow::Context;
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::path::Path;
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_FILE: &str = "model.onnx";
+const TOKENIZER_FILE: &str = "tokenizer.json";
+
+fn inference_error(context: &str, e: impl std::fmt::Display) -> EmbeddingError {
+    EmbeddingError::Other(anyhow::anyhow!("{}: {}", context, e))
+}
+
+/// in-process ONNX sentence embedder
+#[derive(Clone)]
+pub struct LocalEmbedder {
+    session: Arc<Session>,
+    tokenizer: Arc<Tokenizer>,
+}
+
+impl LocalEmbedder {
+    /// load `model.onnx` and `tokenizer.json` from `dir`
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let model_path = dir.as_ref().join(MODEL_FILE);
+        let tokenizer_path = dir.as_ref().join(TOKENIZER_FILE);
+
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(&model_path))
+            .with_context(|| format!("failed to load {}", model_path.display()))?;
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("failed to load {}: {}", tokenizer_path.display(), e))?;
+
+        Ok(Self {
+            session: Arc::new(session),
+            tokenizer: Arc::new(tokenizer),
+        })
+    }
+
+    /// fail unless the model's vectors have `expected` dimensions (no-op when unset)
+    pub fn check_dimension(self, expected: Option<usize>) -> anyhow::Result<Self> {
+        if let Some(expected) = expected {
+            let actual = self.embed_blocking("bufo")?.len();
+            anyhow::ensure!(
+                actual == expected,
+                "local model produces {}-dimensional vectors, expected {}",
+                actual,
+                expected
+            );
+        }
+        Ok(self)
+    }
+
+    /// tokenize, run the model and pool; cpu-bound, so `embed` runs it off the runtime
+    fn embed_blocking(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| inference_error("failed to tokenize", e))?;
+        let tokens = encoding.get_ids().len();
+        if tokens == 0 {
+            return Err(EmbeddingError::EmptyResponse);
+        }
+
+        // feed only the inputs this model declares
+        let mut inputs: Vec<(String, SessionInputValue)> = Vec::new();
+        for input in &self.session.inputs {
+            let values = match input.name.as_str() {
+                "input_ids" => encoding.get_ids(),
+                "attention_mask" => encoding.get_attention_mask(),
+                "token_type_ids" => encoding.get_type_ids(),
+                other => {
+                    return Err(inference_error("unsupported model input", other));
+                }
+            };
+            let values: Vec<i64> = values.iter().map(|v| *v as i64).collect();
+            let tensor = Tensor::from_array(([1, tokens], values))
+                .map_err(|e| inference_error("failed to build input tensor", e))?;
+            inputs.push((input.name.clone(), tensor.into()));
+        }
+
+        let outputs = self
+            .session
+            .run(inputs)
+            .map_err(|e| inference_error("inference failed", e))?;
+        let output = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| inference_error("unexpected model output", e))?;
+        let values: Vec<f32> = output.iter().copied().collect();
+
+        let pooled = match output.shape() {
+            &[1, n, dim] if n == tokens => mean_pool(&values, dim, encoding.get_attention_mask()),
+            &[1, _] => values,
+            shape => {
+                return Err(inference_error("unexpected output shape", format!("{:?}", shape)));
+            }
+        };
+        Ok(l2_normalize(pooled))
+    }
+}
+
+/// average per-token vectors (`values` is `tokens x dim`, row-major), skipping padding
+fn mean_pool(values: &[f32], dim: usize, mask: &[u32]) -> Vec<f32> {
+    let mut sum = vec![0.0; dim];
+    let mut count = 0.0;
+    for (row, keep) in values.chunks(dim).zip(mask) {
+        if *keep == 0 {
+            continue;
+        }
+        for (total, value) in sum.iter_mut().zip(row) {
+            *total += value;
+        }
+        count += 1.0;
+    }
+    if count > 0.0 {
+        sum.iter_mut().for_each(|total| *total /= count);
+    }
+    sum
+}
+
+fn l2_normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+impl Embedder for LocalEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let embedder = self.clone();
+        let text = text.to_string();
+        tokio::task::spawn_blocking(move || embedder.embed_blocking(&text))
+            .await
+            .map_err(|e| inference_error("embedding task failed", e))?
+    }
+
+    fn name(&self) -> &'static str {
+        "local"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a 4-dimensional lookup table over "happy", "bufo" and "sad" (see testdata/)
+    const TINY_MODEL_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/tiny-embedder");
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_mean_pool_skips_padding() {
+        let values = [1.0, 0.0, 0.0, 1.0, 9.0, 9.0];
+        assert_eq!(mean_pool(&values, 2, &[1, 1, 0]), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_l2_normalize() {
+        assert_close(&l2_normalize(vec![3.0, 4.0]), &[0.6, 0.8]);
+        assert_eq!(l2_normalize(vec![0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_tiny_model_is_deterministic() {
+        let embedder = LocalEmbedder::load(TINY_MODEL_DIR).unwrap();
+
+        let first = embedder.embed("Happy Bufo").await.unwrap();
+        let second = embedder.embed("Happy Bufo").await.unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(&first, &[half, half, 0.0, 0.0]);
+        assert_eq!(first, second);
+
+        assert_close(&embedder.embed("sad").await.unwrap(), &[0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_check_dimension() {
+        let embedder = LocalEmbedder::load(TINY_MODEL_DIR).unwrap();
+        let embedder = embedder.check_dimension(Some(4)).unwrap();
+        assert!(embedder.check_dimension(Some(1024)).is_err());
+    }
+}
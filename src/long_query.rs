@@ -0,0 +1,144 @@
+//! chunked embedding of long queries
+//!
+//! a long query embeds as one vector that tends to favour whichever part the model
+//! attends to most. with `LONG_QUERY_POOLING`, a semantic text over
+//! `LONG_QUERY_THRESHOLD` characters is split into overlapping word-aligned chunks,
+//! embedded in one batch, and the chunk vectors mean-pooled. keyword search still gets
+//! the whole query, so turbopuffer's BM25 limit (`MAX_QUERY_LENGTH`) still caps query
+//! length.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+
+/// most characters in a chunk
+const CHUNK_CHARS: usize = 256;
+
+/// characters of trailing words a chunk repeats from the one before
+const CHUNK_OVERLAP: usize = 64;
+
+/// characters in `words` joined by single spaces
+fn joined_len(words: &[&str]) -> usize {
+    words.iter().map(|w| w.chars().count()).sum::<usize>() + words.len().saturating_sub(1)
+}
+
+/// `text` in word-aligned chunks of at most `chunk_chars`, each after the first starting
+/// with up to `overlap` characters of the previous chunk's last words
+///
+/// a word longer than `chunk_chars` is a chunk by itself; overlap is dropped where it
+/// would push the next word out of the chunk.
+pub fn chunk_text(text: &str, chunk_chars: usize, overlap: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let mut end = start + 1;
+        while end < words.len() && joined_len(&words[start..=end]) <= chunk_chars {
+            end += 1;
+        }
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+
+        // back up over the overlap, but never so far that `words[end]` doesn't fit
+        let mut next = end;
+        while next - 1 > start && joined_len(&words[next - 1..end]) <= overlap {
+            next -= 1;
+        }
+        while next < end && joined_len(&words[next..=end]) > chunk_chars {
+            next += 1;
+        }
+        start = next;
+    }
+    chunks
+}
+
+/// the mean of `vectors`, scaled to unit length
+///
+/// provider vectors are unit length but their mean is shorter; cosine distance ignores
+/// that, a dot-product metric wouldn't, so the pooled vector is re-normalized.
+pub fn mean_pool(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let dim = vectors.first().map_or(0, Vec::len);
+    let mut pooled = vec![0.0; dim];
+    for vector in vectors {
+        for (sum, x) in pooled.iter_mut().zip(vector) {
+            *sum += x;
+        }
+    }
+    // dividing by the count first wouldn't change the direction
+    let norm = pooled.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        pooled.iter_mut().for_each(|x| *x /= norm);
+    }
+    pooled
+}
+
+/// the chunks `text` is pooled from, or `None` when it's embedded whole
+///
+/// `threshold` is `None` when pooling is off.
+pub fn pooled_chunks(text: &str, threshold: Option<usize>) -> Option<Vec<String>> {
+    let threshold = threshold?;
+    if text.chars().count() <= threshold {
+        return None;
+    }
+    let chunks = chunk_text(text, CHUNK_CHARS, CHUNK_OVERLAP);
+    (chunks.len() > 1).then_some(chunks)
+}
+
+/// embed `text` as a query, mean-pooling its chunks when it's over `threshold`
+pub async fn embed_query_pooled<E: Embedder>(
+    embedder: &E,
+    text: &str,
+    threshold: Option<usize>,
+) -> Result<Vec<f32>, EmbeddingError> {
+    match pooled_chunks(text, threshold) {
+        Some(chunks) => {
+            let embeddings = embedder.embed_batch(&chunks, InputType::Query).await?;
+            Ok(mean_pool(&embeddings))
+        }
+        None => embedder.embed_query(text).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_overlap_on_word_boundaries() {
+        let chunks = chunk_text("aa bb cc dd ee ff", 8, 2);
+        assert_eq!(chunks, vec!["aa bb cc", "cc dd ee", "ee ff"]);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 8));
+
+        assert_eq!(chunk_text("aa bb", 8, 2), vec!["aa bb"]);
+        assert!(chunk_text("   ", 8, 2).is_empty());
+
+        // an oversized word stands alone
+        assert_eq!(
+            chunk_text("aa bbbbbbbbbb cc", 8, 2),
+            vec!["aa", "bbbbbbbbbb", "cc"]
+        );
+        // the overlap gives way when it would leave no room for the next word
+        assert_eq!(chunk_text("aa bb ccccccc", 8, 2), vec!["aa bb", "ccccccc"]);
+    }
+
+    #[test]
+    fn test_mean_pool_is_unit_length() {
+        let pooled = mean_pool(&[vec![1.0, 0.0], vec![0.0, 1.0]]);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((pooled[0] - half).abs() < 1e-6 && (pooled[1] - half).abs() < 1e-6);
+
+        assert_eq!(mean_pool(&[vec![3.0, 4.0]]), vec![0.6, 0.8]);
+        assert_eq!(mean_pool(&[vec![0.0, 0.0]]), vec![0.0, 0.0]);
+        assert!(mean_pool(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_pooled_chunks_respects_threshold() {
+        let long = "happy bufo ".repeat(40);
+        assert!(pooled_chunks(&long, None).is_none());
+        assert!(pooled_chunks(&long, Some(long.len())).is_none());
+        let chunks = pooled_chunks(&long, Some(100)).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= CHUNK_CHARS));
+    }
+}
@@ -0,0 +1,88 @@
+//! direct bufo lookup by id
+//!
+//! fetches one record's current attributes without any ranking. the family-friendly
+//! blocklist still applies, so a blocked bufo is a 404 in family-friendly mode. an id in
+//! `BLOCKED_IDS_PATH` is a 404 in every mode.
+
+use crate::filter::{ContentFilter, Filter};
+use crate::providers::VectorStore;
+use crate::reload::CurrentConfig;
+use crate::search::{build_store, BufoResult};
+use crate::state::AppState;
+use crate::thumbnail::fill_thumbnails;
+use actix_web::{web, HttpResponse, Result as ActixResult};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct LookupQuery {
+    /// defaults to the server's `DEFAULT_FAMILY_FRIENDLY`
+    #[serde(default)]
+    pub family_friendly: Option<bool>,
+}
+
+/// GET /api/bufo/{id} handler
+///
+/// the returned `score` is always 0 since nothing is ranked.
+pub async fn get_bufo(
+    id: web::Path<String>,
+    query: web::Query<LookupQuery>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    let id = id.into_inner();
+    if state.blocked_ids.current().contains(&id) {
+        return Ok(HttpResponse::NotFound().body("bufo not found"));
+    }
+    let family_friendly = query
+        .family_friendly
+        .unwrap_or(config.default_family_friendly);
+
+    let store = build_store(&config, &config.turbopuffer_namespace);
+    let row = store
+        .get_by_id(&id)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+
+    let mut result = row
+        .map(|row| BufoResult::from_attributes(row.id, 0.0, &row.attributes))
+        .filter(|result| ContentFilter::new(family_friendly, None, None).matches(result));
+    fill_thumbnails(result.as_mut_slice(), config.thumbnail_transform.as_ref());
+
+    match result {
+        Some(result) => Ok(HttpResponse::Ok().json(result)),
+        None => Ok(HttpResponse::NotFound().body("bufo not found")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocked::BlockedIds;
+    use crate::config::Config;
+    use crate::reload::Live;
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn test_blocked_id_is_not_found() {
+        let config = Config::for_tests();
+        let mut state = AppState::new(&config).unwrap();
+        state.blocked_ids = Live::new(BlockedIds::parse("bufo-taken-down\n"));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .route("/bufo/{id}", web::get().to(get_bufo)),
+        )
+        .await;
+
+        // answered without asking turbopuffer, whatever family_friendly says
+        for uri in [
+            "/bufo/bufo-taken-down",
+            "/bufo/bufo-taken-down?family_friendly=false",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 404, "{}", uri);
+        }
+    }
+}
@@ -1,30 +1,266 @@
+mod admin;
+mod alpha;
+mod batch;
+mod blocked;
+mod breaker;
+mod cache;
+mod cluster;
 mod config;
+mod cursor;
+mod dimension;
+mod display;
+mod embed;
 mod embedding;
+mod expansion;
+mod explore;
+mod facets;
 mod filter;
+mod format;
+mod hedge;
+mod history;
+mod https;
 mod image;
+mod language;
+#[cfg(feature = "local-embeddings")]
+mod local;
+mod long_query;
+mod lookup;
+mod models;
+mod negotiate;
+mod openai;
+mod openapi;
+mod plan;
+mod postprocess;
+mod preload;
 mod providers;
+mod query_form;
+mod ratelimit;
+mod reload;
+mod response_cache;
+mod retry;
+mod rewrite;
 mod scoring;
 mod search;
+mod since;
+mod state;
+mod suggest;
+mod thumbnail;
+mod timing;
+mod tokenize;
 mod turbopuffer;
+mod version;
+mod vocabulary;
 
 use actix_cors::Cors;
 use actix_files as fs;
 use actix_governor::{Governor, GovernorConfigBuilder};
-use actix_web::{middleware, web, App, HttpResponse, HttpServer};
-use anyhow::Result;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::KeepAlive;
+use actix_web::middleware::Next;
+use actix_web::{
+    middleware, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, ResponseError,
+};
+use anyhow::{Context, Result};
 use config::Config;
 use opentelemetry_instrumentation_actix_web::{RequestMetrics, RequestTracing};
+use ratelimit::CallerKeyExtractor;
+use response_cache::FallbackCache;
+use serde::Serialize;
+use state::AppState;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::level_filters::LevelFilter;
 
-async fn index() -> HttpResponse {
-    HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(include_str!("../static/index.html"))
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// longest client-supplied request id that's kept
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// correlation id for a single request, stored in request extensions
+///
+/// taken from the incoming `X-Request-Id` header when it's a usable id, otherwise a
+/// fresh uuid.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// whether a client's id is safe to log and echo: short, and only `[A-Za-z0-9._-]`
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+}
+
+/// reads or generates the request id and echoes it back in the response headers
+async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| is_valid_request_id(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut res = next.call(req).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
+
+/// error body for a request the server couldn't parse
+#[derive(Debug, Serialize)]
+struct RequestError {
+    code: &'static str,
+    message: String,
+    /// the field at fault, when serde names it (missing fields)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+}
+
+/// the first backquoted name after "field" in a serde error, e.g. "missing field `query`"
+fn offending_field(message: &str) -> Option<String> {
+    let (_, rest) = message.split_once("field `")?;
+    rest.split_once('`').map(|(field, _)| field.to_string())
+}
+
+/// turns json body errors (bad syntax, wrong types, missing fields) into a `RequestError`
+///
+/// serde reports type errors by position rather than field name, so those messages end
+/// with the line and column of the bad value.
+fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let (message, field) = match &err {
+        JsonPayloadError::Deserialize(e) => {
+            let detail = e.to_string();
+            let field = offending_field(&detail);
+            (format!("invalid request body: {}", detail), field)
+        }
+        other => (other.to_string(), None),
+    };
+    let response = HttpResponse::build(err.status_code()).json(RequestError {
+        code: "invalid_request",
+        message,
+        field,
+    });
+    actix_web::error::InternalError::from_response(err, response).into()
+}
+
+fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(json_error_handler)
+}
+
+/// the index page html
+///
+/// precedence: `{STATIC_DIR}/index.html` read at startup, otherwise the copy embedded
+/// at compile time (so the binary still serves a page without any assets on disk).
+/// the content never changes while running, so its etag is computed once here.
+#[derive(Clone)]
+struct IndexPage {
+    html: String,
+    etag: String,
+}
+
+impl IndexPage {
+    fn load(static_dir: &str) -> Self {
+        let path = Path::new(static_dir).join("index.html");
+        let html = match std::fs::read_to_string(&path) {
+            Ok(html) => {
+                let path = path.display().to_string();
+                logfire::info!("serving index from static dir", path = &path);
+                html
+            }
+            Err(_) => include_str!("../static/index.html").to_string(),
+        };
+
+        let mut hasher = DefaultHasher::new();
+        html.hash(&mut hasher);
+        let etag = format!("\"{}\"", hasher.finish());
+
+        Self { html, etag }
+    }
+
+    /// 200 with caching headers, or 304 when the client already has this version
+    fn respond(&self, req: &HttpRequest, with_body: bool) -> HttpResponse {
+        let not_modified = req
+            .headers()
+            .get("if-none-match")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v == self.etag);
+
+        if not_modified {
+            return HttpResponse::NotModified()
+                .insert_header(("etag", self.etag.clone()))
+                .finish();
+        }
+
+        let mut builder = HttpResponse::Ok();
+        builder
+            .content_type("text/html; charset=utf-8")
+            .insert_header(("etag", self.etag.clone()))
+            .insert_header(("cache-control", "public, no-cache"));
+
+        if with_body {
+            builder.body(self.html.clone())
+        } else {
+            builder.finish()
+        }
+    }
+}
+
+async fn index(page: web::Data<IndexPage>, req: HttpRequest) -> HttpResponse {
+    page.respond(&req, true)
+}
+
+/// HEAD / handler: same headers as GET, no body
+async fn index_head(page: web::Data<IndexPage>, req: HttpRequest) -> HttpResponse {
+    page.respond(&req, false)
+}
+
+/// connection settings for the `HttpServer`, from `Config`
+#[derive(Debug, Clone, Copy)]
+struct ConnectionTuning {
+    keep_alive: KeepAlive,
+    client_request_timeout: Duration,
+    /// bind with h2c support alongside HTTP/1.1
+    http2: bool,
+}
+
+impl ConnectionTuning {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            keep_alive: match config.keep_alive_secs {
+                0 => KeepAlive::Disabled,
+                secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+            },
+            // actix treats a zero timeout as disabled
+            client_request_timeout: Duration::from_secs(config.client_timeout_secs),
+            http2: config.enable_http2,
+        }
+    }
+}
+
+/// serves `/static/*` from the configured directory
+fn static_files(static_dir: &str) -> fs::Files {
+    fs::Files::new("/static", static_dir).show_files_listing()
 }
 
 #[actix_web::main]
 async fn main() -> Result<()> {
-    dotenv::dotenv().ok();
+    reload::load_dotenv();
 
     // initialize logfire with info level filter to exclude trace/debug spans
     let logfire = logfire::configure()
@@ -37,20 +273,44 @@ async fn main() -> Result<()> {
     let config = Config::from_env()?;
     let host = config.host.clone();
     let port = config.port;
+    let tuning = ConnectionTuning::from_config(&config);
 
     logfire::info!("starting bufo search server",
         host = &host,
-        port = port as i64
+        port = port as i64,
+        keep_alive_secs = config.keep_alive_secs as i64,
+        http2 = tuning.http2
     );
 
-    // rate limiter: 10 requests per minute per IP
-    let governor_conf = GovernorConfigBuilder::default()
-        .milliseconds_per_request(6000) // 1 request per 6 seconds = 10 per minute
-        .burst_size(10)
+    let index_page = IndexPage::load(&config.static_dir);
+    let mut state = AppState::new(&config)?;
+    state.response_cache =
+        FallbackCache::connect(config.cache_backend, config.redis_url.as_deref()).await;
+    actix_web::rt::spawn(preload::preload_popular_queries(config.clone(), state.clone()));
+    actix_web::rt::spawn(vocabulary::refresh_periodically(
+        state.vocabulary.clone(),
+        search::build_store(&config, &config.turbopuffer_namespace),
+        Duration::from_secs(config.vocabulary_refresh_secs),
+    ));
+
+    // rate limiters: per IP for anonymous callers, per key for known api keys
+    let api_keys = Arc::new(config.api_keys.clone());
+    let (period, burst) = ratelimit::quota(config.rate_limit_per_minute);
+    let anonymous_governor = GovernorConfigBuilder::default()
+        .key_extractor(CallerKeyExtractor::anonymous(api_keys.clone()))
+        .milliseconds_per_request(period)
+        .burst_size(burst)
+        .finish()
+        .context("invalid RATE_LIMIT_PER_MINUTE")?;
+    let (period, burst) = ratelimit::quota(config.authenticated_rate_limit_per_minute);
+    let authenticated_governor = GovernorConfigBuilder::default()
+        .key_extractor(CallerKeyExtractor::authenticated(api_keys))
+        .milliseconds_per_request(period)
+        .burst_size(burst)
         .finish()
-        .unwrap();
+        .context("invalid AUTHENTICATED_RATE_LIMIT_PER_MINUTE")?;
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let cors = Cors::permissive();
 
         App::new()
@@ -58,23 +318,378 @@ async fn main() -> Result<()> {
             .wrap(RequestTracing::new())
             .wrap(RequestMetrics::default())
             // existing middleware
-            .wrap(middleware::Logger::default())
+            .wrap(if config.log_queries {
+                middleware::Logger::default()
+            } else {
+                // %U is the path alone, so GET search text stays out of the access log
+                middleware::Logger::new(r#"%a "%U" %s %b "%{User-Agent}i" %T"#)
+            })
+            .wrap(middleware::from_fn(request_id))
             .wrap(cors)
-            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(state.clone()))
+            .app_data(web::Data::new(index_page.clone()))
+            .app_data(json_config())
             .route("/", web::get().to(index))
+            .route("/", web::head().to(index_head))
             .service(
                 web::scope("/api")
-                    .wrap(Governor::new(&governor_conf))
+                    .wrap(Governor::new(&anonymous_governor))
+                    .wrap(Governor::new(&authenticated_governor))
                     .route("/search", web::post().to(search::search))
                     .route("/search", web::get().to(search::search_get))
+                    .route("/search/validate", web::post().to(search::validate))
+                    .route("/search/sweep", web::post().to(search::sweep))
+                    .route("/embed", web::post().to(embed::embed))
+                    .route("/embed/batch", web::post().to(embed::embed_batch))
+                    .route("/stats", web::get().to(admin::stats))
+                    .route("/filters", web::get().to(admin::filters))
+                    .route("/admin/reload", web::post().to(reload::reload))
+                    .route("/bufo/{id}", web::get().to(lookup::get_bufo))
+                    .route("/suggest", web::get().to(suggest::suggest))
                     .route("/image", web::get().to(image::resize_image))
+                    .route("/openapi.json", web::get().to(openapi::spec))
                     .route("/health", web::get().to(|| async { HttpResponse::Ok().body("ok") }))
             )
-            .service(fs::Files::new("/static", "./static").show_files_listing())
+            .service(static_files(&config.static_dir))
     })
-    .bind((host.as_str(), port))?
-    .run()
-    .await?;
+    .keep_alive(tuning.keep_alive)
+    .client_request_timeout(tuning.client_request_timeout);
+
+    let server = if tuning.http2 {
+        server.bind_auto_h2c((host.as_str(), port))?
+    } else {
+        server.bind((host.as_str(), port))?
+    };
+    server.run().await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // the module only; importing the `test` macro too would shadow the built-in #[test]
+    use actix_web::test::{self};
+
+    async fn echo_extension(id: web::ReqData<RequestId>) -> HttpResponse {
+        HttpResponse::Ok().body(id.0.clone())
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_is_echoed() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(request_id))
+                .route("/", web::get().to(echo_extension)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "abc-123"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+        assert_eq!(test::read_body(res).await, "abc-123");
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_is_generated_when_absent() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(request_id))
+                .route("/", web::get().to(echo_extension)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let res = test::call_service(&app, req).await;
+
+        let header = res
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        assert_eq!(test::read_body(res).await, header.as_str());
+    }
+
+    #[actix_web::test]
+    async fn test_unusable_request_id_is_replaced() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(request_id))
+                .route("/", web::get().to(echo_extension)),
+        )
+        .await;
+
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for id in ["abc 123", "id\"><script>", "trace/1", too_long.as_str()] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .insert_header((REQUEST_ID_HEADER, id))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+
+            let header = res
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap();
+            assert!(uuid::Uuid::parse_str(header).is_ok(), "{} was kept", id);
+        }
+
+        assert!(is_valid_request_id("span-1.retry_2"));
+        assert!(is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN)));
+    }
+
+    async fn accept_search(query: web::Json<search::SearchQuery>) -> HttpResponse {
+        HttpResponse::Ok().body(query.query.clone())
+    }
+
+    /// post `body` as json and return the status and parsed error body
+    async fn post_malformed(
+        body: &'static str,
+    ) -> (actix_web::http::StatusCode, serde_json::Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(json_config())
+                .route("/", web::post().to(accept_search)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_field_type_is_structured() {
+        let (status, body) = post_malformed(r#"{"query": "happy", "alpha": "high"}"#).await;
+        assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_request");
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("invalid type"), "{}", message);
+        assert!(message.contains("column"), "{}", message);
+    }
+
+    #[actix_web::test]
+    async fn test_missing_field_is_named() {
+        let (status, body) = post_malformed(r#"{"top_k": 5}"#).await;
+        assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_request");
+        assert_eq!(body["field"], "query");
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_json_is_structured() {
+        let (status, body) = post_malformed(r#"{"query": "happy""#).await;
+        assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_request");
+        assert!(body.get("field").is_none());
+    }
+
+    #[test]
+    fn test_offending_field() {
+        assert_eq!(
+            offending_field("missing field `query` at line 1 column 12").as_deref(),
+            Some("query")
+        );
+        assert_eq!(
+            offending_field("unknown field `alhpa`, expected one of ...").as_deref(),
+            Some("alhpa")
+        );
+        assert_eq!(offending_field("invalid type: string \"high\", expected f32"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_custom_static_dir_serves_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("find-bufo-static-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hello.txt"), "hello bufo").unwrap();
+        std::fs::write(dir.join("index.html"), "<p>custom index</p>").unwrap();
+
+        let static_dir = dir.to_str().unwrap().to_string();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(IndexPage::load(&static_dir)))
+                .route("/", web::get().to(index))
+                .service(static_files(&static_dir)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/static/hello.txt").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(test::read_body(res).await, "hello bufo");
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "<p>custom index</p>");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_index_falls_back_to_embedded() {
+        let page = IndexPage::load("/nonexistent/find-bufo/static");
+        assert_eq!(page.html, include_str!("../static/index.html"));
+    }
+
+    #[actix_web::test]
+    async fn test_index_head_matches_get_etag() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(IndexPage::load("./static")))
+                .route("/", web::get().to(index))
+                .route("/", web::head().to(index_head)),
+        )
+        .await;
+
+        let get = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let head = test::call_service(
+            &app,
+            test::TestRequest::default()
+                .method(actix_web::http::Method::HEAD)
+                .uri("/")
+                .to_request(),
+        )
+        .await;
+
+        assert!(get.status().is_success());
+        assert!(head.status().is_success());
+        let etag = get.headers().get("etag").unwrap().clone();
+        assert_eq!(head.headers().get("etag").unwrap(), &etag);
+        assert!(head.headers().get("cache-control").is_some());
+        assert!(test::read_body(head).await.is_empty());
+
+        let revalidate = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("if-none-match", etag.to_str().unwrap()))
+            .to_request();
+        let res = test::call_service(&app, revalidate).await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::NOT_MODIFIED);
+    }
+
+    /// a running single-worker server with `tuning` applied, and its address
+    fn tuned_server(
+        tuning: ConnectionTuning,
+    ) -> (actix_web::dev::ServerHandle, std::net::SocketAddr) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(|| App::new().route("/", web::get().to(|| async { "ok" })))
+            .workers(1)
+            .keep_alive(tuning.keep_alive)
+            .client_request_timeout(tuning.client_request_timeout);
+        let server = if tuning.http2 {
+            server.listen_auto_h2c(listener).unwrap()
+        } else {
+            server.listen(listener).unwrap()
+        }
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        (handle, addr)
+    }
+
+    /// send a GET / over `stream` and return the raw response
+    async fn get_over(stream: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !response.ends_with(b"ok") {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed after {:?}", String::from_utf8_lossy(&response));
+            response.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(response).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_server_keeps_connections_alive() {
+        let tuning = ConnectionTuning {
+            keep_alive: KeepAlive::Timeout(Duration::from_secs(30)),
+            client_request_timeout: Duration::from_secs(5),
+            http2: false,
+        };
+        let (handle, addr) = tuned_server(tuning);
+
+        // two requests over one connection
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert!(get_over(&mut stream).await.starts_with("HTTP/1.1 200"));
+        let second = get_over(&mut stream).await;
+        assert!(second.starts_with("HTTP/1.1 200"));
+        assert!(!second.to_lowercase().contains("connection: close"));
+
+        handle.stop(true).await;
+    }
+
+    #[actix_web::test]
+    async fn test_server_closes_when_keep_alive_disabled() {
+        let tuning = ConnectionTuning {
+            keep_alive: KeepAlive::Disabled,
+            client_request_timeout: Duration::from_secs(5),
+            http2: false,
+        };
+        let (handle, addr) = tuned_server(tuning);
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let response = get_over(&mut stream).await;
+        assert!(response.to_lowercase().contains("connection: close"));
+
+        handle.stop(true).await;
+    }
+
+    #[actix_web::test]
+    async fn test_server_accepts_h2c() {
+        let tuning = ConnectionTuning {
+            keep_alive: KeepAlive::Timeout(Duration::from_secs(30)),
+            client_request_timeout: Duration::from_secs(5),
+            http2: true,
+        };
+        let (handle, addr) = tuned_server(tuning);
+
+        let client = reqwest::Client::builder()
+            .http2_prior_knowledge()
+            .build()
+            .unwrap();
+        let response = client.get(format!("http://{}/", addr)).send().await.unwrap();
+        assert_eq!(response.version(), reqwest::Version::HTTP_2);
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        // plain HTTP/1.1 still works on the same port
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert!(get_over(&mut stream).await.starts_with("HTTP/1.1 200"));
+
+        handle.stop(true).await;
+    }
+
+    #[test]
+    fn test_connection_tuning_from_config() {
+        let mut config = Config::for_tests();
+
+        config.keep_alive_secs = 75;
+        config.client_timeout_secs = 10;
+        let tuning = ConnectionTuning::from_config(&config);
+        assert_eq!(tuning.keep_alive, KeepAlive::Timeout(Duration::from_secs(75)));
+        assert_eq!(tuning.client_request_timeout, Duration::from_secs(10));
+
+        config.keep_alive_secs = 0;
+        assert_eq!(ConnectionTuning::from_config(&config).keep_alive, KeepAlive::Disabled);
+    }
+}
@@ -0,0 +1,135 @@
+//! per-request embedding model selection
+//!
+//! `EMBEDDING_MODELS` allowlists the models a request may pick with `model`, each mapped
+//! to the namespace indexed with that model's vectors (vectors from different models
+//! aren't comparable), e.g.
+//! `voyage-multimodal-3.5=bufos-mm35,text-embedding-3-small=bufos-openai`. only models
+//! the server has a client for can be listed.
+
+use std::str::FromStr;
+
+/// voyage models served by the multimodal embeddings endpoint
+pub const VOYAGE_MODELS: &[&str] = &["voyage-multimodal-3", "voyage-multimodal-3.5"];
+
+/// openai text embedding models
+pub const OPENAI_MODELS: &[&str] = &[
+    "text-embedding-3-small",
+    "text-embedding-3-large",
+    "text-embedding-ada-002",
+];
+
+/// who serves a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelProvider {
+    Voyage,
+    OpenAi,
+}
+
+/// an allowlisted model and the namespace its searches go to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelRoute {
+    pub model: &'static str,
+    pub provider: ModelProvider,
+    pub namespace: String,
+}
+
+/// the `EMBEDDING_MODELS` allowlist, in configured order (empty allows none)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelRoutes(Vec<ModelRoute>);
+
+/// the static name and provider of a model the server can embed with
+fn known_model(name: &str) -> Option<(&'static str, ModelProvider)> {
+    let find = |models: &[&'static str]| models.iter().copied().find(|m| *m == name);
+    find(VOYAGE_MODELS)
+        .map(|m| (m, ModelProvider::Voyage))
+        .or_else(|| find(OPENAI_MODELS).map(|m| (m, ModelProvider::OpenAi)))
+}
+
+impl FromStr for ModelRoutes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut routes: Vec<ModelRoute> = Vec::new();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, namespace) = entry
+                .split_once('=')
+                .map(|(name, namespace)| (name.trim(), namespace.trim()))
+                .filter(|(_, namespace)| !namespace.is_empty())
+                .ok_or_else(|| format!("expected model=namespace, got {}", entry))?;
+            let (model, provider) = known_model(name).ok_or_else(|| {
+                format!(
+                    "unknown model {} (expected one of {:?} or {:?})",
+                    name, VOYAGE_MODELS, OPENAI_MODELS
+                )
+            })?;
+            if routes.iter().any(|r| r.model == model) {
+                return Err(format!("model {} is listed twice", model));
+            }
+            routes.push(ModelRoute {
+                model,
+                provider,
+                namespace: namespace.to_string(),
+            });
+        }
+        Ok(Self(routes))
+    }
+}
+
+impl ModelRoutes {
+    /// the route for `model`, if it's allowlisted
+    pub fn route(&self, model: &str) -> Option<&ModelRoute> {
+        self.0.iter().find(|r| r.model == model)
+    }
+
+    /// allowlisted model names, for error messages
+    pub fn names(&self) -> Vec<&'static str> {
+        self.0.iter().map(|r| r.model).collect()
+    }
+
+    /// true when some route needs an openai client
+    pub fn needs_openai(&self) -> bool {
+        self.0.iter().any(|r| r.provider == ModelProvider::OpenAi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_parse_and_look_up() {
+        let routes: ModelRoutes =
+            " voyage-multimodal-3.5 = bufos-mm35 , text-embedding-3-small=bufos-openai"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            routes.route("voyage-multimodal-3.5"),
+            Some(&ModelRoute {
+                model: "voyage-multimodal-3.5",
+                provider: ModelProvider::Voyage,
+                namespace: "bufos-mm35".to_string(),
+            })
+        );
+        assert_eq!(
+            routes.route("text-embedding-3-small").map(|r| r.provider),
+            Some(ModelProvider::OpenAi)
+        );
+        assert_eq!(routes.route("voyage-multimodal-3"), None);
+        assert!(routes.needs_openai());
+        assert_eq!(
+            routes.names(),
+            vec!["voyage-multimodal-3.5", "text-embedding-3-small"]
+        );
+        assert_eq!("".parse::<ModelRoutes>(), Ok(ModelRoutes::default()));
+    }
+
+    #[test]
+    fn test_routes_reject_bad_entries() {
+        assert!("clip-vit=bufos-clip".parse::<ModelRoutes>().is_err());
+        assert!("voyage-multimodal-3".parse::<ModelRoutes>().is_err());
+        assert!("voyage-multimodal-3=".parse::<ModelRoutes>().is_err());
+        assert!("voyage-multimodal-3=a,voyage-multimodal-3=b"
+            .parse::<ModelRoutes>()
+            .is_err());
+    }
+}
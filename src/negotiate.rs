@@ -0,0 +1,180 @@
+//! modern image formats by content negotiation
+//!
+//! when the cdn can serve a bufo as webp or avif (a path suffix or query param), and
+//! `IMAGE_URL_PATTERN` is set, result urls are rewritten for clients whose `Accept`
+//! header lists one of those types. the rewrite is a regex replace like thumbnails:
+//! `IMAGE_URL_REPLACEMENT` may use `$1`-style groups and `{format}` for the chosen
+//! extension. `IMAGE_FORMATS` lists what the cdn has, most preferred first. only
+//! explicit `image/webp` / `image/avif` entries count; `*/*` says nothing about support.
+
+use crate::search::BufoResult;
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// an alternate image format the cdn may serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Avif,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "avif",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "avif" => Ok(ImageFormat::Avif),
+            "webp" => Ok(ImageFormat::Webp),
+            other => Err(format!(
+                "unknown image format {:?} (expected avif or webp)",
+                other
+            )),
+        }
+    }
+}
+
+/// whether `accept` lists `mime_type` with a non-zero quality
+pub fn accepts(accept: &str, mime_type: &str) -> bool {
+    accept.split(',').any(|range| {
+        let mut parts = range.split(';');
+        let matches = parts
+            .next()
+            .is_some_and(|media| media.trim().eq_ignore_ascii_case(mime_type));
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        matches && quality > 0.0
+    })
+}
+
+/// regex replace from an original image url to its alternate-format url
+#[derive(Debug, Clone)]
+pub struct ImageRewrite {
+    pattern: Regex,
+    replacement: String,
+    /// formats the cdn serves, most preferred first
+    formats: Vec<ImageFormat>,
+}
+
+impl ImageRewrite {
+    pub fn new(
+        pattern: &str,
+        replacement: String,
+        formats: Vec<ImageFormat>,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement,
+            formats,
+        })
+    }
+
+    /// the most preferred format the client accepts, if any
+    pub fn negotiate(&self, accept: Option<&str>) -> Option<ImageFormat> {
+        let accept = accept?;
+        self.formats
+            .iter()
+            .copied()
+            .find(|format| accepts(accept, format.mime_type()))
+    }
+
+    /// `url` in `format`, or `None` when the pattern doesn't match
+    pub fn apply(&self, url: &str, format: ImageFormat) -> Option<String> {
+        let replacement = self.replacement.replace("{format}", format.extension());
+        self.pattern
+            .is_match(url)
+            .then(|| self.pattern.replace(url, replacement.as_str()).into_owned())
+    }
+}
+
+/// point result urls at `format` where the pattern matches
+pub fn rewrite_urls(results: &mut [BufoResult], rewrite: &ImageRewrite, format: ImageFormat) {
+    for result in results.iter_mut() {
+        if let Some(url) = rewrite.apply(&result.url, format) {
+            result.url = url;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(formats: Vec<ImageFormat>) -> ImageRewrite {
+        ImageRewrite::new(
+            r"^(.*)\.(png|gif)$",
+            "$1.$2?format={format}".to_string(),
+            formats,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_negotiate_follows_accept() {
+        let both = rewrite(vec![ImageFormat::Avif, ImageFormat::Webp]);
+        // chrome and firefox list both
+        let chrome = "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8";
+        assert_eq!(both.negotiate(Some(chrome)), Some(ImageFormat::Avif));
+        assert_eq!(
+            both.negotiate(Some("image/webp,*/*")),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(
+            both.negotiate(Some("image/avif;q=0, image/webp;q=0.5")),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(both.negotiate(Some("IMAGE/AVIF")), Some(ImageFormat::Avif));
+
+        // wildcards and plain json clients keep the originals
+        assert_eq!(both.negotiate(Some("*/*")), None);
+        assert_eq!(both.negotiate(Some("image/*")), None);
+        assert_eq!(both.negotiate(Some("application/json")), None);
+        assert_eq!(both.negotiate(None), None);
+
+        // only formats the cdn has
+        let webp_only = rewrite(vec![ImageFormat::Webp]);
+        assert_eq!(webp_only.negotiate(Some("image/avif")), None);
+        assert_eq!(webp_only.negotiate(Some(chrome)), Some(ImageFormat::Webp));
+    }
+
+    #[test]
+    fn test_apply_fills_format() {
+        let rewrite = rewrite(vec![ImageFormat::Webp]);
+        assert_eq!(
+            rewrite
+                .apply("https://cdn.example/bufo-happy.png", ImageFormat::Webp)
+                .as_deref(),
+            Some("https://cdn.example/bufo-happy.png?format=webp")
+        );
+        assert_eq!(
+            rewrite.apply("https://cdn.example/bufo.jpg", ImageFormat::Avif),
+            None
+        );
+        assert_eq!("WEBP".parse::<ImageFormat>(), Ok(ImageFormat::Webp));
+        assert!("jxl".parse::<ImageFormat>().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! openai embedding implementation
+//!
+//! implements the `Embedder` trait for openai's text embedding models. text-only,
+//! so it's used as a secondary ensemble signal alongside voyage rather than a replacement.
+
+use crate::models::OPENAI_MODELS;
+use crate::providers::{Embedder, EmbeddingError};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+const OPENAI_API_URL: &str = "https://api.openai.com/v1/embeddings";
+
+#[derive(Debug, Serialize)]
+struct OpenAiRequest {
+    input: Vec<String>,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiResponse {
+    data: Vec<OpenAiEmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddingData {
+    embedding: Vec<f32>,
+}
+
+/// openai text embedding client
+///
+/// the namespace it searches must be indexed with the same model (e.g. 1536-dim
+/// vectors for text-embedding-3-small).
+#[derive(Clone)]
+pub struct OpenAiEmbedder {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(api_key: String, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model,
+        }
+    }
+}
+
+impl Embedder for OpenAiEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let request = OpenAiRequest {
+            input: vec![text.to_string()],
+            model: self.model.clone(),
+        };
+
+        let response = self
+            .client
+            .post(OPENAI_API_URL)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(EmbeddingError::Api { status, body });
+        }
+
+        let openai_response: OpenAiResponse = response.json().await.map_err(|e| {
+            EmbeddingError::Other(anyhow::anyhow!("failed to parse response: {}", e))
+        })?;
+
+        openai_response
+            .data
+            .into_iter()
+            .next()
+            .map(|d| d.embedding)
+            .ok_or(EmbeddingError::EmptyResponse)
+    }
+
+    /// the model, when it's a known one, so cached vectors are kept apart per model
+    fn name(&self) -> &'static str {
+        OPENAI_MODELS
+            .iter()
+            .copied()
+            .find(|m| *m == self.model)
+            .unwrap_or("openai")
+    }
+}
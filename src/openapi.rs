@@ -0,0 +1,57 @@
+//! OpenAPI 3 description of the search API
+//!
+//! the spec is hand-written in `openapi.json` next to this file. when adding a
+//! `SearchQuery` field or response field, document it there too (for GET as a query
+//! parameter and for POST in the `SearchQuery` schema).
+
+use actix_web::HttpResponse;
+
+const SPEC: &str = include_str!("openapi.json");
+
+/// GET /api/openapi.json handler
+pub async fn spec() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(("cache-control", "public, max-age=3600"))
+        .body(SPEC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self};
+    use actix_web::{web, App};
+    use std::collections::BTreeSet;
+
+    #[actix_web::test]
+    async fn test_served_spec_is_valid_json() {
+        let app = test::init_service(App::new().route("/openapi.json", web::get().to(spec))).await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(doc["paths"]["/api/search"]["get"].is_object());
+        assert!(doc["paths"]["/api/search"]["post"].is_object());
+    }
+
+    #[test]
+    fn test_get_parameters_match_post_schema() {
+        let doc: serde_json::Value = serde_json::from_str(SPEC).unwrap();
+
+        let get_params: BTreeSet<&str> = doc["paths"]["/api/search"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        let post_fields: BTreeSet<&str> = doc["components"]["schemas"]["SearchQuery"]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+
+        assert_eq!(get_params, post_fields);
+    }
+}
@@ -0,0 +1,230 @@
+//! search plans
+//!
+//! `plan=true` describes what a search would do (the upstream calls, the alpha, the
+//! filters and post-processors) without embedding or querying anything, so a request
+//! can be checked before it costs an upstream round trip.
+
+use crate::search::skipped_sides;
+use serde::Serialize;
+
+/// an upstream call a search makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    /// query text to vector, through the embedding provider (or its cache)
+    Embed,
+    /// ANN search in a turbopuffer namespace
+    VectorSearch,
+    /// BM25 search in a turbopuffer namespace
+    KeywordSearch,
+    /// the pseudo-relevance BM25 pass, run only when the vector results suggest terms
+    ExpandedKeywordSearch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedCall {
+    pub kind: CallKind,
+    /// the embedding model for `embed`, else the namespace
+    pub target: String,
+    /// concurrent requests this call makes (texts embedded, alternatives, keyword fields)
+    pub count: usize,
+    /// why the call won't be made; absent when it will
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<&'static str>,
+}
+
+/// what decides a search's upstream calls
+#[derive(Debug)]
+pub struct CallInputs<'a> {
+    pub alpha: f32,
+    pub all_signals: bool,
+    pub pseudo_relevance: bool,
+    pub model: &'a str,
+    /// the query, its spaced filename form and any alternatives
+    pub embedded_texts: usize,
+    /// the query and any alternatives
+    pub vector_queries: usize,
+    pub keyword_fields: usize,
+    pub namespaces: &'a [String],
+    /// (model, namespace) of each ensemble member
+    pub ensemble: &'a [(String, String)],
+}
+
+/// the calls made for `inputs`, in order, with the skipped ones marked
+///
+/// the ensemble is only searched alongside a single namespace.
+pub fn planned_calls(inputs: &CallInputs) -> Vec<PlannedCall> {
+    let (skip_semantic, skip_keyword) = skipped_sides(inputs.alpha, inputs.all_signals);
+    let semantic = skip_semantic.then_some("alpha is 0");
+    let keyword = skip_keyword.then_some("alpha is 1");
+    let call = |kind, target: &str, count, skipped| PlannedCall {
+        kind,
+        target: target.to_string(),
+        count,
+        skipped,
+    };
+
+    let mut calls = Vec::new();
+    for namespace in inputs.namespaces {
+        calls.push(call(
+            CallKind::Embed,
+            inputs.model,
+            inputs.embedded_texts,
+            semantic,
+        ));
+        calls.push(call(
+            CallKind::VectorSearch,
+            namespace,
+            inputs.vector_queries,
+            semantic,
+        ));
+        calls.push(call(
+            CallKind::KeywordSearch,
+            namespace,
+            inputs.keyword_fields,
+            keyword,
+        ));
+        if inputs.pseudo_relevance {
+            calls.push(call(
+                CallKind::ExpandedKeywordSearch,
+                namespace,
+                inputs.keyword_fields,
+                keyword,
+            ));
+        }
+    }
+    if inputs.namespaces.len() == 1 {
+        for (model, namespace) in inputs.ensemble {
+            calls.push(call(CallKind::Embed, model, 1, semantic));
+            calls.push(call(CallKind::VectorSearch, namespace, 1, semantic));
+        }
+    }
+    calls
+}
+
+/// the filters a search applies to its candidates
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFilters {
+    pub family_friendly: bool,
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+    /// the turbopuffer attribute filter, including `since`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<serde_json::Value>,
+    pub min_score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score_percentile: Option<f32>,
+}
+
+/// what a search would do
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchPlan {
+    /// the query after rewriting
+    pub query: String,
+    pub semantic_text: String,
+    pub keyword_text: String,
+    pub alpha: f32,
+    /// `request`, `query`, `category` or `default`
+    pub alpha_source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha_category: Option<String>,
+    pub top_k: usize,
+    /// candidates fetched per upstream search
+    pub candidate_pool: usize,
+    pub calls: Vec<PlannedCall>,
+    /// searched the same way when the primary namespace finds too few results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_namespace: Option<String>,
+    pub filters: PlannedFilters,
+    pub post_processors: Vec<&'static str>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs<'a>(
+        alpha: f32,
+        namespaces: &'a [String],
+        ensemble: &'a [(String, String)],
+    ) -> CallInputs<'a> {
+        CallInputs {
+            alpha,
+            all_signals: false,
+            pseudo_relevance: false,
+            model: "voyage-3-large",
+            embedded_texts: 1,
+            vector_queries: 1,
+            keyword_fields: 1,
+            namespaces,
+            ensemble,
+        }
+    }
+
+    fn skipped(calls: &[PlannedCall]) -> Vec<(CallKind, Option<&'static str>)> {
+        calls.iter().map(|c| (c.kind, c.skipped)).collect()
+    }
+
+    #[test]
+    fn test_alpha_extremes_skip_the_unweighted_side() {
+        let namespaces = vec!["bufos".to_string()];
+        let ensemble = vec![(
+            "text-embedding-3-small".to_string(),
+            "bufos-openai".to_string(),
+        )];
+
+        let semantic_only = planned_calls(&inputs(1.0, &namespaces, &ensemble));
+        assert_eq!(
+            skipped(&semantic_only),
+            vec![
+                (CallKind::Embed, None),
+                (CallKind::VectorSearch, None),
+                (CallKind::KeywordSearch, Some("alpha is 1")),
+                (CallKind::Embed, None),
+                (CallKind::VectorSearch, None),
+            ]
+        );
+
+        let mut keyword_only = inputs(0.0, &namespaces, &ensemble);
+        keyword_only.pseudo_relevance = true;
+        assert_eq!(
+            skipped(&planned_calls(&keyword_only)),
+            vec![
+                (CallKind::Embed, Some("alpha is 0")),
+                (CallKind::VectorSearch, Some("alpha is 0")),
+                (CallKind::KeywordSearch, None),
+                (CallKind::ExpandedKeywordSearch, None),
+                (CallKind::Embed, Some("alpha is 0")),
+                (CallKind::VectorSearch, Some("alpha is 0")),
+            ]
+        );
+
+        // views compare every signal, so nothing is skipped
+        let mut views = inputs(0.0, &namespaces, &[]);
+        views.all_signals = true;
+        assert!(planned_calls(&views).iter().all(|c| c.skipped.is_none()));
+    }
+
+    #[test]
+    fn test_federated_plans_skip_the_ensemble() {
+        let namespaces = vec!["bufos".to_string(), "bufos-archive".to_string()];
+        let ensemble = vec![(
+            "text-embedding-3-small".to_string(),
+            "bufos-openai".to_string(),
+        )];
+        let calls = planned_calls(&inputs(0.5, &namespaces, &ensemble));
+
+        let targets: Vec<&str> = calls.iter().map(|c| c.target.as_str()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                "voyage-3-large",
+                "bufos",
+                "bufos",
+                "voyage-3-large",
+                "bufos-archive",
+                "bufos-archive",
+            ]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! result post-processing pipeline
+//!
+//! after fusion, candidates pass through an ordered list of steps before `min_score`
+//! and `top_k` are applied. the content filter (family-friendly, include, exclude) is
+//! always the first step; the rest are toggled and ordered with `POST_PROCESSORS`,
+//! which defaults to `boost` alone. leaving `boost` out turns off `boost_popularity`.
+
+use crate::filter::{ContentFilter, RejectionCounts};
+use crate::search::BufoResult;
+use crate::tokenize::tokenize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// decimal places kept by the `round` step
+const ROUND_DECIMALS: i32 = 3;
+
+/// score multiplier the `diversify` step applies per earlier result of the same family
+const DIVERSIFY_PENALTY: f32 = 0.9;
+
+/// name tokens shared by nearly every bufo, skipped when finding a name's family
+const FAMILY_STOP_TOKENS: &[&str] = &["bufo", "bufos"];
+
+/// sort by score descending; ties keep their order
+fn sort_by_score(results: &mut [BufoResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// a step that transforms the candidate list
+///
+/// input is sorted by score descending; steps that change scores must re-sort.
+pub trait PostProcessor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn process(&self, results: Vec<BufoResult>) -> Vec<BufoResult>;
+
+    /// candidates this step has rejected so far, by reason (for tracing)
+    fn rejections(&self) -> RejectionCounts {
+        RejectionCounts::default()
+    }
+}
+
+/// the request's content filter, tallying why candidates were rejected
+pub struct ContentFilterStep {
+    filter: ContentFilter,
+    rejections: Mutex<RejectionCounts>,
+}
+
+impl ContentFilterStep {
+    pub fn new(filter: ContentFilter) -> Self {
+        Self {
+            filter,
+            rejections: Mutex::new(RejectionCounts::default()),
+        }
+    }
+}
+
+impl PostProcessor for ContentFilterStep {
+    fn name(&self) -> &'static str {
+        "filter"
+    }
+
+    fn process(&self, results: Vec<BufoResult>) -> Vec<BufoResult> {
+        let mut rejections = self.rejections.lock().unwrap_or_else(|e| e.into_inner());
+        results
+            .into_iter()
+            .filter(|r| match self.filter.check(r) {
+                Some(reason) => {
+                    rejections.record(reason);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    fn rejections(&self) -> RejectionCounts {
+        *self.rejections.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// drops results whose name was already seen (e.g. the same bufo as png and gif)
+pub struct Dedup;
+
+impl PostProcessor for Dedup {
+    fn name(&self) -> &'static str {
+        "dedup"
+    }
+
+    fn process(&self, results: Vec<BufoResult>) -> Vec<BufoResult> {
+        let mut seen = HashSet::new();
+        results
+            .into_iter()
+            .filter(|r| seen.insert(r.name.clone()))
+            .collect()
+    }
+}
+
+/// rounds scores for display; ordering is unchanged
+pub struct RoundScores;
+
+impl PostProcessor for RoundScores {
+    fn name(&self) -> &'static str {
+        "round"
+    }
+
+    fn process(&self, mut results: Vec<BufoResult>) -> Vec<BufoResult> {
+        let factor = 10f32.powi(ROUND_DECIMALS);
+        for result in &mut results {
+            result.score = (result.score * factor).round() / factor;
+        }
+        results
+    }
+}
+
+/// scales scores by the request's `boost_popularity` and re-sorts
+///
+/// `score * (1 + weight * popularity / max_popularity)`, with the max taken over the
+/// results the step sees. results without a popularity (or with a non-positive one)
+/// keep their score.
+pub struct PopularityBoost {
+    weight: f32,
+}
+
+impl PopularityBoost {
+    pub fn new(weight: f32) -> Self {
+        Self { weight }
+    }
+}
+
+impl PostProcessor for PopularityBoost {
+    fn name(&self) -> &'static str {
+        "boost"
+    }
+
+    fn process(&self, mut results: Vec<BufoResult>) -> Vec<BufoResult> {
+        let max_popularity = results
+            .iter()
+            .filter_map(|r| r.popularity)
+            .fold(0.0_f32, f32::max);
+        if self.weight == 0.0 || max_popularity <= 0.0 {
+            return results;
+        }
+
+        for result in &mut results {
+            let normalized = result
+                .popularity
+                .map(|p| (p / max_popularity).clamp(0.0, 1.0))
+                .unwrap_or(0.0);
+            result.score *= 1.0 + self.weight * normalized;
+        }
+        sort_by_score(&mut results);
+        results
+    }
+}
+
+/// demotes results from a name family already ranked above them, and re-sorts
+///
+/// a name's family is its first token after "bufo" (`bufo-happy-dance` is a `happy`);
+/// each earlier result of the same family multiplies the score by `DIVERSIFY_PENALTY`,
+/// so a page isn't all one kind of bufo unless nothing else comes close.
+pub struct Diversify;
+
+fn name_family(name: &str) -> Option<String> {
+    tokenize(name)
+        .into_iter()
+        .find(|t| !FAMILY_STOP_TOKENS.contains(&t.as_str()))
+}
+
+impl PostProcessor for Diversify {
+    fn name(&self) -> &'static str {
+        "diversify"
+    }
+
+    fn process(&self, mut results: Vec<BufoResult>) -> Vec<BufoResult> {
+        let mut seen: HashMap<String, i32> = HashMap::new();
+        for result in &mut results {
+            let Some(family) = name_family(&result.name) else {
+                continue;
+            };
+            let earlier = seen.entry(family).or_insert(0);
+            result.score *= DIVERSIFY_PENALTY.powi(*earlier);
+            *earlier += 1;
+        }
+        sort_by_score(&mut results);
+        results
+    }
+}
+
+/// keeps only results known to be animated (`only_animated`)
+pub struct AnimatedOnly;
+
+impl PostProcessor for AnimatedOnly {
+    fn name(&self) -> &'static str {
+        "animated"
+    }
+
+    fn process(&self, results: Vec<BufoResult>) -> Vec<BufoResult> {
+        results
+            .into_iter()
+            .filter(|r| r.animated == Some(true))
+            .collect()
+    }
+}
+
+/// configurable steps, named in `POST_PROCESSORS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Boost,
+    Dedup,
+    Diversify,
+    Round,
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "boost" => Ok(Step::Boost),
+            "dedup" => Ok(Step::Dedup),
+            "diversify" => Ok(Step::Diversify),
+            "round" => Ok(Step::Round),
+            other => Err(format!("unknown post-processor: {}", other)),
+        }
+    }
+}
+
+/// parse a comma-separated step list, ignoring empty entries
+pub fn parse_steps(raw: &str) -> Result<Vec<Step>, String> {
+    raw.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// ordered post-processing steps
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn PostProcessor>>,
+}
+
+impl Pipeline {
+    /// the content filter followed by the configured steps; `boost` scales by
+    /// `popularity_boost`
+    pub fn from_config(
+        steps: &[Step],
+        content_filter: ContentFilter,
+        popularity_boost: f32,
+    ) -> Self {
+        let mut pipeline = Pipeline::default().with(ContentFilterStep::new(content_filter));
+        for step in steps {
+            pipeline = match step {
+                Step::Boost => pipeline.with(PopularityBoost::new(popularity_boost)),
+                Step::Dedup => pipeline.with(Dedup),
+                Step::Diversify => pipeline.with(Diversify),
+                Step::Round => pipeline.with(RoundScores),
+            };
+        }
+        pipeline
+    }
+
+    pub fn with(mut self, step: impl PostProcessor + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    pub fn step_names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|s| s.name()).collect()
+    }
+
+    /// rejection counts summed across every step
+    pub fn rejections(&self) -> RejectionCounts {
+        self.steps
+            .iter()
+            .map(|s| s.rejections())
+            .fold(RejectionCounts::default(), |acc, r| RejectionCounts {
+                rejected_by_blocklist: acc.rejected_by_blocklist + r.rejected_by_blocklist,
+                rejected_by_exclude: acc.rejected_by_exclude + r.rejected_by_exclude,
+            })
+    }
+
+    pub fn run(&self, results: Vec<BufoResult>) -> Vec<BufoResult> {
+        self.steps
+            .iter()
+            .fold(results, |results, step| step.process(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, name: &str, score: f32) -> BufoResult {
+        BufoResult {
+            id: id.to_string(),
+            url: String::new(),
+            name: name.to_string(),
+            score,
+            thumbnail_url: None,
+            highlights: Vec::new(),
+            source: String::new(),
+            animated: None,
+            display_name: None,
+            width: None,
+            height: None,
+            aspect_ratio: None,
+            popularity: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        assert_eq!(parse_steps("dedup, round").unwrap(), vec![Step::Dedup, Step::Round]);
+        assert_eq!(
+            parse_steps("boost,diversify").unwrap(),
+            vec![Step::Boost, Step::Diversify]
+        );
+        assert_eq!(parse_steps("").unwrap(), vec![]);
+        assert!(parse_steps("dedup,shuffle").is_err());
+    }
+
+    #[test]
+    fn test_dedup_keeps_first() {
+        let results = Dedup.process(vec![
+            result("1", "bufo-happy", 0.9),
+            result("2", "bufo-sad", 0.5),
+            result("3", "bufo-happy", 0.4),
+        ]);
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+    }
+
+    fn popular(id: &str, score: f32, popularity: Option<f32>) -> BufoResult {
+        BufoResult {
+            popularity,
+            ..result(id, &format!("bufo-{}", id), score)
+        }
+    }
+
+    #[test]
+    fn test_popularity_boost_reorders() {
+        let results = PopularityBoost::new(0.5).process(vec![
+            popular("a", 0.6, Some(10.0)),
+            popular("b", 0.5, Some(100.0)),
+        ]);
+
+        // a: 0.6 * (1 + 0.5 * 0.1) = 0.63, b: 0.5 * (1 + 0.5 * 1.0) = 0.75
+        assert_eq!(results[0].id, "b");
+        assert!((results[0].score - 0.75).abs() < 0.001);
+        assert!((results[1].score - 0.63).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_popularity_boost_zero_weight_is_noop() {
+        let results = PopularityBoost::new(0.0).process(vec![
+            popular("a", 0.6, None),
+            popular("b", 0.5, Some(100.0)),
+        ]);
+        let scores: Vec<(&str, f32)> = results.iter().map(|r| (r.id.as_str(), r.score)).collect();
+        assert_eq!(scores, vec![("a", 0.6), ("b", 0.5)]);
+    }
+
+    #[test]
+    fn test_popularity_boost_missing_values_unchanged() {
+        let results = PopularityBoost::new(0.1).process(vec![
+            popular("a", 0.6, None),
+            popular("b", 0.5, Some(5.0)),
+            popular("c", 0.4, Some(-3.0)),
+        ]);
+        let a = results.iter().find(|r| r.id == "a").unwrap();
+        assert!((a.score - 0.6).abs() < 0.001);
+        let c = results.iter().find(|r| r.id == "c").unwrap();
+        assert!((c.score - 0.4).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_diversify_demotes_repeated_families() {
+        let results = Diversify.process(vec![
+            result("1", "bufo-happy", 0.9),
+            result("2", "bufo-happy-dance", 0.88),
+            result("3", "bufos-happy-hour", 0.87),
+            result("4", "bufo-sad", 0.85),
+        ]);
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        // 2 becomes 0.88 * 0.9 = 0.792 and 3 becomes 0.87 * 0.81 = 0.705
+        assert_eq!(ids, vec!["1", "4", "2", "3"]);
+        assert!((results[2].score - 0.792).abs() < 1e-4);
+        assert!((results[3].score - 0.7047).abs() < 1e-4);
+
+        // a lone "bufo" has no family and is left alone
+        let results = Diversify.process(vec![result("1", "bufo", 0.5), result("2", "bufo", 0.4)]);
+        assert_eq!(results[1].score, 0.4);
+    }
+
+    #[test]
+    fn test_round_scores() {
+        let results = RoundScores.process(vec![result("1", "bufo", 0.123_456)]);
+        assert!((results[0].score - 0.123).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_animated_only_drops_static_and_unknown() {
+        let mut results = vec![
+            result("1", "bufo-dance", 0.9),
+            result("2", "bufo-sad", 0.8),
+            result("3", "bufo-mystery", 0.7),
+        ];
+        results[0].animated = Some(true);
+        results[1].animated = Some(false);
+
+        let kept = AnimatedOnly.process(results);
+        let ids: Vec<&str> = kept.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["1"]);
+    }
+
+    #[test]
+    fn test_pipeline_filters_first_then_runs_steps_in_order() {
+        let filter = ContentFilter::new(true, Some("sad"), None);
+        let pipeline = Pipeline::from_config(&[Step::Dedup, Step::Round], filter, 0.0);
+        assert_eq!(pipeline.step_names(), vec!["filter", "dedup", "round"]);
+
+        let results = pipeline.run(vec![
+            result("1", "bufo-juicy", 0.95),
+            result("2", "bufo-happy", 0.91234),
+            result("3", "bufo-sad", 0.8),
+            result("4", "bufo-happy", 0.7),
+        ]);
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["2"]);
+        assert!((results[0].score - 0.912).abs() < 1e-6);
+
+        let rejections = pipeline.rejections();
+        assert_eq!(rejections.rejected_by_blocklist, 1);
+        assert_eq!(rejections.rejected_by_exclude, 1);
+    }
+
+    #[test]
+    fn test_pipeline_boosts_by_the_requested_weight() {
+        let filter = ContentFilter::new(false, None, None);
+        let pipeline = Pipeline::from_config(&[Step::Boost], filter, 1.0);
+        assert_eq!(pipeline.step_names(), vec!["filter", "boost"]);
+
+        let results = pipeline.run(vec![
+            popular("a", 0.6, None),
+            popular("b", 0.5, Some(100.0)),
+        ]);
+        assert_eq!(results[0].id, "b");
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+    }
+}
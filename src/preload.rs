@@ -0,0 +1,172 @@
+//! startup warm-up of the embedding cache for popular queries
+//!
+//! reads one query per line from `POPULAR_QUERIES_PATH` (blank lines and `#` comments
+//! are skipped) and embeds each through the caching embedder, at most
+//! `PRELOAD_CONCURRENCY` at a time so startup doesn't burst the embedding api.
+
+use crate::config::Config;
+use crate::providers::Embedder;
+use crate::query_form;
+use crate::rewrite;
+use crate::search;
+use crate::state::AppState;
+use std::collections::HashSet;
+use tokio::task::JoinSet;
+
+/// outcome of a preload run
+#[derive(Debug, Default)]
+pub struct PreloadSummary {
+    pub preloaded: usize,
+    pub failed: Vec<String>,
+}
+
+/// parse the popular queries file, dropping blanks, comments and duplicates
+pub fn parse_queries(contents: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| seen.insert(line.to_string()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// embed every query with at most `concurrency` requests in flight
+pub async fn preload<E: Embedder + Clone + 'static>(
+    embedder: &E,
+    queries: Vec<String>,
+    concurrency: usize,
+) -> PreloadSummary {
+    let concurrency = concurrency.max(1);
+    let mut summary = PreloadSummary::default();
+    let mut tasks = JoinSet::new();
+
+    let mut record = |outcome: Result<(String, bool), tokio::task::JoinError>| match outcome {
+        Ok((_, true)) => summary.preloaded += 1,
+        Ok((query, false)) => summary.failed.push(query),
+        Err(_) => summary.failed.push("<panicked>".to_string()),
+    };
+
+    for query in queries {
+        if tasks.len() >= concurrency {
+            if let Some(outcome) = tasks.join_next().await {
+                record(outcome);
+            }
+        }
+
+        let embedder = embedder.clone();
+        tasks.spawn(async move {
+            let ok = embedder.embed(&query).await.is_ok();
+            (query, ok)
+        });
+    }
+
+    while let Some(outcome) = tasks.join_next().await {
+        record(outcome);
+    }
+
+    summary
+}
+
+/// the texts a live search for `query` would embed with the primary embedder: the
+/// rewritten query after language handling, and its filename form when blending
+fn embedded_texts(config: &Config, query: &str) -> Vec<String> {
+    let rewritten = rewrite::from_config(config.query_normalization, config.query_collapse_repeats)
+        .rewrite(query);
+    let language = search::detect_language(config, &rewritten);
+    let semantic = search::semantic_text(config, &rewritten, language.as_ref());
+    let spaced = (config.filename_blend_weight > 0.0)
+        .then(|| query_form::spaced_form(&semantic))
+        .flatten();
+    std::iter::once(semantic).chain(spaced).collect()
+}
+
+/// warm the shared cache from `POPULAR_QUERIES_PATH`; a no-op when it's unset
+///
+/// queries go through the same rewriting, language handling and filename blending as
+/// live searches so the cached vectors match what a request would embed. only the
+/// primary embedder is warmed.
+pub async fn preload_popular_queries(config: Config, state: AppState) {
+    let Some(path) = config.popular_queries_path.clone() else {
+        return;
+    };
+
+    let contents = match tokio::fs::read_to_string(&path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            let error = e.to_string();
+            logfire::warn!("failed to read popular queries", path = &path, error = &error);
+            return;
+        }
+    };
+
+    let queries: Vec<String> = parse_queries(&contents)
+        .iter()
+        .flat_map(|q| embedded_texts(&config, q))
+        .collect();
+    let total = queries.len();
+
+    let embedder = search::build_embedder(&config, &state);
+    let summary = preload(&embedder, queries, config.preload_concurrency).await;
+
+    let failed = summary.failed.join(", ");
+    logfire::info!(
+        "popular queries preloaded",
+        path = &path,
+        total = total as i64,
+        preloaded = summary.preloaded as i64,
+        failed_count = summary.failed.len() as i64,
+        failed = &failed,
+        cache_entries = state.embedding_cache.stats().entries as i64
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::EmbeddingError;
+
+    #[derive(Clone)]
+    struct FailingOn(&'static str);
+
+    impl Embedder for FailingOn {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            if text == self.0 {
+                Err(EmbeddingError::EmptyResponse)
+            } else {
+                Ok(vec![1.0])
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "failing-on"
+        }
+    }
+
+    #[test]
+    fn test_parse_queries() {
+        let queries = parse_queries("happy\n\n# comment\n  sad  \nhappy\n");
+        assert_eq!(queries, vec!["happy", "sad"]);
+    }
+
+    #[test]
+    fn test_embedded_texts_are_rewritten_like_searches() {
+        let mut config = Config::for_tests();
+        config.query_normalization = true;
+        config.query_collapse_repeats = Some(2);
+        assert_eq!(embedded_texts(&config, "  Happyyyy "), vec!["happyy"]);
+
+        config.query_collapse_repeats = None;
+        assert_eq!(embedded_texts(&config, "happyyyy"), vec!["happyyyy"]);
+    }
+
+    #[tokio::test]
+    async fn test_preload_reports_failures() {
+        let queries = vec!["happy".into(), "broken".into(), "sad".into()];
+        let summary = preload(&FailingOn("broken"), queries, 2).await;
+
+        assert_eq!(summary.preloaded, 2);
+        assert_eq!(summary.failed, vec!["broken".to_string()]);
+    }
+}
@@ -12,6 +12,7 @@
 //! - async-openai's `Config` trait for backend abstraction
 //! - tower's `Service` trait for composability (though simpler here)
 
+use serde::Deserialize;
 use std::future::Future;
 use thiserror::Error;
 
@@ -27,6 +28,19 @@ pub enum EmbeddingError {
     #[error("no embedding returned from provider")]
     EmptyResponse,
 
+    #[error(
+        "{model} returned {actual}-dimensional embeddings but EXPECTED_EMBEDDING_DIM is {expected}; \
+         the namespace must be indexed with the same model and dimension"
+    )]
+    DimensionMismatch {
+        model: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("{model} is unavailable: circuit breaker open after repeated failures")]
+    CircuitOpen { model: &'static str },
+
     #[error("{0}")]
     Other(#[from] anyhow::Error),
 }
@@ -42,13 +56,75 @@ pub enum EmbeddingError {
 /// let embedding = client.embed("hello world").await?;
 /// ```
 pub trait Embedder: Send + Sync {
-    /// generate an embedding vector for the given text
+    /// generate a query embedding for the given text
     fn embed(&self, text: &str) -> impl Future<Output = Result<Vec<f32>, EmbeddingError>> + Send;
 
+    /// generate an embedding for the given role
+    ///
+    /// asymmetric models (voyage) embed queries and documents differently; the default
+    /// ignores the role and calls `embed`.
+    fn embed_as(
+        &self,
+        text: &str,
+        input_type: InputType,
+    ) -> impl Future<Output = Result<Vec<f32>, EmbeddingError>> + Send {
+        let _ = input_type;
+        self.embed(text)
+    }
+
+    /// embed search text (same as `embed`)
+    fn embed_query(&self, text: &str) -> impl Future<Output = Result<Vec<f32>, EmbeddingError>> + Send {
+        self.embed_as(text, InputType::Query)
+    }
+
+    /// embed text the way indexed bufos are embedded (e.g. for "more like this")
+    fn embed_document(
+        &self,
+        text: &str,
+    ) -> impl Future<Output = Result<Vec<f32>, EmbeddingError>> + Send {
+        self.embed_as(text, InputType::Document)
+    }
+
+    /// embed several texts for the given role, in order
+    ///
+    /// providers that take many inputs per request (voyage) send them together; the
+    /// default embeds one at a time. callers with more texts than one request allows go
+    /// through `batch::embed_chunked`.
+    fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> impl Future<Output = Result<Vec<Vec<f32>>, EmbeddingError>> + Send {
+        async move {
+            let mut embeddings = Vec::with_capacity(texts.len());
+            for text in texts {
+                embeddings.push(self.embed_as(text, input_type).await?);
+            }
+            Ok(embeddings)
+        }
+    }
+
     /// human-readable name for logging/debugging
     fn name(&self) -> &'static str;
 }
 
+/// what the embedded text is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputType {
+    Query,
+    Document,
+}
+
+impl InputType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InputType::Query => "query",
+            InputType::Document => "document",
+        }
+    }
+}
+
 /// errors that can occur during vector search
 #[derive(Debug, Error)]
 pub enum VectorSearchError {
@@ -61,6 +137,9 @@ pub enum VectorSearchError {
     #[error("query too long: {message}")]
     QueryTooLong { message: String },
 
+    #[error("vector dimension mismatch: {message}")]
+    DimensionMismatch { message: String },
+
     #[error("parse error: {0}")]
     Parse(String),
 
@@ -75,9 +154,40 @@ pub struct SearchResult {
     /// raw distance/score from the backend (interpretation varies by method)
     pub score: f32,
     /// arbitrary key-value attributes
+    ///
+    /// non-string values (numbers, bools, arrays) are kept as their JSON text,
+    /// e.g. `"42"` or `"[\"animal\"]"`.
     pub attributes: std::collections::HashMap<String, String>,
 }
 
+/// parse a numeric attribute, returning `None` when missing or non-numeric
+pub fn numeric_attribute(
+    attributes: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Option<f32> {
+    attributes
+        .get(key)
+        .and_then(|v| v.trim().parse::<f32>().ok())
+        .filter(|v| v.is_finite())
+}
+
+/// parse a pixel size attribute, returning `None` unless it's a positive whole number
+pub fn dimension_attribute(
+    attributes: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Option<u32> {
+    numeric_attribute(attributes, key)
+        .filter(|v| *v >= 1.0 && v.fract() == 0.0 && *v <= u32::MAX as f32)
+        .map(|v| v as u32)
+}
+
+/// per-request options applied to both vector and keyword queries
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// backend-native attribute filter, already validated by the backend's parser
+    pub filters: Option<serde_json::Value>,
+}
+
 /// a provider that can perform vector similarity search
 pub trait VectorStore: Send + Sync {
     /// search by vector embedding (ANN/cosine similarity)
@@ -85,6 +195,7 @@ pub trait VectorStore: Send + Sync {
         &self,
         embedding: &[f32],
         top_k: usize,
+        options: &QueryOptions,
     ) -> impl Future<Output = Result<Vec<SearchResult>, VectorSearchError>> + Send;
 
     /// search by keyword (BM25 full-text search)
@@ -92,8 +203,37 @@ pub trait VectorStore: Send + Sync {
         &self,
         query: &str,
         top_k: usize,
+        options: &QueryOptions,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, VectorSearchError>> + Send;
+
+    /// BM25 search over a single full-text indexed field (`search_by_keyword` uses `name`)
+    fn search_by_keyword_field(
+        &self,
+        field: &str,
+        query: &str,
+        top_k: usize,
+        options: &QueryOptions,
     ) -> impl Future<Output = Result<Vec<SearchResult>, VectorSearchError>> + Send;
 
+    /// fetch a single document by id, or `None` if it doesn't exist
+    fn get_by_id(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Option<SearchResult>, VectorSearchError>> + Send;
+
+    /// list up to `limit` documents without ranking (scores are meaningless)
+    fn list_all(
+        &self,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, VectorSearchError>> + Send;
+
+    /// approximate number of documents; `None` when the backend doesn't report one
+    fn approx_row_count(
+        &self,
+    ) -> impl Future<Output = Result<Option<u64>, VectorSearchError>> + Send {
+        async { Ok(None) }
+    }
+
     /// human-readable name for logging/debugging
     fn name(&self) -> &'static str;
 }
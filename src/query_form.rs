@@ -0,0 +1,54 @@
+//! filename-shaped queries
+//!
+//! bufos are embedded from their filename as words ("bufo-jumping-on-bed" → "bufo
+//! jumping on bed"), so a hyphenated query embeds differently from its spaced form.
+//! with `FILENAME_BLEND_WEIGHT` above 0, both forms are embedded and the vectors are
+//! blended before the vector search; keyword search is unaffected.
+
+/// "bufo-jumping_on-bed" → "bufo jumping on bed", or `None` when there's nothing to change
+pub fn spaced_form(query: &str) -> Option<String> {
+    if !query.contains(['-', '_']) {
+        return None;
+    }
+
+    let spaced = query
+        .split(|c: char| c == '-' || c == '_' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!spaced.is_empty() && spaced != query.trim()).then_some(spaced)
+}
+
+/// `(1 - weight) * a + weight * b`
+///
+/// the result isn't re-normalized since cosine distance ignores vector length.
+pub fn blend(a: &[f32], b: &[f32], weight: f32) -> Vec<f32> {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (1.0 - weight) * x + weight * y)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spaced_form() {
+        assert_eq!(spaced_form("bufo-jumping"), Some("bufo jumping".to_string()));
+        assert_eq!(
+            spaced_form("  bufo-jumping_on--bed "),
+            Some("bufo jumping on bed".to_string())
+        );
+        assert_eq!(spaced_form("bufo jumping"), None);
+        assert_eq!(spaced_form("---"), None);
+    }
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend(&[1.0, 0.0], &[0.0, 1.0], 0.0), vec![1.0, 0.0]);
+        assert_eq!(blend(&[1.0, 0.0], &[0.0, 1.0], 0.25), vec![0.75, 0.25]);
+        assert_eq!(blend(&[1.0, 0.0], &[0.0, 1.0], 1.0), vec![0.0, 1.0]);
+    }
+}
@@ -0,0 +1,181 @@
+//! rate limiting keyed by caller
+//!
+//! anonymous requests are limited per client ip (`RATE_LIMIT_PER_MINUTE`). requests
+//! with an `X-Api-Key` listed in `API_KEYS` are limited per key instead
+//! (`AUTHENTICATED_RATE_LIMIT_PER_MINUTE`), so users behind a shared NAT don't use up
+//! each other's quota. unknown keys count as anonymous; otherwise a client could rotate
+//! made-up keys to dodge the limit.
+//!
+//! the two limits are two `Governor` middlewares on the same scope. each one's key
+//! extractor exempts the other class of caller, so every request is counted by one.
+//! both are built when the server starts, so `API_KEYS` and the limits only change on a
+//! restart (`/api/admin/reload` lists them under `requires_restart`).
+
+use actix_governor::{KeyExtractor, SimpleKeyExtractionError};
+use actix_web::dev::ServiceRequest;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// what a request is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Ip(IpAddr),
+    ApiKey(String),
+    /// the request belongs to the other limiter
+    Exempt,
+}
+
+/// keys requests by api key or by ip, for one class of caller
+#[derive(Debug, Clone)]
+pub struct CallerKeyExtractor {
+    api_keys: Arc<HashSet<String>>,
+    /// limit callers with a known api key (true) or anonymous ones (false)
+    authenticated: bool,
+}
+
+impl CallerKeyExtractor {
+    pub fn anonymous(api_keys: Arc<HashSet<String>>) -> Self {
+        Self {
+            api_keys,
+            authenticated: false,
+        }
+    }
+
+    pub fn authenticated(api_keys: Arc<HashSet<String>>) -> Self {
+        Self {
+            api_keys,
+            authenticated: true,
+        }
+    }
+
+    /// the request's api key, when it's one we know
+    fn known_key<'a>(&self, req: &'a ServiceRequest) -> Option<&'a str> {
+        req.headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|key| self.api_keys.contains(*key))
+    }
+}
+
+impl KeyExtractor for CallerKeyExtractor {
+    type Key = RateLimitKey;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        match (self.known_key(req), self.authenticated) {
+            (Some(key), true) => Ok(RateLimitKey::ApiKey(key.to_string())),
+            (Some(_), false) | (None, true) => Ok(RateLimitKey::Exempt),
+            (None, false) => req
+                .peer_addr()
+                .map(|addr| RateLimitKey::Ip(addr.ip()))
+                .ok_or_else(|| {
+                    SimpleKeyExtractionError::new("could not extract peer IP address from request")
+                }),
+        }
+    }
+
+    fn whitelisted_keys(&self) -> Vec<Self::Key> {
+        vec![RateLimitKey::Exempt]
+    }
+}
+
+/// milliseconds per request and burst size for `per_minute` requests a minute, all of
+/// which may come in one burst
+///
+/// the period is at least 1ms (governor rejects 0), so limits above 60000 a minute
+/// replenish at 1000 a second but still allow the full burst.
+pub fn quota(per_minute: u32) -> (u64, u32) {
+    let per_minute = per_minute.max(1);
+    ((60_000 / u64::from(per_minute)).max(1), per_minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_governor::{Governor, GovernorConfigBuilder};
+    use actix_web::test::{self};
+    use actix_web::{web, App, HttpResponse};
+
+    #[test]
+    fn test_quota_spreads_a_minute() {
+        assert_eq!(quota(10), (6000, 10));
+        assert_eq!(quota(60), (1000, 60));
+        assert_eq!(quota(0), (60_000, 1));
+        assert_eq!(quota(60_000), (1, 60_000));
+        assert_eq!(quota(120_000), (1, 120_000));
+
+        // governor refuses a zero period, so a huge limit must still build
+        let (period, burst) = quota(u32::MAX);
+        assert!(GovernorConfigBuilder::default()
+            .milliseconds_per_request(period)
+            .burst_size(burst)
+            .finish()
+            .is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_known_keys_get_their_own_limits() {
+        let api_keys: Arc<HashSet<String>> =
+            Arc::new(["k1", "k2"].into_iter().map(String::from).collect());
+        let (period, burst) = quota(1);
+        let anonymous = GovernorConfigBuilder::default()
+            .key_extractor(CallerKeyExtractor::anonymous(api_keys.clone()))
+            .milliseconds_per_request(period)
+            .burst_size(burst)
+            .finish()
+            .unwrap();
+        let (period, burst) = quota(2);
+        let authenticated = GovernorConfigBuilder::default()
+            .key_extractor(CallerKeyExtractor::authenticated(api_keys))
+            .milliseconds_per_request(period)
+            .burst_size(burst)
+            .finish()
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Governor::new(&anonymous))
+                .wrap(Governor::new(&authenticated))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let status = |ip: &str, key: Option<&str>| {
+            let mut req = test::TestRequest::get()
+                .uri("/")
+                .peer_addr(format!("{}:1234", ip).parse().unwrap());
+            if let Some(key) = key {
+                req = req.insert_header((API_KEY_HEADER, key));
+            }
+            let req = req.to_request();
+            let app = &app;
+            // a limited request comes back as an error rather than a response
+            async move {
+                match test::try_call_service(app, req).await {
+                    Ok(res) => res.status().as_u16(),
+                    Err(e) => e.as_response_error().status_code().as_u16(),
+                }
+            }
+        };
+
+        // anonymous: one a minute per ip
+        assert_eq!(status("10.0.0.1", None).await, 200);
+        assert_eq!(status("10.0.0.1", None).await, 429);
+
+        // the same ip with a key isn't held back by the ip's limit
+        assert_eq!(status("10.0.0.1", Some("k1")).await, 200);
+        assert_eq!(status("10.0.0.1", Some("k1")).await, 200);
+        assert_eq!(status("10.0.0.1", Some("k1")).await, 429);
+        assert_eq!(status("10.0.0.2", Some("k1")).await, 429);
+
+        // each key has its own quota
+        assert_eq!(status("10.0.0.1", Some("k2")).await, 200);
+
+        // unknown keys are anonymous
+        assert_eq!(status("10.0.0.3", Some("made-up")).await, 200);
+        assert_eq!(status("10.0.0.3", Some("another")).await, 429);
+    }
+}
@@ -0,0 +1,449 @@
+//! live configuration reload
+//!
+//! `POST /api/admin/reload` re-reads `.env`, layers it under the environment the process
+//! was launched with (as at startup, a variable set in the environment wins), parses the
+//! result with `Config::from_vars`, and swaps the new config in atomically. the process
+//! environment itself is never modified, so a variable removed from `.env` goes back to
+//! its default. requests already running finish with the config they started with. an
+//! invalid environment leaves the live config untouched.
+//!
+//! settings read per request take effect immediately: fusion and keyword options,
+//! `DEFAULT_ALPHA`, the family-friendly default, namespaces, vector filters,
+//! post-processors, thumbnails and the admin token. the alpha policy and blocked ids
+//! files are read again (even when their paths are unchanged) and swapped in with the
+//! config. each reload bumps `AppState::config_generation`, which GET search etags and
+//! cache keys hash, so responses ranked under the old settings aren't served again.
+//! settings that built the server at startup keep their old values until a
+//! restart: the bind address and connection tuning, static dir, access-log format
+//! (`LOG_QUERIES`), concurrency limit, embedding cache size, local model, vocabulary
+//! refresh interval, cluster centroids and preloading. `API_KEYS` and the rate limits
+//! are built into the rate limiter at startup, so they're restart-only too.
+
+use crate::admin::require_admin;
+use crate::alpha::AlphaPolicy;
+use crate::blocked::BlockedIds;
+use crate::config::Config;
+use crate::state::AppState;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Result as ActixResult};
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// names of the listed fields whose values differ between two configs
+macro_rules! changed_fields {
+    ($old:expr, $new:expr, $($field:ident),+ $(,)?) => {{
+        let mut changed = Vec::new();
+        $(
+            if format!("{:?}", $old.$field) != format!("{:?}", $new.$field) {
+                changed.push(stringify!($field));
+            }
+        )+
+        changed
+    }};
+}
+
+/// a shared value that reloading swaps whole (cheap to clone)
+pub struct Live<T> {
+    inner: Arc<RwLock<Arc<T>>>,
+}
+
+// derived `Clone` would require `T: Clone`
+impl<T> Clone for Live<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Live<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(value))),
+        }
+    }
+
+    pub fn current(&self) -> Arc<T> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn replace(&self, value: T) {
+        *self.inner.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(value);
+    }
+}
+
+/// the shared, swappable config
+pub type LiveConfig = Live<Config>;
+
+/// the environment the process was launched with, and the `.env` startup applied to it
+struct Launch {
+    env: HashMap<String, String>,
+    dotenv_path: Option<PathBuf>,
+}
+
+static LAUNCH: OnceLock<Launch> = OnceLock::new();
+
+/// the process environment, skipping variables that aren't unicode
+fn process_env() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// apply `.env` to the environment at startup, remembering what came before it
+///
+/// call once, first thing in `main`, so reloads can tell launch variables from `.env` ones.
+pub fn load_dotenv() {
+    let env = process_env();
+    let dotenv_path = dotenv::dotenv().ok();
+    LAUNCH.get_or_init(|| Launch { env, dotenv_path });
+}
+
+/// handler argument for the config as of the start of the request
+pub struct CurrentConfig(Arc<Config>);
+
+impl Deref for CurrentConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.0
+    }
+}
+
+impl FromRequest for CurrentConfig {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.app_data::<web::Data<AppState>>()
+                .map(|state| CurrentConfig(state.config.current()))
+                .ok_or_else(|| actix_web::error::ErrorInternalServerError("app state missing")),
+        )
+    }
+}
+
+/// keep the startup-only settings of `old` in `new`, returning the ones that differed
+fn keep_restart_only(old: &Config, new: &mut Config) -> Vec<&'static str> {
+    let pending = changed_fields!(
+        old,
+        new,
+        host,
+        port,
+        static_dir,
+        log_queries,
+        max_concurrent_searches,
+        keep_alive_secs,
+        client_timeout_secs,
+        enable_http2,
+        embedding_cache_size,
+        embedding_breaker_threshold,
+        embedding_breaker_cooldown,
+        query_history_size,
+        local_model_dir,
+        local_embedding_dimension,
+        vocabulary_refresh_secs,
+        popular_queries_path,
+        cluster_centroids_path,
+        preload_concurrency,
+        api_keys,
+        rate_limit_per_minute,
+        authenticated_rate_limit_per_minute,
+        cache_backend,
+        redis_url,
+    );
+
+    new.host = old.host.clone();
+    new.port = old.port;
+    new.static_dir = old.static_dir.clone();
+    new.log_queries = old.log_queries;
+    new.max_concurrent_searches = old.max_concurrent_searches;
+    new.keep_alive_secs = old.keep_alive_secs;
+    new.client_timeout_secs = old.client_timeout_secs;
+    new.enable_http2 = old.enable_http2;
+    new.embedding_cache_size = old.embedding_cache_size;
+    new.embedding_breaker_threshold = old.embedding_breaker_threshold;
+    new.embedding_breaker_cooldown = old.embedding_breaker_cooldown;
+    new.query_history_size = old.query_history_size;
+    new.local_model_dir = old.local_model_dir.clone();
+    new.local_embedding_dimension = old.local_embedding_dimension;
+    new.vocabulary_refresh_secs = old.vocabulary_refresh_secs;
+    new.popular_queries_path = old.popular_queries_path.clone();
+    new.cluster_centroids_path = old.cluster_centroids_path.clone();
+    new.preload_concurrency = old.preload_concurrency;
+    new.api_keys = old.api_keys.clone();
+    new.rate_limit_per_minute = old.rate_limit_per_minute;
+    new.authenticated_rate_limit_per_minute = old.authenticated_rate_limit_per_minute;
+    new.cache_backend = old.cache_backend;
+    new.redis_url = old.redis_url.clone();
+
+    pending
+}
+
+/// the per-request settings that differ (names only; several are secrets)
+fn reloaded_changes(old: &Config, new: &Config) -> Vec<&'static str> {
+    changed_fields!(
+        old,
+        new,
+        turbopuffer_api_key,
+        turbopuffer_namespace,
+        search_namespaces,
+        fallback_namespace,
+        turbopuffer_consistency,
+        vector_max_distance,
+        turbopuffer_timeout,
+        voyage_api_key,
+        voyage_output_dimension,
+        voyage_hedge_delay,
+        voyage_timeout,
+        voyage_empty_retries,
+        embed_batch_size,
+        embed_batch_concurrency,
+        expected_embedding_dim,
+        openai_api_key,
+        openai_embedding_model,
+        openai_namespace,
+        openai_ensemble_weight,
+        embedding_models,
+        default_family_friendly,
+        default_alpha,
+        bm25_prefix_match,
+        bm25_hyphen_mode,
+        bm25_min_term_length,
+        bm25_position_boost,
+        bm25_coverage_weight,
+        exact_match_boost,
+        sticky_hysteresis,
+        candidate_cap,
+        keyword_normalization,
+        similarity_curve,
+        keyword_fields,
+        attribute_merge,
+        search_cache_max_age_secs,
+        response_cache_ttl_secs,
+        query_normalization,
+        query_collapse_repeats,
+        language_detection,
+        language_hints,
+        remove_embedding_stopwords,
+        embedding_stopwords,
+        filename_blend_weight,
+        long_query_pooling,
+        long_query_threshold,
+        post_processors,
+        max_returned_results,
+        max_response_bytes,
+        max_filter_patterns,
+        slow_query_ms,
+        thumbnail_transform,
+        display_name_case,
+        https_urls,
+        image_rewrite,
+        alpha_policy_path,
+        blocked_ids_path,
+        admin_token,
+    )
+}
+
+/// `KEY=value` lines of a `.env` file
+///
+/// blank lines, `#` comments and an `export ` prefix are skipped, and one pair of
+/// matching quotes around a value is removed. `${VAR}` substitution isn't supported.
+fn parse_dotenv(raw: &str) -> HashMap<String, String> {
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let unquoted = ['"', '\'']
+                .iter()
+                .find_map(|&q| value.strip_prefix(q)?.strip_suffix(q))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), unquoted.to_string()))
+        })
+        .collect()
+}
+
+/// `dotenv` values with `env` layered over them, so the environment wins as at startup
+fn layered(
+    mut dotenv: HashMap<String, String>,
+    env: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    dotenv.extend(env.iter().map(|(key, value)| (key.clone(), value.clone())));
+    dotenv
+}
+
+/// the variables a reload parses: the current `.env` under the launch environment
+fn reload_vars() -> anyhow::Result<HashMap<String, String>> {
+    let launch = LAUNCH.get_or_init(|| Launch {
+        env: process_env(),
+        dotenv_path: None,
+    });
+    let path = launch
+        .dotenv_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(".env"));
+    let dotenv = match std::fs::read_to_string(&path) {
+        Ok(raw) => parse_dotenv(&raw),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    Ok(layered(dotenv, &launch.env))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReloadResponse {
+    /// settings now in effect with new values
+    pub changed: Vec<&'static str>,
+    /// settings that changed but keep their old values until a restart
+    pub requires_restart: Vec<&'static str>,
+}
+
+/// make reloaded settings live, so cached responses ranked under the old ones miss
+pub fn swap_in(
+    state: &AppState,
+    config: Config,
+    alpha_policy: AlphaPolicy,
+    blocked_ids: BlockedIds,
+) {
+    state.alpha_policy.replace(alpha_policy);
+    state.blocked_ids.replace(blocked_ids);
+    state.config.replace(config);
+    state.config_generation.fetch_add(1, Ordering::Relaxed);
+}
+
+/// POST /api/admin/reload handler
+pub async fn reload(
+    req: HttpRequest,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if let Err(response) = require_admin(&req, &config) {
+        return Ok(response);
+    }
+
+    let not_reloaded = |e: anyhow::Error| {
+        actix_web::error::ErrorBadRequest(format!("config not reloaded: {:#}", e))
+    };
+    let vars = reload_vars().map_err(not_reloaded)?;
+    let mut next = Config::from_vars(|key| vars.get(key).cloned()).map_err(not_reloaded)?;
+    let requires_restart = keep_restart_only(&config, &mut next);
+    let alpha_policy =
+        AlphaPolicy::load(next.alpha_policy_path.as_deref()).map_err(not_reloaded)?;
+    let blocked_ids = BlockedIds::load(next.blocked_ids_path.as_deref()).map_err(not_reloaded)?;
+    let changed = reloaded_changes(&config, &next);
+    swap_in(&state, next, alpha_policy, blocked_ids);
+
+    let changed_list = changed.join(", ");
+    let restart_list = requires_restart.join(", ");
+    logfire::info!(
+        "configuration reloaded",
+        changed = &changed_list,
+        requires_restart = &restart_list
+    );
+
+    Ok(HttpResponse::Ok().json(ReloadResponse {
+        changed,
+        requires_restart,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_only_settings_are_kept() {
+        let old = Config::for_tests();
+        let mut new = old.clone();
+        new.port = old.port + 1;
+        new.bm25_position_boost = old.bm25_position_boost + 0.5;
+        new.default_family_friendly = !old.default_family_friendly;
+
+        assert_eq!(keep_restart_only(&old, &mut new), vec!["port"]);
+        assert_eq!(new.port, old.port);
+        assert_eq!(
+            reloaded_changes(&old, &new),
+            vec!["default_family_friendly", "bm25_position_boost"]
+        );
+    }
+
+    #[test]
+    fn test_api_keys_and_rate_limits_need_a_restart() {
+        let old = Config::for_tests();
+        let mut new = old.clone();
+        new.api_keys.insert("key-one".to_string());
+        new.rate_limit_per_minute = old.rate_limit_per_minute + 1;
+
+        assert_eq!(
+            keep_restart_only(&old, &mut new),
+            vec!["api_keys", "rate_limit_per_minute"]
+        );
+        assert!(new.api_keys.is_empty());
+        assert_eq!(new.rate_limit_per_minute, old.rate_limit_per_minute);
+    }
+
+    #[test]
+    fn test_policy_files_and_default_alpha_are_reloadable() {
+        let old = Config::for_tests();
+        let mut new = old.clone();
+        new.default_alpha = 0.3;
+        new.blocked_ids_path = Some("./blocked_ids.txt".to_string());
+        new.alpha_policy_path = Some("./alpha_policy.json".to_string());
+
+        assert!(keep_restart_only(&old, &mut new).is_empty());
+        assert_eq!(
+            reloaded_changes(&old, &new),
+            vec!["default_alpha", "alpha_policy_path", "blocked_ids_path"]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let vars = parse_dotenv(
+            "# comment\n\nDEFAULT_ALPHA=0.5\nexport HOST = 127.0.0.1 \nNAME=\"bufo party\"\nQUOTE='it'\nEMPTY=\nnot a pair\n",
+        );
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["DEFAULT_ALPHA"], "0.5");
+        assert_eq!(vars["HOST"], "127.0.0.1");
+        assert_eq!(vars["NAME"], "bufo party");
+        assert_eq!(vars["QUOTE"], "it");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn test_launch_environment_wins_over_dotenv() {
+        let dotenv = parse_dotenv("DEFAULT_ALPHA=0.5\nBM25_POSITION_BOOST=0.2\n");
+        let env = HashMap::from([("DEFAULT_ALPHA".to_string(), "0.9".to_string())]);
+        let vars = layered(dotenv, &env);
+        assert_eq!(vars["DEFAULT_ALPHA"], "0.9");
+        assert_eq!(vars["BM25_POSITION_BOOST"], "0.2");
+
+        // a key dropped from .env is unset again rather than keeping its old value
+        let vars = layered(parse_dotenv("DEFAULT_ALPHA=0.5\n"), &HashMap::new());
+        assert!(!vars.contains_key("BM25_POSITION_BOOST"));
+    }
+
+    #[test]
+    fn test_live_config_swaps() {
+        let live = LiveConfig::new(Config::for_tests());
+        let before = live.current();
+
+        let mut next = (*before).clone();
+        next.bm25_position_boost += 0.25;
+        live.replace(next);
+
+        // requests holding the old config keep seeing it
+        let after = live.current();
+        assert_eq!(after.bm25_position_boost, before.bm25_position_boost + 0.25);
+        assert!(!Arc::ptr_eq(&before, &after));
+    }
+}
@@ -0,0 +1,371 @@
+//! read-through cache for rendered GET search responses
+//!
+//! entries are keyed by the response's etag (a hash of the query params, image format,
+//! envelope version, reload generation, blocked ids and `DEFAULT_ALPHA`) and expire after `RESPONSE_CACHE_TTL_SECS`. with
+//! `CACHE_BACKEND=redis`, entries live in redis at `REDIS_URL` so they survive restarts
+//! and are shared across replicas. when redis isn't configured, can't be reached at
+//! startup, or fails a call, the in-memory cache is used instead and a warning logged;
+//! a cache problem never fails a search.
+
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// redis keys are namespaced so the instance can be shared with other apps
+const KEY_PREFIX: &str = "find-bufo:search:";
+
+/// entries kept by the in-memory cache
+const MEMORY_CAPACITY: usize = 1000;
+
+/// longest a search waits on redis before using the in-memory cache
+const REDIS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// longest startup waits to connect to redis
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+/// where response cache entries are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheBackend {
+    #[default]
+    Memory,
+    Redis,
+}
+
+impl FromStr for CacheBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "memory" => Ok(CacheBackend::Memory),
+            "redis" => Ok(CacheBackend::Redis),
+            other => Err(format!(
+                "unknown cache backend: {} (use memory or redis)",
+                other
+            )),
+        }
+    }
+}
+
+/// a key-value store for rendered responses
+pub trait ResponseCache: Send + Sync {
+    /// the value stored for `key`, unless it's missing or expired
+    fn get(&self, key: &str) -> impl Future<Output = Result<Option<Vec<u8>>, CacheError>> + Send;
+
+    /// store `value` for `key` until `ttl` passes
+    fn set(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<(), CacheError>> + Send;
+
+    /// backend name for logs
+    fn name(&self) -> &'static str;
+}
+
+/// cached bodies by key, with when each expires
+type Entries = HashMap<String, (Instant, Vec<u8>)>;
+
+/// bounded in-process cache (cheap to clone)
+#[derive(Clone)]
+pub struct MemoryResponseCache {
+    entries: Arc<Mutex<Entries>>,
+    capacity: usize,
+}
+
+impl MemoryResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            capacity,
+        }
+    }
+}
+
+impl ResponseCache for MemoryResponseCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(entries
+            .get(key)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, value)| value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), CacheError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if !entries.contains_key(key) && entries.len() >= self.capacity {
+            entries.retain(|_, (expires, _)| *expires > now);
+        }
+        if !entries.contains_key(key) && entries.len() >= self.capacity {
+            // still full of live entries: the one closest to expiring goes
+            let evicted = entries
+                .iter()
+                .min_by_key(|(_, (expires, _))| *expires)
+                .map(|(key, _)| key.clone());
+            if let Some(evicted) = evicted {
+                entries.remove(&evicted);
+            }
+        }
+        entries.insert(key.to_string(), (now + ttl, value));
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+}
+
+/// cache shared through redis (cheap to clone)
+#[derive(Clone)]
+pub struct RedisResponseCache {
+    connection: ConnectionManager,
+}
+
+impl RedisResponseCache {
+    /// connect to the redis at `url`
+    pub async fn connect(url: &str) -> Result<Self, CacheError> {
+        let client = redis::Client::open(url)?;
+        let connection =
+            tokio::time::timeout(REDIS_CONNECT_TIMEOUT, ConnectionManager::new(client))
+                .await
+                .map_err(|_| CacheError::Timeout(REDIS_CONNECT_TIMEOUT))??;
+        Ok(Self { connection })
+    }
+}
+
+impl ResponseCache for RedisResponseCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let mut connection = self.connection.clone();
+        let key = format!("{}{}", KEY_PREFIX, key);
+        tokio::time::timeout(REDIS_TIMEOUT, connection.get(key))
+            .await
+            .map_err(|_| CacheError::Timeout(REDIS_TIMEOUT))?
+            .map_err(CacheError::from)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), CacheError> {
+        let mut connection = self.connection.clone();
+        let key = format!("{}{}", KEY_PREFIX, key);
+        // redis rejects an expiry of 0
+        let seconds = ttl.as_secs().max(1);
+        tokio::time::timeout(
+            REDIS_TIMEOUT,
+            connection.set_ex::<_, _, ()>(key, value, seconds),
+        )
+        .await
+        .map_err(|_| CacheError::Timeout(REDIS_TIMEOUT))?
+        .map_err(CacheError::from)
+    }
+
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+}
+
+/// the configured backend, backed by the in-memory cache whenever it fails
+///
+/// while a remote backend is healthy, the in-memory cache is left alone so replicas
+/// don't serve entries the others never saw.
+#[derive(Clone)]
+pub struct FallbackCache<R = RedisResponseCache> {
+    remote: Option<R>,
+    memory: MemoryResponseCache,
+}
+
+impl FallbackCache {
+    /// the cache for `backend`; redis that's unconfigured or unreachable falls back to memory
+    pub async fn connect(backend: CacheBackend, redis_url: Option<&str>) -> Self {
+        let remote = match (backend, redis_url) {
+            (CacheBackend::Memory, _) => None,
+            (CacheBackend::Redis, None) => {
+                logfire::warn!(
+                    "CACHE_BACKEND=redis without REDIS_URL; caching responses in memory"
+                );
+                None
+            }
+            (CacheBackend::Redis, Some(url)) => match RedisResponseCache::connect(url).await {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    let error = e.to_string();
+                    logfire::warn!(
+                        "failed to connect to redis; caching responses in memory",
+                        error = &error
+                    );
+                    None
+                }
+            },
+        };
+        Self::new(remote)
+    }
+}
+
+impl<R: ResponseCache> FallbackCache<R> {
+    pub fn new(remote: Option<R>) -> Self {
+        Self {
+            remote,
+            memory: MemoryResponseCache::new(MEMORY_CAPACITY),
+        }
+    }
+
+    /// the backend serving requests while it's healthy
+    pub fn backend(&self) -> &'static str {
+        self.remote.as_ref().map_or(self.memory.name(), R::name)
+    }
+
+    fn warn(&self, operation: &'static str, error: &CacheError) {
+        let error = error.to_string();
+        logfire::warn!(
+            "response cache failed; using memory",
+            backend = self.backend(),
+            operation = operation,
+            error = &error
+        );
+    }
+
+    /// the cached value for `key`, if any backend has one
+    pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        if let Some(remote) = &self.remote {
+            match remote.get(key).await {
+                Ok(value) => return value,
+                Err(e) => self.warn("get", &e),
+            }
+        }
+        self.memory.get(key).await.ok().flatten()
+    }
+
+    /// cache `value` for `key`
+    pub async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+        if let Some(remote) = &self.remote {
+            match remote.set(key, value.clone(), ttl).await {
+                Ok(()) => return,
+                Err(e) => self.warn("set", &e),
+            }
+        }
+        let _ = self.memory.set(key, value, ttl).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// an in-memory stand-in for a remote backend that can be taken down
+    #[derive(Clone)]
+    struct MockRemote {
+        store: MemoryResponseCache,
+        down: Arc<AtomicBool>,
+    }
+
+    impl MockRemote {
+        fn new() -> Self {
+            Self {
+                store: MemoryResponseCache::new(10),
+                down: Arc::new(AtomicBool::new(false)),
+            }
+        }
+
+        fn fail(&self) -> Result<(), CacheError> {
+            if self.down.load(Ordering::SeqCst) {
+                Err(CacheError::Timeout(REDIS_TIMEOUT))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl ResponseCache for MockRemote {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+            self.fail()?;
+            self.store.get(key).await
+        }
+
+        async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), CacheError> {
+            self.fail()?;
+            self.store.set(key, value, ttl).await
+        }
+
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+    }
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn test_memory_cache_expires_and_evicts() {
+        let cache = MemoryResponseCache::new(2);
+        cache.set("a", b"1".to_vec(), TTL).await.unwrap();
+        cache
+            .set("gone", b"2".to_vec(), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(cache.get("gone").await.unwrap(), None);
+
+        // the expired entry makes room before any live one is evicted
+        let later = TTL + Duration::from_secs(1);
+        cache.set("b", b"3".to_vec(), later).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(b"1".to_vec()));
+
+        // full of live entries: the one expiring soonest goes
+        cache.set("c", b"4".to_vec(), later).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), None);
+        assert_eq!(cache.get("b").await.unwrap(), Some(b"3".to_vec()));
+        assert_eq!(cache.get("c").await.unwrap(), Some(b"4".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_remote_is_shared_while_healthy() {
+        let remote = MockRemote::new();
+        let first = FallbackCache::new(Some(remote.clone()));
+        let second = FallbackCache::new(Some(remote.clone()));
+        assert_eq!(first.backend(), "mock");
+
+        first.set("key", b"body".to_vec(), TTL).await;
+        assert_eq!(second.get("key").await, Some(b"body".to_vec()));
+        // the local fallback stays empty while the remote works
+        assert_eq!(first.memory.get("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_memory_when_remote_fails() {
+        let remote = MockRemote::new();
+        let cache = FallbackCache::new(Some(remote.clone()));
+        remote.down.store(true, Ordering::SeqCst);
+
+        assert_eq!(cache.get("key").await, None);
+        cache.set("key", b"body".to_vec(), TTL).await;
+        assert_eq!(cache.get("key").await, Some(b"body".to_vec()));
+        assert_eq!(remote.store.get("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_redis_uses_memory() {
+        let cache = FallbackCache::connect(CacheBackend::Redis, None).await;
+        assert_eq!(cache.backend(), "memory");
+        cache.set("key", b"body".to_vec(), TTL).await;
+        assert_eq!(cache.get("key").await, Some(b"body".to_vec()));
+
+        assert_eq!("redis".parse::<CacheBackend>(), Ok(CacheBackend::Redis));
+        assert!("memcached".parse::<CacheBackend>().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! retries for empty embedding responses
+//!
+//! voyage now and then answers a valid request with an empty `data` array. that's
+//! transient, so `EmptyResponse` is retried up to `VOYAGE_EMPTY_RETRIES` times
+//! (default 1) before giving up. other errors are returned immediately: http failures
+//! are the hedge's business, and retrying them here would multiply its duplicate
+//! requests.
+
+use crate::providers::{Embedder, EmbeddingError, InputType};
+
+/// an embedder that asks again when the provider returns no embedding
+#[derive(Clone)]
+pub struct RetryOnEmpty<E> {
+    inner: E,
+    /// extra attempts after the first (0 disables retrying)
+    retries: u32,
+}
+
+impl<E: Embedder> RetryOnEmpty<E> {
+    pub fn new(inner: E, retries: u32) -> Self {
+        Self { inner, retries }
+    }
+
+    /// run `call`, again while it comes back empty and retries remain
+    async fn retrying<T, F, Fut>(&self, call: F) -> Result<T, EmbeddingError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T, EmbeddingError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match call().await {
+                Err(EmbeddingError::EmptyResponse) if attempt < self.retries => {
+                    attempt += 1;
+                    logfire::warn!(
+                        "empty embedding response, retrying",
+                        model = self.inner.name(),
+                        attempt = attempt as i64,
+                        max_retries = self.retries as i64
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<E: Embedder> Embedder for RetryOnEmpty<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed_as(text, InputType::Query).await
+    }
+
+    async fn embed_as(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, EmbeddingError> {
+        self.retrying(|| self.inner.embed_as(text, input_type))
+            .await
+    }
+
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        self.retrying(|| self.inner.embed_batch(texts, input_type))
+            .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// returns `EmptyResponse` for the first `empty` calls, then a one-element embedding
+    #[derive(Clone)]
+    struct EmptyFirst {
+        calls: Arc<AtomicUsize>,
+        empty: usize,
+    }
+
+    impl EmptyFirst {
+        fn new(empty: usize) -> Self {
+            Self {
+                calls: Arc::new(AtomicUsize::new(0)),
+                empty,
+            }
+        }
+    }
+
+    impl Embedder for EmptyFirst {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.empty {
+                Err(EmbeddingError::EmptyResponse)
+            } else {
+                Ok(vec![1.0])
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "empty-first"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_then_embedding_succeeds() {
+        let inner = EmptyFirst::new(1);
+        let embedder = RetryOnEmpty::new(inner.clone(), 1);
+
+        assert_eq!(embedder.embed("happy").await.unwrap(), vec![1.0]);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_retries() {
+        let inner = EmptyFirst::new(3);
+        let embedder = RetryOnEmpty::new(inner.clone(), 2);
+
+        assert!(matches!(
+            embedder.embed("happy").await,
+            Err(EmbeddingError::EmptyResponse)
+        ));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+
+        let inner = EmptyFirst::new(1);
+        let embedder = RetryOnEmpty::new(inner.clone(), 0);
+        assert!(embedder.embed("happy").await.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        #[derive(Clone)]
+        struct Failing(Arc<AtomicUsize>);
+
+        impl Embedder for Failing {
+            async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Err(EmbeddingError::Api {
+                    status: 500,
+                    body: String::new(),
+                })
+            }
+
+            fn name(&self) -> &'static str {
+                "failing"
+            }
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let embedder = RetryOnEmpty::new(Failing(calls.clone()), 3);
+        assert!(matches!(
+            embedder.embed("happy").await,
+            Err(EmbeddingError::Api { status: 500, .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! query rewriting before search
+//!
+//! a `QueryRewriter` sees the query text first thing in a search, before language
+//! detection, embedding and BM25, so every later stage works on the same rewritten
+//! text. `QUERY_NORMALIZATION=true` selects `Normalize`, and `QUERY_COLLAPSE_REPEATS`
+//! (on by default) shortens stretched words like "happyyyy" with `CollapseRepeats`.
+//! the embedding cache applies `Normalize` to embedded text either way (see `cache`).
+
+/// turns the query as sent into the query that's searched
+pub trait QueryRewriter: Send + Sync {
+    /// the rewritten query; unchanged by default
+    fn rewrite(&self, query: &str) -> String {
+        query.to_string()
+    }
+}
+
+/// searches queries exactly as sent
+pub struct Passthrough;
+
+impl QueryRewriter for Passthrough {}
+
+/// trims, collapses runs of whitespace to one space and lowercases
+pub struct Normalize;
+
+impl QueryRewriter for Normalize {
+    fn rewrite(&self, query: &str) -> String {
+        query
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    }
+}
+
+/// shortest run of one character that `CollapseRepeats` shortens; doubles are left
+/// alone since english is full of them ("happy", "cool")
+const MIN_COLLAPSED_RUN: usize = 3;
+
+/// `text` with every run of `MIN_COLLAPSED_RUN` or more identical characters cut to `keep`
+///
+/// works on unicode characters, not bytes. digits are never collapsed, so "1000" stays
+/// a number.
+pub fn collapse_repeats(text: &str, keep: usize) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut run = 1;
+        while chars.next_if_eq(&c).is_some() {
+            run += 1;
+        }
+        let kept = if run >= MIN_COLLAPSED_RUN && !c.is_numeric() {
+            keep.min(run)
+        } else {
+            run
+        };
+        collapsed.extend(std::iter::repeat_n(c, kept));
+    }
+    collapsed
+}
+
+/// cuts runs of 3 or more identical characters to `keep` ("soooo cute" → "soo cute")
+pub struct CollapseRepeats {
+    pub keep: usize,
+}
+
+impl QueryRewriter for CollapseRepeats {
+    fn rewrite(&self, query: &str) -> String {
+        collapse_repeats(query, self.keep)
+    }
+}
+
+/// applies each rewriter in turn
+pub struct Chain(Vec<Box<dyn QueryRewriter>>);
+
+impl QueryRewriter for Chain {
+    fn rewrite(&self, query: &str) -> String {
+        self.0
+            .iter()
+            .fold(query.to_string(), |text, rewriter| rewriter.rewrite(&text))
+    }
+}
+
+/// the configured rewriter; `collapse_repeats` is the run length kept, `None` to disable
+pub fn from_config(normalize: bool, collapse_repeats: Option<usize>) -> Box<dyn QueryRewriter> {
+    let mut steps: Vec<Box<dyn QueryRewriter>> = Vec::new();
+    if normalize {
+        steps.push(Box::new(Normalize));
+    }
+    if let Some(keep) = collapse_repeats {
+        steps.push(Box::new(CollapseRepeats { keep }));
+    }
+    match steps.len() {
+        0 => Box::new(Passthrough),
+        1 => steps.remove(0),
+        _ => Box::new(Chain(steps)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(Normalize.rewrite("  Happy \t BUFO\n dance "), "happy bufo dance");
+        assert_eq!(Normalize.rewrite("bufo-Jumping"), "bufo-jumping");
+        assert_eq!(Normalize.rewrite("   "), "");
+    }
+
+    #[test]
+    fn test_passthrough_is_default() {
+        assert_eq!(Passthrough.rewrite("  Happy  BUFO "), "  Happy  BUFO ");
+        assert_eq!(from_config(false, None).rewrite(" Sad "), " Sad ");
+        assert_eq!(from_config(true, None).rewrite(" Sad "), "sad");
+    }
+
+    #[test]
+    fn test_collapse_repeats() {
+        assert_eq!(collapse_repeats("happyyyyy", 2), "happyy");
+        assert_eq!(collapse_repeats("happyyyyy", 1), "happy");
+        assert_eq!(collapse_repeats("soooo cute!!!", 1), "so cute!");
+        // doubles and digits are left alone
+        assert_eq!(collapse_repeats("cool happy bufo", 1), "cool happy bufo");
+        assert_eq!(collapse_repeats("1000 bufos", 1), "1000 bufos");
+        // runs are per character, not per byte
+        assert_eq!(collapse_repeats("ñññño 🐸🐸🐸🐸", 1), "ño 🐸");
+        assert_eq!(collapse_repeats("ééé", 2), "éé");
+        assert_eq!(collapse_repeats("", 2), "");
+    }
+
+    #[test]
+    fn test_rewriters_chain_in_order() {
+        let rewriter = from_config(true, Some(2));
+        assert_eq!(rewriter.rewrite("  SOOOO   Cute "), "soo cute");
+        assert_eq!(from_config(false, Some(1)).rewrite("Yaaay"), "Yay");
+    }
+}
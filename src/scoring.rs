@@ -6,7 +6,14 @@
 //! ## normalization strategies
 //!
 //! - **cosine distance → similarity**: `1.0 - (distance / 2.0)` maps [0, 2] → [1, 0]
+//!   by default; `SimilarityCurve::Sigmoid` instead drops off around a midpoint distance,
+//!   separating very close neighbours from merely close ones
 //! - **BM25 max-scaling**: divide by max score so top result = 1.0
+//! - **BM25 sharpening** (optional, `KeywordNormalization`): softmax or power applied
+//!   to the max-scaled scores, so weak keyword matches fade instead of forming a long
+//!   mid-range tail
+//! - **multi-field BM25** (optional, `KeywordFields`): each field is normalized on its own,
+//!   then combined as a weighted sum into the single keyword signal
 //!
 //! ## fusion formula
 //!
@@ -14,9 +21,15 @@
 //! score = α * semantic + (1 - α) * keyword
 //! ```
 //!
+//! with an embedder ensemble this generalizes to `score = Σ wᵢ * signalᵢ`, where the
+//! semantic weight α is split across embedders and the weights sum to 1.
+//!
 //! reference: https://opensourceconnections.com/blog/2023/02/27/hybrid-vigor-winning-at-hybrid-search/
 
+use crate::dimension::DimensionTracker;
+use crate::tokenize::tokenize;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 /// configuration for score fusion
 #[derive(Debug, Clone)]
@@ -25,6 +38,30 @@ pub struct FusionConfig {
     pub alpha: f32,
     /// minimum fused score to include in results (filters noise)
     pub min_score: f32,
+    /// weight of the early-match boost applied to keyword scores before fusion (0 = off)
+    pub position_boost: f32,
+    /// how much keyword scores are scaled by the share of query terms in the name (0 = off)
+    pub coverage_weight: f32,
+    /// how raw BM25 scores are mapped to [0, 1]
+    pub keyword_normalization: KeywordNormalization,
+    /// how cosine distances are mapped to semantic scores
+    pub similarity_curve: SimilarityCurve,
+    /// BM25 fields making up the keyword signal, and their weights
+    pub keyword_fields: KeywordFields,
+    /// added to the fused score of names that exactly match the query (0 = off)
+    pub exact_match_boost: f32,
+    /// how attributes are combined for ids both searches returned
+    pub attribute_merge: AttributeMerge,
+    /// ids in the order a previous search returned them, for sticky ordering
+    pub prev_order: Vec<String>,
+    /// largest bonus `apply_sticky_order` gives a previously returned id
+    pub hysteresis: f32,
+    /// most candidates fetched from each upstream search, however large `top_k` is
+    pub candidate_cap: usize,
+    /// where query embedding sizes are tracked for drift (`None` skips it)
+    pub dimension_tracker: Option<DimensionTracker>,
+    /// semantic texts longer than this are embedded in chunks and pooled (`None` = never)
+    pub long_query_threshold: Option<usize>,
 }
 
 impl Default for FusionConfig {
@@ -32,17 +69,42 @@ impl Default for FusionConfig {
         Self {
             alpha: 0.7,
             min_score: 0.001,
+            position_boost: 0.0,
+            coverage_weight: 0.0,
+            keyword_normalization: KeywordNormalization::MaxScale,
+            similarity_curve: SimilarityCurve::Linear,
+            keyword_fields: KeywordFields::default(),
+            exact_match_boost: 0.0,
+            attribute_merge: AttributeMerge::default(),
+            prev_order: Vec::new(),
+            hysteresis: 0.0,
+            candidate_cap: 200,
+            dimension_tracker: None,
+            long_query_threshold: None,
         }
     }
 }
 
 impl FusionConfig {
+    /// `alpha` is clamped to [0, 1]
     pub fn new(alpha: f32) -> Self {
         Self {
-            alpha,
+            alpha: alpha.clamp(0.0, 1.0),
             ..Default::default()
         }
     }
+
+    /// expand alpha into a weight vector for `fuse_scores_multi`
+    ///
+    /// `semantic_shares` splits α across semantic signals (they should sum to 1);
+    /// the keyword weight `1 - α` is appended last.
+    pub fn weights(&self, semantic_shares: &[f32]) -> Vec<f32> {
+        semantic_shares
+            .iter()
+            .map(|share| self.alpha * share)
+            .chain(std::iter::once(1.0 - self.alpha))
+            .collect()
+    }
 }
 
 /// normalize cosine distance to similarity score
@@ -54,6 +116,72 @@ pub fn cosine_distance_to_similarity(distance: f32) -> f32 {
     1.0 - (distance / 2.0)
 }
 
+/// how cosine distance (0-2) becomes a semantic score in [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SimilarityCurve {
+    /// `1 - d / 2` (`cosine_distance_to_similarity`)
+    #[default]
+    Linear,
+    /// `1 / (1 + exp(steepness * (d - midpoint)))`: 0.5 at the midpoint, and higher
+    /// steepness makes the drop around it sharper
+    Sigmoid { steepness: f32, midpoint: f32 },
+}
+
+impl SimilarityCurve {
+    pub fn similarity(&self, distance: f32) -> f32 {
+        match *self {
+            SimilarityCurve::Linear => cosine_distance_to_similarity(distance),
+            SimilarityCurve::Sigmoid {
+                steepness,
+                midpoint,
+            } => 1.0 / (1.0 + (steepness * (distance - midpoint)).exp()),
+        }
+    }
+}
+
+/// the `SIMILARITY_CURVE` form, e.g. `sigmoid:10:0.6`
+impl std::fmt::Display for SimilarityCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimilarityCurve::Linear => write!(f, "linear"),
+            SimilarityCurve::Sigmoid {
+                steepness,
+                midpoint,
+            } => write!(f, "sigmoid:{}:{}", steepness, midpoint),
+        }
+    }
+}
+
+impl FromStr for SimilarityCurve {
+    type Err = String;
+
+    /// `linear` or `sigmoid:<steepness>:<midpoint>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(':').map(str::trim);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("linear"), None, None, None) => Ok(SimilarityCurve::Linear),
+            (Some("sigmoid"), Some(steepness), Some(midpoint), None) => {
+                let steepness = match steepness.parse::<f32>() {
+                    Ok(v) if v.is_finite() && v > 0.0 => v,
+                    _ => return Err(format!("steepness must be a positive number, got {}", steepness)),
+                };
+                let midpoint = match midpoint.parse::<f32>() {
+                    Ok(v) if (0.0..=2.0).contains(&v) => v,
+                    _ => return Err(format!("midpoint must be between 0 and 2, got {}", midpoint)),
+                };
+                Ok(SimilarityCurve::Sigmoid {
+                    steepness,
+                    midpoint,
+                })
+            }
+            _ => Err(format!(
+                "unknown similarity curve: {} (expected linear or sigmoid:<steepness>:<midpoint>)",
+                s
+            )),
+        }
+    }
+}
+
 /// normalize BM25 scores using max-scaling
 ///
 /// divides all scores by the maximum score, ensuring:
@@ -73,29 +201,224 @@ pub fn normalize_bm25_scores(scores: &[(String, f32)]) -> HashMap<String, f32> {
         .collect()
 }
 
+/// how raw BM25 scores are mapped to [0, 1] before fusion
+///
+/// every strategy starts from the max-scaled score `x = score / max`, so the top match
+/// is always 1.0 and the parameters don't depend on the raw BM25 scale.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum KeywordNormalization {
+    /// `x` (linear)
+    #[default]
+    MaxScale,
+    /// `exp((x - 1) / temperature)`: softmax over the results, rescaled so the top is 1.0;
+    /// lower temperatures fade weak matches faster
+    Softmax { temperature: f32 },
+    /// `x ^ exponent`: exponents above 1 sharpen, below 1 flatten
+    Power { exponent: f32 },
+}
+
+impl KeywordNormalization {
+    pub fn normalize(&self, scores: &[(String, f32)]) -> HashMap<String, f32> {
+        let mut normalized = normalize_bm25_scores(scores);
+        match *self {
+            KeywordNormalization::MaxScale => {}
+            KeywordNormalization::Softmax { temperature } => {
+                for score in normalized.values_mut() {
+                    *score = ((*score - 1.0) / temperature).exp();
+                }
+            }
+            KeywordNormalization::Power { exponent } => {
+                for score in normalized.values_mut() {
+                    *score = score.max(0.0).powf(exponent);
+                }
+            }
+        }
+        normalized
+    }
+}
+
+/// the `KEYWORD_NORMALIZATION` form, e.g. `softmax:0.25`
+impl std::fmt::Display for KeywordNormalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeywordNormalization::MaxScale => write!(f, "max"),
+            KeywordNormalization::Softmax { temperature } => write!(f, "softmax:{}", temperature),
+            KeywordNormalization::Power { exponent } => write!(f, "power:{}", exponent),
+        }
+    }
+}
+
+impl FromStr for KeywordNormalization {
+    type Err = String;
+
+    /// `max`, `softmax:<temperature>` or `power:<exponent>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, param) = match s.trim().split_once(':') {
+            Some((kind, param)) => (kind.trim(), Some(param.trim())),
+            None => (s.trim(), None),
+        };
+
+        let positive = |name: &str| -> Result<f32, String> {
+            let raw = param.ok_or_else(|| format!("{} needs a parameter, e.g. {}:0.5", kind, kind))?;
+            match raw.parse::<f32>() {
+                Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+                _ => Err(format!("{} must be a positive number, got {}", name, raw)),
+            }
+        };
+
+        match kind {
+            "max" if param.is_none() => Ok(KeywordNormalization::MaxScale),
+            "softmax" => Ok(KeywordNormalization::Softmax {
+                temperature: positive("temperature")?,
+            }),
+            "power" => Ok(KeywordNormalization::Power {
+                exponent: positive("exponent")?,
+            }),
+            _ => Err(format!(
+                "unknown keyword normalization: {} (expected max, softmax:<t> or power:<p>)",
+                s
+            )),
+        }
+    }
+}
+
+/// BM25 fields searched for the keyword signal, with their relative weights
+///
+/// the default is `name` alone. weights are relative: each field's normalized scores are
+/// combined as `Σ (wᵢ / Σw) * scoreᵢ`, so the keyword signal stays within [0, 1] and a
+/// bufo matching in every field outranks one matching only in its name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordFields(Vec<(String, f32)>);
+
+impl Default for KeywordFields {
+    fn default() -> Self {
+        KeywordFields(vec![("name".to_string(), 1.0)])
+    }
+}
+
+impl KeywordFields {
+    /// field names, in configured order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(field, _)| field.as_str())
+    }
+
+    /// true for the default single-field (`name`) search
+    pub fn is_name_only(&self) -> bool {
+        matches!(self.0.as_slice(), [(field, _)] if field == "name")
+    }
+
+    /// weighted sum of per-field scores, given in the same order as `names`
+    pub fn combine(&self, per_field: &[HashMap<String, f32>]) -> HashMap<String, f32> {
+        let total: f32 = self.0.iter().map(|(_, weight)| weight).sum();
+        let mut combined: HashMap<String, f32> = HashMap::new();
+        for ((_, weight), scores) in self.0.iter().zip(per_field) {
+            let share = weight / total;
+            for (id, score) in scores {
+                *combined.entry(id.clone()).or_insert(0.0) += share * score;
+            }
+        }
+        combined
+    }
+}
+
+/// the `KEYWORD_FIELDS` form, e.g. `name:1,tags:0.5`
+impl std::fmt::Display for KeywordFields {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .0
+            .iter()
+            .map(|(field, weight)| format!("{}:{}", field, weight))
+            .collect();
+        write!(f, "{}", fields.join(","))
+    }
+}
+
+impl FromStr for KeywordFields {
+    type Err = String;
+
+    /// comma-separated `field` or `field:weight` entries (weight defaults to 1)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields: Vec<(String, f32)> = Vec::new();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (field, weight) = match entry.split_once(':') {
+                Some((field, raw)) => {
+                    let weight = raw
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|w| w.is_finite() && *w >= 0.0)
+                        .ok_or_else(|| {
+                            format!(
+                                "weight for {} must be a non-negative number, got {}",
+                                field.trim(),
+                                raw.trim()
+                            )
+                        })?;
+                    (field.trim(), weight)
+                }
+                None => (entry, 1.0),
+            };
+            if field.is_empty() {
+                return Err(format!("missing field name in {:?}", entry));
+            }
+            if fields.iter().any(|(existing, _)| existing == field) {
+                return Err(format!("field {} listed twice", field));
+            }
+            fields.push((field.to_string(), weight));
+        }
+
+        if fields.is_empty() {
+            return Err("at least one field is required".to_string());
+        }
+        if fields.iter().map(|(_, weight)| weight).sum::<f32>() <= 0.0 {
+            return Err("field weights must not all be zero".to_string());
+        }
+        Ok(KeywordFields(fields))
+    }
+}
+
 /// fuse semantic and keyword scores using weighted combination
 ///
 /// returns items sorted by fused score (descending), filtered by min_score.
+/// this is the two-signal special case of `fuse_scores_multi`.
 pub fn fuse_scores(
     semantic_scores: &HashMap<String, f32>,
     keyword_scores: &HashMap<String, f32>,
     config: &FusionConfig,
 ) -> Vec<(String, f32)> {
+    fuse_scores_multi(
+        &[semantic_scores, keyword_scores],
+        &config.weights(&[1.0]),
+        config.min_score,
+    )
+}
+
+/// fuse any number of normalized score maps with one weight per map
+///
+/// ids missing from a signal contribute 0 for it. returns items sorted by fused
+/// score (descending), filtered by min_score.
+pub fn fuse_scores_multi(
+    signals: &[&HashMap<String, f32>],
+    weights: &[f32],
+    min_score: f32,
+) -> Vec<(String, f32)> {
+    debug_assert_eq!(signals.len(), weights.len(), "one weight per signal");
+
     // collect all unique IDs
-    let all_ids: std::collections::HashSet<_> = semantic_scores
-        .keys()
-        .chain(keyword_scores.keys())
-        .collect();
+    let all_ids: std::collections::HashSet<&String> =
+        signals.iter().flat_map(|scores| scores.keys()).collect();
 
     let mut fused: Vec<(String, f32)> = all_ids
         .into_iter()
         .map(|id| {
-            let semantic = semantic_scores.get(id).copied().unwrap_or(0.0);
-            let keyword = keyword_scores.get(id).copied().unwrap_or(0.0);
-            let score = config.alpha * semantic + (1.0 - config.alpha) * keyword;
+            let score = signals
+                .iter()
+                .zip(weights)
+                .map(|(scores, weight)| weight * scores.get(id).copied().unwrap_or(0.0))
+                .sum::<f32>();
             (id.clone(), score)
         })
-        .filter(|(_, score)| *score > config.min_score)
+        .filter(|(_, score)| *score > min_score)
         .collect();
 
     // sort descending by score
@@ -104,10 +427,264 @@ pub fn fuse_scores(
     fused
 }
 
+/// name tokens that carry no meaning for position (nearly every name starts with them)
+const POSITION_STOP_TOKENS: &[&str] = &["bufo", "bufos"];
+
+/// how early a query term appears in a bufo name, from 1.0 (first meaningful token)
+/// falling off as `1 / (1 + position)`; 0.0 when no query term appears
+pub fn match_position_factor(name: &str, query: &str) -> f32 {
+    let query_terms = tokenize(query);
+
+    tokenize(name)
+        .into_iter()
+        .filter(|t| !POSITION_STOP_TOKENS.contains(&t.as_str()))
+        .position(|t| query_terms.contains(&t))
+        .map(|position| 1.0 / (1.0 + position as f32))
+        .unwrap_or(0.0)
+}
+
+/// boost keyword scores for names where the query matches early
+///
+/// `keyword * (1 + weight * match_position_factor)`, then max-scaled again if anything
+/// went above 1 so the keyword signal stays in [0, 1] for fusion.
+pub fn apply_position_boost(
+    keyword_scores: &mut HashMap<String, f32>,
+    names: &HashMap<String, String>,
+    query: &str,
+    weight: f32,
+) {
+    if weight == 0.0 {
+        return;
+    }
+
+    for (id, score) in keyword_scores.iter_mut() {
+        if let Some(name) = names.get(id) {
+            *score *= 1.0 + weight * match_position_factor(name, query);
+        }
+    }
+
+    let max = keyword_scores.values().copied().fold(0.0_f32, f32::max);
+    if max > 1.0 {
+        for score in keyword_scores.values_mut() {
+            *score /= max;
+        }
+    }
+}
+
+/// share of the distinct query terms that appear in `name`, in [0, 1]
+///
+/// an empty query covers everything (1.0), so it never penalizes a match.
+pub fn token_coverage(name: &str, query: &str) -> f32 {
+    let mut query_terms = tokenize(query);
+    query_terms.sort();
+    query_terms.dedup();
+    if query_terms.is_empty() {
+        return 1.0;
+    }
+
+    let name_terms = tokenize(name);
+    let present = query_terms
+        .iter()
+        .filter(|term| name_terms.contains(term))
+        .count();
+    present as f32 / query_terms.len() as f32
+}
+
+/// scale keyword scores down for names that match only some of the query terms
+///
+/// `keyword * ((1 - weight) + weight * token_coverage)`: a weight of 1 multiplies in the
+/// coverage fully, 0 leaves scores alone. scores only go down, so no rescaling is needed.
+pub fn apply_coverage_scaling(
+    keyword_scores: &mut HashMap<String, f32>,
+    names: &HashMap<String, String>,
+    query: &str,
+    weight: f32,
+) {
+    if weight == 0.0 {
+        return;
+    }
+
+    for (id, score) in keyword_scores.iter_mut() {
+        if let Some(name) = names.get(id) {
+            *score *= (1.0 - weight) + weight * token_coverage(name, query);
+        }
+    }
+}
+
+/// whether `name` is the query itself, up to case and separators
+///
+/// both sides go through the shared tokenizer, so "bufo happy", "Bufo_Happy" and
+/// "bufo-happy" all match the name "bufo-happy". an empty query matches nothing.
+pub fn is_exact_name_match(name: &str, query: &str) -> bool {
+    let query_terms = tokenize(query);
+    !query_terms.is_empty() && tokenize(name) == query_terms
+}
+
+/// lift exact name matches above the rest of the fused ranking and re-sort
+///
+/// `boost` is added to the fused score of every candidate whose name matches the query
+/// exactly; since fused scores are at most 1, the default
+/// of 1 puts them first. ties, such as several bufos sharing a name, are broken by id.
+pub fn apply_exact_match_boost(
+    fused: &mut [(String, f32)],
+    names: &HashMap<String, String>,
+    query: &str,
+    boost: f32,
+) {
+    if boost == 0.0 {
+        return;
+    }
+
+    let mut boosted = false;
+    for (id, score) in fused.iter_mut() {
+        if names
+            .get(id)
+            .is_some_and(|name| is_exact_name_match(name, query))
+        {
+            *score += boost;
+            boosted = true;
+        }
+    }
+
+    if boosted {
+        fused.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+    }
+}
+
+/// nudge the ranking toward a previous one so near-ties keep their old order
+///
+/// an id at rank `r` of the `n` in `prev_order` gets `hysteresis * (n - r) / n` added
+/// to its score (ids not in it get nothing), then candidates are re-sorted. two
+/// previously returned results only swap when the new ranking separates them by more
+/// than their bonus difference, so a small alpha change doesn't reshuffle the page.
+/// scores and order stay consistent, which keeps cursors valid.
+pub fn apply_sticky_order(fused: &mut [(String, f32)], prev_order: &[String], hysteresis: f32) {
+    if prev_order.is_empty() || hysteresis == 0.0 {
+        return;
+    }
+
+    let n = prev_order.len() as f32;
+    let mut bonus: HashMap<&str, f32> = HashMap::new();
+    for (rank, id) in prev_order.iter().enumerate() {
+        // a repeated id keeps its first (higher) rank
+        bonus
+            .entry(id.as_str())
+            .or_insert(hysteresis * (n - rank as f32) / n);
+    }
+
+    for (id, score) in fused.iter_mut() {
+        *score += bonus.get(id.as_str()).copied().unwrap_or(0.0);
+    }
+    fused.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+}
+
+/// width of each `score_histogram` bucket
+pub const HISTOGRAM_BUCKET_WIDTH: f32 = 0.1;
+
+/// count scores in `HISTOGRAM_BUCKET_WIDTH` buckets over [0, 1]
+///
+/// scores below 0 land in the first bucket and scores at or above 1 (possible with a
+/// popularity boost) in the last; NaN scores are skipped.
+pub fn score_histogram(scores: impl IntoIterator<Item = f32>) -> Vec<usize> {
+    let buckets = (1.0 / HISTOGRAM_BUCKET_WIDTH).round() as usize;
+    let mut counts = vec![0; buckets];
+    for score in scores.into_iter().filter(|s| !s.is_nan()) {
+        let bucket = (score / HISTOGRAM_BUCKET_WIDTH).floor().max(0.0) as usize;
+        counts[bucket.min(buckets - 1)] += 1;
+    }
+    counts
+}
+
+/// how attributes are combined when more than one result set returns an id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttributeMerge {
+    /// every field any set has; where several do, the first non-empty value wins
+    #[default]
+    Fields,
+    /// the whole set with the most non-empty fields; the first-seen on a tie
+    Richer,
+}
+
+impl AttributeMerge {
+    /// fold `incoming` into `existing`, the attributes already collected for the same id
+    pub fn merge(self, existing: &mut HashMap<String, String>, incoming: &HashMap<String, String>) {
+        let filled =
+            |attrs: &HashMap<String, String>| attrs.values().filter(|v| !v.is_empty()).count();
+        match self {
+            AttributeMerge::Fields => {
+                for (key, value) in incoming {
+                    if existing.get(key).is_none_or(|v| v.is_empty()) {
+                        existing.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            AttributeMerge::Richer => {
+                if filled(incoming) > filled(existing) {
+                    *existing = incoming.clone();
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for AttributeMerge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "fields" => Ok(AttributeMerge::Fields),
+            "richer" => Ok(AttributeMerge::Richer),
+            other => Err(format!(
+                "unknown attribute merge: {} (use fields or richer)",
+                other
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_attribute_merge_fields_fills_gaps() {
+        let mut existing = attrs(&[("name", "bufo-happy"), ("tags", "")]);
+        let incoming = attrs(&[("name", "bufo-happy-v2"), ("tags", "joy"), ("url", "u")]);
+        AttributeMerge::Fields.merge(&mut existing, &incoming);
+        assert_eq!(
+            existing,
+            attrs(&[("name", "bufo-happy"), ("tags", "joy"), ("url", "u")])
+        );
+    }
+
+    #[test]
+    fn test_attribute_merge_richer_takes_the_fuller_set() {
+        let mut existing = attrs(&[("name", "bufo-happy"), ("tags", "")]);
+        AttributeMerge::Richer.merge(&mut existing, &attrs(&[("url", "u"), ("tags", "joy")]));
+        assert_eq!(existing, attrs(&[("url", "u"), ("tags", "joy")]));
+
+        // a tie keeps what was there
+        AttributeMerge::Richer.merge(&mut existing, &attrs(&[("name", "a"), ("url", "b")]));
+        assert_eq!(existing, attrs(&[("url", "u"), ("tags", "joy")]));
+        assert_eq!("richer".parse(), Ok(AttributeMerge::Richer));
+        assert!("union".parse::<AttributeMerge>().is_err());
+    }
+
     #[test]
     fn test_cosine_distance_to_similarity() {
         assert!((cosine_distance_to_similarity(0.0) - 1.0).abs() < 0.001);
@@ -130,6 +707,116 @@ mod tests {
         assert!((normalized["c"] - 0.25).abs() < 0.001);
     }
 
+    fn bm25_fixture() -> Vec<(String, f32)> {
+        [("a", 10.0), ("b", 8.0), ("c", 5.0), ("d", 2.0)]
+            .into_iter()
+            .map(|(id, s)| (id.to_string(), s))
+            .collect()
+    }
+
+    fn in_order(normalized: &HashMap<String, f32>) -> Vec<f32> {
+        ["a", "b", "c", "d"].iter().map(|id| normalized[*id]).collect()
+    }
+
+    #[test]
+    fn test_linear_curve_matches_cosine_similarity() {
+        for i in 0..=40 {
+            let distance = i as f32 * 0.05;
+            assert_eq!(
+                SimilarityCurve::Linear.similarity(distance),
+                cosine_distance_to_similarity(distance)
+            );
+        }
+    }
+
+    #[test]
+    fn test_similarity_curves_decrease_with_distance() {
+        let sigmoid = SimilarityCurve::Sigmoid {
+            steepness: 10.0,
+            midpoint: 0.6,
+        };
+        for curve in [SimilarityCurve::Linear, sigmoid] {
+            let scores: Vec<f32> = (0..=40).map(|i| curve.similarity(i as f32 * 0.05)).collect();
+            assert!(scores.windows(2).all(|w| w[0] > w[1]), "{} isn't decreasing", curve);
+            assert!(scores.iter().all(|s| (0.0..=1.0).contains(s)));
+        }
+
+        assert!((sigmoid.similarity(0.6) - 0.5).abs() < 1e-6);
+        // close neighbours are pulled further apart than the linear curve spaces them
+        let gap = |c: SimilarityCurve| c.similarity(0.4) - c.similarity(0.8);
+        assert!(gap(sigmoid) > gap(SimilarityCurve::Linear));
+    }
+
+    #[test]
+    fn test_similarity_curve_round_trips() {
+        for raw in ["linear", "sigmoid:10:0.6"] {
+            assert_eq!(raw.parse::<SimilarityCurve>().unwrap().to_string(), raw);
+        }
+        assert!("sigmoid:10".parse::<SimilarityCurve>().is_err());
+        assert!("sigmoid:-1:0.5".parse::<SimilarityCurve>().is_err());
+        assert!("sigmoid:10:3".parse::<SimilarityCurve>().is_err());
+        assert!("cubic".parse::<SimilarityCurve>().is_err());
+    }
+
+    #[test]
+    fn test_keyword_normalization_distributions() {
+        let scores = bm25_fixture();
+        let max = in_order(&KeywordNormalization::MaxScale.normalize(&scores));
+        let power = in_order(&KeywordNormalization::Power { exponent: 2.0 }.normalize(&scores));
+        let softmax =
+            in_order(&KeywordNormalization::Softmax { temperature: 0.25 }.normalize(&scores));
+
+        for (got, want) in max.iter().zip([1.0, 0.8, 0.5, 0.2]) {
+            assert!((got - want).abs() < 1e-6);
+        }
+        for (got, want) in power.iter().zip([1.0, 0.64, 0.25, 0.04]) {
+            assert!((got - want).abs() < 1e-6);
+        }
+        for (got, want) in softmax.iter().zip([1.0, (-0.8f32).exp(), (-2.0f32).exp(), (-3.2f32).exp()]) {
+            assert!((got - want).abs() < 1e-6);
+        }
+
+        // every strategy keeps the top match at 1.0 and the ranking intact, while the
+        // sharpening ones shrink the tail
+        for dist in [&max, &power, &softmax] {
+            assert!((dist[0] - 1.0).abs() < 1e-6);
+            assert!(dist.windows(2).all(|w| w[0] > w[1]));
+        }
+        let tail = |d: &[f32]| d[1..].iter().sum::<f32>();
+        assert!(tail(&power) < tail(&max));
+        assert!(tail(&softmax) < tail(&max));
+    }
+
+    #[test]
+    fn test_flattening_power_lifts_the_tail() {
+        let scores = bm25_fixture();
+        let sqrt = in_order(&KeywordNormalization::Power { exponent: 0.5 }.normalize(&scores));
+        let max = in_order(&KeywordNormalization::MaxScale.normalize(&scores));
+        assert!(sqrt[1..].iter().zip(&max[1..]).all(|(s, m)| s > m));
+    }
+
+    #[test]
+    fn test_parse_keyword_normalization() {
+        assert_eq!("max".parse::<KeywordNormalization>(), Ok(KeywordNormalization::MaxScale));
+        assert_eq!(
+            "softmax:0.5".parse::<KeywordNormalization>(),
+            Ok(KeywordNormalization::Softmax { temperature: 0.5 })
+        );
+        assert_eq!(
+            " power : 2 ".parse::<KeywordNormalization>(),
+            Ok(KeywordNormalization::Power { exponent: 2.0 })
+        );
+        assert!("softmax".parse::<KeywordNormalization>().is_err());
+        assert!("power:0".parse::<KeywordNormalization>().is_err());
+        assert!("power:nan".parse::<KeywordNormalization>().is_err());
+        assert!("max:2".parse::<KeywordNormalization>().is_err());
+        assert!("log".parse::<KeywordNormalization>().is_err());
+
+        for raw in ["max", "softmax:0.25", "power:2"] {
+            assert_eq!(raw.parse::<KeywordNormalization>().unwrap().to_string(), raw);
+        }
+    }
+
     #[test]
     fn test_fuse_scores_pure_semantic() {
         let mut semantic = HashMap::new();
@@ -161,4 +848,253 @@ mod tests {
         // 0.5 * 0.8 + 0.5 * 0.4 = 0.6
         assert!((fused[0].1 - 0.6).abs() < 0.001);
     }
+
+    #[test]
+    fn test_fusion_weights_sum_to_one() {
+        let config = FusionConfig::new(0.6);
+        let weights = config.weights(&[0.75, 0.25]);
+
+        assert_eq!(weights.len(), 3);
+        assert!((weights[0] - 0.45).abs() < 0.001);
+        assert!((weights[1] - 0.15).abs() < 0.001);
+        assert!((weights[2] - 0.4).abs() < 0.001);
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_fuse_scores_multi_three_signals() {
+        let mut voyage = HashMap::new();
+        voyage.insert("a".to_string(), 1.0);
+        voyage.insert("b".to_string(), 0.2);
+
+        let mut openai = HashMap::new();
+        openai.insert("b".to_string(), 1.0);
+
+        let mut keyword = HashMap::new();
+        keyword.insert("c".to_string(), 1.0);
+
+        let fused = fuse_scores_multi(&[&voyage, &openai, &keyword], &[0.5, 0.3, 0.2], 0.001);
+        let scores: HashMap<_, _> = fused.iter().cloned().collect();
+
+        // a: 0.5 * 1.0, b: 0.5 * 0.2 + 0.3 * 1.0, c: 0.2 * 1.0
+        assert_eq!(fused[0].0, "a");
+        assert!((scores["a"] - 0.5).abs() < 0.001);
+        assert!((scores["b"] - 0.4).abs() < 0.001);
+        assert!((scores["c"] - 0.2).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_fuse_scores_multi_matches_two_signal_path() {
+        let mut semantic = HashMap::new();
+        semantic.insert("a".to_string(), 0.8);
+        semantic.insert("b".to_string(), 0.3);
+
+        let mut keyword = HashMap::new();
+        keyword.insert("a".to_string(), 0.4);
+        keyword.insert("c".to_string(), 0.9);
+
+        let config = FusionConfig::new(0.7);
+        let mut two = fuse_scores(&semantic, &keyword, &config);
+        let mut multi = fuse_scores_multi(&[&semantic, &keyword], &[0.7, 0.3], config.min_score);
+        two.sort_by(|a, b| a.0.cmp(&b.0));
+        multi.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(two.len(), multi.len());
+        for (t, m) in two.iter().zip(&multi) {
+            assert_eq!(t.0, m.0);
+            assert!((t.1 - m.1).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn test_score_histogram_buckets() {
+        let counts = score_histogram([0.05, 0.15, 0.19, 0.95, 1.0, 1.4, -0.2, f32::NAN]);
+        assert_eq!(counts.len(), 10);
+        assert_eq!(counts[0], 2);
+        assert_eq!(counts[1], 2);
+        assert_eq!(counts[9], 3);
+        assert_eq!(counts.iter().sum::<usize>(), 7);
+    }
+
+    #[test]
+    fn test_match_position_factor() {
+        assert!((match_position_factor("bufo-happy", "happy") - 1.0).abs() < 1e-6);
+        assert!((match_position_factor("bufo-is-very-happy", "happy") - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(match_position_factor("bufo-sad", "happy"), 0.0);
+        assert!((match_position_factor("Bufo-Happy-Dance", "dance happy") - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_position_boost_prefers_early_matches() {
+        let mut keyword_scores = HashMap::new();
+        keyword_scores.insert("early".to_string(), 0.9);
+        keyword_scores.insert("late".to_string(), 1.0);
+        let mut names = HashMap::new();
+        names.insert("early".to_string(), "bufo-happy".to_string());
+        names.insert("late".to_string(), "bufo-is-not-really-that-happy".to_string());
+
+        apply_position_boost(&mut keyword_scores, &names, "happy", 0.5);
+
+        assert!(keyword_scores["early"] > keyword_scores["late"]);
+        assert!(keyword_scores.values().all(|s| *s <= 1.0));
+    }
+
+    #[test]
+    fn test_exact_name_match() {
+        assert!(is_exact_name_match("bufo-happy", "bufo-happy"));
+        assert!(is_exact_name_match("bufo-happy", "Bufo Happy"));
+        assert!(is_exact_name_match("bufo_happy", " bufo-happy! "));
+        assert!(!is_exact_name_match("bufo-happy", "happy"));
+        assert!(!is_exact_name_match("bufo-happy-dance", "bufo happy"));
+        assert!(!is_exact_name_match("happy-bufo", "bufo happy"));
+        assert!(!is_exact_name_match("bufo", "--"));
+    }
+
+    #[test]
+    fn test_exact_match_boost_lifts_buried_match() {
+        let mut fused = vec![
+            ("a".to_string(), 0.8),
+            ("b".to_string(), 0.6),
+            ("exact-2".to_string(), 0.2),
+            ("exact-1".to_string(), 0.2),
+        ];
+        let names = HashMap::from([
+            ("a".to_string(), "bufo-happy-dance".to_string()),
+            ("b".to_string(), "happy-bufo".to_string()),
+            ("exact-1".to_string(), "bufo-happy".to_string()),
+            ("exact-2".to_string(), "bufo_happy".to_string()),
+        ]);
+
+        apply_exact_match_boost(&mut fused, &names, "bufo happy", 1.0);
+
+        let order: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        // equal scores among exact matches fall back to id order
+        assert_eq!(order, vec!["exact-1", "exact-2", "a", "b"]);
+        assert!((fused[0].1 - 1.2).abs() < 1e-6);
+
+        let mut unchanged = vec![("a".to_string(), 0.8), ("exact-1".to_string(), 0.2)];
+        apply_exact_match_boost(&mut unchanged, &names, "bufo happy", 0.0);
+        assert_eq!(unchanged[0].0, "a");
+    }
+
+    #[test]
+    fn test_token_coverage() {
+        assert_eq!(token_coverage("bufo-happy-dance", "happy dance"), 1.0);
+        assert_eq!(token_coverage("bufo-happy", "happy dance"), 0.5);
+        assert_eq!(token_coverage("bufo-happy", "happy happy dance"), 0.5);
+        assert_eq!(token_coverage("bufo-sad", "happy dance"), 0.0);
+        assert_eq!(token_coverage("bufo-sad", "  "), 1.0);
+    }
+
+    #[test]
+    fn test_coverage_scaling_penalizes_partial_matches() {
+        // equal raw BM25, so both normalize to 1.0 before scaling
+        let mut keyword_scores =
+            normalize_bm25_scores(&[("full".to_string(), 4.0), ("partial".to_string(), 4.0)]);
+        let names: HashMap<String, String> = [
+            ("full".to_string(), "bufo-happy-dance".to_string()),
+            ("partial".to_string(), "bufo-happy".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut unscaled = keyword_scores.clone();
+        apply_coverage_scaling(&mut unscaled, &names, "happy dance", 0.0);
+        assert_eq!(unscaled["full"], unscaled["partial"]);
+
+        apply_coverage_scaling(&mut keyword_scores, &names, "happy dance", 1.0);
+        assert_eq!(keyword_scores["full"], 1.0);
+        assert!((keyword_scores["partial"] - 0.5).abs() < 1e-6);
+
+        let mut half = unscaled.clone();
+        apply_coverage_scaling(&mut half, &names, "happy dance", 0.5);
+        assert_eq!(half["full"], 1.0);
+        assert!((half["partial"] - 0.75).abs() < 1e-6);
+    }
+
+    /// ids fused at `alpha` from signals that cross over between 0.70 and 0.72
+    fn near_tied_at(alpha: f32) -> Vec<(String, f32)> {
+        let signal = |pairs: &[(&str, f32)]| -> HashMap<String, f32> {
+            pairs.iter().map(|(id, s)| (id.to_string(), *s)).collect()
+        };
+        let semantic = signal(&[("a", 0.8), ("b", 0.7), ("c", 0.6), ("d", 0.5)]);
+        let keyword = signal(&[("a", 0.2), ("b", 0.45), ("c", 0.7), ("d", 0.95)]);
+        fuse_scores(&semantic, &keyword, &FusionConfig::new(alpha))
+    }
+
+    fn ids(fused: &[(String, f32)]) -> Vec<&str> {
+        fused.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sticky_order_survives_small_alpha_change() {
+        let before = near_tied_at(0.70);
+        let prev_order: Vec<String> = before.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(prev_order, vec!["d", "c", "b", "a"]);
+
+        // free mode: a 0.02 nudge reverses the whole page
+        let free = near_tied_at(0.72);
+        assert_eq!(ids(&free), vec!["a", "b", "c", "d"]);
+
+        let mut sticky = near_tied_at(0.72);
+        apply_sticky_order(&mut sticky, &prev_order, 0.05);
+        assert_eq!(ids(&sticky), vec!["d", "c", "b", "a"]);
+
+        // a big change still wins over the hysteresis
+        let mut moved = near_tied_at(1.0);
+        apply_sticky_order(&mut moved, &prev_order, 0.05);
+        assert_eq!(ids(&moved)[0], "a");
+    }
+
+    #[test]
+    fn test_sticky_order_off_without_prev_order_or_weight() {
+        let mut fused = near_tied_at(0.72);
+        let free = fused.clone();
+        apply_sticky_order(&mut fused, &[], 0.05);
+        assert_eq!(fused, free);
+        apply_sticky_order(&mut fused, &["d".to_string()], 0.0);
+        assert_eq!(fused, free);
+    }
+
+    #[test]
+    fn test_position_boost_zero_weight_unchanged() {
+        let mut keyword_scores = HashMap::new();
+        keyword_scores.insert("a".to_string(), 0.4);
+        let mut names = HashMap::new();
+        names.insert("a".to_string(), "bufo-happy".to_string());
+
+        apply_position_boost(&mut keyword_scores, &names, "happy", 0.0);
+
+        assert_eq!(keyword_scores["a"], 0.4);
+    }
+
+    #[test]
+    fn test_keyword_fields_parsing() {
+        let fields: KeywordFields = "name:1, tags:0.5,description:0.25".parse().unwrap();
+        assert_eq!(fields.names().collect::<Vec<_>>(), vec!["name", "tags", "description"]);
+        assert_eq!(fields.to_string(), "name:1,tags:0.5,description:0.25");
+        assert!(!fields.is_name_only());
+
+        assert_eq!("name".parse::<KeywordFields>().unwrap(), KeywordFields::default());
+        assert!(KeywordFields::default().is_name_only());
+
+        assert!("".parse::<KeywordFields>().is_err());
+        assert!("name:high".parse::<KeywordFields>().is_err());
+        assert!("name:-1".parse::<KeywordFields>().is_err());
+        assert!("name,name:2".parse::<KeywordFields>().is_err());
+        assert!("name:0,tags:0".parse::<KeywordFields>().is_err());
+        assert!(":1".parse::<KeywordFields>().is_err());
+    }
+
+    #[test]
+    fn test_keyword_fields_combine_weights() {
+        let fields: KeywordFields = "name:3,tags:1".parse().unwrap();
+        let name = HashMap::from([("a".to_string(), 1.0), ("b".to_string(), 0.5)]);
+        let tags = HashMap::from([("b".to_string(), 1.0), ("c".to_string(), 1.0)]);
+
+        let combined = fields.combine(&[name, tags]);
+        assert!((combined["a"] - 0.75).abs() < 1e-6);
+        assert!((combined["b"] - (0.75 * 0.5 + 0.25)).abs() < 1e-6);
+        assert!((combined["c"] - 0.25).abs() < 1e-6);
+    }
 }
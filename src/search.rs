@@ -14,6 +14,8 @@
 //!
 //! ### 2. keyword search (BM25)
 //! - full-text search on bufo `name` field (filename without extension)
+//! - with `KEYWORD_FIELDS`, other fields (`tags`, `description`) are searched too and
+//!   their normalized scores combined by weight into one keyword score
 //! - BM25 ranking: IDF-weighted term frequency with document length normalization
 //! - **strength**: excellent for exact/partial matches (e.g., "jumping" → "bufos-jumping-on-the-bed")
 //! - **weakness**: no semantic understanding (e.g., "happy" won't find "excited" or "smiling")
@@ -27,23 +29,82 @@
 //!   - `α=0.5`: balanced (equal weight to semantic and keyword signals)
 //!   - `α=0.0`: pure keyword (best for exact filename searches)
 //!
+//! ### 4. embedder ensemble (optional)
+//! - when `OPENAI_API_KEY` and `OPENAI_NAMESPACE` are set, the query is also embedded with
+//!   openai and searched against that namespace
+//! - α is split between the embedders (`OPENAI_ENSEMBLE_WEIGHT` goes to openai), so the
+//!   fused weights still sum to 1
+//!
+//! ### 5. query language (optional)
+//! - the query language is detected (`LANGUAGE_DETECTION`) and logged with each search
+//! - with `LANGUAGE_HINTS`, reliably non-english queries are embedded as
+//!   `"{language}: {query}"`; BM25 still gets the query as typed
+//!
+//! ### 6. filename-shaped queries (optional)
+//! - with `FILENAME_BLEND_WEIGHT`, a hyphenated query ("bufo-jumping") is also embedded
+//!   in spaced form ("bufo jumping") and the two vectors are blended before the vector
+//!   search, matching how bufo filenames were embedded at ingest
+//!
 //! ## references
 //!
 //! - voyage multimodal embeddings: https://docs.voyageai.com/docs/multimodal-embeddings
 //! - turbopuffer BM25: https://turbopuffer.com/docs/fts
 //! - weighted fusion: standard approach in modern hybrid search systems (2024)
 
+use crate::alpha::AlphaSource;
+use crate::breaker::{BreakerEmbedder, CircuitBreaker};
+use crate::cache::{CachingEmbedder, EmbeddingCache};
 use crate::config::Config;
-use crate::embedding::VoyageEmbedder;
-use crate::filter::{ContentFilter, Filter, Filterable};
-use crate::providers::{Embedder, VectorSearchError, VectorStore};
-use crate::scoring::{cosine_distance_to_similarity, fuse_scores, normalize_bm25_scores, FusionConfig};
-use crate::turbopuffer::TurbopufferStore;
-use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
-use serde::{Deserialize, Serialize};
+use crate::cursor::Cursor;
+use crate::dimension::DimensionChecked;
+use crate::display::fill_display_names;
+use crate::embedding::{EmbeddingProvider, VoyageEmbedder};
+use crate::expansion::{expanded_query, expansion_terms, FEEDBACK_RESULTS, MAX_EXPANSION_TERMS};
+use crate::explore::sample_by_score;
+use crate::facets::{facet_counts, parse_tags};
+use crate::filter::{split_patterns, ContentFilter, FilterError, Filterable, RejectionCounts};
+use crate::format::detect_animated;
+use crate::hedge::HedgedEmbedder;
+use crate::https::{HttpsOnly, HttpsUrls, UpgradeHttp};
+use crate::language::{self, DetectedLanguage};
+use crate::long_query::{embed_query_pooled, pooled_chunks};
+use crate::models::{ModelProvider, ModelRoute, ModelRoutes};
+use crate::negotiate::{rewrite_urls, ImageFormat};
+use crate::openai::OpenAiEmbedder;
+use crate::plan::{planned_calls, CallInputs, PlannedFilters, SearchPlan};
+use crate::postprocess::{AnimatedOnly, Pipeline};
+use crate::providers::{
+    dimension_attribute, numeric_attribute, Embedder, EmbeddingError, InputType, QueryOptions,
+    SearchResult, VectorSearchError, VectorStore,
+};
+use crate::query_form;
+use crate::reload::CurrentConfig;
+use crate::retry::RetryOnEmpty;
+use crate::rewrite;
+use crate::scoring::{
+    apply_coverage_scaling, apply_exact_match_boost, apply_position_boost, apply_sticky_order,
+    fuse_scores, fuse_scores_multi, score_histogram, FusionConfig, KeywordFields,
+    HISTOGRAM_BUCKET_WIDTH,
+};
+use crate::since::parse_since;
+use crate::state::AppState;
+use crate::thumbnail::fill_thumbnails;
+use crate::timing::{is_slow, millis, StageTimings};
+use crate::tokenize::{
+    contains_all_terms, matched_terms, tokenize, without_short_terms, without_stopwords,
+};
+use crate::turbopuffer::{added_after, all_of, parse_filters, Bm25Options, TurbopufferStore};
+use crate::version::{v2_envelope, ApiVersion};
+use crate::RequestId;
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder, Result as ActixResult};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::Instrument;
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
@@ -51,35 +112,327 @@ pub struct SearchQuery {
     #[serde(default = "default_top_k")]
     pub top_k: usize,
     /// alpha parameter for weighted fusion (0.0 = pure keyword, 1.0 = pure semantic)
-    /// default 0.7 favors semantic search while still considering exact matches
-    #[serde(default = "default_alpha")]
-    pub alpha: f32,
-    /// family-friendly mode: filters out inappropriate content (default true)
-    #[serde(default = "default_family_friendly")]
-    pub family_friendly: bool,
+    /// when omitted, `ALPHA_POLICY_PATH` may set it per query (see `alpha`); otherwise
+    /// the default 0.7 favors semantic search while still considering exact matches
+    #[serde(default)]
+    pub alpha: Option<f32>,
+    /// family-friendly mode: filters out inappropriate content
+    /// when omitted, falls back to the server's `DEFAULT_FAMILY_FRIENDLY` (true unless configured)
+    #[serde(default)]
+    pub family_friendly: Option<bool>,
     /// comma-separated regex patterns to exclude from results (e.g., "excited,party")
     #[serde(default)]
     pub exclude: Option<String>,
     /// comma-separated regex patterns to include (overrides exclude)
     #[serde(default)]
     pub include: Option<String>,
+    /// match exclude/include patterns case-sensitively (default: case-insensitive)
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// boost weight for the `popularity` attribute: `score * (1 + w * normalized_popularity)`,
+    /// applied by the `boost` post-processor (0 to 10)
+    #[serde(default)]
+    pub boost_popularity: Option<f32>,
+    /// minimum fused score for a result (default 0.001)
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// minimum score as a percentile (0-100) of the fused candidates' scores, e.g. 20
+    /// keeps roughly the top 80%; can't be combined with `min_score`
+    #[serde(default)]
+    pub min_score_percentile: Option<f32>,
+    /// relax `min_score` until at least this many results survive filtering (capped at top_k)
+    #[serde(default)]
+    pub min_results: Option<usize>,
+    /// return every result tied with the last one at the `top_k` cut, even past `top_k`;
+    /// otherwise ties at the cut are broken by id
+    #[serde(default)]
+    pub include_ties: bool,
+    /// turbopuffer attribute filter applied server-side, e.g. `["tags", "Eq", "animal"]`
+    /// (a JSON-encoded string in GET query params)
+    #[serde(default)]
+    pub filters: Option<serde_json::Value>,
+    /// attach a histogram of every fused candidate score to the response
+    #[serde(default)]
+    pub include_score_histogram: bool,
+    /// attach tag facet counts over every candidate that passed the content filter
+    #[serde(default)]
+    pub facets: bool,
+    /// attach terms from the top result names that the query doesn't have
+    #[serde(default)]
+    pub related: bool,
+    /// attach diagnostics (language, filter rejections) to the response
+    #[serde(default)]
+    pub debug: bool,
+    /// describe the upstream calls, alpha, filters and post-processors instead of searching
+    #[serde(default)]
+    pub plan: bool,
+    /// embed with this `EMBEDDING_MODELS` model and search the namespace indexed with it
+    #[serde(default)]
+    pub model: Option<String>,
+    /// federate over these allowlisted namespaces instead of the default one
+    /// (comma-separated in GET query params)
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub namespaces: Option<Vec<String>>,
+    /// also return these rankings by name: `semantic`, `keyword` and/or `fused`
+    /// (comma-separated in GET query params)
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub views: Option<Vec<String>>,
+    /// skip conditional-request shortcuts and mark the response `no-store` (`nocache=1`)
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub nocache: bool,
+    /// re-run keyword search with terms from the top semantic results' names (slower)
+    #[serde(default)]
+    pub pseudo_relevance: bool,
+    /// sample results from the candidates above `min_score`, softmax-weighted by score at
+    /// this temperature, instead of returning the strict top_k (e.g. 0.1 for a shuffle)
+    #[serde(default)]
+    pub explore: Option<f32>,
+    /// list the query terms found in each result's name under `highlights`
+    #[serde(default)]
+    pub highlight: bool,
+    /// only return bufos known to be animated (gifs)
+    #[serde(default)]
+    pub only_animated: bool,
+    /// keep only keyword matches whose name has every query term (AND instead of BM25's
+    /// OR); trades recall for precision, and semantic results are unaffected
+    #[serde(default)]
+    pub require_all_terms: bool,
+    /// `next_cursor` from the previous page; results continue below it
+    pub cursor: Option<String>,
+    /// only bufos added after this time (RFC 3339 or a date), by their `added_at`
+    pub since: Option<String>,
+    /// response body layout (`json` or `ndjson`)
+    #[serde(default)]
+    pub format: ResponseFormat,
+    /// json envelope version (`v1` or `v2`); overrides a versioned `Accept` header
+    pub api_version: Option<ApiVersion>,
+    /// order of the selected results (`score`, `name` or `popularity`)
+    #[serde(default)]
+    pub sort: SortOrder,
+    /// add a `display_name` ("Jumping on the bed") to each result; `name` is unchanged
+    #[serde(default)]
+    pub pretty_names: bool,
+    /// send `Link` preload headers for the top this-many result images (GET only, at most
+    /// `MAX_PRELOAD`)
+    pub preload: Option<usize>,
+    /// alternatives OR-ed with `query` ("happy" or "excited"): each is embedded and its
+    /// neighbours unioned with the query's (comma-separated in GET query params)
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub queries: Option<Vec<String>>,
+    /// result ids from a previous search, best first: near-tied results keep this order
+    /// (`STICKY_HYSTERESIS`) so nudging alpha doesn't reshuffle the page
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub prev_order: Option<Vec<String>>,
 }
 
-fn default_top_k() -> usize {
-    10
+/// how the selected results are ordered
+///
+/// relevance still decides which results are returned; the other orders only rearrange
+/// that page.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// fused score, highest first
+    #[default]
+    Score,
+    /// name, alphabetically (case-insensitive)
+    Name,
+    /// the numeric `popularity` attribute, highest first; bufos without one come last
+    Popularity,
 }
 
-fn default_alpha() -> f32 {
-    0.7
+/// how the search response body is laid out
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// the full `SearchResponse` object
+    #[default]
+    Json,
+    /// one `BufoResult` per line (`application/x-ndjson`); the other response fields
+    /// are left out
+    Ndjson,
 }
 
-fn default_family_friendly() -> bool {
-    true
+/// a ranking the response can include under `views`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    /// primary embedder similarity alone
+    Semantic,
+    /// normalized BM25 alone
+    Keyword,
+    /// the weighted fusion (same as `results`)
+    Fused,
 }
 
-#[derive(Debug, Serialize)]
+impl std::str::FromStr for View {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "semantic" => Ok(View::Semantic),
+            "keyword" => Ok(View::Keyword),
+            "fused" => Ok(View::Fused),
+            other => Err(format!("unknown view: {} (expected semantic, keyword or fused)", other)),
+        }
+    }
+}
+
+/// accept a JSON array or a comma-separated string (GET query params)
+fn deserialize_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(Option::<StringOrList>::deserialize(deserializer)?.map(|value| match value {
+        StringOrList::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        StringOrList::List(items) => items,
+    }))
+}
+
+/// a boolean that also accepts `1`/`0`, as in `?nocache=1`
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(flag) => Ok(flag),
+        BoolOrString::String(s) => match s.trim() {
+            "1" | "true" => Ok(true),
+            "0" | "false" | "" => Ok(false),
+            other => Err(serde::de::Error::custom(format!(
+                "expected 1, 0, true or false, got {:?}",
+                other
+            ))),
+        },
+    }
+}
+
+pub fn default_top_k() -> usize {
+    10
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<BufoResult>,
+    /// spelling-corrected query when some terms aren't in the bufo vocabulary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// why `results` is empty (absent when there are results)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_results_reason: Option<NoResultsReason>,
+    /// true when `min_score` was lowered to reach `min_results`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub relaxed: bool,
+    /// distribution of fused scores before filtering and truncation (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_histogram: Option<ScoreHistogram>,
+    /// tag → candidate count, over the filtered candidates before `min_score`/`top_k` (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<HashMap<String, usize>>,
+    /// distinctive terms from the returned names, most common first (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub related_terms: Option<Vec<String>>,
+    /// true when `MAX_RETURNED_RESULTS` or `MAX_RESPONSE_BYTES` cut the result list short
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// true when the vector or keyword search failed and results come from the other alone
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+    /// each requested ranking, content-filtered and cut to `top_k` (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub views: Option<BTreeMap<View, Vec<BufoResult>>>,
+    /// diagnostics for relevance debugging (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<SearchDebug>,
+    /// what the search would do, returned instead of results (on request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<SearchPlan>,
+    /// pass as `cursor` for the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// request values that were adjusted rather than rejected (e.g. a clamped `alpha`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<FieldError>,
+}
+
+/// the stage at which every candidate dropped out of an empty response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoResultsReason {
+    /// neither the vector nor the keyword search found anything
+    NoCandidates,
+    /// the content filter (family-friendly blocklist, `exclude`, ...) removed them all
+    Filtered,
+    /// the `cursor` is past the last candidate
+    EndOfResults,
+    /// every remaining candidate scored at or below `min_score`
+    BelowMinScore,
+}
+
+impl NoResultsReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoResultsReason::NoCandidates => "no_candidates",
+            NoResultsReason::Filtered => "filtered",
+            NoResultsReason::EndOfResults => "end_of_results",
+            NoResultsReason::BelowMinScore => "below_min_score",
+        }
+    }
+}
+
+/// why a search came back empty, from the candidate count at each stage
+///
+/// `None` when there are results.
+fn no_results_reason(
+    fused: usize,
+    filtered: usize,
+    paged: usize,
+    selected: usize,
+) -> Option<NoResultsReason> {
+    if selected > 0 {
+        None
+    } else if fused == 0 {
+        Some(NoResultsReason::NoCandidates)
+    } else if filtered == 0 {
+        Some(NoResultsReason::Filtered)
+    } else if paged == 0 {
+        Some(NoResultsReason::EndOfResults)
+    } else {
+        Some(NoResultsReason::BelowMinScore)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchDebug {
+    /// detected ISO 639-3 query language
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<&'static str>,
+    #[serde(flatten)]
+    pub rejections: RejectionCounts,
+    /// per-stage latency of this search
+    pub timings: StageTimings,
+    /// label of the `CLUSTER_CENTROIDS_PATH` centroid nearest the query embedding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_cluster: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreHistogram {
+    pub bucket_width: f32,
+    pub counts: Vec<usize>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -88,6 +441,64 @@ pub struct BufoResult {
     pub url: String,
     pub name: String,
     pub score: f32,
+    /// smaller image for galleries, stored or derived with `THUMBNAIL_URL_PATTERN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    /// query terms that appear in the name, for bolding matches (on request)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<String>,
+    /// namespace the result was found in
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// animated (gif) or static, from the `format` attribute or file extension when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animated: Option<bool>,
+    /// `name` for display, e.g. "Jumping on the bed" (on request, see `display`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// image size in pixels, from the `width`/`height` attributes when both are valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// `width / height`, so galleries can reserve space before the image loads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f32>,
+    /// the `popularity` attribute, for the `boost` post-processor (not returned)
+    #[serde(skip)]
+    pub popularity: Option<f32>,
+}
+
+/// attribute key the searched namespace is recorded under on fused candidates
+const SOURCE_ATTRIBUTE: &str = "$namespace";
+
+impl BufoResult {
+    /// build a result from store attributes, falling back to the id for a missing name
+    ///
+    /// dimensions are all or nothing: a missing or malformed `width` or `height` leaves
+    /// both unset rather than reporting half a size.
+    pub fn from_attributes(id: String, score: f32, attrs: &HashMap<String, String>) -> Self {
+        let dimensions =
+            dimension_attribute(attrs, "width").zip(dimension_attribute(attrs, "height"));
+        Self {
+            url: attrs.get("url").cloned().unwrap_or_default(),
+            name: attrs.get("name").cloned().unwrap_or_else(|| id.clone()),
+            thumbnail_url: attrs.get("thumbnail_url").cloned(),
+            highlights: Vec::new(),
+            source: attrs.get(SOURCE_ATTRIBUTE).cloned().unwrap_or_default(),
+            display_name: None,
+            animated: detect_animated(
+                attrs.get("format").map(String::as_str),
+                attrs.get("filename").or_else(|| attrs.get("url")).map(String::as_str),
+            ),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            aspect_ratio: dimensions.map(|(width, height)| width as f32 / height as f32),
+            popularity: numeric_attribute(attrs, "popularity"),
+            id,
+            score,
+        }
+    }
 }
 
 impl Filterable for BufoResult {
@@ -114,291 +525,4565 @@ impl SearchError {
                     "search query is too long (max 1024 characters for text search). try a shorter query."
                 )
             }
+            SearchError::VectorSearch(VectorSearchError::DimensionMismatch { message }) => {
+                actix_web::error::ErrorInternalServerError(format!(
+                    "query embedding size doesn't match the indexed vectors ({}). check VOYAGE_OUTPUT_DIMENSION against the namespace.",
+                    message
+                ))
+            }
             _ => actix_web::error::ErrorInternalServerError(self.to_string()),
         }
     }
 }
 
-/// generate etag for caching based on query parameters
-fn generate_etag(
-    query: &str,
-    top_k: usize,
-    alpha: f32,
-    family_friendly: bool,
-    exclude: &Option<String>,
-    include: &Option<String>,
-) -> String {
-    let mut hasher = DefaultHasher::new();
-    query.hash(&mut hasher);
-    top_k.hash(&mut hasher);
-    alpha.to_bits().hash(&mut hasher);
-    family_friendly.hash(&mut hasher);
-    exclude.hash(&mut hasher);
-    include.hash(&mut hasher);
-    format!("\"{}\"", hasher.finish())
-}
+/// longest query turbopuffer accepts for BM25
+const MAX_QUERY_LENGTH: usize = 1024;
 
-/// execute hybrid search using the provided embedder and vector store
-async fn execute_hybrid_search<E: Embedder, V: VectorStore>(
-    query: &str,
-    top_k: usize,
-    fusion_config: &FusionConfig,
-    embedder: &E,
-    vector_store: &V,
-) -> Result<Vec<(String, f32, HashMap<String, String>)>, SearchError> {
-    // fetch extra results to ensure we have enough after filtering
-    let search_top_k = top_k * 5;
-    let query_owned = query.to_string();
+/// largest `top_k` a request may ask for
+const MAX_TOP_K: usize = 100;
 
-    // generate query embedding
-    let _embed_span = logfire::span!(
-        "embedding.generate",
-        query = &query_owned,
-        model = embedder.name()
-    )
-    .entered();
+/// most preload `Link` headers a response carries, however many were asked for
+const MAX_PRELOAD: usize = 6;
 
-    let query_embedding = embedder.embed(query).await?;
+/// most `related_terms` a response carries
+const MAX_RELATED_TERMS: usize = 8;
 
-    logfire::info!(
-        "embedding generated",
-        query = &query_owned,
-        embedding_dim = query_embedding.len() as i64
-    );
+/// most alternatives `queries` may add to a search (each costs a vector query)
+const MAX_QUERY_ALTERNATIVES: usize = 4;
 
-    // run both searches in sequence (could parallelize with tokio::join! if needed)
-    let namespace = vector_store.name().to_string();
+/// largest `boost_popularity`; the most popular bufo's score is multiplied by 1 + this
+const MAX_BOOST_POPULARITY: f32 = 10.0;
 
-    let vector_results = {
-        let _span = logfire::span!(
-            "turbopuffer.vector_search",
-            query = &query_owned,
-            top_k = search_top_k as i64,
-            namespace = &namespace
+/// a validation failure tied to one request field
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// `alpha` clamped to [0, 1], with a warning naming both values when it was out of range
+fn effective_alpha(alpha: f32) -> (f32, Option<FieldError>) {
+    let clamped = alpha.clamp(0.0, 1.0);
+    let warning = (clamped != alpha).then(|| {
+        FieldError::new(
+            "alpha",
+            format!("alpha {} is outside 0 to 1; using {}", alpha, clamped),
         )
-        .entered();
+    });
+    (clamped, warning)
+}
 
-        vector_store
-            .search_by_vector(&query_embedding, search_top_k)
-            .await?
-    };
+/// text that can be searched (or embedded): not blank and within `MAX_QUERY_LENGTH`
+pub fn check_query_text(field: &'static str, text: &str) -> Option<FieldError> {
+    if text.trim().is_empty() {
+        Some(FieldError::new(field, format!("{} must not be empty", field)))
+    } else if text.chars().count() > MAX_QUERY_LENGTH {
+        Some(FieldError::new(
+            field,
+            format!("{} must be at most {} characters", field, MAX_QUERY_LENGTH),
+        ))
+    } else {
+        None
+    }
+}
 
-    logfire::info!(
-        "vector search completed",
-        query = &query_owned,
-        results_found = vector_results.len() as i64
-    );
+/// every check a request must pass before searching, shared by search and validate
+///
+/// `allowed_namespaces` is the `SEARCH_NAMESPACES` allowlist.
+fn validate_query(query: &SearchQuery, allowed_namespaces: &[String]) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
 
-    let bm25_results = {
-        let _span = logfire::span!(
-            "turbopuffer.bm25_search",
-            query = &query_owned,
-            top_k = search_top_k as i64,
-            namespace = &namespace
-        )
-        .entered();
+    errors.extend(check_query_text("query", &query.query));
+    if let Some(alternatives) = &query.queries {
+        if alternatives.len() > MAX_QUERY_ALTERNATIVES {
+            errors.push(FieldError::new(
+                "queries",
+                format!(
+                    "queries may add at most {} alternatives, got {}",
+                    MAX_QUERY_ALTERNATIVES,
+                    alternatives.len()
+                ),
+            ));
+        }
+        errors.extend(
+            alternatives
+                .iter()
+                .filter_map(|q| check_query_text("queries", q)),
+        );
+    }
+    if let Some(prev_order) = &query.prev_order {
+        if prev_order.len() > MAX_TOP_K {
+            errors.push(FieldError::new(
+                "prev_order",
+                format!(
+                    "prev_order may list at most {} ids, got {}",
+                    MAX_TOP_K,
+                    prev_order.len()
+                ),
+            ));
+        }
+    }
+    // finite values outside [0, 1] are clamped with a warning (see `effective_alpha`)
+    if let Some(alpha) = query.alpha.filter(|alpha| !alpha.is_finite()) {
+        errors.push(FieldError::new(
+            "alpha",
+            format!("alpha must be a number between 0 and 1, got {}", alpha),
+        ));
+    }
+    if !(1..=MAX_TOP_K).contains(&query.top_k) {
+        errors.push(FieldError::new(
+            "top_k",
+            format!("top_k must be between 1 and {}, got {}", MAX_TOP_K, query.top_k),
+        ));
+    }
+    if let Some(boost) = query.boost_popularity {
+        if !boost.is_finite() || !(0.0..=MAX_BOOST_POPULARITY).contains(&boost) {
+            errors.push(FieldError::new(
+                "boost_popularity",
+                format!(
+                    "boost_popularity must be between 0 and {}, got {}",
+                    MAX_BOOST_POPULARITY, boost
+                ),
+            ));
+        }
+    }
+    if let Some(percentile) = query.min_score_percentile {
+        if query.min_score.is_some() {
+            errors.push(FieldError::new(
+                "min_score_percentile",
+                "min_score and min_score_percentile can't both be set",
+            ));
+        }
+        if !percentile.is_finite() || !(0.0..=100.0).contains(&percentile) {
+            errors.push(FieldError::new(
+                "min_score_percentile",
+                format!("min_score_percentile must be between 0 and 100, got {}", percentile),
+            ));
+        }
+    }
+    if let Some(temperature) = query.explore {
+        if !temperature.is_finite() || temperature <= 0.0 {
+            errors.push(FieldError::new(
+                "explore",
+                format!("explore must be a positive temperature, got {}", temperature),
+            ));
+        }
+    }
+    if let Some(cursor) = &query.cursor {
+        if let Err(e) = Cursor::decode(cursor) {
+            errors.push(FieldError::new("cursor", e.to_string()));
+        }
+        if query.explore.is_some() {
+            errors.push(FieldError::new(
+                "cursor",
+                "cursor can't be combined with explore, whose draws aren't ranked",
+            ));
+        }
+    }
+    if let Some(Err(e)) = query.since.as_deref().map(parse_since) {
+        errors.push(FieldError::new("since", e.to_string()));
+    }
+    if let Err(e) = build_content_filter(query, true) {
+        errors.push(FieldError::new(e.field, e.to_string()));
+    }
+    if let Some(Err(e)) = query.filters.as_ref().map(parse_filters) {
+        errors.push(FieldError::new("filters", e.to_string()));
+    }
+    if let Some(namespaces) = &query.namespaces {
+        if namespaces.is_empty() {
+            errors.push(FieldError::new("namespaces", "namespaces must not be empty"));
+        }
+        let unknown: Vec<&str> = namespaces
+            .iter()
+            .filter(|ns| !allowed_namespaces.contains(ns))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            errors.push(FieldError::new(
+                "namespaces",
+                format!("namespaces not allowed: {}", unknown.join(", ")),
+            ));
+        }
+    }
 
-        vector_store.search_by_keyword(query, search_top_k).await?
-    };
+    if let Some(views) = &query.views {
+        if let Err(e) = views.iter().map(|v| v.parse::<View>()).collect::<Result<Vec<_>, _>>() {
+            errors.push(FieldError::new("views", e));
+        }
+    }
 
-    // normalize scores
-    let semantic_scores: HashMap<String, f32> = vector_results
-        .iter()
-        .map(|r| (r.id.clone(), cosine_distance_to_similarity(r.score)))
-        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
 
-    let bm25_raw: Vec<(String, f32)> = bm25_results
+/// 400 listing every field error
+fn validation_error(errors: Vec<FieldError>) -> actix_web::Error {
+    let message = errors
         .iter()
-        .map(|r| (r.id.clone(), r.score))
-        .collect();
-    let keyword_scores = normalize_bm25_scores(&bm25_raw);
+        .map(|e| e.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    actix_web::error::ErrorBadRequest(message)
+}
 
-    let max_bm25 = bm25_raw
-        .iter()
-        .map(|(_, s)| *s)
-        .fold(f32::NEG_INFINITY, f32::max);
+/// seconds clients are asked to wait when every search slot is taken
+const BUSY_RETRY_AFTER_SECS: u64 = 1;
 
-    logfire::info!(
-        "bm25 search completed",
-        query = &query_owned,
-        results_found = bm25_results.len() as i64,
-        max_bm25 = max_bm25 as f64,
-        top_bm25_raw = bm25_raw.first().map(|(_, s)| *s).unwrap_or(0.0) as f64
-    );
+/// take a search slot without waiting; a 503 with retry-after when none are free
+fn acquire_search_permit(permits: Option<&Semaphore>) -> ActixResult<Option<SemaphorePermit<'_>>> {
+    let Some(permits) = permits else {
+        return Ok(None);
+    };
 
-    // fuse scores
-    let fused = fuse_scores(&semantic_scores, &keyword_scores, fusion_config);
+    permits.try_acquire().map(Some).map_err(|_| {
+        let response = HttpResponse::ServiceUnavailable()
+            .insert_header(("retry-after", BUSY_RETRY_AFTER_SECS.to_string()))
+            .body("too many searches in progress, try again shortly");
+        actix_web::error::InternalError::from_response("search capacity exhausted", response).into()
+    })
+}
 
-    logfire::info!(
-        "weighted fusion completed",
-        total_candidates = (vector_results.len() + bm25_results.len()) as i64,
-        alpha = fusion_config.alpha as f64,
-        pre_filter_results = fused.len() as i64
-    );
+/// exclude/include lists with more than `max_patterns` patterns
+fn check_pattern_counts(query: &SearchQuery, max_patterns: usize) -> Vec<FieldError> {
+    [("exclude", &query.exclude), ("include", &query.include)]
+        .into_iter()
+        .filter_map(|(field, patterns)| {
+            let count = split_patterns(patterns.as_deref()?).count();
+            (count > max_patterns).then(|| {
+                FieldError::new(
+                    field,
+                    format!(
+                        "{} may have at most {} patterns, got {}",
+                        field, max_patterns, count
+                    ),
+                )
+            })
+        })
+        .collect()
+}
 
-    // collect attributes from both result sets
-    let mut all_attributes: HashMap<String, HashMap<String, String>> = HashMap::new();
-    for result in vector_results.into_iter().chain(bm25_results.into_iter()) {
-        all_attributes
-            .entry(result.id.clone())
-            .or_insert(result.attributes);
+/// `validate_query` with the configured limits
+///
+/// pattern counts are checked first: compiling hundreds of patterns is the work the
+/// limit is there to prevent.
+fn validate_request(query: &SearchQuery, config: &Config) -> Result<(), Vec<FieldError>> {
+    let mut errors = check_pattern_counts(query, config.max_filter_patterns);
+    errors.extend(check_model(query, &config.embedding_models));
+    if !errors.is_empty() {
+        return Err(errors);
     }
+    validate_query(query, &config.search_namespaces)
+}
 
-    // return fused results with attributes
-    Ok(fused
-        .into_iter()
-        .map(|(id, score)| {
-            let attrs = all_attributes.remove(&id).unwrap_or_default();
-            (id, score, attrs)
-        })
-        .collect())
+/// a requested `model` must be allowlisted, and brings its own namespace
+fn check_model(query: &SearchQuery, routes: &ModelRoutes) -> Option<FieldError> {
+    let model = query.model.as_deref()?;
+    if query.namespaces.is_some() {
+        return Some(FieldError::new(
+            "model",
+            "model can't be combined with namespaces; it searches its own namespace",
+        ));
+    }
+    if routes.route(model).is_some() {
+        return None;
+    }
+    let message = match routes.names().as_slice() {
+        [] => "this server doesn't allow choosing a model".to_string(),
+        names => format!("unknown model {} (allowed: {})", model, names.join(", ")),
+    };
+    Some(FieldError::new("model", message))
 }
 
-/// shared search implementation used by both POST and GET handlers
-async fn perform_search(
-    query_text: String,
-    top_k_val: usize,
-    alpha: f32,
-    family_friendly: bool,
-    exclude: Option<String>,
-    include: Option<String>,
-    config: &Config,
-) -> ActixResult<SearchResponse> {
-    let content_filter = ContentFilter::new(
-        family_friendly,
-        exclude.as_deref(),
-        include.as_deref(),
-    );
+/// the namespace a single-namespace search goes to: the requested one, else the chosen
+/// model's, else `default`
+fn primary_namespace<'a>(
+    query: &'a SearchQuery,
+    routes: &'a ModelRoutes,
+    default: &'a str,
+) -> &'a str {
+    let requested = query.namespaces.as_deref().and_then(|ns| ns.first());
+    let routed = query.model.as_deref().and_then(|m| routes.route(m));
+    requested
+        .map(String::as_str)
+        .or(routed.map(|r| r.namespace.as_str()))
+        .unwrap_or(default)
+}
 
-    let _search_span = logfire::span!(
-        "bufo_search",
-        query = &query_text,
-        top_k = top_k_val as i64,
-        alpha = alpha as f64,
-        family_friendly = family_friendly,
-        exclude_patterns_count = content_filter.exclude_pattern_count() as i64
+/// the request's content filter; fails on exclude/include patterns that aren't valid regex
+fn build_content_filter(query: &SearchQuery, family_friendly: bool) -> Result<ContentFilter, FilterError> {
+    ContentFilter::try_new(
+        family_friendly,
+        query.exclude.as_deref(),
+        query.include.as_deref(),
+        !query.case_sensitive,
     )
-    .entered();
-
-    logfire::info!(
-        "search request received",
-        query = &query_text,
-        top_k = top_k_val as i64,
-        alpha = alpha as f64,
-        exclude_patterns = &content_filter.exclude_patterns_str()
-    );
+}
 
-    // create clients
-    let embedder = VoyageEmbedder::new(config.voyage_api_key.clone());
-    let vector_store = TurbopufferStore::new(
-        config.turbopuffer_api_key.clone(),
-        config.turbopuffer_namespace.clone(),
+/// the request's post-processing: `filter`, the configured steps (`boost` by the
+/// request's `boost_popularity`), then `only_animated`
+fn request_pipeline(query: &SearchQuery, config: &Config, filter: ContentFilter) -> Pipeline {
+    let mut pipeline = Pipeline::from_config(
+        &config.post_processors,
+        filter,
+        query.boost_popularity.unwrap_or(0.0),
     );
+    pipeline = match config.https_urls {
+        HttpsUrls::Off => pipeline,
+        HttpsUrls::Upgrade => pipeline.with(UpgradeHttp),
+        HttpsUrls::Strict => pipeline.with(HttpsOnly),
+    };
+    if query.only_animated {
+        pipeline.with(AnimatedOnly)
+    } else {
+        pipeline
+    }
+}
 
-    let fusion_config = FusionConfig::new(alpha);
-
-    // execute hybrid search
-    let fused_results = execute_hybrid_search(
-        &query_text,
-        top_k_val,
-        &fusion_config,
-        &embedder,
-        &vector_store,
-    )
-    .await
-    .map_err(|e| e.into_actix_error())?;
+/// generate etag for caching based on query parameters
+///
+/// `family_friendly` is the effective value after applying the server default.
+fn generate_etag(query: &SearchQuery, family_friendly: bool) -> String {
+    let mut hasher = DefaultHasher::new();
+    query.query.hash(&mut hasher);
+    query.top_k.hash(&mut hasher);
+    query.alpha.map(f32::to_bits).hash(&mut hasher);
+    family_friendly.hash(&mut hasher);
+    query.exclude.hash(&mut hasher);
+    query.include.hash(&mut hasher);
+    query.case_sensitive.hash(&mut hasher);
+    query.boost_popularity.map(f32::to_bits).hash(&mut hasher);
+    query.min_score.map(f32::to_bits).hash(&mut hasher);
+    query.min_score_percentile.map(f32::to_bits).hash(&mut hasher);
+    query.min_results.hash(&mut hasher);
+    query.include_ties.hash(&mut hasher);
+    query.filters.as_ref().map(|f| f.to_string()).hash(&mut hasher);
+    query.include_score_histogram.hash(&mut hasher);
+    query.facets.hash(&mut hasher);
+    query.related.hash(&mut hasher);
+    query.debug.hash(&mut hasher);
+    query.plan.hash(&mut hasher);
+    query.model.hash(&mut hasher);
+    query.namespaces.hash(&mut hasher);
+    query.views.hash(&mut hasher);
+    query.pseudo_relevance.hash(&mut hasher);
+    query.explore.map(f32::to_bits).hash(&mut hasher);
+    query.highlight.hash(&mut hasher);
+    query.only_animated.hash(&mut hasher);
+    query.require_all_terms.hash(&mut hasher);
+    query.cursor.hash(&mut hasher);
+    query.since.hash(&mut hasher);
+    query.format.hash(&mut hasher);
+    query.api_version.hash(&mut hasher);
+    query.sort.hash(&mut hasher);
+    query.pretty_names.hash(&mut hasher);
+    query.preload.hash(&mut hasher);
+    query.queries.hash(&mut hasher);
+    query.prev_order.hash(&mut hasher);
+    format!("\"{}\"", hasher.finish())
+}
 
-    // convert to BufoResults and apply filtering
-    let results: Vec<BufoResult> = fused_results
-        .into_iter()
-        .map(|(id, score, attrs)| BufoResult {
-            id: id.clone(),
-            url: attrs.get("url").cloned().unwrap_or_default(),
-            name: attrs.get("name").cloned().unwrap_or_else(|| id.clone()),
-            score,
-        })
-        .filter(|result| content_filter.matches(result))
-        .take(top_k_val)
-        .collect();
+/// the alternate image format to serve, from the request's `Accept` header
+fn negotiated_format(req: &HttpRequest, config: &Config) -> Option<ImageFormat> {
+    let accept = req.headers().get("accept").and_then(|v| v.to_str().ok());
+    config.image_rewrite.as_ref()?.negotiate(accept)
+}
 
-    let results_count = results.len() as i64;
-    let top_result_name = results
-        .first()
-        .map(|r| r.name.clone())
-        .unwrap_or_else(|| "none".to_string());
-    let top_score_val = results.first().map(|r| r.score as f64).unwrap_or(0.0);
-    let avg_score_val = if !results.is_empty() {
-        results.iter().map(|r| r.score as f64).sum::<f64>() / results.len() as f64
-    } else {
-        0.0
+/// point every returned url at `format` (no-op without a negotiated format)
+fn apply_image_format(response: &mut SearchResponse, config: &Config, format: Option<ImageFormat>) {
+    let (Some(rewrite), Some(format)) = (&config.image_rewrite, format) else {
+        return;
     };
+    rewrite_urls(&mut response.results, rewrite, format);
+    if let Some(views) = &mut response.views {
+        for ranking in views.values_mut() {
+            rewrite_urls(ranking, rewrite, format);
+        }
+    }
+}
 
-    logfire::info!(
-        "search completed successfully",
-        query = &query_text,
-        results_count = results_count,
-        top_result = &top_result_name,
-        top_score = top_score_val,
-        avg_score = avg_score_val
-    );
+/// `url` as a `Link` target: percent-encodes what would end the `<...>` or split the
+/// header; `None` for urls that aren't http(s)
+fn link_target(url: &str) -> Option<String> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return None;
+    }
+    let mut target = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            '<' | '>' | '"' | ',' | ';' | ' ' => target.push_str(&format!("%{:02X}", c as u32)),
+            c if c.is_control() => {}
+            c => target.push(c),
+        }
+    }
+    Some(target)
+}
 
-    Ok(SearchResponse { results })
+/// terms the returned names share that `query` lacks, mined like pseudo-relevance
+/// feedback terms (see `expansion_terms`)
+fn related_terms(query: &str, results: &[BufoResult]) -> Vec<String> {
+    let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+    expansion_terms(query, &names, MAX_RELATED_TERMS)
 }
 
-/// POST /api/search handler (existing API)
-pub async fn search(
-    query: web::Json<SearchQuery>,
-    config: web::Data<Config>,
-) -> ActixResult<HttpResponse> {
-    let response = perform_search(
-        query.query.clone(),
-        query.top_k,
-        query.alpha,
-        query.family_friendly,
-        query.exclude.clone(),
-        query.include.clone(),
-        &config,
-    )
-    .await?;
-    Ok(HttpResponse::Ok().json(response))
+/// one `Link: <url>; rel=preload; as=image` header value for each of the top `count` results
+fn preload_links(results: &[BufoResult], count: usize) -> Vec<String> {
+    results
+        .iter()
+        .filter_map(|r| link_target(&r.url))
+        .take(count.min(MAX_PRELOAD))
+        .map(|target| format!("<{}>; rel=preload; as=image", target))
+        .collect()
 }
 
-/// GET /api/search handler for shareable URLs
-pub async fn search_get(
-    query: web::Query<SearchQuery>,
-    config: web::Data<Config>,
-    req: HttpRequest,
-) -> ActixResult<HttpResponse> {
-    let etag = generate_etag(
-        &query.query,
-        query.top_k,
-        query.alpha,
-        query.family_friendly,
-        &query.exclude,
-        &query.include,
-    );
+/// the envelope version for a request: `api_version`, then `Accept`, then v1
+fn negotiated_version(req: &HttpRequest, query: &SearchQuery) -> ApiVersion {
+    let accept = req.headers().get("accept").and_then(|v| v.to_str().ok());
+    query
+        .api_version
+        .or_else(|| ApiVersion::negotiate(accept))
+        .unwrap_or_default()
+}
 
-    if let Some(if_none_match) = req.headers().get("if-none-match") {
-        if if_none_match.to_str().unwrap_or("") == etag {
-            return Ok(HttpResponse::NotModified()
-                .insert_header(("etag", etag))
-                .finish());
+/// the etag for a response in `version`'s envelope (v1 etags are unchanged)
+fn etag_for_version(etag: String, version: ApiVersion) -> String {
+    match version {
+        ApiVersion::V1 => etag,
+        version => format!("{}-{}\"", etag.trim_end_matches('"'), version),
+    }
+}
+
+/// the etag for a response whose urls point at `format`
+fn etag_for_format(etag: String, format: Option<ImageFormat>) -> String {
+    match format {
+        Some(format) => format!("{}-{}\"", etag.trim_end_matches('"'), format),
+        None => etag,
+    }
+}
+
+/// the etag for a response ranked under the current reloadable settings
+///
+/// the reload generation misses anything cached before a reload; the blocklist digest
+/// and `DEFAULT_ALPHA` cover a restart that changed them, since redis outlives the
+/// process.
+fn etag_for_settings(etag: String, state: &AppState, config: &Config) -> String {
+    let mut hasher = DefaultHasher::new();
+    state
+        .config_generation
+        .load(Ordering::Relaxed)
+        .hash(&mut hasher);
+    state.blocked_ids.current().digest().hash(&mut hasher);
+    config.default_alpha.to_bits().hash(&mut hasher);
+    format!("{}-{:x}\"", etag.trim_end_matches('"'), hasher.finish())
+}
+
+/// cache-control for a GET search response
+///
+/// `nocache`, exploratory and degraded responses are `no-store`; the etag is sent
+/// either way.
+fn search_cache_control(nocache: bool, degraded: bool, max_age_secs: u64) -> String {
+    if nocache || degraded {
+        "no-store".to_string()
+    } else {
+        format!("public, max-age={}", max_age_secs)
+    }
+}
+
+/// an extra embedder searched against its own namespace and fused with the primary
+pub struct EnsembleMember<E, V> {
+    pub embedder: E,
+    pub vector_store: V,
+    /// share of the semantic weight (α) given to this member
+    pub weight: f32,
+}
+
+/// build a turbopuffer store for `namespace` with the configured query options
+pub fn build_store(config: &Config, namespace: &str) -> TurbopufferStore {
+    TurbopufferStore::new(config.turbopuffer_api_key.clone(), namespace.to_string())
+        .with_bm25_options(Bm25Options {
+            last_as_prefix: config.bm25_prefix_match,
+            hyphens: config.bm25_hyphen_mode,
+        })
+        .with_consistency(config.turbopuffer_consistency)
+        .with_max_distance(config.vector_max_distance)
+        .with_timeout(config.turbopuffer_timeout)
+}
+
+/// embedder type shared by the primary search and ensemble members
+///
+/// the cache sits outside the hedge, so cache hits never send a request at all. empty
+/// responses are retried inside each hedged request, and the breaker counts a hedged
+/// request as one attempt.
+pub type SearchEmbedder = CachingEmbedder<
+    DimensionChecked<BreakerEmbedder<HedgedEmbedder<RetryOnEmpty<EmbeddingProvider>>>>,
+>;
+
+/// the primary embedder, backed by the shared cache
+///
+/// voyage (hedged when configured), or the in-process model when `EMBEDDING_PROVIDER=local`.
+pub fn build_embedder(config: &Config, state: &AppState) -> SearchEmbedder {
+    let cache = &state.embedding_cache;
+    let checked = |embedder| {
+        DimensionChecked::new(
+            BreakerEmbedder::new(embedder, state.embedding_breaker.clone()),
+            config.expected_embedding_dim,
+            state.embedding_dimension.clone(),
+        )
+    };
+    #[cfg(feature = "local-embeddings")]
+    if let Some(local) = &state.local_embedder {
+        return CachingEmbedder::new(
+            checked(HedgedEmbedder::new(
+                RetryOnEmpty::new(EmbeddingProvider::Local(local.clone()), 0),
+                None,
+            )),
+            cache.clone(),
+            config.query_normalization,
+        );
+    }
+
+    CachingEmbedder::new(
+        checked(HedgedEmbedder::new(
+            RetryOnEmpty::new(
+                EmbeddingProvider::Voyage(
+                    VoyageEmbedder::new(config.voyage_api_key.clone())
+                        .with_output_dimension(config.voyage_output_dimension)
+                        .with_timeout(config.voyage_timeout),
+                ),
+                config.voyage_empty_retries,
+            ),
+            config.voyage_hedge_delay,
+        )),
+        cache.clone(),
+        config.query_normalization,
+    )
+}
+
+/// an embedder for a per-request `model`, backed by the shared cache
+///
+/// its vectors go to the model's own namespace, so they aren't checked against the
+/// primary embedding size, and its failures don't trip the primary's breaker.
+fn build_routed_embedder(
+    config: &Config,
+    cache: &EmbeddingCache,
+    route: &ModelRoute,
+) -> SearchEmbedder {
+    let provider = match route.provider {
+        ModelProvider::Voyage => EmbeddingProvider::Voyage(
+            VoyageEmbedder::new(config.voyage_api_key.clone())
+                .with_model(route.model)
+                .with_timeout(config.voyage_timeout),
+        ),
+        // config requires OPENAI_API_KEY when an openai model is allowlisted
+        ModelProvider::OpenAi => EmbeddingProvider::OpenAi(OpenAiEmbedder::new(
+            config.openai_api_key.clone().unwrap_or_default(),
+            route.model.to_string(),
+        )),
+    };
+    CachingEmbedder::new(
+        DimensionChecked::unchecked(BreakerEmbedder::new(
+            HedgedEmbedder::new(
+                RetryOnEmpty::new(provider, config.voyage_empty_retries),
+                None,
+            ),
+            CircuitBreaker::disabled(),
+        )),
+        cache.clone(),
+        config.query_normalization,
+    )
+}
+
+/// build the ensemble members enabled by config (empty when unconfigured)
+fn build_ensemble(
+    config: &Config,
+    cache: &EmbeddingCache,
+) -> Vec<EnsembleMember<SearchEmbedder, TurbopufferStore>> {
+    match (&config.openai_api_key, &config.openai_namespace) {
+        (Some(api_key), Some(namespace)) => vec![EnsembleMember {
+            embedder: CachingEmbedder::new(
+                DimensionChecked::unchecked(BreakerEmbedder::new(
+                    HedgedEmbedder::new(
+                        RetryOnEmpty::new(
+                            EmbeddingProvider::OpenAi(OpenAiEmbedder::new(
+                                api_key.clone(),
+                                config.openai_embedding_model.clone(),
+                            )),
+                            0,
+                        ),
+                        None,
+                    ),
+                    CircuitBreaker::disabled(),
+                )),
+                cache.clone(),
+                config.query_normalization,
+            ),
+            vector_store: build_store(config, namespace),
+            weight: config.openai_ensemble_weight,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// detect the query language when `LANGUAGE_DETECTION` is on
+pub fn detect_language(config: &Config, query: &str) -> Option<DetectedLanguage> {
+    if config.language_detection {
+        language::detect(query)
+    } else {
+        None
+    }
+}
+
+/// the text sent to the embedders: without stopwords when `REMOVE_EMBEDDING_STOPWORDS`
+/// is on, and with a language hint when `LANGUAGE_HINTS` is on
+pub fn semantic_text(config: &Config, query: &str, language: Option<&DetectedLanguage>) -> String {
+    let query = if config.remove_embedding_stopwords {
+        without_stopwords(query, &config.embedding_stopwords)
+    } else {
+        query.to_string()
+    };
+    if config.language_hints {
+        language::with_language_hint(&query, language)
+    } else {
+        query
+    }
+}
+
+/// embed the `alternatives` of a query in one batch (no request when there are none)
+async fn embed_alternatives<E: Embedder>(
+    embedder: &E,
+    alternatives: &[String],
+) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    if alternatives.is_empty() {
+        return Ok(Vec::new());
+    }
+    embedder.embed_batch(alternatives, InputType::Query).await
+}
+
+/// union of vector result lists, each id at its nearest (smallest) distance, nearest first
+///
+/// a single list is returned as-is.
+fn merge_nearest(mut lists: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    if lists.len() == 1 {
+        return lists.pop().unwrap_or_default();
+    }
+    let mut nearest: HashMap<String, SearchResult> = HashMap::new();
+    for row in lists.into_iter().flatten() {
+        match nearest.get(&row.id) {
+            Some(kept) if kept.score <= row.score => {}
+            _ => {
+                nearest.insert(row.id.clone(), row);
+            }
         }
     }
+    let mut merged: Vec<SearchResult> = nearest.into_values().collect();
+    merged.sort_by(|a, b| {
+        a.score
+            .partial_cmp(&b.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    merged
+}
 
-    let response = perform_search(
-        query.query.clone(),
-        query.top_k,
-        query.alpha,
-        query.family_friendly,
-        query.exclude.clone(),
-        query.include.clone(),
-        &config,
+/// a fused result: id, score and the row's attributes
+pub type FusedCandidate = (String, f32, HashMap<String, String>);
+
+/// the query as it may appear in logs: as typed, or a stable hash when `LOG_QUERIES` is off
+///
+/// the hash still lets operators spot repeated searches without recording the text.
+fn loggable_query(query: &str, log_queries: bool) -> String {
+    if log_queries {
+        return query.to_string();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    format!("redacted:{:016x}", hasher.finish())
+}
+
+/// number of query terms, logged even when the text isn't
+fn query_term_count(query: &str) -> usize {
+    tokenize(query).len()
+}
+
+/// query text for each side of the hybrid search
+///
+/// the semantic text may carry hints for the embedder (e.g. a language prefix) that
+/// would only add noise to BM25, so keyword search gets the text as typed.
+pub struct QueryText<'a> {
+    pub semantic: &'a str,
+    pub keyword: &'a str,
+    /// the query as it may appear in logs (see `loggable_query`)
+    pub logged: &'a str,
+    /// spaced form of a hyphenated semantic text and its share of the query embedding
+    pub blend: Option<(&'a str, f32)>,
+    /// expand the keyword query from the top semantic results (see `expansion`)
+    pub pseudo_relevance: bool,
+    /// drop keyword results whose name lacks any (non-stopword) keyword term
+    pub require_all_terms: bool,
+    /// fetch both signals even when `alpha` weighs one at zero (for `views` and sweeps)
+    pub all_signals: bool,
+    /// more semantic texts OR-ed with `semantic`; each bufo keeps its nearest distance
+    pub alternatives: &'a [String],
+}
+
+/// embed the semantic text, blended with its spaced form when there is one
+///
+/// a semantic text over `long_query_threshold` is pooled from chunks (see `long_query`).
+async fn embed_semantic<E: Embedder>(
+    embedder: &E,
+    query: &QueryText<'_>,
+    long_query_threshold: Option<usize>,
+) -> Result<Vec<f32>, EmbeddingError> {
+    let typed = embed_query_pooled(embedder, query.semantic, long_query_threshold);
+    match query.blend {
+        Some((spaced, weight)) => {
+            let (typed, spaced) = futures::try_join!(typed, embedder.embed_query(spaced))?;
+            Ok(query_form::blend(&typed, &spaced, weight))
+        }
+        None => typed.await,
+    }
+}
+
+/// fused candidates from a single namespace
+struct HybridResults {
+    candidates: Vec<FusedCandidate>,
+    /// the primary semantic signal alone, best first (for `views`)
+    semantic: Vec<FusedCandidate>,
+    /// the normalized keyword signal alone, best first (for `views`)
+    keyword: Vec<FusedCandidate>,
+    /// true when the vector or keyword search (or an ensemble member) failed and the
+    /// rest contributed without it
+    degraded: bool,
+    /// time spent in each stage (filtering and the total are filled in by the caller)
+    timings: StageTimings,
+    /// the primary embedder's query vector, when the semantic side embedded one
+    query_embedding: Option<Vec<f32>>,
+}
+
+/// sort candidates best first, ties broken by id so rankings are deterministic
+fn sort_candidates(candidates: &mut [FusedCandidate]) {
+    candidates.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+}
+
+/// BM25 results for each of `fields`, in order
+///
+/// the default `name`-only search is a single `search_by_keyword` call; extra fields
+/// are queried concurrently, and any field failing fails the keyword side.
+async fn keyword_search<V: VectorStore>(
+    store: &V,
+    fields: &KeywordFields,
+    query: &str,
+    top_k: usize,
+    options: &QueryOptions,
+) -> Result<Vec<Vec<SearchResult>>, VectorSearchError> {
+    if fields.is_name_only() {
+        return Ok(vec![store.search_by_keyword(query, top_k, options).await?]);
+    }
+    futures::future::try_join_all(
+        fields
+            .names()
+            .map(|field| store.search_by_keyword_field(field, query, top_k, options)),
     )
-    .await?;
+    .await
+}
+
+/// candidates fetched per upstream search for each requested result, so enough survive
+/// filtering
+const OVERFETCH_MULTIPLIER: usize = 5;
+
+/// how many candidates to fetch for `top_k` results, and whether `cap` cut it short
+///
+/// the cap never goes below `top_k` itself.
+fn candidate_pool_size(top_k: usize, cap: usize) -> (usize, bool) {
+    let wanted = top_k * OVERFETCH_MULTIPLIER;
+    let cap = cap.max(top_k);
+    (wanted.min(cap), wanted > cap)
+}
+
+/// whether the (semantic, keyword) sides go unfetched at `alpha`
+///
+/// a side weighted at zero can't move the fused ranking, so it isn't fetched; at alpha 0
+/// that saves the embedding too. views need every signal, so `all_signals` fetches both.
+pub fn skipped_sides(alpha: f32, all_signals: bool) -> (bool, bool) {
+    (alpha == 0.0 && !all_signals, alpha == 1.0 && !all_signals)
+}
+
+/// execute hybrid search using the provided embedder and vector store
+///
+/// each ensemble member contributes an additional semantic signal; with no members
+/// this is the plain two-signal fusion. if only one of the vector and keyword searches
+/// fails, the other's results are used and the outcome is marked degraded. a failed
+/// member likewise drops out of the fusion and marks it degraded.
+async fn execute_hybrid_search<E: Embedder, V: VectorStore>(
+    query: &QueryText<'_>,
+    top_k: usize,
+    fusion_config: &FusionConfig,
+    embedder: &E,
+    vector_store: &V,
+    ensemble: &[EnsembleMember<E, V>],
+    options: &QueryOptions,
+) -> Result<HybridResults, SearchError> {
+    // fetch extra results to ensure we have enough after filtering
+    let (search_top_k, capped) = candidate_pool_size(top_k, fusion_config.candidate_cap);
+    let query_owned = query.logged.to_string();
+    if capped {
+        let requested = (top_k * OVERFETCH_MULTIPLIER) as i64;
+        logfire::info!(
+            "candidate pool capped",
+            query = &query_owned,
+            top_k = top_k as i64,
+            requested = requested,
+            cap = search_top_k as i64
+        );
+    }
+
+    let namespace = vector_store.name().to_string();
+
+    let (skip_semantic, skip_keyword) = skipped_sides(fusion_config.alpha, query.all_signals);
+
+    // the semantic side (embedding + ANN) and the keyword side run concurrently
+    let mut query_vector = None;
+    let semantic_side = async {
+        if skip_semantic {
+            return (Ok(Vec::new()), 0.0, 0.0);
+        }
+        let started = Instant::now();
+        let mut embedded = None;
+        let outcome = async {
+            let alternative_count = query.alternatives.len() as i64;
+            let (query_embedding, alternative_embeddings) = async {
+                futures::try_join!(
+                    embed_semantic(embedder, query, fusion_config.long_query_threshold),
+                    embed_alternatives(embedder, query.alternatives)
+                )
+            }
+            .instrument(logfire::span!(
+                "embedding.generate",
+                query = &query_owned,
+                model = embedder.name(),
+                alternatives = alternative_count
+            ))
+            .await?;
+
+            logfire::info!(
+                "embedding generated",
+                query = &query_owned,
+                embedding_dim = query_embedding.len() as i64
+            );
+            if let Some(tracker) = &fusion_config.dimension_tracker {
+                tracker.check(embedder.name(), query_embedding.len());
+            }
+            embedded = Some(Instant::now());
+
+            // one vector query per alternative, unioned at each bufo's nearest distance
+            let searches = std::iter::once(&query_embedding)
+                .chain(&alternative_embeddings)
+                .map(|embedding| vector_store.search_by_vector(embedding, search_top_k, options));
+            let results = futures::future::try_join_all(searches)
+                .instrument(logfire::span!(
+                    "turbopuffer.vector_search",
+                    query = &query_owned,
+                    top_k = search_top_k as i64,
+                    namespace = &namespace
+                ))
+                .await
+                .map(merge_nearest)?;
+
+            logfire::info!(
+                "vector search completed",
+                query = &query_owned,
+                results_found = results.len() as i64
+            );
+            query_vector = Some(query_embedding);
+
+            Ok::<_, SearchError>(results)
+        }
+        .await;
+
+        // a failed embedding counts entirely as embedding time
+        let finished = Instant::now();
+        let embed_ms = millis(embedded.unwrap_or(finished) - started);
+        let vector_ms = embedded.map_or(0.0, |at| millis(finished - at));
+        (outcome, embed_ms, vector_ms)
+    };
+
+    let keyword_side = async {
+        if skip_keyword {
+            return (Ok(Vec::new()), 0.0);
+        }
+        let started = Instant::now();
+        let outcome = keyword_search(
+            vector_store,
+            &fusion_config.keyword_fields,
+            query.keyword,
+            search_top_k,
+            options,
+        )
+        .instrument(logfire::span!(
+            "turbopuffer.bm25_search",
+            query = &query_owned,
+            top_k = search_top_k as i64,
+            namespace = &namespace
+        ))
+        .await;
+        (outcome, millis(started.elapsed()))
+    };
+
+    let ((vector_outcome, embed_ms, vector_ms), (bm25_outcome, bm25_ms)) =
+        futures::join!(semantic_side, keyword_side);
+    let mut timings = StageTimings {
+        embed_ms,
+        vector_ms,
+        bm25_ms,
+        ..StageTimings::default()
+    };
+
+    // one failed side degrades to the other; only fail when neither has results
+    let (vector_results, bm25_results, degraded) = match (vector_outcome, bm25_outcome) {
+        (Ok(vector), Ok(bm25)) => (vector, bm25, false),
+        // a query turbopuffer rejects is the caller's problem, not an outage
+        (_, Err(e @ VectorSearchError::QueryTooLong { .. })) => return Err(e.into()),
+        (Ok(vector), Err(e)) => {
+            let error = e.to_string();
+            logfire::warn!(
+                "bm25 search failed, using vector results only",
+                query = &query_owned,
+                namespace = &namespace,
+                error = &error
+            );
+            (vector, Vec::new(), true)
+        }
+        (Err(e), Ok(bm25)) => {
+            let error = e.to_string();
+            logfire::warn!(
+                "vector search failed, using bm25 results only",
+                query = &query_owned,
+                namespace = &namespace,
+                error = &error
+            );
+            (Vec::new(), bm25, true)
+        }
+        (Err(e), Err(_)) => return Err(e),
+    };
+
+    // pseudo-relevance feedback waits for the vector results, so it adds a sequential
+    // keyword round trip; the first-pass results are kept if the second pass fails
+    let mut bm25_results = bm25_results;
+    if query.pseudo_relevance && !degraded && !skip_keyword {
+        let names: Vec<&str> = vector_results
+            .iter()
+            .take(FEEDBACK_RESULTS)
+            .filter_map(|r| r.attributes.get("name").map(String::as_str))
+            .collect();
+        let terms = expansion_terms(query.keyword, &names, MAX_EXPANSION_TERMS);
+        if let Some(expanded) = expanded_query(query.keyword, &terms) {
+            let started = Instant::now();
+            let outcome = keyword_search(
+                vector_store,
+                &fusion_config.keyword_fields,
+                &expanded,
+                search_top_k,
+                options,
+            )
+            .instrument(logfire::span!(
+                "turbopuffer.bm25_search_expanded",
+                query = &query_owned,
+                added_terms = terms.len() as i64,
+                namespace = &namespace
+            ))
+            .await;
+            timings.bm25_ms += millis(started.elapsed());
+            match outcome {
+                Ok(results) => bm25_results = results,
+                Err(e) => {
+                    let error = e.to_string();
+                    logfire::warn!(
+                        "expanded bm25 search failed, using the original keyword results",
+                        query = &query_owned,
+                        namespace = &namespace,
+                        error = &error
+                    );
+                }
+            }
+        }
+    }
+
+    // BM25 matches any term; strict mode keeps the names that have them all. checked
+    // against the original query, so pseudo-relevance terms stay optional
+    if query.require_all_terms {
+        for results in &mut bm25_results {
+            results.retain(|r| {
+                r.attributes
+                    .get("name")
+                    .is_some_and(|name| contains_all_terms(query.keyword, name))
+            });
+        }
+    }
+
+    // members embed and search concurrently; a failed member only loses its own signal
+    let member_searches = ensemble.iter().map(|member| {
+        let query_owned = &query_owned;
+        async move {
+            if skip_semantic {
+                return (Ok(Vec::new()), 0.0, 0.0);
+            }
+            let started = Instant::now();
+            let mut embedded = None;
+            let outcome = async {
+                let member_embedding =
+                    embed_semantic(&member.embedder, query, fusion_config.long_query_threshold)
+                        .await?;
+                embedded = Some(Instant::now());
+                let results = member
+                    .vector_store
+                    .search_by_vector(&member_embedding, search_top_k, options)
+                    .await?;
+
+                logfire::info!(
+                    "ensemble vector search completed",
+                    query = query_owned,
+                    model = member.embedder.name(),
+                    results_found = results.len() as i64
+                );
+                Ok::<_, SearchError>(results)
+            }
+            .instrument(logfire::span!(
+                "ensemble.vector_search",
+                query = query_owned,
+                model = member.embedder.name(),
+                top_k = search_top_k as i64
+            ))
+            .await;
+
+            let finished = Instant::now();
+            let embed_ms = millis(embedded.unwrap_or(finished) - started);
+            let vector_ms = embedded.map_or(0.0, |at| millis(finished - at));
+            (outcome, embed_ms, vector_ms)
+        }
+    });
+
+    // a failed member's signal is dropped (`None`), leaving its share to the primary
+    let mut degraded = degraded;
+    let mut ensemble_results: Vec<Option<Vec<SearchResult>>> = Vec::with_capacity(ensemble.len());
+    let (mut member_embed_ms, mut member_vector_ms) = (0.0f64, 0.0f64);
+    for (member, (outcome, embed_ms, vector_ms)) in ensemble
+        .iter()
+        .zip(futures::future::join_all(member_searches).await)
+    {
+        member_embed_ms = member_embed_ms.max(embed_ms);
+        member_vector_ms = member_vector_ms.max(vector_ms);
+        match outcome {
+            Ok(results) => ensemble_results.push(Some(results)),
+            Err(e) => {
+                let error = e.to_string();
+                logfire::warn!(
+                    "ensemble member failed, dropping its signal",
+                    query = &query_owned,
+                    model = member.embedder.name(),
+                    error = &error
+                );
+                degraded = true;
+                ensemble_results.push(None);
+            }
+        }
+    }
+    timings.embed_ms += member_embed_ms;
+    timings.vector_ms += member_vector_ms;
+
+    // normalize scores
+    let fusion_started = Instant::now();
+    let semantic_scores: HashMap<String, f32> = vector_results
+        .iter()
+        .map(|r| (r.id.clone(), fusion_config.similarity_curve.similarity(r.score)))
+        .collect();
+
+    // each field is normalized against its own best match, then the fields are weighted
+    let per_field_scores: Vec<HashMap<String, f32>> = bm25_results
+        .iter()
+        .map(|results| {
+            let raw: Vec<(String, f32)> =
+                results.iter().map(|r| (r.id.clone(), r.score)).collect();
+            fusion_config.keyword_normalization.normalize(&raw)
+        })
+        .collect();
+    let mut keyword_scores = fusion_config.keyword_fields.combine(&per_field_scores);
+    let bm25_rows: Vec<&SearchResult> = bm25_results.iter().flatten().collect();
+
+    if fusion_config.position_boost > 0.0 || fusion_config.coverage_weight > 0.0 {
+        let names: HashMap<String, String> = bm25_rows
+            .iter()
+            .filter_map(|r| r.attributes.get("name").map(|n| (r.id.clone(), n.clone())))
+            .collect();
+        apply_coverage_scaling(
+            &mut keyword_scores,
+            &names,
+            query.keyword,
+            fusion_config.coverage_weight,
+        );
+        apply_position_boost(&mut keyword_scores, &names, query.keyword, fusion_config.position_boost);
+    }
+
+    let max_bm25 = bm25_rows
+        .iter()
+        .map(|r| r.score)
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    logfire::info!(
+        "bm25 search completed",
+        query = &query_owned,
+        results_found = bm25_rows.len() as i64,
+        fields = bm25_results.len() as i64,
+        max_bm25 = max_bm25 as f64,
+        top_bm25_raw = bm25_rows.first().map(|r| r.score).unwrap_or(0.0) as f64
+    );
+
+    // fuse scores
+    let live_members: Vec<(&EnsembleMember<E, V>, &Vec<SearchResult>)> = ensemble
+        .iter()
+        .zip(&ensemble_results)
+        .filter_map(|(member, results)| Some((member, results.as_ref()?)))
+        .collect();
+    let mut fused = if live_members.is_empty() {
+        fuse_scores(&semantic_scores, &keyword_scores, fusion_config)
+    } else {
+        let ensemble_scores: Vec<HashMap<String, f32>> = live_members
+            .iter()
+            .map(|(_, results)| {
+                results
+                    .iter()
+                    .map(|r| (r.id.clone(), fusion_config.similarity_curve.similarity(r.score)))
+                    .collect()
+            })
+            .collect();
+
+        let member_shares: Vec<f32> = live_members.iter().map(|(m, _)| m.weight).collect();
+        let primary_share = (1.0 - member_shares.iter().sum::<f32>()).max(0.0);
+        let shares: Vec<f32> = std::iter::once(primary_share).chain(member_shares).collect();
+
+        let mut signals = vec![&semantic_scores];
+        signals.extend(ensemble_scores.iter());
+        signals.push(&keyword_scores);
+
+        fuse_scores_multi(&signals, &fusion_config.weights(&shares), fusion_config.min_score)
+    };
+
+    // collect attributes from all result sets, combined per `ATTRIBUTE_MERGE` where
+    // several return the same id
+    let mut all_attributes: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for result in vector_results
+        .iter()
+        .chain(bm25_rows.iter().copied())
+        .chain(ensemble_results.iter().flatten().flatten())
+    {
+        match all_attributes.get_mut(&result.id) {
+            Some(existing) => fusion_config
+                .attribute_merge
+                .merge(existing, &result.attributes),
+            None => {
+                all_attributes.insert(result.id.clone(), result.attributes.clone());
+            }
+        }
+    }
+
+    // an exact name match is what the user typed, so fusion shouldn't bury it
+    if fusion_config.exact_match_boost > 0.0 {
+        let names: HashMap<String, String> = all_attributes
+            .iter()
+            .filter_map(|(id, attrs)| attrs.get("name").map(|n| (id.clone(), n.clone())))
+            .collect();
+        apply_exact_match_boost(
+            &mut fused,
+            &names,
+            query.keyword,
+            fusion_config.exact_match_boost,
+        );
+    }
+
+    apply_sticky_order(
+        &mut fused,
+        &fusion_config.prev_order,
+        fusion_config.hysteresis,
+    );
+
+    logfire::info!(
+        "weighted fusion completed",
+        total_candidates = (vector_results.len() + bm25_rows.len()) as i64,
+        alpha = fusion_config.alpha as f64,
+        ensemble_members = live_members.len() as i64,
+        pre_filter_results = fused.len() as i64
+    );
+
+    // single-signal rankings reuse the results already fetched
+    let ranked = |scores: &HashMap<String, f32>| -> Vec<FusedCandidate> {
+        let mut list: Vec<FusedCandidate> = scores
+            .iter()
+            .map(|(id, score)| {
+                let attrs = all_attributes.get(id).cloned().unwrap_or_default();
+                (id.clone(), *score, attrs)
+            })
+            .collect();
+        sort_candidates(&mut list);
+        list
+    };
+    let semantic = ranked(&semantic_scores);
+    let keyword = ranked(&keyword_scores);
+
+    // return fused results with attributes
+    let candidates = fused
+        .into_iter()
+        .map(|(id, score)| {
+            let attrs = all_attributes.remove(&id).unwrap_or_default();
+            (id, score, attrs)
+        })
+        .collect();
+    timings.fusion_ms = millis(fusion_started.elapsed());
+
+    Ok(HybridResults {
+        candidates,
+        semantic,
+        keyword,
+        degraded,
+        timings,
+        query_embedding: query_vector,
+    })
+}
+
+/// a single-signal ranking for `views`, filtered and cut like the fused results
+fn signal_view(
+    ranked: &[FusedCandidate],
+    pipeline: &Pipeline,
+    top_k: usize,
+    cap: Option<usize>,
+) -> Vec<BufoResult> {
+    let mut results = pipeline.run(
+        ranked
+            .iter()
+            .map(|(id, score, attrs)| BufoResult::from_attributes(id.clone(), *score, attrs))
+            .collect(),
+    );
+    results.truncate(top_k);
+    apply_result_cap(&mut results, cap);
+    results
+}
+
+/// the cursor for the page after `results`, when it's full and candidates remain below it
+fn next_page_cursor(
+    results: &[BufoResult],
+    candidates: &[BufoResult],
+    top_k: usize,
+    truncated: bool,
+) -> Option<String> {
+    let last = results.last()?;
+    let next = Cursor::new(last.score, last.id.clone());
+    let page_full = truncated || results.len() >= top_k;
+    (page_full && candidates.iter().any(|r| next.precedes(r.score, &r.id))).then(|| next.encode())
+}
+
+/// reorder the selected results; ties keep their score order
+fn sort_results(results: &mut [BufoResult], sort: SortOrder, popularity: &HashMap<String, f32>) {
+    match sort {
+        SortOrder::Score => {}
+        SortOrder::Name => results.sort_by_cached_key(|r| r.name.to_lowercase()),
+        SortOrder::Popularity => results.sort_by(|a, b| {
+            let (a, b) = (popularity.get(&a.id), popularity.get(&b.id));
+            b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal)
+        }),
+    }
+}
+
+/// record which terms of `query` each result's name contains
+fn fill_highlights(results: &mut [BufoResult], query: &str) {
+    for result in results {
+        result.highlights = matched_terms(query, &result.name);
+    }
+}
+
+/// results as newline-delimited JSON, one complete object per line
+fn ndjson_body(results: &[BufoResult]) -> Result<String, serde_json::Error> {
+    let mut body = String::new();
+    for result in results {
+        body.push_str(&serde_json::to_string(result)?);
+        body.push('\n');
+    }
+    Ok(body)
+}
 
-    Ok(HttpResponse::Ok()
-        .insert_header(("etag", etag.clone()))
-        .insert_header(("cache-control", "public, max-age=300"))
-        .json(response))
+/// the response body in the requested format and envelope version
+///
+/// ndjson has no envelope, so `version` only applies to json.
+fn serialize_response(
+    response: &SearchResponse,
+    format: ResponseFormat,
+    version: ApiVersion,
+) -> Result<String, serde_json::Error> {
+    match format {
+        ResponseFormat::Json => match version {
+            ApiVersion::V1 => serde_json::to_string(response),
+            ApiVersion::V2 => serde_json::to_string(&v2_envelope(response)?),
+        },
+        ResponseFormat::Ndjson => ndjson_body(&response.results),
+    }
+}
+
+/// index of the result that ranks last in score order (the one a cursor would end on)
+fn last_ranked(results: &[BufoResult]) -> Option<usize> {
+    (0..results.len()).max_by(|&a, &b| {
+        let (a, b) = (&results[a], &results[b]);
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.id.cmp(&b.id))
+    })
+}
+
+/// serialize `response`, dropping its lowest-ranked results until it fits in `max_bytes`
+///
+/// the remaining page is still a score-order prefix, so `next_cursor` moves to its last
+/// result and paging picks up the dropped ones. a response that can't fit even one
+/// result is a 413.
+fn fit_response(
+    response: &mut SearchResponse,
+    query: &SearchQuery,
+    version: ApiVersion,
+    max_bytes: Option<usize>,
+) -> ActixResult<String> {
+    let serialize = |response: &SearchResponse| {
+        serialize_response(response, query.format, version)
+            .map_err(actix_web::error::ErrorInternalServerError)
+    };
+    let mut body = serialize(response)?;
+    let Some(max_bytes) = max_bytes.filter(|&max| body.len() > max) else {
+        return Ok(body);
+    };
+
+    let total = response.results.len();
+    response.truncated = true;
+    response.warnings.push(FieldError::new(
+        "results",
+        format!("results were dropped to keep the response under {} bytes", max_bytes),
+    ));
+    while body.len() > max_bytes && response.results.len() > 1 {
+        // drop enough whole results to cover the overshoot, then measure again
+        let mut excess = body.len() - max_bytes;
+        while excess > 0 && response.results.len() > 1 {
+            let Some(last) = last_ranked(&response.results) else {
+                break;
+            };
+            let dropped = response.results.remove(last);
+            let size = serde_json::to_string(&dropped).map_or(0, |s| s.len());
+            excess = excess.saturating_sub(size + 1);
+        }
+        if query.explore.is_none() {
+            let last = &response.results[last_ranked(&response.results).unwrap_or(0)];
+            response.next_cursor = Some(Cursor::new(last.score, last.id.clone()).encode());
+        }
+        body = serialize(response)?;
+    }
+
+    if body.len() > max_bytes {
+        return Err(actix_web::error::ErrorPayloadTooLarge(format!(
+            "search response is {} bytes, over the {} byte limit even after dropping results",
+            body.len(),
+            max_bytes
+        )));
+    }
+    logfire::warn!(
+        "search response cut to fit",
+        max_bytes = max_bytes as i64,
+        results_count = response.results.len() as i64,
+        dropped = (total - response.results.len()) as i64
+    );
+    Ok(body)
+}
+
+/// finish a search response in the requested format and envelope version, within
+/// `MAX_RESPONSE_BYTES`
+fn render(
+    mut builder: HttpResponseBuilder,
+    response: &mut SearchResponse,
+    query: &SearchQuery,
+    version: ApiVersion,
+    max_bytes: Option<usize>,
+) -> ActixResult<HttpResponse> {
+    let body = fit_response(response, query, version, max_bytes)?;
+    if response.truncated {
+        builder.insert_header((TRUNCATED_HEADER, "true"));
+    }
+    Ok(builder.content_type(content_type(query.format)).body(body))
+}
+
+fn content_type(format: ResponseFormat) -> &'static str {
+    match format {
+        ResponseFormat::Json => "application/json",
+        ResponseFormat::Ndjson => "application/x-ndjson",
+    }
+}
+
+/// a rendered GET search, as kept in the response cache
+#[derive(Debug, Serialize, Deserialize)]
+struct RenderedSearch {
+    body: String,
+    truncated: bool,
+    /// preload `link` header values
+    links: Vec<String>,
+}
+
+impl RenderedSearch {
+    fn new(
+        response: &mut SearchResponse,
+        query: &SearchQuery,
+        version: ApiVersion,
+        max_bytes: Option<usize>,
+    ) -> ActixResult<Self> {
+        let body = fit_response(response, query, version, max_bytes)?;
+        let links = query
+            .preload
+            .map(|count| preload_links(&response.results, count))
+            .unwrap_or_default();
+        Ok(Self {
+            body,
+            truncated: response.truncated,
+            links,
+        })
+    }
+
+    fn respond(self, etag: String, cache_control: String, format: ResponseFormat) -> HttpResponse {
+        let mut builder = HttpResponse::Ok();
+        builder
+            .insert_header(("etag", etag))
+            .insert_header(("cache-control", cache_control))
+            .insert_header(("vary", "accept"));
+        for link in self.links {
+            builder.append_header(("link", link));
+        }
+        if self.truncated {
+            builder.insert_header((TRUNCATED_HEADER, "true"));
+        }
+        builder.content_type(content_type(format)).body(self.body)
+    }
+}
+
+/// header set when `MAX_RETURNED_RESULTS` or `MAX_RESPONSE_BYTES` truncated the response
+const TRUNCATED_HEADER: &str = "x-results-truncated";
+
+/// clamp the final results to the configured cap, returning whether anything was cut
+fn apply_result_cap(results: &mut Vec<BufoResult>, cap: Option<usize>) -> bool {
+    match cap {
+        Some(cap) if results.len() > cap => {
+            results.truncate(cap);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// merge per-namespace fused results for a federated search
+///
+/// each id keeps its best score across namespaces, then scores are max-scaled over the
+/// combined set (each namespace normalizes BM25 against its own results, so raw fused
+/// scores aren't directly comparable) and re-sorted.
+fn merge_namespace_results(sets: Vec<Vec<FusedCandidate>>) -> Vec<FusedCandidate> {
+    let mut best: HashMap<String, FusedCandidate> = HashMap::new();
+    for candidate in sets.into_iter().flatten() {
+        match best.get(&candidate.0) {
+            Some(existing) if existing.1 >= candidate.1 => {}
+            _ => {
+                best.insert(candidate.0.clone(), candidate);
+            }
+        }
+    }
+
+    let mut merged: Vec<FusedCandidate> = best.into_values().collect();
+    let max_score = merged.iter().map(|c| c.1).fold(0.0_f32, f32::max);
+    if max_score > 0.0 {
+        for candidate in &mut merged {
+            candidate.1 /= max_score;
+        }
+    }
+    sort_candidates(&mut merged);
+    merged
+}
+
+/// record `namespace` as the source of every candidate in `results`
+fn tag_source(results: &mut HybridResults, namespace: &str) {
+    for list in [&mut results.candidates, &mut results.semantic, &mut results.keyword] {
+        for (_, _, attrs) in list.iter_mut() {
+            attrs.insert(SOURCE_ATTRIBUTE.to_string(), namespace.to_string());
+        }
+    }
+}
+
+/// merge per-namespace hybrid results (see `merge_namespace_results`)
+fn merge_hybrid_results(sets: Vec<HybridResults>) -> HybridResults {
+    let degraded = sets.iter().any(|r| r.degraded);
+    let timings = StageTimings::slowest(sets.iter().map(|r| r.timings));
+    let mut query_embedding = None;
+    let mut candidates = Vec::with_capacity(sets.len());
+    let mut semantic = Vec::with_capacity(sets.len());
+    let mut keyword = Vec::with_capacity(sets.len());
+    for results in sets {
+        query_embedding = query_embedding.or(results.query_embedding);
+        candidates.push(results.candidates);
+        semantic.push(results.semantic);
+        keyword.push(results.keyword);
+    }
+    HybridResults {
+        candidates: merge_namespace_results(candidates),
+        semantic: merge_namespace_results(semantic),
+        keyword: merge_namespace_results(keyword),
+        degraded,
+        timings,
+        query_embedding,
+    }
+}
+
+/// how many candidates pass `pipeline` with a score above `min_score`
+fn qualifying_count(candidates: &[FusedCandidate], pipeline: &Pipeline, min_score: f32) -> usize {
+    pipeline
+        .run(
+            candidates
+                .iter()
+                .map(|(id, score, attrs)| BufoResult::from_attributes(id.clone(), *score, attrs))
+                .collect(),
+        )
+        .iter()
+        .filter(|r| r.score > min_score)
+        .count()
+}
+
+/// the `min_score` that keeps the best `100 - percentile`% of `scores`
+///
+/// selection keeps scores strictly above the threshold, so this is the best score that
+/// falls outside the kept share (or -inf when everything is kept). ties with it are
+/// dropped too.
+fn percentile_cutoff(scores: impl IntoIterator<Item = f32>, percentile: f32) -> f32 {
+    let mut scores: Vec<f32> = scores.into_iter().collect();
+    scores.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let keep = ((1.0 - percentile / 100.0) * scores.len() as f32).ceil() as usize;
+    scores.get(keep).copied().unwrap_or(f32::NEG_INFINITY)
+}
+
+/// how many times `min_score` is halved before the threshold is dropped entirely
+const MAX_RELAXATION_STEPS: u32 = 4;
+
+/// pick the top `top_k` candidates above `min_score`
+///
+/// candidates must already be post-processed and sorted by score. when fewer than
+/// `min_results` survive (capped at `top_k`), the threshold is progressively halved and
+/// finally removed, re-selecting from the same candidates. returns whether relaxation
+/// happened.
+fn select_results(
+    candidates: &[BufoResult],
+    top_k: usize,
+    min_score: f32,
+    min_results: usize,
+) -> (Vec<BufoResult>, bool) {
+    let select = |threshold: f32| -> Vec<BufoResult> {
+        candidates
+            .iter()
+            .filter(|&r| r.score > threshold)
+            .take(top_k)
+            .cloned()
+            .collect()
+    };
+
+    let target = min_results.min(top_k);
+    let mut results = select(min_score);
+    if results.len() >= target {
+        return (results, false);
+    }
+
+    for step in 1..=MAX_RELAXATION_STEPS + 1 {
+        let threshold = if step > MAX_RELAXATION_STEPS {
+            f32::NEG_INFINITY
+        } else {
+            min_score / 2f32.powi(step as i32)
+        };
+        results = select(threshold);
+        if results.len() >= target {
+            break;
+        }
+    }
+
+    (results, true)
+}
+
+/// extend `results` (selected from the sorted `candidates`) with the candidates tied
+/// with its last score
+///
+/// `select_results` cuts at `top_k` even mid-tie, keeping the tied ids that sort
+/// first; this keeps the whole tie instead.
+fn extend_ties(results: &mut Vec<BufoResult>, candidates: &[BufoResult]) {
+    let Some(boundary) = results.last().map(|r| r.score) else {
+        return;
+    };
+    let tied = candidates
+        .iter()
+        .skip(results.len())
+        .take_while(|r| r.score == boundary);
+    results.extend(tied.cloned());
+}
+
+/// shared search implementation used by both POST and GET handlers
+async fn perform_search(
+    query: &SearchQuery,
+    config: &Config,
+    state: &AppState,
+    request_id: &str,
+) -> ActixResult<SearchResponse> {
+    let request_id = request_id.to_owned();
+    // held until the search returns, so upstream slowness can't pile up unbounded work
+    let _permit = acquire_search_permit(state.search_permits.as_deref()).inspect_err(|_| {
+        logfire::warn!("search rejected, concurrency limit reached", request_id = &request_id);
+    })?;
+    let started = Instant::now();
+
+    let rewritten = rewrite::from_config(config.query_normalization, config.query_collapse_repeats)
+        .rewrite(&query.query);
+    let query_text = rewritten.as_str();
+    let top_k_val = query.top_k;
+    let alpha_policy = state.alpha_policy.current();
+    let (requested_alpha, alpha_source) =
+        alpha_policy.decide(query.alpha, query_text, config.default_alpha);
+    let (alpha, alpha_warning) = effective_alpha(requested_alpha);
+    let alpha_category = match alpha_source {
+        AlphaSource::Category(name) => name.to_string(),
+        _ => String::new(),
+    };
+    logfire::info!(
+        "alpha chosen",
+        request_id = &request_id,
+        source = alpha_source.as_str(),
+        category = &alpha_category,
+        alpha = alpha as f64
+    );
+    let family_friendly = query
+        .family_friendly
+        .unwrap_or(config.default_family_friendly);
+
+    let content_filter = build_content_filter(query, family_friendly)
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+
+    let user_filters = query
+        .filters
+        .as_ref()
+        .map(parse_filters)
+        .transpose()
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let since = query
+        .since
+        .as_deref()
+        .map(parse_since)
+        .transpose()
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let options = QueryOptions {
+        filters: all_of(user_filters.into_iter().chain(since.map(added_after))),
+    };
+
+    let detected_language = detect_language(config, query_text);
+    let language_code = detected_language.as_ref().map_or("unknown", |l| l.code);
+    let logged_query = loggable_query(query_text, config.log_queries);
+
+    let _search_span = logfire::span!(
+        "bufo_search",
+        request_id = &request_id,
+        query = &logged_query,
+        query_length = query_text.chars().count() as i64,
+        query_terms = query_term_count(query_text) as i64,
+        top_k = top_k_val as i64,
+        alpha = alpha as f64,
+        family_friendly = family_friendly,
+        language = language_code,
+        exclude_patterns_count = content_filter.exclude_pattern_count() as i64
+    )
+    .entered();
+
+    if let Some(lang) = &detected_language {
+        logfire::info!(
+            "query language detected",
+            request_id = &request_id,
+            language = lang.code,
+            confidence = lang.confidence,
+            reliable = lang.reliable
+        );
+    }
+
+    let semantic_query = semantic_text(config, query_text, detected_language.as_ref());
+
+    // alternatives are embedded like the query, and BM25 (which matches any term) gets
+    // all of their words
+    let rewriter = rewrite::from_config(config.query_normalization, config.query_collapse_repeats);
+    let alternatives: Vec<String> = query
+        .queries
+        .iter()
+        .flatten()
+        .map(|q| rewriter.rewrite(q))
+        .collect();
+    let semantic_alternatives: Vec<String> = alternatives
+        .iter()
+        .map(|q| semantic_text(config, q, detected_language.as_ref()))
+        .collect();
+    let keyword_text = std::iter::once(query_text)
+        .chain(alternatives.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let keyword_text = without_short_terms(&keyword_text, config.bm25_min_term_length);
+
+    logfire::info!(
+        "search request received",
+        request_id = &request_id,
+        query = &logged_query,
+        top_k = top_k_val as i64,
+        alpha = alpha as f64,
+        exclude_patterns = &content_filter.exclude_patterns_str()
+    );
+
+    let pipeline = request_pipeline(query, config, content_filter.clone());
+
+    // create clients; a chosen model is compared on its own, without the ensemble
+    let route = query
+        .model
+        .as_deref()
+        .and_then(|model| config.embedding_models.route(model));
+    let (embedder, ensemble) = match route {
+        Some(route) => (
+            build_routed_embedder(config, &state.embedding_cache, route),
+            Vec::new(),
+        ),
+        None => (
+            build_embedder(config, state),
+            build_ensemble(config, &state.embedding_cache),
+        ),
+    };
+    let namespace = primary_namespace(
+        query,
+        &config.embedding_models,
+        &config.turbopuffer_namespace,
+    );
+    // the fallback namespace is indexed with the default model
+    let fallback_namespace = config
+        .fallback_namespace
+        .as_deref()
+        .filter(|ns| *ns != namespace && route.is_none());
+
+    let mut fusion_config = FusionConfig::new(alpha);
+    fusion_config.position_boost = config.bm25_position_boost;
+    fusion_config.coverage_weight = config.bm25_coverage_weight;
+    fusion_config.keyword_normalization = config.keyword_normalization;
+    fusion_config.similarity_curve = config.similarity_curve;
+    fusion_config.keyword_fields = config.keyword_fields.clone();
+    fusion_config.exact_match_boost = config.exact_match_boost;
+    fusion_config.attribute_merge = config.attribute_merge;
+    fusion_config.prev_order = query.prev_order.clone().unwrap_or_default();
+    fusion_config.hysteresis = config.sticky_hysteresis;
+    fusion_config.candidate_cap = config.candidate_cap;
+    // another model's vector size isn't drift
+    fusion_config.dimension_tracker = route.is_none().then(|| state.dimension_tracker.clone());
+    fusion_config.long_query_threshold = config
+        .long_query_pooling
+        .then_some(config.long_query_threshold);
+    let min_score = query.min_score.unwrap_or(fusion_config.min_score);
+    // keep every fused candidate; min_score is applied during selection so it can be relaxed
+    fusion_config.min_score = f32::NEG_INFINITY;
+
+    let spaced_text = if config.filename_blend_weight > 0.0 {
+        query_form::spaced_form(&semantic_query)
+    } else {
+        None
+    };
+    let query_texts = QueryText {
+        semantic: &semantic_query,
+        keyword: &keyword_text,
+        logged: &logged_query,
+        blend: spaced_text
+            .as_deref()
+            .map(|spaced| (spaced, config.filename_blend_weight)),
+        pseudo_relevance: query.pseudo_relevance,
+        require_all_terms: query.require_all_terms,
+        all_signals: query.views.is_some(),
+        alternatives: &semantic_alternatives,
+    };
+
+    // everything above is local, so a plan is complete before any upstream call
+    if query.plan {
+        let namespaces = match query.namespaces.as_deref() {
+            Some(namespaces) if namespaces.len() > 1 => namespaces.to_vec(),
+            _ => vec![namespace.to_string()],
+        };
+        let members: Vec<(String, String)> = ensemble
+            .iter()
+            .map(|m| {
+                (
+                    m.embedder.name().to_string(),
+                    m.vector_store.name().to_string(),
+                )
+            })
+            .collect();
+        let calls = planned_calls(&CallInputs {
+            alpha,
+            all_signals: query_texts.all_signals,
+            pseudo_relevance: query.pseudo_relevance,
+            model: embedder.name(),
+            embedded_texts: pooled_chunks(&semantic_query, fusion_config.long_query_threshold)
+                .map_or(1, |chunks| chunks.len())
+                + usize::from(query_texts.blend.is_some())
+                + alternatives.len(),
+            vector_queries: 1 + alternatives.len(),
+            keyword_fields: fusion_config.keyword_fields.names().count(),
+            namespaces: &namespaces,
+            ensemble: &members,
+        });
+        let patterns = |list: &Option<String>| -> Vec<String> {
+            list.as_deref()
+                .map(|p| split_patterns(p).map(String::from).collect())
+                .unwrap_or_default()
+        };
+
+        return Ok(SearchResponse {
+            plan: Some(SearchPlan {
+                query: query_text.to_string(),
+                semantic_text: semantic_query.clone(),
+                keyword_text: keyword_text.clone(),
+                alpha,
+                alpha_source: alpha_source.as_str(),
+                alpha_category: (!alpha_category.is_empty()).then(|| alpha_category.to_string()),
+                top_k: top_k_val,
+                candidate_pool: candidate_pool_size(top_k_val, config.candidate_cap).0,
+                calls,
+                fallback_namespace: fallback_namespace
+                    .filter(|_| namespaces.len() == 1)
+                    .map(String::from),
+                filters: PlannedFilters {
+                    family_friendly,
+                    exclude: patterns(&query.exclude),
+                    include: patterns(&query.include),
+                    attributes: options.filters.clone(),
+                    min_score,
+                    min_score_percentile: query.min_score_percentile,
+                },
+                post_processors: pipeline.step_names(),
+            }),
+            warnings: alpha_warning.into_iter().collect(),
+            ..SearchResponse::default()
+        });
+    }
+
+    // execute hybrid search, federated when the request names several namespaces
+    let mut hybrid = match query.namespaces.as_deref() {
+        Some(namespaces) if namespaces.len() > 1 => {
+            let stores: Vec<TurbopufferStore> =
+                namespaces.iter().map(|ns| build_store(config, ns)).collect();
+
+            let namespace_list = namespaces.join(",");
+            logfire::info!(
+                "federated search",
+                request_id = &request_id,
+                namespaces = &namespace_list
+            );
+
+            // the ensemble has its own namespace, so federated searches skip it
+            let mut per_namespace = futures::future::try_join_all(stores.iter().map(|store| {
+                execute_hybrid_search(
+                    &query_texts,
+                    top_k_val,
+                    &fusion_config,
+                    &embedder,
+                    store,
+                    &[],
+                    &options,
+                )
+            }))
+            .await
+            .map_err(|e| e.into_actix_error())?;
+
+            for (results, namespace) in per_namespace.iter_mut().zip(namespaces) {
+                tag_source(results, namespace);
+            }
+            merge_hybrid_results(per_namespace)
+        }
+        _ => {
+            let vector_store = build_store(config, namespace);
+
+            let mut primary = execute_hybrid_search(
+                &query_texts,
+                top_k_val,
+                &fusion_config,
+                &embedder,
+                &vector_store,
+                &ensemble,
+                &options,
+            )
+            .await
+            .map_err(|e| e.into_actix_error())?;
+            tag_source(&mut primary, namespace);
+
+            match fallback_namespace {
+                Some(fallback) => {
+                    // a throwaway pipeline, so the real one's rejection counts stay accurate
+                    let check = request_pipeline(query, config, content_filter.clone());
+                    let threshold = match query.min_score_percentile {
+                        Some(p) => percentile_cutoff(primary.candidates.iter().map(|c| c.1), p),
+                        None => min_score,
+                    };
+                    let wanted = query.min_results.unwrap_or(1).min(top_k_val);
+                    let found = qualifying_count(&primary.candidates, &check, threshold);
+
+                    if found >= wanted {
+                        primary
+                    } else {
+                        logfire::info!(
+                            "searching fallback namespace",
+                            request_id = &request_id,
+                            namespace = &fallback.to_string(),
+                            primary_results = found as i64,
+                            wanted = wanted as i64
+                        );
+                        // the ensemble is tied to the primary namespace, so it's skipped here
+                        let fallback_store = build_store(config, fallback);
+                        match execute_hybrid_search(
+                            &query_texts,
+                            top_k_val,
+                            &fusion_config,
+                            &embedder,
+                            &fallback_store,
+                            &[],
+                            &options,
+                        )
+                        .await
+                        {
+                            Ok(mut extra) => {
+                                tag_source(&mut extra, fallback);
+                                // the fallback ran after the primary, so its stages add up
+                                let timings = primary.timings.then(extra.timings);
+                                let mut merged = merge_hybrid_results(vec![primary, extra]);
+                                merged.timings = timings;
+                                merged
+                            }
+                            Err(e) => {
+                                let error = e.to_string();
+                                logfire::warn!(
+                                    "fallback namespace search failed",
+                                    request_id = &request_id,
+                                    namespace = &fallback.to_string(),
+                                    error = &error
+                                );
+                                primary
+                            }
+                        }
+                    }
+                }
+                None => primary,
+            }
+        }
+    };
+
+    let mut timings = hybrid.timings;
+    let filter_started = Instant::now();
+    let query_cluster = hybrid
+        .query_embedding
+        .as_deref()
+        .filter(|_| query.debug)
+        .and_then(|embedding| state.clusterer.label(embedding))
+        .map(str::to_string);
+
+    // views are built from the single-signal lists, so blocked ids leave those too
+    let blocked_ids = state.blocked_ids.current();
+    let blocked = blocked_ids.remove_blocked(&mut hybrid.candidates);
+    blocked_ids.remove_blocked(&mut hybrid.semantic);
+    blocked_ids.remove_blocked(&mut hybrid.keyword);
+    if blocked > 0 {
+        logfire::info!(
+            "blocked ids removed",
+            request_id = &request_id,
+            removed = blocked as i64
+        );
+    }
+
+    // convert to BufoResults and run the post-processing pipeline
+    // tags are only kept for facets, which are counted once the pipeline has run
+    let mut tags_by_id: HashMap<String, Vec<String>> = HashMap::new();
+    let mut popularity_by_id: HashMap<String, f32> = HashMap::new();
+    let candidates: Vec<BufoResult> = hybrid
+        .candidates
+        .into_iter()
+        .map(|(id, score, attrs)| {
+            if query.facets {
+                if let Some(raw) = attrs.get("tags") {
+                    tags_by_id.insert(id.clone(), parse_tags(raw));
+                }
+            }
+            if query.sort == SortOrder::Popularity {
+                if let Some(popularity) = numeric_attribute(&attrs, "popularity") {
+                    popularity_by_id.insert(id.clone(), popularity);
+                }
+            }
+            BufoResult::from_attributes(id, score, &attrs)
+        })
+        .collect();
+
+    let histogram = query.include_score_histogram.then(|| ScoreHistogram {
+        bucket_width: HISTOGRAM_BUCKET_WIDTH,
+        counts: score_histogram(candidates.iter().map(|r| r.score)),
+    });
+
+    // a percentile threshold comes from every fused candidate, before filtering
+    let min_score = match query.min_score_percentile {
+        Some(percentile) => {
+            let cutoff = percentile_cutoff(candidates.iter().map(|r| r.score), percentile);
+            logfire::info!(
+                "min_score from percentile",
+                request_id = &request_id,
+                percentile = percentile as f64,
+                min_score = cutoff as f64
+            );
+            cutoff
+        }
+        None => min_score,
+    };
+
+    let fused_count = candidates.len();
+    let candidates = pipeline.run(candidates);
+    let filtered_count = candidates.len();
+
+    let facets = query.facets.then(|| {
+        facet_counts(
+            candidates
+                .iter()
+                .filter_map(|r| tags_by_id.get(&r.id))
+                .map(Vec::as_slice),
+        )
+    });
+
+    let rejections = pipeline.rejections();
+    logfire::info!(
+        "content filter applied",
+        request_id = &request_id,
+        rejected_total = rejections.total() as i64,
+        rejected_by_blocklist = rejections.rejected_by_blocklist as i64,
+        rejected_by_exclude = rejections.rejected_by_exclude as i64
+    );
+
+    // a later page continues below the previous page's last result; facets still count
+    // every candidate
+    let candidates: Vec<BufoResult> = match &query.cursor {
+        Some(cursor) => {
+            let cursor = Cursor::decode(cursor)
+                .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+            candidates
+                .into_iter()
+                .filter(|r| cursor.precedes(r.score, &r.id))
+                .collect()
+        }
+        None => candidates,
+    };
+    let paged_count = candidates.len();
+
+    let (mut results, relaxed) = match query.explore {
+        // the whole pool above min_score (relaxed as usual), then a weighted draw from it
+        Some(temperature) => {
+            let (pool, relaxed) = select_results(
+                &candidates,
+                candidates.len(),
+                min_score,
+                query.min_results.unwrap_or(0).min(top_k_val),
+            );
+            let pool_size = pool.len() as i64;
+            let sampled = sample_by_score(
+                pool,
+                |r| r.score,
+                top_k_val,
+                temperature,
+                &mut rand::thread_rng(),
+            );
+            logfire::info!(
+                "results sampled",
+                request_id = &request_id,
+                temperature = temperature as f64,
+                pool_size = pool_size
+            );
+            (sampled, relaxed)
+        }
+        None => {
+            let (mut results, relaxed) = select_results(
+                &candidates,
+                top_k_val,
+                min_score,
+                query.min_results.unwrap_or(0),
+            );
+            if query.include_ties {
+                extend_ties(&mut results, &candidates);
+            }
+            (results, relaxed)
+        }
+    };
+    timings.filter_ms = millis(filter_started.elapsed());
+
+    let no_results_reason =
+        no_results_reason(fused_count, filtered_count, paged_count, results.len());
+    if let Some(reason) = no_results_reason {
+        logfire::info!(
+            "search found no results",
+            request_id = &request_id,
+            reason = reason.as_str()
+        );
+    }
+
+    if relaxed {
+        logfire::info!(
+            "min_score relaxed",
+            request_id = &request_id,
+            min_score = min_score as f64,
+            min_results = query.min_results.unwrap_or(0) as i64,
+            results_count = results.len() as i64
+        );
+    }
+
+    let truncated = apply_result_cap(&mut results, config.max_returned_results);
+    let next_cursor = next_page_cursor(&results, &candidates, top_k_val, truncated)
+        .filter(|_| query.explore.is_none());
+    // after the cursor, which follows score order whatever the page's order
+    sort_results(&mut results, query.sort, &popularity_by_id);
+    fill_thumbnails(&mut results, config.thumbnail_transform.as_ref());
+    if query.highlight {
+        fill_highlights(&mut results, &keyword_text);
+    }
+    if query.pretty_names {
+        fill_display_names(&mut results, config.display_name_case);
+    }
+
+    let views = match &query.views {
+        Some(names) => {
+            let mut views = BTreeMap::new();
+            for view in names.iter().filter_map(|name| name.parse::<View>().ok()) {
+                let ranked = match view {
+                    View::Fused => {
+                        views.insert(view, results.clone());
+                        continue;
+                    }
+                    View::Semantic => &hybrid.semantic,
+                    View::Keyword => &hybrid.keyword,
+                };
+                // a fresh pipeline so the fused list's rejection counts stay as they were
+                let filter = build_content_filter(query, family_friendly)
+                    .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+                let pipeline = request_pipeline(query, config, filter);
+                let mut ranking =
+                    signal_view(ranked, &pipeline, top_k_val, config.max_returned_results);
+                fill_thumbnails(&mut ranking, config.thumbnail_transform.as_ref());
+                if query.highlight {
+                    fill_highlights(&mut ranking, &keyword_text);
+                }
+                if query.pretty_names {
+                    fill_display_names(&mut ranking, config.display_name_case);
+                }
+                views.insert(view, ranking);
+            }
+            Some(views)
+        }
+        None => None,
+    };
+
+    let results_count = results.len() as i64;
+    let top_result_name = results
+        .first()
+        .map(|r| r.name.clone())
+        .unwrap_or_else(|| "none".to_string());
+    let top_score_val = results.first().map(|r| r.score as f64).unwrap_or(0.0);
+    let avg_score_val = if !results.is_empty() {
+        results.iter().map(|r| r.score as f64).sum::<f64>() / results.len() as f64
+    } else {
+        0.0
+    };
+
+    logfire::info!(
+        "search completed successfully",
+        request_id = &request_id,
+        query = &logged_query,
+        results_count = results_count,
+        top_result = &top_result_name,
+        top_score = top_score_val,
+        avg_score = avg_score_val,
+        degraded = hybrid.degraded
+    );
+
+    timings.total_ms = millis(started.elapsed());
+    if is_slow(timings.total_ms, config.slow_query_ms) {
+        logfire::warn!(
+            "slow search",
+            request_id = &request_id,
+            query = &logged_query,
+            results_count = results_count,
+            degraded = hybrid.degraded,
+            total_ms = timings.total_ms,
+            embed_ms = timings.embed_ms,
+            vector_ms = timings.vector_ms,
+            bm25_ms = timings.bm25_ms,
+            fusion_ms = timings.fusion_ms,
+            filter_ms = timings.filter_ms
+        );
+    }
+
+    let suggestion = state.vocabulary.current().suggest(query_text);
+    let related_terms = query.related.then(|| related_terms(query_text, &results));
+
+    // later pages would count the same search again
+    if !results.is_empty() && query.cursor.is_none() {
+        state.query_history.record(&query.query);
+    }
+
+    Ok(SearchResponse {
+        results,
+        suggestion,
+        no_results_reason,
+        relaxed,
+        score_histogram: histogram,
+        facets,
+        related_terms,
+        views,
+        truncated,
+        degraded: hybrid.degraded,
+        debug: query.debug.then(|| SearchDebug {
+            language: detected_language.as_ref().map(|l| l.code),
+            rejections,
+            timings,
+            query_cluster,
+        }),
+        plan: None,
+        next_cursor,
+        warnings: alpha_warning.into_iter().collect(),
+    })
+}
+
+/// POST /api/search handler (existing API)
+pub async fn search(
+    query: web::Json<SearchQuery>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+    request_id: web::ReqData<RequestId>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse> {
+    validate_request(&query, &config).map_err(validation_error)?;
+    let mut response = perform_search(&query, &config, &state, &request_id.0).await?;
+    apply_image_format(&mut response, &config, negotiated_format(&req, &config));
+
+    let mut builder = HttpResponse::Ok();
+    // the envelope version (and image format) can come from Accept
+    builder.insert_header(("vary", "accept"));
+    let version = negotiated_version(&req, &query);
+    render(
+        builder,
+        &mut response,
+        &query,
+        version,
+        config.max_response_bytes,
+    )
+}
+
+/// GET /api/search handler for shareable URLs
+pub async fn search_get(
+    query: web::Query<SearchQuery>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+    request_id: web::ReqData<RequestId>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse> {
+    validate_request(&query, &config).map_err(validation_error)?;
+
+    let family_friendly = query
+        .family_friendly
+        .unwrap_or(config.default_family_friendly);
+    // urls differ by negotiated image format, so the etag does too
+    let image_format = negotiated_format(&req, &config);
+    let version = negotiated_version(&req, &query);
+    let etag = etag_for_settings(
+        etag_for_version(
+            etag_for_format(generate_etag(&query, family_friendly), image_format),
+            version,
+        ),
+        &state,
+        &config,
+    );
+
+    // nocache asks for fresh results and explore draws new ones each time, so a matching
+    // etag doesn't short-circuit either
+    let fresh = query.nocache || query.explore.is_some();
+    if let Some(if_none_match) = req.headers().get("if-none-match").filter(|_| !fresh) {
+        if if_none_match.to_str().unwrap_or("") == etag {
+            return Ok(HttpResponse::NotModified()
+                .insert_header(("etag", etag))
+                .finish());
+        }
+    }
+
+    // the etag already hashes everything that shapes the body, so it doubles as the key
+    let cache_key = (!fresh && config.response_cache_ttl_secs > 0).then(|| etag.clone());
+    if let Some(key) = &cache_key {
+        let cached = state.response_cache.get(key).await;
+        if let Some(rendered) =
+            cached.and_then(|bytes| serde_json::from_slice::<RenderedSearch>(&bytes).ok())
+        {
+            // only healthy responses are cached
+            let cache_control =
+                search_cache_control(false, false, config.search_cache_max_age_secs);
+            return Ok(rendered.respond(etag, cache_control, query.format));
+        }
+    }
+
+    let mut response = perform_search(&query, &config, &state, &request_id.0).await?;
+    apply_image_format(&mut response, &config, image_format);
+    let rendered = RenderedSearch::new(&mut response, &query, version, config.max_response_bytes)?;
+
+    // a degraded response shouldn't outlive the outage in caches
+    if let Some(key) = cache_key.filter(|_| !response.degraded) {
+        if let Ok(bytes) = serde_json::to_vec(&rendered) {
+            let cache = state.response_cache.clone();
+            let ttl = Duration::from_secs(config.response_cache_ttl_secs);
+            // the client needn't wait on the write
+            actix_web::rt::spawn(async move { cache.set(&key, bytes, ttl).await });
+        }
+    }
+    let cache_control =
+        search_cache_control(fresh, response.degraded, config.search_cache_max_age_secs);
+    Ok(rendered.respond(etag, cache_control, query.format))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationResult {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// values a search would adjust rather than reject
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<FieldError>,
+}
+
+/// POST /api/search/validate: run the search checks without any upstream calls
+pub async fn validate(query: web::Json<SearchQuery>, config: CurrentConfig) -> HttpResponse {
+    let errors = validate_request(&query, &config).err().unwrap_or_default();
+    let alpha_warning = query.alpha.and_then(|alpha| effective_alpha(alpha).1);
+    HttpResponse::Ok().json(ValidationResult {
+        valid: errors.is_empty(),
+        errors,
+        warnings: alpha_warning.into_iter().collect(),
+    })
+}
+
+/// most alpha values one sweep may fuse
+const MAX_SWEEP_ALPHAS: usize = 21;
+
+#[derive(Debug, Deserialize)]
+pub struct SweepRequest {
+    pub query: String,
+    pub alphas: Vec<f32>,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// defaults to the server's `DEFAULT_FAMILY_FRIENDLY`
+    #[serde(default)]
+    pub family_friendly: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SweepResponse {
+    /// top_k results per alpha, keyed by the alpha as given
+    pub results: BTreeMap<String, Vec<BufoResult>>,
+}
+
+fn validate_sweep(request: &SweepRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors: Vec<FieldError> = check_query_text("query", &request.query).into_iter().collect();
+    if request.alphas.is_empty() || request.alphas.len() > MAX_SWEEP_ALPHAS {
+        errors.push(FieldError::new(
+            "alphas",
+            format!("alphas must have between 1 and {} values", MAX_SWEEP_ALPHAS),
+        ));
+    }
+    if let Some(alpha) = request
+        .alphas
+        .iter()
+        .find(|a| !a.is_finite() || !(0.0..=1.0).contains(*a))
+    {
+        errors.push(FieldError::new(
+            "alphas",
+            format!("alpha must be between 0 and 1, got {}", alpha),
+        ));
+    }
+    if !(1..=MAX_TOP_K).contains(&request.top_k) {
+        errors.push(FieldError::new(
+            "top_k",
+            format!("top_k must be between 1 and {}, got {}", MAX_TOP_K, request.top_k),
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// re-fuse already fetched single-signal rankings at another alpha
+fn fuse_at_alpha(
+    semantic: &[FusedCandidate],
+    keyword: &[FusedCandidate],
+    fusion_config: &FusionConfig,
+    alpha: f32,
+) -> Vec<FusedCandidate> {
+    let scores = |list: &[FusedCandidate]| -> HashMap<String, f32> {
+        list.iter().map(|(id, score, _)| (id.clone(), *score)).collect()
+    };
+    let attributes: HashMap<&str, &HashMap<String, String>> = semantic
+        .iter()
+        .chain(keyword)
+        .map(|(id, _, attrs)| (id.as_str(), attrs))
+        .collect();
+
+    let fusion_config = FusionConfig {
+        alpha: alpha.clamp(0.0, 1.0),
+        ..fusion_config.clone()
+    };
+
+    let mut fused: Vec<FusedCandidate> =
+        fuse_scores(&scores(semantic), &scores(keyword), &fusion_config)
+            .into_iter()
+            .map(|(id, score)| {
+                let attrs = attributes.get(id.as_str()).map(|a| (*a).clone()).unwrap_or_default();
+                (id, score, attrs)
+            })
+            .collect();
+    sort_candidates(&mut fused);
+    fused
+}
+
+/// POST /api/search/sweep: top results for several alphas from one set of upstream calls
+///
+/// admin only. the query is embedded and searched once; only fusion repeats per alpha.
+/// the ensemble and popularity boost are left out so each alpha is a plain two-signal mix.
+pub async fn sweep(
+    req: HttpRequest,
+    request: web::Json<SweepRequest>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if let Err(response) = crate::admin::require_admin(&req, &config) {
+        return Ok(response);
+    }
+    validate_sweep(&request).map_err(validation_error)?;
+    let _permit = acquire_search_permit(state.search_permits.as_deref())?;
+
+    let family_friendly = request
+        .family_friendly
+        .unwrap_or(config.default_family_friendly);
+    let detected_language = detect_language(&config, &request.query);
+    let semantic_text = semantic_text(&config, &request.query, detected_language.as_ref());
+    let logged_query = loggable_query(&request.query, config.log_queries);
+
+    let fusion_config = FusionConfig {
+        position_boost: config.bm25_position_boost,
+        coverage_weight: config.bm25_coverage_weight,
+        keyword_normalization: config.keyword_normalization,
+        similarity_curve: config.similarity_curve,
+        keyword_fields: config.keyword_fields.clone(),
+        exact_match_boost: config.exact_match_boost,
+        candidate_cap: config.candidate_cap,
+        dimension_tracker: Some(state.dimension_tracker.clone()),
+        long_query_threshold: config
+            .long_query_pooling
+            .then_some(config.long_query_threshold),
+        ..FusionConfig::default()
+    };
+    let keyword_text = without_short_terms(&request.query, config.bm25_min_term_length);
+
+    let query_texts = QueryText {
+        semantic: &semantic_text,
+        keyword: &keyword_text,
+        logged: &logged_query,
+        blend: None,
+        pseudo_relevance: false,
+        require_all_terms: false,
+        all_signals: true,
+        alternatives: &[],
+    };
+    let embedder = build_embedder(&config, &state);
+    let store = build_store(&config, &config.turbopuffer_namespace);
+    let mut hybrid = execute_hybrid_search(
+        &query_texts,
+        request.top_k,
+        &fusion_config,
+        &embedder,
+        &store,
+        &[],
+        &QueryOptions::default(),
+    )
+    .await
+    .map_err(|e| e.into_actix_error())?;
+    let blocked_ids = state.blocked_ids.current();
+    blocked_ids.remove_blocked(&mut hybrid.semantic);
+    blocked_ids.remove_blocked(&mut hybrid.keyword);
+
+    let pipeline = Pipeline::from_config(
+        &config.post_processors,
+        ContentFilter::new(family_friendly, None, None),
+        0.0,
+    );
+    let results = request
+        .alphas
+        .iter()
+        .map(|alpha| {
+            let fused = fuse_at_alpha(&hybrid.semantic, &hybrid.keyword, &fusion_config, *alpha);
+            let mut results = signal_view(&fused, &pipeline, request.top_k, None);
+            fill_thumbnails(&mut results, config.thumbnail_transform.as_ref());
+            (alpha.to_string(), results)
+        })
+        .collect();
+
+    logfire::info!(
+        "alpha sweep completed",
+        query = &logged_query,
+        alphas = request.alphas.len() as i64,
+        degraded = hybrid.degraded
+    );
+
+    Ok(HttpResponse::Ok().json(SweepResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::AttributeMerge;
+
+    fn result(id: &str, score: f32) -> BufoResult {
+        BufoResult {
+            id: id.to_string(),
+            url: String::new(),
+            name: format!("bufo-{}", id),
+            score,
+            thumbnail_url: None,
+            highlights: Vec::new(),
+            source: String::new(),
+            animated: None,
+            display_name: None,
+            width: None,
+            height: None,
+            aspect_ratio: None,
+            popularity: None,
+        }
+    }
+
+    fn candidates() -> Vec<BufoResult> {
+        vec![
+            result("a", 0.9),
+            result("b", 0.5),
+            result("c", 0.3),
+            result("d", 0.1),
+            result("e", 0.01),
+        ]
+    }
+
+    #[test]
+    fn test_sort_results() {
+        let ids = |results: &[BufoResult]| results.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+        let mut selected = candidates();
+        selected[0].name = "Zebra-bufo".to_string();
+        selected[1].name = "apple-bufo".to_string();
+        selected[2].name = "Mango-bufo".to_string();
+        let popularity = HashMap::from([
+            ("c".to_string(), 50.0),
+            ("d".to_string(), 80.0),
+            ("a".to_string(), 10.0),
+        ]);
+
+        let mut by_score = selected.clone();
+        sort_results(&mut by_score, SortOrder::Score, &popularity);
+        assert_eq!(ids(&by_score), vec!["a", "b", "c", "d", "e"]);
+
+        let mut by_name = selected.clone();
+        sort_results(&mut by_name, SortOrder::Name, &popularity);
+        assert_eq!(ids(&by_name), vec!["b", "d", "e", "c", "a"]);
+
+        // no popularity for b and e: last, in score order
+        let mut by_popularity = selected.clone();
+        sort_results(&mut by_popularity, SortOrder::Popularity, &popularity);
+        assert_eq!(ids(&by_popularity), vec!["d", "c", "a", "b", "e"]);
+
+        assert_eq!(
+            query(serde_json::json!({"query": "happy"})).sort,
+            SortOrder::Score
+        );
+        let get = web::Query::<SearchQuery>::from_query("query=happy&sort=popularity").unwrap();
+        assert_eq!(get.sort, SortOrder::Popularity);
+        assert!(web::Query::<SearchQuery>::from_query("query=happy&sort=date").is_err());
+    }
+
+    #[test]
+    fn test_select_results_without_relaxation() {
+        let (results, relaxed) = select_results(&candidates(), 10, 0.2, 0);
+        assert_eq!(results.len(), 3);
+        assert!(!relaxed);
+    }
+
+    #[test]
+    fn test_select_results_relaxes_to_min_results() {
+        // 0.4 keeps only a and b; halving to 0.2 adds c
+        let (results, relaxed) = select_results(&candidates(), 10, 0.4, 3);
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert!(relaxed);
+    }
+
+    #[test]
+    fn test_select_results_relaxation_after_filtering() {
+        let filtered: Vec<BufoResult> =
+            candidates().into_iter().filter(|r| r.id != "c").collect();
+        let (results, relaxed) = select_results(&filtered, 10, 0.4, 3);
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        // c is filtered, so relaxation continues until d (0.1) passes at 0.05
+        assert_eq!(ids, vec!["a", "b", "d"]);
+        assert!(relaxed);
+    }
+
+    #[test]
+    fn test_select_results_exhausts_candidates() {
+        let (results, relaxed) = select_results(&candidates(), 10, 0.95, 8);
+        assert_eq!(results.len(), 5);
+        assert!(relaxed);
+    }
+
+    #[test]
+    fn test_select_results_min_results_capped_at_top_k() {
+        let (results, relaxed) = select_results(&candidates(), 2, 0.4, 5);
+        assert_eq!(results.len(), 2);
+        assert!(!relaxed);
+    }
+
+    #[test]
+    fn test_ties_at_the_top_k_cut() {
+        let candidates = vec![
+            result("a", 0.9),
+            result("b", 0.5),
+            result("c", 0.5),
+            result("d", 0.5),
+            result("e", 0.2),
+        ];
+        let ids = |results: &[BufoResult]| -> Vec<String> {
+            results.iter().map(|r| r.id.clone()).collect()
+        };
+
+        // strict: exactly top_k, the tie broken by candidate order
+        let (mut results, _) = select_results(&candidates, 2, 0.0, 0);
+        assert_eq!(ids(&results), vec!["a", "b"]);
+
+        extend_ties(&mut results, &candidates);
+        assert_eq!(ids(&results), vec!["a", "b", "c", "d"]);
+
+        // a cut between different scores has no tie to extend
+        let (mut results, _) = select_results(&candidates, 4, 0.0, 0);
+        extend_ties(&mut results, &candidates);
+        assert_eq!(ids(&results), vec!["a", "b", "c", "d"]);
+
+        // ties below min_score aren't selected in the first place
+        let (mut results, _) = select_results(&candidates, 1, 0.5, 0);
+        extend_ties(&mut results, &candidates);
+        assert_eq!(ids(&results), vec!["a"]);
+    }
+
+    #[test]
+    fn test_cursor_pages_through_candidates() {
+        let page = |candidates: &[BufoResult], cursor: Option<&str>| {
+            let remaining: Vec<BufoResult> = match cursor.map(Cursor::decode) {
+                Some(cursor) => {
+                    let cursor = cursor.unwrap();
+                    candidates
+                        .iter()
+                        .filter(|r| cursor.precedes(r.score, &r.id))
+                        .cloned()
+                        .collect()
+                }
+                None => candidates.to_vec(),
+            };
+            let (results, _) = select_results(&remaining, 2, f32::NEG_INFINITY, 0);
+            let next = next_page_cursor(&results, &remaining, 2, false);
+            let ids: Vec<String> = results.into_iter().map(|r| r.id).collect();
+            (ids, next)
+        };
+
+        let (first, next) = page(&candidates(), None);
+        assert_eq!(first, vec!["a", "b"]);
+
+        // a bufo indexed between requests doesn't shift the next page
+        let mut grown = candidates();
+        grown.insert(0, result("new", 0.95));
+        let (second, next) = page(&grown, next.as_deref());
+        assert_eq!(second, vec!["c", "d"]);
+
+        let (third, next) = page(&grown, next.as_deref());
+        assert_eq!(third, vec!["e"]);
+        assert_eq!(next, None);
+
+        // a full last page has nothing below it either
+        let (_, next) = page(&candidates()[..2], None);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_since_validation() {
+        let check = |value| validate_query(&query(value), &[]).err().unwrap_or_default();
+        assert!(check(serde_json::json!({"query": "happy", "since": "2024-05-01"})).is_empty());
+        assert_eq!(
+            check(serde_json::json!({"query": "happy", "since": "last week"}))[0].field,
+            "since"
+        );
+    }
+
+    #[test]
+    fn test_cursor_validation() {
+        let check = |value| validate_query(&query(value), &[]).err().unwrap_or_default();
+        let cursor = Cursor::new(0.5, "a").encode();
+
+        assert!(check(serde_json::json!({"query": "happy", "cursor": &cursor})).is_empty());
+        assert_eq!(
+            check(serde_json::json!({"query": "happy", "cursor": "%%%"}))[0].field,
+            "cursor"
+        );
+        assert_eq!(
+            check(serde_json::json!({"query": "happy", "cursor": &cursor, "explore": 0.5}))[0].field,
+            "cursor"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_is_one_result_per_line() {
+        let mut results = candidates();
+        results[0].highlights = vec!["happy".to_string()];
+        results[1].name = "bufo \"quoted\"\nname".to_string();
+
+        let body = ndjson_body(&results).unwrap();
+        assert!(body.ends_with('\n'));
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), results.len());
+        for (line, result) in lines.iter().zip(&results) {
+            // f32 scores only compare equal through text; `to_value` widens them
+            let parsed: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed["name"], result.name.as_str());
+            assert_eq!(*line, serde_json::to_string(result).unwrap());
+        }
+        assert_eq!(ndjson_body(&[]).unwrap(), "");
+
+        let get = web::Query::<SearchQuery>::from_query("query=happy&format=ndjson").unwrap();
+        assert_eq!(get.format, ResponseFormat::Ndjson);
+        assert_eq!(
+            query(serde_json::json!({"query": "happy"})).format,
+            ResponseFormat::Json
+        );
+        assert!(web::Query::<SearchQuery>::from_query("query=happy&format=xml").is_err());
+    }
+
+    #[test]
+    fn test_apply_result_cap() {
+        let mut results = candidates();
+        assert!(!apply_result_cap(&mut results, None));
+        assert!(!apply_result_cap(&mut results, Some(5)));
+        assert_eq!(results.len(), 5);
+
+        assert!(apply_result_cap(&mut results, Some(2)));
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_acquire_search_permit() {
+        assert!(acquire_search_permit(None).unwrap().is_none());
+
+        let permits = Semaphore::new(1);
+        let held = acquire_search_permit(Some(&permits)).unwrap();
+        assert!(held.is_some());
+
+        let busy = acquire_search_permit(Some(&permits)).unwrap_err().error_response();
+        assert_eq!(busy.status(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(busy.headers().get("retry-after").unwrap(), "1");
+
+        drop(held);
+        assert!(acquire_search_permit(Some(&permits)).unwrap().is_some());
+    }
+
+    fn fused(id: &str, score: f32) -> FusedCandidate {
+        (id.to_string(), score, HashMap::new())
+    }
+
+    #[test]
+    fn test_merge_namespace_results() {
+        let merged = merge_namespace_results(vec![
+            vec![fused("a", 0.8), fused("b", 0.4)],
+            vec![fused("b", 0.6), fused("c", 0.2)],
+        ]);
+
+        let ids: Vec<&str> = merged.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        // b keeps its best score (0.6) and everything is scaled by the max (0.8)
+        assert!((merged[0].1 - 1.0).abs() < 1e-6);
+        assert!((merged[1].1 - 0.75).abs() < 1e-6);
+        assert!((merged[2].1 - 0.25).abs() < 1e-6);
+    }
+
+    fn hybrid(candidates: Vec<FusedCandidate>) -> HybridResults {
+        HybridResults {
+            semantic: candidates.clone(),
+            keyword: candidates.clone(),
+            candidates,
+            degraded: false,
+            timings: StageTimings::default(),
+            query_embedding: None,
+        }
+    }
+
+    #[test]
+    fn test_fallback_results_keep_their_source() {
+        let mut primary = hybrid(vec![fused("a", 0.9)]);
+        let mut fallback = hybrid(vec![fused("b", 0.6), fused("a", 0.3)]);
+        tag_source(&mut primary, "bufos-curated");
+        tag_source(&mut fallback, "bufos");
+
+        let merged = merge_hybrid_results(vec![primary, fallback]);
+        let results: Vec<BufoResult> = merged
+            .candidates
+            .iter()
+            .map(|(id, score, attrs)| BufoResult::from_attributes(id.clone(), *score, attrs))
+            .collect();
+        let sources: Vec<(&str, &str)> = results
+            .iter()
+            .map(|r| (r.id.as_str(), r.source.as_str()))
+            .collect();
+        assert_eq!(sources, vec![("a", "bufos-curated"), ("b", "bufos")]);
+    }
+
+    #[test]
+    fn test_qualifying_count() {
+        let pipeline = Pipeline::from_config(
+            &[],
+            ContentFilter::try_new(false, Some("sad"), None, true).unwrap(),
+            0.0,
+        );
+        let mut candidates = vec![fused("a", 0.9), fused("b", 0.5), fused("c", 0.1)];
+        candidates[1]
+            .2
+            .insert("name".to_string(), "bufo-sad".to_string());
+
+        assert_eq!(qualifying_count(&candidates, &pipeline, 0.0), 2);
+        assert_eq!(qualifying_count(&candidates, &pipeline, 0.2), 1);
+    }
+
+    struct StubEmbedder;
+
+    impl Embedder for StubEmbedder {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(vec![1.0, 0.0])
+        }
+
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    /// a store whose vector or keyword search can be made to fail
+    #[derive(Default)]
+    struct StubStore {
+        vector_fails: bool,
+        keyword_fails: bool,
+        /// every keyword query received, in order
+        keyword_queries: std::sync::Mutex<Vec<String>>,
+        vector_searches: std::sync::atomic::AtomicUsize,
+    }
+
+    fn row(id: &str, score: f32) -> crate::providers::SearchResult {
+        crate::providers::SearchResult {
+            id: id.to_string(),
+            score,
+            attributes: HashMap::from([("name".to_string(), format!("bufo-{}", id))]),
+        }
+    }
+
+    fn named_row(id: &str, score: f32, name: &str) -> crate::providers::SearchResult {
+        crate::providers::SearchResult {
+            id: id.to_string(),
+            score,
+            attributes: HashMap::from([("name".to_string(), name.to_string())]),
+        }
+    }
+
+    fn unavailable() -> VectorSearchError {
+        VectorSearchError::Api {
+            status: 503,
+            body: "unavailable".to_string(),
+        }
+    }
+
+    impl VectorStore for StubStore {
+        async fn search_by_vector(
+            &self,
+            embedding: &[f32],
+            _top_k: usize,
+            _options: &QueryOptions,
+        ) -> Result<Vec<crate::providers::SearchResult>, VectorSearchError> {
+            self.vector_searches
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if self.vector_fails {
+                return Err(unavailable());
+            }
+            // `WordEmbedder`'s "excited" has its own neighbours, overlapping on b
+            if embedding.first() == Some(&2.0) {
+                return Ok(vec![row("b", 0.1), row("z", 0.3)]);
+            }
+            Ok(vec![row("a", 0.2), row("b", 0.6)])
+        }
+
+        async fn search_by_keyword(
+            &self,
+            query: &str,
+            _top_k: usize,
+            _options: &QueryOptions,
+        ) -> Result<Vec<crate::providers::SearchResult>, VectorSearchError> {
+            self.keyword_queries.lock().unwrap().push(query.to_string());
+            if self.keyword_fails {
+                return Err(unavailable());
+            }
+            // b's keyword row has what its vector row lacks
+            if query == "complementary" {
+                let attributes = HashMap::from([
+                    ("url".to_string(), "https://cdn.example/b.png".to_string()),
+                    ("tags".to_string(), "joy".to_string()),
+                ]);
+                return Ok(vec![crate::providers::SearchResult {
+                    id: "b".to_string(),
+                    score: 1.0,
+                    attributes,
+                }]);
+            }
+            if query == "happy dance" {
+                return Ok(vec![
+                    named_row("e", 5.0, "bufo-happy-dance"),
+                    named_row("f", 4.0, "bufo-happy"),
+                    named_row("g", 3.0, "bufo-dance-party"),
+                    named_row("h", 2.0, "happy-bufo-dance"),
+                ]);
+            }
+            if query != "happy" {
+                return Ok(vec![row("d", 3.0)]);
+            }
+            Ok(vec![row("b", 4.0), row("c", 2.0)])
+        }
+
+        async fn search_by_keyword_field(
+            &self,
+            field: &str,
+            query: &str,
+            top_k: usize,
+            options: &QueryOptions,
+        ) -> Result<Vec<crate::providers::SearchResult>, VectorSearchError> {
+            match field {
+                "name" => self.search_by_keyword(query, top_k, options).await,
+                "tags" => Ok(vec![row("c", 5.0), row("d", 2.5)]),
+                _ => Ok(vec![]),
+            }
+        }
+
+        async fn get_by_id(
+            &self,
+            _id: &str,
+        ) -> Result<Option<crate::providers::SearchResult>, VectorSearchError> {
+            Ok(None)
+        }
+
+        async fn list_all(
+            &self,
+            _limit: usize,
+        ) -> Result<Vec<crate::providers::SearchResult>, VectorSearchError> {
+            Ok(vec![])
+        }
+
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    async fn search_stub(store: &StubStore) -> Result<HybridResults, SearchError> {
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        execute_hybrid_search(
+            &text,
+            10,
+            &FusionConfig::new(0.7),
+            &StubEmbedder,
+            store,
+            &[],
+            &QueryOptions::default(),
+        )
+        .await
+    }
+
+    fn candidate_ids(results: &HybridResults) -> Vec<&str> {
+        let mut ids: Vec<&str> = results.candidates.iter().map(|c| c.0.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_uses_both_sides() {
+        let store = StubStore {
+            vector_fails: false,
+            keyword_fails: false,
+            ..Default::default()
+        };
+        let results = search_stub(&store).await.unwrap();
+        assert!(!results.degraded);
+        assert_eq!(candidate_ids(&results), vec!["a", "b", "c"]);
+
+        // single-signal rankings come from the same fetch
+        let ids = |list: &[FusedCandidate]| list.iter().map(|c| c.0.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&results.semantic), vec!["a", "b"]);
+        assert_eq!(ids(&results.keyword), vec!["b", "c"]);
+        assert!((results.keyword[0].1 - 1.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_query_embedding_is_kept_for_cluster_labels() {
+        let store = StubStore::default();
+        let results = search_stub(&store).await.unwrap();
+        assert_eq!(results.query_embedding, Some(vec![1.0, 0.0]));
+
+        let clusterer = crate::cluster::Clusterer::parse(
+            r#"{ "clusters": [
+                { "label": "animals", "centroid": [0.0, 1.0] },
+                { "label": "reactions", "centroid": [0.9, 0.1] }
+            ] }"#,
+        )
+        .unwrap();
+        let embedding = results.query_embedding.as_deref().unwrap();
+        assert_eq!(clusterer.label(embedding), Some("reactions"));
+
+        // merged namespaces keep the first embedding any of them made
+        let merged = merge_hybrid_results(vec![hybrid(Vec::new()), results]);
+        assert_eq!(merged.query_embedding, Some(vec![1.0, 0.0]));
+    }
+
+    /// a `StubEmbedder` that counts its calls
+    #[derive(Default)]
+    struct CountingEmbedder {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Embedder for CountingEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            StubEmbedder.embed(text).await
+        }
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    /// embeds "excited" apart from everything else
+    struct WordEmbedder;
+
+    impl Embedder for WordEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(if text == "excited" {
+                vec![2.0, 0.0]
+            } else {
+                vec![1.0, 0.0]
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "word"
+        }
+    }
+
+    /// returns one more dimension on every call, as if the provider changed models
+    #[derive(Default)]
+    struct DriftingEmbedder {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Embedder for DriftingEmbedder {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            let calls = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(vec![1.0; 2 + calls])
+        }
+
+        fn name(&self) -> &'static str {
+            "drifting"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dimension_drift_is_tracked_across_searches() {
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let tracker = crate::dimension::DimensionTracker::default();
+        let mut fusion_config = FusionConfig::new(0.7);
+        fusion_config.dimension_tracker = Some(tracker.clone());
+        let embedder = DriftingEmbedder::default();
+        let store = StubStore::default();
+        let options = QueryOptions::default();
+
+        let search = || {
+            execute_hybrid_search(
+                &text,
+                10,
+                &fusion_config,
+                &embedder,
+                &store,
+                &[],
+                &options,
+            )
+        };
+        search().await.unwrap();
+        assert_eq!(tracker.last(), Some(2));
+        // the second search sees a different size; the search itself still runs
+        search().await.unwrap();
+        assert_eq!(tracker.last(), Some(3));
+        assert_eq!(tracker.observe(3), None);
+    }
+
+    #[test]
+    fn test_candidate_pool_is_capped() {
+        // under the cap: the full over-fetch
+        assert_eq!(candidate_pool_size(10, 200), (50, false));
+        assert_eq!(candidate_pool_size(40, 200), (200, false));
+        // over it: the cap
+        assert_eq!(candidate_pool_size(100, 200), (200, true));
+        // never fewer than top_k
+        assert_eq!(candidate_pool_size(100, 20), (100, true));
+    }
+
+    #[test]
+    fn test_merge_nearest_keeps_each_ids_smallest_distance() {
+        let merged = merge_nearest(vec![
+            vec![row("a", 0.2), row("b", 0.6)],
+            vec![row("b", 0.1), row("z", 0.3)],
+            vec![row("a", 0.5)],
+        ]);
+        let rows: Vec<(&str, f32)> = merged.iter().map(|r| (r.id.as_str(), r.score)).collect();
+        assert_eq!(rows, vec![("b", 0.1), ("a", 0.2), ("z", 0.3)]);
+
+        // a single list is untouched, even out of order
+        let single = merge_nearest(vec![vec![row("b", 0.6), row("a", 0.2)]]);
+        assert_eq!(single[0].id, "b");
+    }
+
+    #[tokio::test]
+    async fn test_alternatives_union_semantic_neighbours() {
+        let store = StubStore::default();
+        let alternatives = vec!["excited".to_string()];
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &alternatives,
+        };
+        let results = execute_hybrid_search(
+            &text,
+            10,
+            &FusionConfig::new(1.0),
+            &WordEmbedder,
+            &store,
+            &[],
+            &QueryOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            store
+                .vector_searches
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+        // b is nearer to "excited" than to "happy", so it takes that similarity
+        let ids: Vec<&str> = results.semantic.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "z"]);
+        assert!((results.semantic[0].1 - 0.95).abs() < 1e-6);
+    }
+
+    async fn search_at_alpha(
+        alpha: f32,
+        all_signals: bool,
+        store: &StubStore,
+        embedder: &CountingEmbedder,
+    ) -> HybridResults {
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: true,
+            require_all_terms: false,
+            all_signals,
+            alternatives: &[],
+        };
+        execute_hybrid_search(
+            &text,
+            10,
+            &FusionConfig::new(alpha),
+            embedder,
+            store,
+            &[],
+            &QueryOptions::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pure_semantic_skips_the_keyword_search() {
+        use std::sync::atomic::Ordering;
+        let (store, embedder) = (StubStore::default(), CountingEmbedder::default());
+        let results = search_at_alpha(1.0, false, &store, &embedder).await;
+
+        // pseudo-relevance feedback would be a second keyword call; it's skipped too
+        assert!(store.keyword_queries.lock().unwrap().is_empty());
+        assert_eq!(store.vector_searches.load(Ordering::SeqCst), 1);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 1);
+        assert!(!results.degraded);
+        assert_eq!(candidate_ids(&results), vec!["a", "b"]);
+        assert!(results.keyword.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pure_keyword_skips_the_embedding_and_vector_search() {
+        use std::sync::atomic::Ordering;
+        let (store, embedder) = (StubStore::default(), CountingEmbedder::default());
+        let results = search_at_alpha(0.0, false, &store, &embedder).await;
+
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 0);
+        assert_eq!(store.vector_searches.load(Ordering::SeqCst), 0);
+        assert_eq!(*store.keyword_queries.lock().unwrap(), vec!["happy"]);
+        assert!(!results.degraded);
+        assert_eq!(candidate_ids(&results), vec!["b", "c"]);
+        // keyword-only candidates still carry their attributes
+        assert!(results.candidates.iter().all(|c| c.2.contains_key("name")));
+    }
+
+    #[tokio::test]
+    async fn test_views_fetch_both_signals_at_the_extremes() {
+        use std::sync::atomic::Ordering;
+        let (store, embedder) = (StubStore::default(), CountingEmbedder::default());
+        let results = search_at_alpha(0.0, true, &store, &embedder).await;
+
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(store.vector_searches.load(Ordering::SeqCst), 1);
+        assert_eq!(results.semantic.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_attributes_merge_across_vector_and_keyword_rows() {
+        let store = StubStore::default();
+        let text = QueryText {
+            semantic: "complementary",
+            keyword: "complementary",
+            logged: "complementary",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let (text, store) = (&text, &store);
+        let b_attributes = |attribute_merge| async move {
+            let fusion_config = FusionConfig {
+                attribute_merge,
+                ..FusionConfig::new(0.7)
+            };
+            let results = execute_hybrid_search(
+                text,
+                10,
+                &fusion_config,
+                &StubEmbedder,
+                store,
+                &[],
+                &QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+            let b = results.candidates.into_iter().find(|c| c.0 == "b").unwrap();
+            let mut keys: Vec<String> = b.2.into_keys().collect();
+            keys.sort();
+            keys
+        };
+
+        // the vector row's name survives alongside the keyword row's url and tags
+        assert_eq!(
+            b_attributes(AttributeMerge::Fields).await,
+            vec!["name", "tags", "url"]
+        );
+        // the keyword row has more, so it replaces the vector row's attributes
+        assert_eq!(
+            b_attributes(AttributeMerge::Richer).await,
+            vec!["tags", "url"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyword_fields_are_weighted() {
+        let store = StubStore::default();
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let mut fusion_config = FusionConfig::new(0.7);
+        fusion_config.keyword_fields = "name:3,tags:1".parse().unwrap();
+
+        let results = execute_hybrid_search(
+            &text,
+            10,
+            &fusion_config,
+            &StubEmbedder,
+            &store,
+            &[],
+            &QueryOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        // name: b 1.0, c 0.5; tags: c 1.0, d 0.5; weighted 3:1
+        let keyword: HashMap<&str, f32> =
+            results.keyword.iter().map(|c| (c.0.as_str(), c.1)).collect();
+        assert!((keyword["b"] - 0.75).abs() < 1e-6);
+        assert!((keyword["c"] - (0.75 * 0.5 + 0.25)).abs() < 1e-6);
+        assert!((keyword["d"] - 0.125).abs() < 1e-6);
+        assert_eq!(results.keyword[0].0, "b");
+        // tags-only matches still carry attributes
+        assert_eq!(results.keyword[2].2.get("name").map(String::as_str), Some("bufo-d"));
+    }
+
+    #[tokio::test]
+    async fn test_exact_name_match_is_lifted_to_the_top() {
+        let store = StubStore::default();
+        // semantic: a 0.9, b 0.7; keyword: only bufo-d, the exact match
+        let text = QueryText {
+            semantic: "bufo d",
+            keyword: "bufo d",
+            logged: "bufo d",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let search = |fusion_config: FusionConfig| {
+            let text = &text;
+            let store = &store;
+            async move {
+                execute_hybrid_search(
+                    text,
+                    10,
+                    &fusion_config,
+                    &StubEmbedder,
+                    store,
+                    &[],
+                    &QueryOptions::default(),
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        let fused = search(FusionConfig::new(0.7)).await;
+        assert_eq!(fused.candidates.last().unwrap().0, "d");
+
+        let mut fusion_config = FusionConfig::new(0.7);
+        fusion_config.exact_match_boost = 1.0;
+        let boosted = search(fusion_config).await;
+        assert_eq!(boosted.candidates[0].0, "d");
+        assert!((boosted.candidates[0].1 - 1.3).abs() < 1e-6);
+        // the single-signal views are untouched
+        assert_eq!(boosted.semantic[0].0, "a");
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_degrades_when_keyword_fails() {
+        let store = StubStore {
+            vector_fails: false,
+            keyword_fails: true,
+            ..Default::default()
+        };
+        let results = search_stub(&store).await.unwrap();
+        assert!(results.degraded);
+        assert_eq!(candidate_ids(&results), vec!["a", "b"]);
+        // attributes still come through from the vector side
+        assert_eq!(results.candidates[0].2.get("name").map(String::as_str), Some("bufo-a"));
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_degrades_when_vector_fails() {
+        let store = StubStore {
+            vector_fails: true,
+            keyword_fails: false,
+            ..Default::default()
+        };
+        let results = search_stub(&store).await.unwrap();
+        assert!(results.degraded);
+        assert_eq!(candidate_ids(&results), vec!["b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_fails_when_both_sides_fail() {
+        let store = StubStore {
+            vector_fails: true,
+            keyword_fails: true,
+            ..Default::default()
+        };
+        assert!(search_stub(&store).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_ensemble_member_drops_its_signal() {
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: false,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let member = |vector_fails| EnsembleMember {
+            embedder: StubEmbedder,
+            vector_store: StubStore {
+                vector_fails,
+                ..Default::default()
+            },
+            weight: 0.3,
+        };
+        let search = |ensemble| async {
+            let store = StubStore::default();
+            let ensemble: Vec<_> = ensemble;
+            execute_hybrid_search(
+                &text,
+                10,
+                &FusionConfig::new(0.7),
+                &StubEmbedder,
+                &store,
+                &ensemble,
+                &QueryOptions::default(),
+            )
+            .await
+        };
+
+        let healthy = search(vec![member(false), member(false)]).await.unwrap();
+        assert!(!healthy.degraded);
+
+        // the search still succeeds, ranked as if the failed member weren't configured
+        let alone = search(vec![member(false)]).await.unwrap();
+        let with_failure = search(vec![member(false), member(true)]).await.unwrap();
+        assert!(with_failure.degraded);
+        let scores = |results: &HybridResults| {
+            let mut scores: Vec<(String, f32)> = results
+                .candidates
+                .iter()
+                .map(|c| (c.0.clone(), c.1))
+                .collect();
+            scores.sort_by(|a, b| a.0.cmp(&b.0));
+            scores
+        };
+        assert_eq!(scores(&with_failure), scores(&alone));
+
+        let all_failed = search(vec![member(true)]).await.unwrap();
+        assert!(all_failed.degraded);
+        assert_eq!(
+            scores(&all_failed),
+            scores(&search(Vec::new()).await.unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pseudo_relevance_expands_keyword_query() {
+        let store = StubStore::default();
+        let text = QueryText {
+            semantic: "happy",
+            keyword: "happy",
+            logged: "happy",
+            blend: None,
+            pseudo_relevance: true,
+            require_all_terms: false,
+            all_signals: false,
+            alternatives: &[],
+        };
+        let results = execute_hybrid_search(
+            &text,
+            10,
+            &FusionConfig::new(0.7),
+            &StubEmbedder,
+            &store,
+            &[],
+            &QueryOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        // "bufo-a" and "bufo-b" contribute "b" ("a" is a stopword)
+        assert_eq!(*store.keyword_queries.lock().unwrap(), vec!["happy", "happy b"]);
+        let keyword_ids: Vec<&str> = results.keyword.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(keyword_ids, vec!["d"]);
+        assert_eq!(candidate_ids(&results), vec!["a", "b", "d"]);
+    }
+
+    #[tokio::test]
+    async fn test_require_all_terms_filters_keyword_results() {
+        let store = StubStore::default();
+        let search = |require_all_terms| {
+            let text = QueryText {
+                semantic: "happy dance",
+                keyword: "happy dance",
+                logged: "happy dance",
+                blend: None,
+                pseudo_relevance: false,
+                require_all_terms,
+                all_signals: false,
+                alternatives: &[],
+            };
+            let store = &store;
+            async move {
+                execute_hybrid_search(
+                    &text,
+                    10,
+                    &FusionConfig::new(0.7),
+                    &StubEmbedder,
+                    store,
+                    &[],
+                    &QueryOptions::default(),
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let keyword_ids = |results: &HybridResults| {
+            let mut ids: Vec<String> = results.keyword.iter().map(|c| c.0.clone()).collect();
+            ids.sort();
+            ids
+        };
+
+        // terms apart ("bufo-happy", "bufo-dance-party") match with OR semantics only
+        assert_eq!(keyword_ids(&search(false).await), vec!["e", "f", "g", "h"]);
+        let strict = search(true).await;
+        assert_eq!(keyword_ids(&strict), vec!["e", "h"]);
+        // normalized against the best surviving match
+        assert!((strict.keyword[0].1 - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_namespaces_accepts_list_or_comma_string() {
+        let post = query(serde_json::json!({"query": "happy", "namespaces": ["a", "b"]}));
+        assert_eq!(post.namespaces, Some(vec!["a".to_string(), "b".to_string()]));
+
+        let get = web::Query::<SearchQuery>::from_query("query=happy&namespaces=a,%20b")
+            .unwrap()
+            .into_inner();
+        assert_eq!(get.namespaces, Some(vec!["a".to_string(), "b".to_string()]));
+
+        assert_eq!(query(serde_json::json!({"query": "happy"})).namespaces, None);
+    }
+
+    fn query(value: serde_json::Value) -> SearchQuery {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_query_alpha_bounds() {
+        assert!(validate_query(&query(serde_json::json!({"query": "happy"})), &[]).is_ok());
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "alpha": 0.0})), &[]).is_ok());
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "alpha": 1.0})), &[]).is_ok());
+        // out-of-range values are clamped by the search, not rejected
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "alpha": 1.5})), &[]).is_ok());
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "alpha": -0.1})), &[]).is_ok());
+
+        let mut nan = query(serde_json::json!({"query": "happy"}));
+        nan.alpha = Some(f32::NAN);
+        assert!(validate_query(&nan, &[]).is_err());
+    }
+
+    #[test]
+    fn test_effective_alpha_clamps_with_a_warning() {
+        for (requested, effective, warned) in [
+            (-0.5, 0.0, true),
+            (0.0, 0.0, false),
+            (0.7, 0.7, false),
+            (1.0, 1.0, false),
+            (2.0, 1.0, true),
+        ] {
+            let (alpha, warning) = effective_alpha(requested);
+            assert_eq!(alpha, effective, "alpha {}", requested);
+            assert_eq!(warning.is_some(), warned, "alpha {}", requested);
+            if let Some(FieldError { field, message }) = warning {
+                assert_eq!(field, "alpha");
+                assert!(message.contains(&requested.to_string()), "{}", message);
+                assert!(message.contains(&effective.to_string()), "{}", message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_query_rejects_invalid_patterns() {
+        let errors = validate_query(&query(serde_json::json!({"query": "happy", "exclude": "(oops"})), &[])
+            .unwrap_err();
+        assert_eq!(errors[0].field, "exclude");
+        assert!(errors[0].message.contains("(oops"));
+    }
+
+    #[test]
+    fn test_too_many_patterns_are_rejected() {
+        let patterns = |n: usize| {
+            (0..n)
+                .map(|i| format!("p{}", i))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let at_limit = query(serde_json::json!({"query": "happy", "exclude": patterns(3)}));
+        assert!(check_pattern_counts(&at_limit, 3).is_empty());
+
+        // empty entries don't count
+        let padded =
+            query(serde_json::json!({"query": "happy", "include": format!("{},,", patterns(3))}));
+        assert!(check_pattern_counts(&padded, 3).is_empty());
+
+        let over = query(serde_json::json!({
+            "query": "happy",
+            "exclude": patterns(4),
+            "include": patterns(5),
+        }));
+        let errors = check_pattern_counts(&over, 3);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "exclude");
+        assert!(errors[0].message.contains("at most 3 patterns, got 4"));
+        assert_eq!(errors[1].field, "include");
+    }
+
+    #[test]
+    fn test_model_must_be_allowlisted_and_routes_to_its_namespace() {
+        let routes: ModelRoutes = "voyage-multimodal-3.5=bufos-mm35".parse().unwrap();
+
+        let chosen = query(serde_json::json!({"query": "happy", "model": "voyage-multimodal-3.5"}));
+        assert_eq!(check_model(&chosen, &routes), None);
+        assert_eq!(primary_namespace(&chosen, &routes, "bufos"), "bufos-mm35");
+
+        let unknown =
+            query(serde_json::json!({"query": "happy", "model": "text-embedding-3-small"}));
+        let error = check_model(&unknown, &routes).unwrap();
+        assert_eq!(error.field, "model");
+        assert!(
+            error.message.contains("allowed: voyage-multimodal-3.5"),
+            "{}",
+            error.message
+        );
+        assert!(check_model(&chosen, &ModelRoutes::default()).is_some());
+
+        // a model already decides the namespace
+        let both = query(serde_json::json!({
+            "query": "happy",
+            "model": "voyage-multimodal-3.5",
+            "namespaces": ["bufos"],
+        }));
+        assert!(check_model(&both, &routes).is_some());
+
+        let plain = query(serde_json::json!({"query": "happy"}));
+        assert_eq!(check_model(&plain, &routes), None);
+        assert_eq!(primary_namespace(&plain, &routes, "bufos"), "bufos");
+        let requested = query(serde_json::json!({"query": "happy", "namespaces": ["bufos-memes"]}));
+        assert_eq!(
+            primary_namespace(&requested, &routes, "bufos"),
+            "bufos-memes"
+        );
+    }
+
+    #[test]
+    fn test_validate_query_rejects_oversized_patterns() {
+        let errors = validate_query(
+            &query(serde_json::json!({"query": "happy", "exclude": "(\\w{1000}){1000}"})),
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].field, "exclude");
+        assert!(
+            errors[0].message.starts_with("oversized"),
+            "{}",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn test_validate_query_collects_field_errors() {
+        let long = "a".repeat(MAX_QUERY_LENGTH + 1);
+        let errors = validate_query(&query(serde_json::json!({
+            "query": long,
+            "alpha": 2.0,
+            "top_k": 0,
+            "include": "[bad",
+            "filters": ["tags", "Bogus", "x"],
+        })), &[])
+        .unwrap_err();
+
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        // alpha 2.0 is clamped with a warning rather than an error
+        assert_eq!(fields, vec!["query", "top_k", "include", "filters"]);
+    }
+
+    #[test]
+    fn test_validate_query_top_k_bounds() {
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "top_k": 100})), &[]).is_ok());
+        assert!(validate_query(&query(serde_json::json!({"query": "happy", "top_k": 101})), &[]).is_err());
+        assert!(validate_query(&query(serde_json::json!({"query": "   "})), &[]).is_err());
+    }
+
+    #[test]
+    fn test_validate_query_boost_popularity_bounds() {
+        for ok in [0.0, 0.5, MAX_BOOST_POPULARITY] {
+            let query = query(serde_json::json!({"query": "happy", "boost_popularity": ok}));
+            assert!(validate_query(&query, &[]).is_ok(), "boost_popularity {}", ok);
+        }
+        for bad in [-0.1, MAX_BOOST_POPULARITY + 0.5, f32::INFINITY] {
+            let mut query = query(serde_json::json!({"query": "happy"}));
+            query.boost_popularity = Some(bad);
+            let errors = validate_query(&query, &[]).unwrap_err();
+            assert_eq!(errors[0].field, "boost_popularity", "boost_popularity {}", bad);
+        }
+        let mut query = query(serde_json::json!({"query": "happy"}));
+        query.boost_popularity = Some(f32::NAN);
+        assert!(validate_query(&query, &[]).is_err());
+    }
+
+    #[test]
+    fn test_validate_query_namespace_allowlist() {
+        let allowed = vec!["bufos".to_string(), "bufos-memes".to_string()];
+        let ok = query(serde_json::json!({"query": "happy", "namespaces": ["bufos", "bufos-memes"]}));
+        assert!(validate_query(&ok, &allowed).is_ok());
+
+        let unknown = query(serde_json::json!({"query": "happy", "namespaces": ["bufos", "secret"]}));
+        let errors = validate_query(&unknown, &allowed).unwrap_err();
+        assert_eq!(errors[0].field, "namespaces");
+        assert!(errors[0].message.contains("secret"));
+
+        let empty = query(serde_json::json!({"query": "happy", "namespaces": []}));
+        assert!(validate_query(&empty, &allowed).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_validate_endpoint() {
+        use actix_web::{test, App};
+
+        let config = Config::for_tests();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::new(&config).unwrap()))
+                .route("/validate", web::post().to(validate)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(serde_json::json!({"query": "happy"}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["valid"], true);
+
+        let req = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(serde_json::json!({"query": "happy", "alpha": -1.0}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        // an out-of-range alpha is clamped, so it only warns
+        assert_eq!(body["valid"], true);
+        assert_eq!(body["warnings"][0]["field"], "alpha");
+
+        let req = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(serde_json::json!({"query": " "}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["errors"][0]["field"], "query");
+    }
+
+    #[test]
+    fn test_loggable_query() {
+        assert_eq!(loggable_query("happy bufo", true), "happy bufo");
+
+        let redacted = loggable_query("happy bufo", false);
+        assert!(redacted.starts_with("redacted:"));
+        assert!(!redacted.contains("happy"));
+        assert_eq!(redacted, loggable_query("happy bufo", false));
+        assert_ne!(redacted, loggable_query("sad bufo", false));
+    }
+
+    #[test]
+    fn test_query_term_count() {
+        assert_eq!(query_term_count("bufo-jumping on  bed"), 4);
+        assert_eq!(query_term_count("   "), 0);
+    }
+
+    #[test]
+    fn test_views_parse_and_validate() {
+        let get = web::Query::<SearchQuery>::from_query("query=happy&views=semantic,fused")
+            .unwrap()
+            .into_inner();
+        assert_eq!(get.views, Some(vec!["semantic".to_string(), "fused".to_string()]));
+        assert!(validate_query(&get, &[]).is_ok());
+
+        let bad = query(serde_json::json!({"query": "happy", "views": ["keyword", "bm25"]}));
+        let errors = validate_query(&bad, &[]).unwrap_err();
+        assert_eq!(errors[0].field, "views");
+        assert!(errors[0].message.contains("bm25"));
+    }
+
+    /// the reason `perform_search` gives for `candidates` fused, filtered and selected
+    fn empty_reason(candidates: Vec<BufoResult>, min_score: f32) -> Option<NoResultsReason> {
+        let pipeline = Pipeline::from_config(&[], ContentFilter::new(true, None, None), 0.0);
+        let fused = candidates.len();
+        let filtered = pipeline.run(candidates);
+        let (results, _) = select_results(&filtered, 10, min_score, 0);
+        no_results_reason(fused, filtered.len(), filtered.len(), results.len())
+    }
+
+    #[test]
+    fn test_no_results_reason_without_upstream_hits() {
+        assert_eq!(
+            empty_reason(vec![], 0.0),
+            Some(NoResultsReason::NoCandidates)
+        );
+    }
+
+    #[test]
+    fn test_no_results_reason_when_all_blocklisted() {
+        let juicy = BufoResult {
+            name: "bufo-juicy".to_string(),
+            ..result("j", 0.9)
+        };
+        assert_eq!(
+            empty_reason(vec![juicy], 0.0),
+            Some(NoResultsReason::Filtered)
+        );
+    }
+
+    #[test]
+    fn test_no_results_reason_when_all_below_min_score() {
+        assert_eq!(
+            empty_reason(candidates(), 0.95),
+            Some(NoResultsReason::BelowMinScore)
+        );
+        assert_eq!(empty_reason(candidates(), 0.2), None);
+        // a cursor past the last candidate
+        assert_eq!(
+            no_results_reason(5, 5, 0, 0),
+            Some(NoResultsReason::EndOfResults)
+        );
+    }
+
+    #[test]
+    fn test_signal_view_filters_and_cuts() {
+        let ranked = vec![
+            ("1".to_string(), 0.9, HashMap::from([("name".to_string(), "bufo-juicy".to_string())])),
+            ("2".to_string(), 0.8, HashMap::from([("name".to_string(), "bufo-happy".to_string())])),
+            ("3".to_string(), 0.7, HashMap::from([("name".to_string(), "bufo-sad".to_string())])),
+            ("4".to_string(), 0.6, HashMap::from([("name".to_string(), "bufo-calm".to_string())])),
+        ];
+        let pipeline = Pipeline::from_config(&[], ContentFilter::new(true, None, None), 0.0);
+
+        let ids = |results: Vec<BufoResult>| results.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids(signal_view(&ranked, &pipeline, 2, None)), vec!["2", "3"]);
+        assert_eq!(ids(signal_view(&ranked, &pipeline, 10, Some(1))), vec!["2"]);
+    }
+
+    #[test]
+    fn test_fuse_at_alpha() {
+        let with_name = |id: &str, score: f32| {
+            (id.to_string(), score, HashMap::from([("name".to_string(), format!("bufo-{}", id))]))
+        };
+        let semantic = vec![with_name("a", 1.0), with_name("b", 0.5)];
+        let keyword = vec![with_name("b", 1.0), with_name("c", 0.8)];
+
+        let config = FusionConfig::default();
+        let ids = |fused: Vec<FusedCandidate>| fused.into_iter().map(|c| c.0).collect::<Vec<_>>();
+        assert_eq!(ids(fuse_at_alpha(&semantic, &keyword, &config, 1.0)), vec!["a", "b"]);
+        assert_eq!(ids(fuse_at_alpha(&semantic, &keyword, &config, 0.0)), vec!["b", "c"]);
+        assert_eq!(ids(fuse_at_alpha(&semantic, &keyword, &config, 0.5)), vec!["b", "a", "c"]);
+
+        // attributes survive for keyword-only matches
+        let fused = fuse_at_alpha(&semantic, &keyword, &config, 0.0);
+        assert_eq!(fused[1].2["name"], "bufo-c");
+
+        // the configured fusion settings apply at every alpha
+        let strict = FusionConfig {
+            min_score: 0.6,
+            ..FusionConfig::default()
+        };
+        assert_eq!(ids(fuse_at_alpha(&semantic, &keyword, &strict, 0.5)), vec!["b"]);
+    }
+
+    #[test]
+    fn test_validate_sweep() {
+        let sweep = |value: serde_json::Value| serde_json::from_value::<SweepRequest>(value).unwrap();
+        assert!(validate_sweep(&sweep(serde_json::json!({"query": "happy", "alphas": [0.0, 0.5, 1.0]}))).is_ok());
+        assert!(validate_sweep(&sweep(serde_json::json!({"query": "happy", "alphas": []}))).is_err());
+        assert!(validate_sweep(&sweep(serde_json::json!({"query": "happy", "alphas": [0.5, 1.5]}))).is_err());
+
+        let too_many = vec![0.5; MAX_SWEEP_ALPHAS + 1];
+        let errors = validate_sweep(&sweep(serde_json::json!({"query": "happy", "alphas": too_many}))).unwrap_err();
+        assert_eq!(errors[0].field, "alphas");
+    }
+
+    #[test]
+    fn test_nocache_sets_no_store() {
+        let cached = web::Query::<SearchQuery>::from_query("query=happy").unwrap();
+        assert!(!cached.nocache);
+        assert_eq!(search_cache_control(cached.nocache, false, 300), "public, max-age=300");
+        assert_eq!(search_cache_control(cached.nocache, false, 60), "public, max-age=60");
+
+        let fresh = web::Query::<SearchQuery>::from_query("query=happy&nocache=1").unwrap();
+        assert!(fresh.nocache);
+        assert_eq!(search_cache_control(fresh.nocache, false, 300), "no-store");
+        assert_eq!(search_cache_control(false, true, 300), "no-store");
+
+        // same results, so the etag doesn't change
+        assert_eq!(generate_etag(&cached, true), generate_etag(&fresh, true));
+        assert!(web::Query::<SearchQuery>::from_query("query=happy&nocache=yes").is_err());
+    }
+
+    #[test]
+    fn test_percentile_cutoff() {
+        let scores = [0.9, 0.1, 0.5, 0.7, 0.3];
+        // top 80% of 5 is 4 results: everything above 0.1
+        assert_eq!(percentile_cutoff(scores, 20.0), 0.1);
+        // top 50% rounds up to 3 results: everything above 0.3
+        assert_eq!(percentile_cutoff(scores, 50.0), 0.3);
+        assert_eq!(percentile_cutoff(scores, 100.0), 0.9);
+        assert_eq!(percentile_cutoff(scores, 0.0), f32::NEG_INFINITY);
+        assert_eq!(percentile_cutoff([], 50.0), f32::NEG_INFINITY);
+
+        let mut sorted: Vec<BufoResult> = scores
+            .iter()
+            .map(|&score| BufoResult::from_attributes(score.to_string(), score, &HashMap::new()))
+            .collect();
+        sorted.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        let (results, _) = select_results(&sorted, 10, percentile_cutoff(scores, 20.0), 0);
+        assert_eq!(results.len(), 4);
+    }
+
+    #[test]
+    fn test_min_score_percentile_validation() {
+        let check = |value| validate_query(&query(value), &[]).err().unwrap_or_default();
+
+        assert!(check(serde_json::json!({"query": "happy", "min_score_percentile": 80})).is_empty());
+        let errors = check(serde_json::json!({"query": "happy", "min_score": 0.2, "min_score_percentile": 80}));
+        assert_eq!(errors[0].field, "min_score_percentile");
+        assert!(!check(serde_json::json!({"query": "happy", "min_score_percentile": 120})).is_empty());
+    }
+
+    #[test]
+    fn test_explore_validation() {
+        let check = |value| validate_query(&query(value), &[]).err().unwrap_or_default();
+
+        assert!(check(serde_json::json!({"query": "happy", "explore": 0.1})).is_empty());
+        assert_eq!(check(serde_json::json!({"query": "happy", "explore": 0}))[0].field, "explore");
+        assert!(!check(serde_json::json!({"query": "happy", "explore": -1.0})).is_empty());
+    }
+
+    #[test]
+    fn test_etag_is_stable() {
+        let a = query(serde_json::json!({"query": "happy", "alpha": 0.5, "exclude": "sad"}));
+        let b = query(serde_json::json!({"query": "happy", "alpha": 0.5, "exclude": "sad"}));
+        assert_eq!(generate_etag(&a, true), generate_etag(&b, true));
+    }
+
+    #[test]
+    fn test_etag_changes_with_each_parameter() {
+        let base = serde_json::json!({"query": "happy"});
+        let base_etag = generate_etag(&query(base.clone()), true);
+
+        let variations = [
+            ("query", serde_json::json!("sad")),
+            ("top_k", serde_json::json!(5)),
+            ("alpha", serde_json::json!(0.5)),
+            ("exclude", serde_json::json!("party")),
+            ("include", serde_json::json!("party")),
+            ("boost_popularity", serde_json::json!(0.2)),
+            ("min_score", serde_json::json!(0.1)),
+            ("min_score_percentile", serde_json::json!(20.0)),
+            ("min_results", serde_json::json!(3)),
+            ("include_ties", serde_json::json!(true)),
+            ("filters", serde_json::json!(["tags", "Eq", "animal"])),
+            ("include_score_histogram", serde_json::json!(true)),
+            ("facets", serde_json::json!(true)),
+            ("related", serde_json::json!(true)),
+            ("debug", serde_json::json!(true)),
+            ("plan", serde_json::json!(true)),
+            ("model", serde_json::json!("voyage-multimodal-3.5")),
+            ("case_sensitive", serde_json::json!(true)),
+            ("namespaces", serde_json::json!(["bufos", "bufos-memes"])),
+            ("views", serde_json::json!(["semantic"])),
+            ("pseudo_relevance", serde_json::json!(true)),
+            ("explore", serde_json::json!(0.1)),
+            ("highlight", serde_json::json!(true)),
+            ("only_animated", serde_json::json!(true)),
+            ("require_all_terms", serde_json::json!(true)),
+            ("cursor", serde_json::json!(Cursor::new(0.5, "a").encode())),
+            ("since", serde_json::json!("2024-05-01")),
+            ("format", serde_json::json!("ndjson")),
+            ("api_version", serde_json::json!("v2")),
+            ("sort", serde_json::json!("name")),
+            ("pretty_names", serde_json::json!(true)),
+            ("preload", serde_json::json!(3)),
+            ("queries", serde_json::json!(["excited"])),
+            ("prev_order", serde_json::json!(["a", "b"])),
+        ];
+        for (field, value) in variations {
+            let mut changed = base.clone();
+            changed[field] = value;
+            assert_ne!(
+                generate_etag(&query(changed), true),
+                base_etag,
+                "etag ignores {}",
+                field
+            );
+        }
+
+        assert_ne!(generate_etag(&query(base), false), base_etag);
+    }
+
+    #[test]
+    fn test_etag_varies_by_image_format() {
+        let etag = generate_etag(&query(serde_json::json!({"query": "happy"})), true);
+        assert_eq!(etag_for_format(etag.clone(), None), etag);
+
+        let webp = etag_for_format(etag.clone(), Some(ImageFormat::Webp));
+        let avif = etag_for_format(etag.clone(), Some(ImageFormat::Avif));
+        assert!(webp.starts_with('"') && webp.ends_with("-webp\""), "{}", webp);
+        assert_ne!(webp, avif);
+    }
+
+    fn response() -> SearchResponse {
+        SearchResponse {
+            results: candidates(),
+            suggestion: Some("happy".to_string()),
+            no_results_reason: None,
+            relaxed: true,
+            score_histogram: None,
+            facets: None,
+            related_terms: None,
+            truncated: false,
+            degraded: false,
+            views: None,
+            debug: None,
+            plan: None,
+            next_cursor: Some("next".to_string()),
+            warnings: Vec::new(),
+        }
+    }
+
+    async fn rendered(version: ApiVersion) -> actix_web::web::Bytes {
+        let rendered = render(
+            HttpResponse::Ok(),
+            &mut response(),
+            &query(serde_json::json!({"query": "happy"})),
+            version,
+            None,
+        );
+        actix_web::body::to_bytes(rendered.unwrap().into_body())
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_v1_is_the_flat_response() {
+        let body = rendered(ApiVersion::V1).await;
+        assert_eq!(body, serde_json::to_vec(&response()).unwrap());
+
+        let v1: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mut keys: Vec<&str> = v1.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["next_cursor", "relaxed", "results", "suggestion"]
+        );
+
+        let v2: serde_json::Value =
+            serde_json::from_slice(&rendered(ApiVersion::V2).await).unwrap();
+        assert_eq!(v2["results"], v1["results"]);
+        assert_eq!(
+            v2["meta"],
+            serde_json::json!({"next_cursor": "next", "relaxed": true, "suggestion": "happy"})
+        );
+        assert_eq!(v2.as_object().unwrap().len(), 2);
+    }
+
+    /// a page of `n` results with long urls, in descending score order
+    fn large_response(n: usize) -> SearchResponse {
+        let results = (0..n)
+            .map(|i| BufoResult {
+                url: format!("https://example.com/{}", "x".repeat(1000)),
+                ..result(&format!("{:03}", i), 1.0 - i as f32 / 100.0)
+            })
+            .collect();
+        SearchResponse {
+            results,
+            next_cursor: None,
+            ..response()
+        }
+    }
+
+    #[test]
+    fn test_oversized_response_drops_lowest_ranked_results() {
+        let query = query(serde_json::json!({"query": "happy"}));
+        let mut response = large_response(50);
+        // results come back name-sorted, so the lowest-ranked isn't last
+        response.results.reverse();
+
+        let body = fit_response(&mut response, &query, ApiVersion::V1, Some(10_000)).unwrap();
+        assert!(body.len() <= 10_000, "{}", body.len());
+        assert!(response.truncated);
+        assert!(response.results.len() > 1 && response.results.len() < 50);
+        assert_eq!(response.warnings[0].field, "results");
+
+        // the highest-scoring results survive and the cursor continues below them
+        let mut ids: Vec<&str> = response.results.iter().map(|r| r.id.as_str()).collect();
+        ids.sort();
+        let kept = ids.len();
+        assert_eq!(ids.last().unwrap(), &format!("{:03}", kept - 1));
+        let cursor = Cursor::decode(response.next_cursor.as_deref().unwrap()).unwrap();
+        assert!(cursor.precedes(1.0 - kept as f32 / 100.0, &format!("{:03}", kept)));
+
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["truncated"], true);
+        assert_eq!(parsed["results"].as_array().unwrap().len(), kept);
+    }
+
+    #[test]
+    fn test_response_within_limit_is_untouched() {
+        let query = query(serde_json::json!({"query": "happy"}));
+        let mut response = large_response(5);
+        let body = fit_response(&mut response, &query, ApiVersion::V1, Some(1_000_000)).unwrap();
+        assert_eq!(body, serde_json::to_string(&large_response(5)).unwrap());
+        assert!(!response.truncated && response.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_response_too_large_for_one_result_is_413() {
+        let query = query(serde_json::json!({"query": "happy"}));
+        let err =
+            fit_response(&mut large_response(50), &query, ApiVersion::V1, Some(500)).unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn test_related_terms_rank_by_frequency_without_query_terms() {
+        let results: Vec<BufoResult> = [
+            "bufo-the-party-is-over",
+            "bufo-happy-dance",
+            "bufo-dance-party",
+            "bufo-happy-dance-party",
+        ]
+        .into_iter()
+        .map(|name| BufoResult {
+            name: name.to_string(),
+            ..result(name, 0.5)
+        })
+        .collect();
+
+        // "party" is in three names, "dance" in three but seen later, "over" in one;
+        // "the" and "is" are stopwords and "happy" is the query's own
+        assert_eq!(
+            related_terms("Happy", &results),
+            vec!["party", "dance", "over"]
+        );
+        assert!(related_terms("happy", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_preload_links_for_the_top_results() {
+        let mut results = candidates();
+        for r in &mut results {
+            r.url = format!("https://cdn.example/{}.png", r.name);
+        }
+        results[1].url = "https://cdn.example/bufo with, spaces.png".to_string();
+        results[2].url = "data:image/png;base64,AAAA".to_string();
+
+        assert_eq!(
+            preload_links(&results, 3),
+            vec![
+                "<https://cdn.example/bufo-a.png>; rel=preload; as=image",
+                "<https://cdn.example/bufo%20with%2C%20spaces.png>; rel=preload; as=image",
+                // the data url is skipped, not counted
+                "<https://cdn.example/bufo-d.png>; rel=preload; as=image",
+            ]
+        );
+        assert!(preload_links(&results, 0).is_empty());
+        assert_eq!(preload_links(&results, 50).len(), 4);
+
+        let many: Vec<BufoResult> = (0..10).map(|_| results[0].clone()).collect();
+        assert_eq!(preload_links(&many, 50).len(), MAX_PRELOAD);
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let v2_accept = actix_web::test::TestRequest::default()
+            .insert_header(("accept", "application/vnd.bufo.v2+json"))
+            .to_http_request();
+        let plain = actix_web::test::TestRequest::default().to_http_request();
+        let default = query(serde_json::json!({"query": "happy"}));
+        let pinned = query(serde_json::json!({"query": "happy", "api_version": "v1"}));
+
+        assert_eq!(negotiated_version(&plain, &default), ApiVersion::V1);
+        assert_eq!(negotiated_version(&v2_accept, &default), ApiVersion::V2);
+        // the param wins over the header
+        assert_eq!(negotiated_version(&v2_accept, &pinned), ApiVersion::V1);
+
+        let etag = generate_etag(&default, true);
+        assert_eq!(etag_for_version(etag.clone(), ApiVersion::V1), etag);
+        assert!(etag_for_version(etag, ApiVersion::V2).ends_with("-v2\""));
+    }
+
+    fn sized(width: Option<&str>, height: Option<&str>) -> BufoResult {
+        let mut attrs = HashMap::from([("name".to_string(), "bufo-wide".to_string())]);
+        for (key, value) in [("width", width), ("height", height)] {
+            if let Some(value) = value {
+                attrs.insert(key.to_string(), value.to_string());
+            }
+        }
+        BufoResult::from_attributes("w".to_string(), 0.5, &attrs)
+    }
+
+    #[test]
+    fn test_dimensions_from_attributes() {
+        let result = sized(Some("640"), Some("480"));
+        assert_eq!((result.width, result.height), (Some(640), Some(480)));
+        assert!((result.aspect_ratio.unwrap() - 4.0 / 3.0).abs() < 1e-6);
+
+        // numbers come back from turbopuffer as their json text
+        let result = sized(Some("128.0"), Some(" 256 "));
+        assert_eq!((result.width, result.height), (Some(128), Some(256)));
+        assert_eq!(result.aspect_ratio, Some(0.5));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["width"], 128);
+        assert_eq!(json["aspect_ratio"], 0.5);
+    }
+
+    #[test]
+    fn test_missing_dimensions_are_omitted() {
+        for result in [sized(None, None), sized(Some("640"), None)] {
+            assert_eq!(result.width, None);
+            assert_eq!(result.height, None);
+            assert_eq!(result.aspect_ratio, None);
+            let json = serde_json::to_value(&result).unwrap();
+            assert!(json.get("width").is_none());
+            assert!(json.get("aspect_ratio").is_none());
+        }
+    }
+
+    #[test]
+    fn test_malformed_dimensions_are_ignored() {
+        for bad in ["wide", "", "-640", "0", "12.5", "NaN", "inf"] {
+            let result = sized(Some(bad), Some("480"));
+            assert_eq!(result.width, None, "width {:?}", bad);
+            assert_eq!(result.height, None, "width {:?}", bad);
+            assert_eq!(result.aspect_ratio, None, "width {:?}", bad);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_search_serves_cached_responses() {
+        use actix_web::dev::Service;
+        use actix_web::{test, App, HttpMessage};
+
+        let mut config = Config::for_tests();
+        config.response_cache_ttl_secs = 60;
+        let state = AppState::new(&config).unwrap();
+
+        let cached = query(serde_json::json!({"query": "happy", "preload": 1}));
+        let family_friendly = config.default_family_friendly;
+        let etag = etag_for_settings(
+            etag_for_version(
+                etag_for_format(generate_etag(&cached, family_friendly), None),
+                ApiVersion::V1,
+            ),
+            &state,
+            &config,
+        );
+        let rendered = RenderedSearch {
+            body: "{\"results\":[]}".to_string(),
+            truncated: true,
+            links: vec!["<https://cdn.example/a.png>; rel=preload; as=image".to_string()],
+        };
+        state
+            .response_cache
+            .set(
+                &etag,
+                serde_json::to_vec(&rendered).unwrap(),
+                Duration::from_secs(60),
+            )
+            .await;
+
+        // a hit never reaches the (unreachable) embedding provider or turbopuffer
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(RequestId("test".to_string()));
+                    srv.call(req)
+                })
+                .route("/search", web::get().to(search_get)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/search?query=happy&preload=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), 200);
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(header("etag"), etag);
+        assert_eq!(header("content-type"), "application/json");
+        assert_eq!(header(TRUNCATED_HEADER), "true");
+        assert_eq!(header("link"), rendered.links[0]);
+        assert!(header("cache-control").starts_with("public"));
+        let body = test::read_body(resp).await;
+        assert_eq!(body, rendered.body.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn test_reload_invalidates_cached_get_responses() {
+        use crate::alpha::AlphaPolicy;
+        use crate::blocked::BlockedIds;
+        use crate::reload::swap_in;
+        use actix_web::dev::Service;
+        use actix_web::{test, App, HttpMessage};
+
+        let mut config = Config::for_tests();
+        config.response_cache_ttl_secs = 60;
+        config.max_concurrent_searches = 1;
+        let state = AppState::new(&config).unwrap();
+
+        let cached = query(serde_json::json!({"query": "happy"}));
+        let etag = etag_for_settings(
+            etag_for_version(
+                etag_for_format(generate_etag(&cached, config.default_family_friendly), None),
+                ApiVersion::V1,
+            ),
+            &state,
+            &config,
+        );
+        let rendered = RenderedSearch {
+            body: "{\"results\":[{\"id\":\"bufo-taken-down\"}]}".to_string(),
+            truncated: false,
+            links: Vec::new(),
+        };
+        state
+            .response_cache
+            .set(
+                &etag,
+                serde_json::to_vec(&rendered).unwrap(),
+                Duration::from_secs(60),
+            )
+            .await;
+
+        // hold the only search permit, so a cache miss is turned away instead of searching
+        let permits = state.search_permits.clone().unwrap();
+        let _held = permits.try_acquire().unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(RequestId("test".to_string()));
+                    srv.call(req)
+                })
+                .route("/search", web::get().to(search_get)),
+        )
+        .await;
+        let get = || {
+            test::TestRequest::get()
+                .uri("/search?query=happy")
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, get()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(test::read_body(resp).await, rendered.body.as_bytes());
+
+        // block the cached top result and reload
+        swap_in(
+            &state,
+            config.clone(),
+            AlphaPolicy::default(),
+            BlockedIds::parse("bufo-taken-down\n"),
+        );
+
+        let resp = test::call_service(&app, get()).await;
+        assert_eq!(resp.status(), 503);
+        let body = test::read_body(resp).await;
+        assert!(!String::from_utf8_lossy(&body).contains("bufo-taken-down"));
+    }
+
+    #[actix_web::test]
+    async fn test_plan_reflects_skipped_calls_without_searching() {
+        use crate::plan::CallKind;
+
+        let config = Config::for_tests();
+        let state = AppState::new(&config).unwrap();
+
+        // the providers are unreachable, so any upstream call would fail the search
+        let plan_for = |params: serde_json::Value| {
+            let query = query(params);
+            let (config, state) = (&config, &state);
+            async move {
+                let response = perform_search(&query, config, state, "test").await.unwrap();
+                assert!(response.results.is_empty());
+                response.plan.unwrap()
+            }
+        };
+        let skipped = |plan: &SearchPlan| -> Vec<(CallKind, bool)> {
+            plan.calls
+                .iter()
+                .map(|c| (c.kind, c.skipped.is_some()))
+                .collect()
+        };
+
+        let keyword_only =
+            plan_for(serde_json::json!({"query": "happy", "alpha": 0.0, "plan": true})).await;
+        assert_eq!(keyword_only.alpha, 0.0);
+        assert_eq!(keyword_only.alpha_source, "request");
+        assert_eq!(
+            skipped(&keyword_only),
+            vec![
+                (CallKind::Embed, true),
+                (CallKind::VectorSearch, true),
+                (CallKind::KeywordSearch, false),
+            ]
+        );
+
+        let semantic_only = plan_for(serde_json::json!({
+            "query": "happy", "alpha": 1.0, "plan": true, "exclude": "sad, angry"
+        }))
+        .await;
+        assert_eq!(
+            skipped(&semantic_only),
+            vec![
+                (CallKind::Embed, false),
+                (CallKind::VectorSearch, false),
+                (CallKind::KeywordSearch, true),
+            ]
+        );
+        assert_eq!(semantic_only.filters.exclude, vec!["sad", "angry"]);
+        assert_eq!(semantic_only.post_processors[0], "filter");
+    }
+
+    #[actix_web::test]
+    async fn test_plan_routes_a_chosen_model_to_its_namespace() {
+        use crate::plan::CallKind;
+
+        let mut config = Config::for_tests();
+        config.embedding_models = "voyage-multimodal-3.5=bufos-mm35".parse().unwrap();
+        let state = AppState::new(&config).unwrap();
+
+        let query = query(serde_json::json!({
+            "query": "happy", "model": "voyage-multimodal-3.5", "plan": true
+        }));
+        let plan = perform_search(&query, &config, &state, "test")
+            .await
+            .unwrap()
+            .plan
+            .unwrap();
+        let calls: Vec<(CallKind, &str)> = plan
+            .calls
+            .iter()
+            .map(|c| (c.kind, c.target.as_str()))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (CallKind::Embed, "voyage-multimodal-3.5"),
+                (CallKind::VectorSearch, "bufos-mm35"),
+                (CallKind::KeywordSearch, "bufos-mm35"),
+            ]
+        );
+        assert_eq!(plan.fallback_namespace, None);
+    }
+
+    #[actix_web::test]
+    async fn test_short_terms_leave_the_keyword_query_only() {
+        let mut config = Config::for_tests();
+        config.bm25_min_term_length = 3;
+        let state = AppState::new(&config).unwrap();
+
+        let plan_for = |text: &str| {
+            let query = query(serde_json::json!({"query": text, "plan": true}));
+            let (config, state) = (&config, &state);
+            async move {
+                perform_search(&query, config, state, "test")
+                    .await
+                    .unwrap()
+                    .plan
+                    .unwrap()
+            }
+        };
+
+        let plan = plan_for("me as a happy bufo").await;
+        assert_eq!(plan.keyword_text, "happy bufo");
+        assert_eq!(plan.semantic_text, "me as a happy bufo");
+
+        // nothing long enough to keep: the keyword search gets the query as typed
+        let plan = plan_for("ok hi").await;
+        assert_eq!(plan.keyword_text, "ok hi");
+        assert_eq!(plan.semantic_text, "ok hi");
+    }
 }
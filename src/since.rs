@@ -0,0 +1,64 @@
+//! "new bufos" timestamps
+//!
+//! `since` takes an RFC 3339 timestamp (`2024-05-01T12:00:00Z`, any offset, optional
+//! fractional seconds) or a bare date (`2024-05-01`, midnight UTC). a date-time without
+//! an offset is rejected rather than guessed at. the result is compared against the
+//! `added_at` attribute, which ingestion stores as unix seconds.
+
+use chrono::{DateTime, NaiveDate};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+#[error(
+    "since must be an RFC 3339 timestamp like 2024-05-01T12:00:00Z (with an offset) or a date like 2024-05-01, got {0:?}"
+)]
+pub struct SinceError(String);
+
+/// unix seconds for a `since` value (fractions are dropped)
+pub fn parse_since(raw: &str) -> Result<i64, SinceError> {
+    let raw = raw.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(timestamp.timestamp());
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc().timestamp())
+        .ok_or_else(|| SinceError(raw.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since_accepts_timestamps_and_dates() {
+        assert_eq!(parse_since("2024-05-01T12:00:00Z"), Ok(1_714_564_800));
+        assert_eq!(parse_since("2024-05-01T14:00:00+02:00"), Ok(1_714_564_800));
+        assert_eq!(parse_since("2024-05-01T12:00:00.999Z"), Ok(1_714_564_800));
+        assert_eq!(parse_since(" 2024-05-01 "), Ok(1_714_521_600));
+        assert_eq!(parse_since("1970-01-01"), Ok(0));
+        assert_eq!(parse_since("1969-12-31T23:59:59Z"), Ok(-1));
+    }
+
+    #[test]
+    fn test_parse_since_rejects_ambiguous_and_malformed() {
+        for raw in [
+            "",
+            "yesterday",
+            "1714564800",
+            "2024-05-01T12:00:00",
+            "2024-05-01 12:00:00",
+            "2024-13-01",
+            "2024-02-30",
+            "2024-05-01T25:00:00Z",
+            "05/01/2024",
+        ] {
+            assert!(parse_since(raw).is_err(), "accepted {:?}", raw);
+        }
+        assert!(parse_since("soon")
+            .unwrap_err()
+            .to_string()
+            .contains("\"soon\""));
+    }
+}
@@ -0,0 +1,92 @@
+//! shared runtime state that outlives a single request
+//!
+//! holds the pieces that are built once at startup and updated in the background,
+//! including the live `Config` (and the files it names) that `/api/admin/reload` can swap.
+
+use crate::alpha::AlphaPolicy;
+use crate::blocked::BlockedIds;
+use crate::breaker::CircuitBreaker;
+use crate::cache::EmbeddingCache;
+use crate::cluster::Clusterer;
+use crate::config::Config;
+use crate::dimension::DimensionTracker;
+use crate::history::QueryHistory;
+#[cfg(feature = "local-embeddings")]
+use crate::local::LocalEmbedder;
+use crate::reload::{Live, LiveConfig};
+use crate::response_cache::FallbackCache;
+use crate::vocabulary::VocabularyCache;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+#[derive(Clone)]
+pub struct AppState {
+    /// the current config; handlers read it through `CurrentConfig`
+    pub config: LiveConfig,
+    /// bufo name vocabulary used for "did you mean" suggestions
+    pub vocabulary: VocabularyCache,
+    /// query embeddings shared across requests
+    pub embedding_cache: EmbeddingCache,
+    /// trips on repeated primary embedding failures, shared across requests
+    pub embedding_breaker: CircuitBreaker,
+    /// past successful queries for autocompletion; disabled unless `LOG_QUERIES` is on
+    pub query_history: QueryHistory,
+    /// tuned alphas from `ALPHA_POLICY_PATH`, for requests without their own
+    pub alpha_policy: Live<AlphaPolicy>,
+    /// bufos from `BLOCKED_IDS_PATH`, never returned whatever their name
+    pub blocked_ids: Live<BlockedIds>,
+    /// bumped by every reload, so responses cached under older settings miss
+    pub config_generation: Arc<AtomicU64>,
+    /// labeled centroids from `CLUSTER_CENTROIDS_PATH`, for debug `query_cluster`
+    pub clusterer: Arc<Clusterer>,
+    /// rendered GET search responses; in memory until startup connects `CACHE_BACKEND`
+    pub response_cache: FallbackCache,
+    /// one permit per in-flight search; `None` when `MAX_CONCURRENT_SEARCHES` is 0
+    pub search_permits: Option<Arc<Semaphore>>,
+    /// when the server started, for uptime reporting
+    pub started_at: Instant,
+    /// size of the first query embedding the primary embedder returned
+    pub embedding_dimension: Arc<OnceLock<usize>>,
+    /// size of the latest primary query embedding, to catch upstream changes
+    pub dimension_tracker: DimensionTracker,
+    /// in-process query embedder loaded from `LOCAL_MODEL_DIR`
+    #[cfg(feature = "local-embeddings")]
+    pub local_embedder: Option<LocalEmbedder>,
+}
+
+impl AppState {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: LiveConfig::new(config.clone()),
+            vocabulary: VocabularyCache::default(),
+            embedding_cache: EmbeddingCache::new(config.embedding_cache_size),
+            embedding_breaker: CircuitBreaker::new(
+                config.embedding_breaker_threshold,
+                config.embedding_breaker_cooldown,
+            ),
+            query_history: QueryHistory::new(if config.log_queries {
+                config.query_history_size
+            } else {
+                0
+            }),
+            alpha_policy: Live::new(AlphaPolicy::load(config.alpha_policy_path.as_deref())?),
+            blocked_ids: Live::new(BlockedIds::load(config.blocked_ids_path.as_deref())?),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            clusterer: Arc::new(Clusterer::load(config.cluster_centroids_path.as_deref())?),
+            response_cache: FallbackCache::new(None),
+            search_permits: (config.max_concurrent_searches > 0)
+                .then(|| Arc::new(Semaphore::new(config.max_concurrent_searches))),
+            started_at: Instant::now(),
+            embedding_dimension: Arc::new(OnceLock::new()),
+            dimension_tracker: DimensionTracker::default(),
+            #[cfg(feature = "local-embeddings")]
+            local_embedder: config
+                .local_model_dir
+                .as_ref()
+                .map(|dir| LocalEmbedder::load(dir)?.check_dimension(config.local_embedding_dimension))
+                .transpose()?,
+        })
+    }
+}
@@ -0,0 +1,198 @@
+//! query autocompletion
+//!
+//! blends two sources: bufo names whose terms start with the typed prefix, and popular
+//! past queries (`history::QueryHistory`) that start with it. each candidate scores
+//! `ln(1 + count) + covered`, where `count` is how often the query was searched (0 for
+//! names) and `covered` is the share of the candidate the prefix already spells out, so
+//! frequent queries rise and near-complete matches edge out long ones. candidates that
+//! tokenize the same are shown once, keeping the higher scoring source.
+
+use crate::filter::ContentFilter;
+use crate::reload::CurrentConfig;
+use crate::state::AppState;
+use crate::tokenize::tokenize;
+use actix_web::{web, HttpResponse, Result as ActixResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// most suggestions a single request can ask for
+const MAX_SUGGESTIONS: usize = 20;
+
+fn default_limit() -> usize {
+    8
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestQuery {
+    /// what the user has typed so far
+    pub q: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// defaults to the server's `DEFAULT_FAMILY_FRIENDLY`
+    #[serde(default)]
+    pub family_friendly: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    Name,
+    Query,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub text: String,
+    pub source: SuggestionSource,
+    /// times the query was searched; absent for names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuggestResponse {
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// lowercased terms joined by single spaces ("Bufo-Party!" → "bufo party")
+fn phrase(text: &str) -> String {
+    tokenize(text).join(" ")
+}
+
+/// rank `names` and `queries` completing `prefix`, keeping the top `limit`
+pub fn blend(
+    prefix: &str,
+    names: &[&str],
+    queries: &[(String, u64)],
+    limit: usize,
+) -> Vec<Suggestion> {
+    let typed = phrase(prefix).chars().count() as f64;
+
+    let candidates = queries
+        .iter()
+        .map(|(text, count)| Suggestion {
+            text: text.clone(),
+            source: SuggestionSource::Query,
+            count: Some(*count),
+        })
+        .chain(names.iter().map(|name| Suggestion {
+            text: name.to_string(),
+            source: SuggestionSource::Name,
+            count: None,
+        }));
+
+    let mut best: HashMap<String, (f64, Suggestion)> = HashMap::new();
+    for suggestion in candidates {
+        let key = phrase(&suggestion.text);
+        let covered = typed / key.chars().count().max(1) as f64;
+        let score = (suggestion.count.unwrap_or(0) as f64).ln_1p() + covered.min(1.0);
+        // queries come first, so an equal-scoring name never replaces one
+        if best.get(&key).is_none_or(|(kept, _)| score > *kept) {
+            best.insert(key, (score, suggestion));
+        }
+    }
+
+    let mut ranked: Vec<(f64, Suggestion)> = best.into_values().collect();
+    ranked.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.1.text.cmp(&b.1.text))
+    });
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, suggestion)| suggestion)
+        .collect()
+}
+
+/// whether `text` contains a blocklisted name's terms
+fn blocked(text: &str, filter: &ContentFilter) -> bool {
+    let text = phrase(text);
+    filter
+        .blocklist()
+        .iter()
+        .any(|name| text.contains(&phrase(name)))
+}
+
+/// GET /api/suggest handler
+pub async fn suggest(
+    query: web::Query<SuggestQuery>,
+    config: CurrentConfig,
+    state: web::Data<AppState>,
+) -> ActixResult<HttpResponse> {
+    if query.limit == 0 || query.limit > MAX_SUGGESTIONS {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_SUGGESTIONS
+        )));
+    }
+    let family_friendly = query
+        .family_friendly
+        .unwrap_or(config.default_family_friendly);
+    let filter = ContentFilter::new(family_friendly, None, None);
+    let allowed = |text: &str| !family_friendly || !blocked(text, &filter);
+
+    let vocabulary = state.vocabulary.current();
+    let names: Vec<&str> = vocabulary
+        .names_with_prefix(&query.q)
+        .into_iter()
+        .filter(|name| allowed(name))
+        .collect();
+    let queries: Vec<(String, u64)> = state
+        .query_history
+        .completions(&query.q)
+        .into_iter()
+        .filter(|(text, _)| allowed(text))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SuggestResponse {
+        suggestions: blend(&query.q, &names, &queries, query.limit),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_blend_ranks_popular_queries_above_names() {
+        let queries = vec![("party time".to_string(), 5), ("party".to_string(), 1)];
+        let suggestions = blend("part", &["bufo-party", "bufo-party-hat"], &queries, 10);
+
+        assert_eq!(
+            texts(&suggestions),
+            vec!["party time", "party", "bufo-party", "bufo-party-hat"]
+        );
+        assert_eq!(suggestions[0].source, SuggestionSource::Query);
+        assert_eq!(suggestions[0].count, Some(5));
+        assert_eq!(suggestions[2].count, None);
+        assert_eq!(blend("part", &["bufo-party"], &queries, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_blend_prefers_closer_names() {
+        let suggestions = blend("bufo-ha", &["bufo-happy-dance", "bufo-hat"], &[], 10);
+        assert_eq!(texts(&suggestions), vec!["bufo-hat", "bufo-happy-dance"]);
+    }
+
+    #[test]
+    fn test_blend_dedupes_by_terms() {
+        let queries = vec![("bufo party".to_string(), 2)];
+        let suggestions = blend("bufo p", &["bufo-party"], &queries, 10);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].source, SuggestionSource::Query);
+    }
+
+    #[test]
+    fn test_blocked_matches_blocklisted_terms() {
+        let filter = ContentFilter::new(true, None, None);
+        assert!(blocked("bufo-juicy", &filter));
+        assert!(blocked("Bufo Juicy", &filter));
+        assert!(!blocked("bufo-happy", &filter));
+    }
+}
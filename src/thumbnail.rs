@@ -0,0 +1,91 @@
+//! thumbnail urls for gallery views
+//!
+//! a stored `thumbnail_url` attribute is returned as-is. otherwise, when
+//! `THUMBNAIL_URL_PATTERN` is set, the thumbnail is derived from the full image url with
+//! a regex replace (`THUMBNAIL_URL_REPLACEMENT`, which may use `$1`-style groups). urls
+//! the pattern doesn't match get no thumbnail.
+
+use crate::search::BufoResult;
+use regex::Regex;
+
+/// regex replace from a full image url to its thumbnail
+#[derive(Debug, Clone)]
+pub struct ThumbnailTransform {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl ThumbnailTransform {
+    pub fn new(pattern: &str, replacement: String) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement,
+        })
+    }
+
+    /// the thumbnail for `url`, or `None` when the pattern doesn't match
+    pub fn apply(&self, url: &str) -> Option<String> {
+        self.pattern
+            .is_match(url)
+            .then(|| self.pattern.replace(url, self.replacement.as_str()).into_owned())
+    }
+}
+
+/// derive thumbnails for results that don't already have one
+pub fn fill_thumbnails(results: &mut [BufoResult], transform: Option<&ThumbnailTransform>) {
+    let Some(transform) = transform else {
+        return;
+    };
+    for result in results.iter_mut().filter(|r| r.thumbnail_url.is_none()) {
+        result.thumbnail_url = transform.apply(&result.url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(url: &str, thumbnail: Option<&str>) -> BufoResult {
+        let mut attrs = HashMap::from([("url".to_string(), url.to_string())]);
+        if let Some(thumbnail) = thumbnail {
+            attrs.insert("thumbnail_url".to_string(), thumbnail.to_string());
+        }
+        BufoResult::from_attributes("1".to_string(), 0.5, &attrs)
+    }
+
+    #[test]
+    fn test_transform_rewrites_matching_urls() {
+        let transform =
+            ThumbnailTransform::new(r"^(.*)/bufos/(.*)$", "$1/thumbs/$2".to_string()).unwrap();
+        assert_eq!(
+            transform.apply("https://cdn.example/bufos/bufo-happy.png").as_deref(),
+            Some("https://cdn.example/thumbs/bufo-happy.png")
+        );
+        assert_eq!(transform.apply("https://elsewhere.example/bufo.png"), None);
+        assert!(ThumbnailTransform::new("(unclosed", String::new()).is_err());
+    }
+
+    #[test]
+    fn test_explicit_thumbnail_attribute_wins() {
+        let transform = ThumbnailTransform::new("bufos", "thumbs".to_string()).unwrap();
+        let mut results = vec![
+            result("https://cdn.example/bufos/a.png", Some("https://cdn.example/small/a.png")),
+            result("https://cdn.example/bufos/b.png", None),
+        ];
+
+        fill_thumbnails(&mut results, Some(&transform));
+        assert_eq!(
+            results[0].thumbnail_url.as_deref(),
+            Some("https://cdn.example/small/a.png")
+        );
+        assert_eq!(
+            results[1].thumbnail_url.as_deref(),
+            Some("https://cdn.example/thumbs/b.png")
+        );
+
+        let mut untouched = vec![result("https://cdn.example/bufos/c.png", None)];
+        fill_thumbnails(&mut untouched, None);
+        assert_eq!(untouched[0].thumbnail_url, None);
+    }
+}
@@ -0,0 +1,94 @@
+//! per-stage search latency
+//!
+//! `execute_hybrid_search` records how long the embedding, vector, BM25 and fusion
+//! stages took; `perform_search` adds filtering and the total. searches slower than
+//! `SLOW_QUERY_MS` are logged at warn level with the breakdown, and `debug=true`
+//! responses include it. the semantic and keyword sides run concurrently, so the
+//! stages can add up to more than the total.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// milliseconds spent in each stage of one search
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct StageTimings {
+    /// query embedding, including ensemble members
+    pub embed_ms: f64,
+    /// nearest-neighbour search, including ensemble members
+    pub vector_ms: f64,
+    /// keyword search, including the pseudo-relevance pass
+    pub bm25_ms: f64,
+    /// normalization, fusion and boosts
+    pub fusion_ms: f64,
+    /// content filter, post-processors and selection
+    pub filter_ms: f64,
+    pub total_ms: f64,
+}
+
+impl StageTimings {
+    /// the slowest of each stage, for searches that ran concurrently (federation)
+    pub fn slowest(timings: impl IntoIterator<Item = StageTimings>) -> Self {
+        timings.into_iter().fold(Self::default(), |a, b| Self {
+            embed_ms: a.embed_ms.max(b.embed_ms),
+            vector_ms: a.vector_ms.max(b.vector_ms),
+            bm25_ms: a.bm25_ms.max(b.bm25_ms),
+            fusion_ms: a.fusion_ms.max(b.fusion_ms),
+            filter_ms: a.filter_ms.max(b.filter_ms),
+            total_ms: a.total_ms.max(b.total_ms),
+        })
+    }
+
+    /// both searches' stages added up, for one that ran after the other (fallback)
+    pub fn then(self, next: StageTimings) -> Self {
+        Self {
+            embed_ms: self.embed_ms + next.embed_ms,
+            vector_ms: self.vector_ms + next.vector_ms,
+            bm25_ms: self.bm25_ms + next.bm25_ms,
+            fusion_ms: self.fusion_ms + next.fusion_ms,
+            filter_ms: self.filter_ms + next.filter_ms,
+            total_ms: self.total_ms + next.total_ms,
+        }
+    }
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// whether a search taking `total_ms` crosses `SLOW_QUERY_MS` (never when unset)
+pub fn is_slow(total_ms: f64, threshold_ms: Option<u64>) -> bool {
+    threshold_ms.is_some_and(|threshold| total_ms > threshold as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(embed_ms: f64, bm25_ms: f64) -> StageTimings {
+        StageTimings {
+            embed_ms,
+            bm25_ms,
+            ..StageTimings::default()
+        }
+    }
+
+    #[test]
+    fn test_combining_timings() {
+        let slowest = StageTimings::slowest([timings(10.0, 2.0), timings(4.0, 8.0)]);
+        assert_eq!(slowest, timings(10.0, 8.0));
+        assert_eq!(StageTimings::slowest([]), StageTimings::default());
+
+        assert_eq!(
+            timings(10.0, 2.0).then(timings(4.0, 8.0)),
+            timings(14.0, 10.0)
+        );
+        assert_eq!(millis(Duration::from_micros(1500)), 1.5);
+    }
+
+    #[test]
+    fn test_is_slow() {
+        assert!(is_slow(501.0, Some(500)));
+        assert!(!is_slow(500.0, Some(500)));
+        assert!(!is_slow(10_000.0, None));
+    }
+}
@@ -0,0 +1,253 @@
+//! shared query tokenization
+//!
+//! query-analysis features (spelling suggestions, position boosts, term counts,
+//! highlights) split text here so they agree on what a term is. by default terms are
+//! lowercased runs of letters and digits, so whitespace, hyphens, underscores and
+//! punctuation all separate terms ("bufo-jumping_on bed!" → bufo, jumping, on, bed).
+
+use std::collections::HashSet;
+
+/// common english words that carry little meaning in a bufo query
+pub const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "at", "for", "in", "is", "of", "on", "or", "the", "to", "with",
+];
+
+/// tokenization options; `Tokenizer::default()` is what `tokenize` uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tokenizer {
+    pub lowercase: bool,
+    /// split on hyphens and underscores too; when false, filename-style terms like
+    /// "bufo-jumping" stay whole
+    pub split_hyphens: bool,
+    /// drop `STOPWORDS` (matched case-insensitively)
+    pub remove_stopwords: bool,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            split_hyphens: true,
+            remove_stopwords: false,
+        }
+    }
+}
+
+impl Tokenizer {
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let joiner = |c: char| !self.split_hyphens && (c == '-' || c == '_');
+
+        text.split(|c: char| !c.is_alphanumeric() && !joiner(c))
+            .map(|term| term.trim_matches(joiner))
+            .filter(|term| !term.is_empty())
+            .filter(|term| !self.remove_stopwords || !is_stopword(term))
+            .map(|term| {
+                if self.lowercase {
+                    term.to_lowercase()
+                } else {
+                    term.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_stopword(term: &str) -> bool {
+    STOPWORDS.contains(&term.to_lowercase().as_str())
+}
+
+/// tokenize with the default options
+pub fn tokenize(text: &str) -> Vec<String> {
+    Tokenizer::default().tokenize(text)
+}
+
+/// `text` without words made up only of `stopwords` (matched case-insensitively)
+///
+/// words are whitespace-separated and otherwise kept as written. when every word is a
+/// stopword the text is returned unchanged, so there's always something left to embed.
+pub fn without_stopwords(text: &str, stopwords: &[String]) -> String {
+    let is_stopword = |term: &String| stopwords.iter().any(|s| s.eq_ignore_ascii_case(term));
+    let kept: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| {
+            let terms = tokenize(word);
+            terms.is_empty() || !terms.iter().all(is_stopword)
+        })
+        .collect();
+
+    if kept.iter().any(|word| !tokenize(word).is_empty()) {
+        kept.join(" ")
+    } else {
+        text.to_string()
+    }
+}
+
+/// `text` rebuilt from its terms of at least `min_chars` characters, space-separated
+///
+/// for the BM25 query, where one- and two-letter terms match noisily. terms keep their
+/// case and hyphenated terms stay whole, so the keyword search sees them as typed. when
+/// no term is long enough the text is returned unchanged, so there's still a query.
+pub fn without_short_terms(text: &str, min_chars: usize) -> String {
+    if min_chars <= 1 {
+        return text.to_string();
+    }
+    let kept: Vec<String> = Tokenizer {
+        lowercase: false,
+        split_hyphens: false,
+        remove_stopwords: false,
+    }
+    .tokenize(text)
+    .into_iter()
+    .filter(|term| term.chars().count() >= min_chars)
+    .collect();
+
+    if kept.is_empty() {
+        text.to_string()
+    } else {
+        kept.join(" ")
+    }
+}
+
+/// terms of `query` that also appear in `text`, in query order without repeats
+pub fn matched_terms(query: &str, text: &str) -> Vec<String> {
+    let text_terms: HashSet<String> = tokenize(text).into_iter().collect();
+    let mut seen = HashSet::new();
+    tokenize(query)
+        .into_iter()
+        .filter(|term| text_terms.contains(term) && seen.insert(term.clone()))
+        .collect()
+}
+
+/// whether `text` has every term of `query`, ignoring stopwords in the query
+pub fn contains_all_terms(query: &str, text: &str) -> bool {
+    let text_terms: HashSet<String> = tokenize(text).into_iter().collect();
+    Tokenizer {
+        remove_stopwords: true,
+        ..Tokenizer::default()
+    }
+    .tokenize(query)
+    .iter()
+    .all(|term| text_terms.contains(term))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyphenated_filenames() {
+        assert_eq!(
+            tokenize("bufo-jumping_on-the-bed"),
+            vec!["bufo", "jumping", "on", "the", "bed"]
+        );
+
+        let whole = Tokenizer {
+            split_hyphens: false,
+            ..Tokenizer::default()
+        };
+        assert_eq!(
+            whole.tokenize("bufo-jumping and bufo_sad -x-"),
+            vec!["bufo-jumping", "and", "bufo_sad", "x"]
+        );
+    }
+
+    #[test]
+    fn test_mixed_case() {
+        assert_eq!(tokenize("Happy BUFO"), vec!["happy", "bufo"]);
+
+        let keep_case = Tokenizer {
+            lowercase: false,
+            ..Tokenizer::default()
+        };
+        assert_eq!(keep_case.tokenize("Happy BUFO"), vec!["Happy", "BUFO"]);
+    }
+
+    #[test]
+    fn test_punctuation() {
+        assert_eq!(
+            tokenize("bufo's happy!! (very), ok?"),
+            vec!["bufo", "s", "happy", "very", "ok"]
+        );
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("  -- ,, !").is_empty());
+    }
+
+    #[test]
+    fn test_stopwords() {
+        let tokenizer = Tokenizer {
+            remove_stopwords: true,
+            ..Tokenizer::default()
+        };
+        assert_eq!(
+            tokenizer.tokenize("The bufo is on a bed"),
+            vec!["bufo", "bed"]
+        );
+    }
+
+    #[test]
+    fn test_matched_terms() {
+        assert_eq!(
+            matched_terms("Happy Dance", "bufo-happy-dance-party"),
+            vec!["happy", "dance"]
+        );
+        assert_eq!(
+            matched_terms("bufo-JUMPING on bed", "bufos-jumping-on-the-bed"),
+            vec!["jumping", "on", "bed"]
+        );
+        assert_eq!(matched_terms("sad sad bufo", "bufo-sad"), vec!["sad", "bufo"]);
+        assert!(matched_terms("happy", "bufo-sad").is_empty());
+        assert!(matched_terms("", "bufo-sad").is_empty());
+    }
+
+    #[test]
+    fn test_contains_all_terms() {
+        assert!(contains_all_terms("happy dance", "bufo-happy-dance"));
+        assert!(contains_all_terms("Dance HAPPY", "bufo-happy-dance-party"));
+        assert!(!contains_all_terms("happy dance", "bufo-happy"));
+        // stopwords in the query aren't required
+        assert!(contains_all_terms("jumping on a bed", "bufo-jumping-bed"));
+        assert!(contains_all_terms("the", "bufo-sad"));
+    }
+
+    fn english() -> Vec<String> {
+        STOPWORDS.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_without_stopwords() {
+        assert_eq!(
+            without_stopwords("the happy bufo in a giving mood", &english()),
+            "happy bufo giving mood"
+        );
+        assert_eq!(
+            without_stopwords("The Bufo-Jumping, ON the bed!", &english()),
+            "Bufo-Jumping, bed!"
+        );
+
+        let custom = vec!["bufo".to_string()];
+        assert_eq!(without_stopwords("the bufo dance", &custom), "the dance");
+        assert_eq!(without_stopwords("happy bufo", &[]), "happy bufo");
+    }
+
+    #[test]
+    fn test_without_short_terms() {
+        assert_eq!(without_short_terms("a Happy bufo, ok?", 3), "Happy bufo");
+        assert_eq!(without_short_terms("me bufo-ok", 3), "bufo-ok");
+        // 1 keeps the text as typed
+        assert_eq!(without_short_terms("a happy bufo!", 1), "a happy bufo!");
+        // nothing long enough: the whole query stays
+        assert_eq!(without_short_terms("ok hi", 3), "ok hi");
+    }
+
+    #[test]
+    fn test_without_stopwords_keeps_a_content_token() {
+        assert_eq!(without_stopwords("to be or not to be", &english()), "be not be");
+        assert_eq!(without_stopwords("the of and", &english()), "the of and");
+        assert_eq!(without_stopwords("the ?! a", &english()), "the ?! a");
+        assert_eq!(without_stopwords("", &english()), "");
+    }
+}
@@ -1,17 +1,75 @@
 //! turbopuffer vector database implementation
 //!
 //! implements the `VectorStore` trait for turbopuffer's hybrid search API.
+//!
+//! ## rank_by options
+//!
+//! - vector: `["vector", "ANN", <embedding>]`
+//! - keyword: `["name", "BM25", <query>]`, plus `{"last_as_prefix": true}` when
+//!   `Bm25Options::last_as_prefix` is set so "jump" also matches "jumping". with
+//!   `KEYWORD_FIELDS`, the same clause is sent once per field (`tags`, ...); each field
+//!   must have been written with `full_text_search` enabled
+//!
+//! names are hyphenated ("bufo-happy"), so whether a hyphen separates tokens depends on
+//! the namespace's tokenizer. the default word tokenizer splits on it, and the query is
+//! sent as typed (`BM25_HYPHEN_MODE=keep`). for namespaces whose tokenizer keeps
+//! "bufo-happy" whole, `split` replaces hyphens in the query with spaces so it matches
+//! the name's own words however they were indexed.
+//!
+//! `max_distance` is a similarity floor for vector queries. turbopuffer has no distance
+//! cutoff for ANN, so the store still asks for `top_k` neighbours and drops any whose
+//! cosine distance exceeds the floor; obscure queries can therefore return fewer than
+//! `top_k` semantic results (even none). since similarity is `1 - distance / 2`, a
+//! `max_distance` of 0.8 keeps neighbours with a semantic score of at least 0.6.
+//!
+//! request-supplied `filters` are validated by `parse_filters` and added to both query
+//! bodies, so filtering happens server-side before `top_k` is applied. server-built
+//! conditions (`since` becomes `["added_at", "Gt", <unix seconds>]`) are and-ed with
+//! them by `all_of`. turbopuffer compares numbers numerically, so timestamps are stored
+//! and compared as unix seconds rather than strings.
+//!
+//! `consistency` is sent only when configured (`TURBOPUFFER_CONSISTENCY`); otherwise
+//! turbopuffer's default applies. `strong` reads include every acknowledged write (so
+//! freshly ingested bufos are searchable immediately) but must check the write-ahead log
+//! and are slower; `eventual` reads may lag recent writes by a short interval but are
+//! served straight from cache.
+//!
+//! tokenization (stemming, case sensitivity) is part of the namespace schema, not the
+//! query: turbopuffer analyzes the query with the attribute's own tokenizer. stemming is
+//! enabled at ingest time with `BM25_STEMMING=true`; namespaces indexed without it keep
+//! exact-token matching.
 
-use crate::providers::{SearchResult, VectorSearchError, VectorStore};
+use crate::providers::{QueryOptions, SearchResult, VectorSearchError, VectorStore};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
 
 const TURBOPUFFER_API_BASE: &str = "https://api.turbopuffer.com/v1/vectors";
 
+/// namespace size reported on `HEAD /v1/vectors/{namespace}`
+const APPROX_COUNT_HEADER: &str = "x-turbopuffer-approx-num-vectors";
+
+/// attributes returned with every row (`popularity`, `tags`, `width` and `height` are
+/// optional in the schema)
+const INCLUDE_ATTRIBUTES: &[&str] = &[
+    "url",
+    "name",
+    "filename",
+    "format",
+    "popularity",
+    "tags",
+    "thumbnail_url",
+    "width",
+    "height",
+];
+
 /// raw response row from turbopuffer API
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct QueryRow {
     pub id: String,
+    /// absent for unranked (list) queries
+    #[serde(default)]
     pub dist: f32,
     pub attributes: serde_json::Map<String, serde_json::Value>,
 }
@@ -21,7 +79,11 @@ impl From<QueryRow> for SearchResult {
         let attributes = row
             .attributes
             .iter()
-            .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+            .filter_map(|(k, v)| match v {
+                serde_json::Value::Null => None,
+                serde_json::Value::String(s) => Some((k.clone(), s.clone())),
+                other => Some((k.clone(), other.to_string())),
+            })
             .collect();
 
         SearchResult {
@@ -39,6 +101,237 @@ struct ErrorResponse {
     status: String,
 }
 
+/// comparison operators accepted in user-supplied filters
+const FILTER_OPERATORS: &[&str] = &[
+    "Eq",
+    "NotEq",
+    "In",
+    "NotIn",
+    "Lt",
+    "Lte",
+    "Gt",
+    "Gte",
+    "Glob",
+    "NotGlob",
+    "IGlob",
+    "NotIGlob",
+    "Contains",
+    "NotContains",
+    "ContainsAny",
+    "NotContainsAny",
+];
+
+/// bounds on user-supplied filters so a request can't build an enormous query
+const MAX_FILTER_DEPTH: usize = 8;
+const MAX_FILTER_CLAUSES: usize = 32;
+
+/// a user-supplied filter expression that isn't valid turbopuffer filter syntax
+#[derive(Debug, thiserror::Error)]
+#[error("invalid filters: {0}")]
+pub struct FilterValidationError(String);
+
+impl FilterValidationError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+/// parse and validate a user-supplied filter expression
+///
+/// accepts the filter itself (POST bodies) or a JSON-encoded string (GET query params).
+/// supported forms: `[attr, op, value]`, `["And" | "Or", [filters...]]`, `["Not", filter]`.
+pub fn parse_filters(raw: &Value) -> Result<Value, FilterValidationError> {
+    let value = match raw {
+        Value::String(s) => serde_json::from_str(s)
+            .map_err(|e| FilterValidationError::new(format!("not valid JSON: {}", e)))?,
+        other => other.clone(),
+    };
+
+    let mut clauses = 0;
+    validate_filter(&value, 0, &mut clauses)?;
+    Ok(value)
+}
+
+fn validate_filter(
+    value: &Value,
+    depth: usize,
+    clauses: &mut usize,
+) -> Result<(), FilterValidationError> {
+    if depth > MAX_FILTER_DEPTH {
+        return Err(FilterValidationError::new("filters are nested too deeply"));
+    }
+
+    let items = value
+        .as_array()
+        .ok_or_else(|| FilterValidationError::new("each filter must be an array"))?;
+
+    match items.as_slice() {
+        [Value::String(op), Value::Array(children)] if op == "And" || op == "Or" => {
+            if children.is_empty() {
+                return Err(FilterValidationError::new(format!("{} needs at least one filter", op)));
+            }
+            children
+                .iter()
+                .try_for_each(|child| validate_filter(child, depth + 1, clauses))
+        }
+        [Value::String(op), child] if op == "Not" => validate_filter(child, depth + 1, clauses),
+        [Value::String(attr), Value::String(op), operand] => {
+            *clauses += 1;
+            if *clauses > MAX_FILTER_CLAUSES {
+                return Err(FilterValidationError::new(format!(
+                    "at most {} filter conditions are allowed",
+                    MAX_FILTER_CLAUSES
+                )));
+            }
+            if attr.is_empty() {
+                return Err(FilterValidationError::new("attribute name can't be empty"));
+            }
+            if !FILTER_OPERATORS.contains(&op.as_str()) {
+                return Err(FilterValidationError::new(format!("unsupported operator {}", op)));
+            }
+            let scalar = |v: &Value| !v.is_array() && !v.is_object();
+            let valid_operand = match operand {
+                Value::Array(values) => values.iter().all(scalar),
+                other => scalar(other),
+            };
+            if !valid_operand {
+                return Err(FilterValidationError::new(
+                    "filter values must be scalars or arrays of scalars",
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(FilterValidationError::new(
+            "expected [attribute, operator, value], [\"And\"|\"Or\", [filters]] or [\"Not\", filter]",
+        )),
+    }
+}
+
+/// attribute holding when a bufo was added, in unix seconds
+pub const ADDED_AT_ATTRIBUTE: &str = "added_at";
+
+/// bufos added strictly after `unix_secs`
+pub fn added_after(unix_secs: i64) -> Value {
+    serde_json::json!([ADDED_AT_ATTRIBUTE, "Gt", unix_secs])
+}
+
+/// the conjunction of `filters`: `None` when empty, the filter itself when alone
+pub fn all_of(filters: impl IntoIterator<Item = Value>) -> Option<Value> {
+    let mut filters: Vec<Value> = filters.into_iter().collect();
+    match filters.len() {
+        0 => None,
+        1 => filters.pop(),
+        _ => Some(serde_json::json!(["And", filters])),
+    }
+}
+
+/// add per-request options to a query body
+fn apply_options(mut request: Value, options: &QueryOptions) -> Value {
+    if let Some(filters) = &options.filters {
+        request["filters"] = filters.clone();
+    }
+    request
+}
+
+/// read consistency level for queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consistency {
+    Strong,
+    Eventual,
+}
+
+impl Consistency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Consistency::Strong => "strong",
+            Consistency::Eventual => "eventual",
+        }
+    }
+}
+
+impl std::str::FromStr for Consistency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "strong" => Ok(Consistency::Strong),
+            "eventual" => Ok(Consistency::Eventual),
+            other => Err(format!("expected strong or eventual, got {:?}", other)),
+        }
+    }
+}
+
+/// add the consistency level to a query body, leaving turbopuffer's default when unset
+fn apply_consistency(mut request: Value, consistency: Option<Consistency>) -> Value {
+    if let Some(level) = consistency {
+        request["consistency"] = serde_json::json!({ "level": level.as_str() });
+    }
+    request
+}
+
+/// drop vector rows farther than `max_distance` (no-op when unset)
+fn within_max_distance(rows: Vec<QueryRow>, max_distance: Option<f32>) -> Vec<QueryRow> {
+    match max_distance {
+        Some(max) => rows.into_iter().filter(|row| row.dist <= max).collect(),
+        None => rows,
+    }
+}
+
+/// how hyphens in the keyword query are sent to turbopuffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HyphenMode {
+    /// replace hyphens with spaces ("bufo-happy" -> "bufo happy")
+    Split,
+    /// send the query as typed
+    #[default]
+    Keep,
+}
+
+impl HyphenMode {
+    /// rewrite `query` to match how names were indexed
+    pub fn normalize(&self, query: &str) -> String {
+        match self {
+            HyphenMode::Split => query
+                .split(|c: char| c == '-' || c.is_whitespace())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            HyphenMode::Keep => query.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for HyphenMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "split" => Ok(HyphenMode::Split),
+            "keep" => Ok(HyphenMode::Keep),
+            other => Err(format!("expected split or keep, got {:?}", other)),
+        }
+    }
+}
+
+/// query-time BM25 options
+#[derive(Debug, Clone, Default)]
+pub struct Bm25Options {
+    /// treat the last query token as a prefix (e.g. "jump" matches "jumping")
+    pub last_as_prefix: bool,
+    /// hyphen handling applied to the query before it's sent
+    pub hyphens: HyphenMode,
+}
+
+/// build the BM25 `rank_by` clause for a field
+fn bm25_rank_by(field: &str, query: &str, options: &Bm25Options) -> serde_json::Value {
+    let query = options.hyphens.normalize(query);
+    if options.last_as_prefix {
+        serde_json::json!([field, "BM25", query, { "last_as_prefix": true }])
+    } else {
+        serde_json::json!([field, "BM25", query])
+    }
+}
+
 /// turbopuffer vector database client
 ///
 /// supports both ANN vector search and BM25 full-text search.
@@ -47,6 +340,11 @@ pub struct TurbopufferStore {
     client: Client,
     api_key: String,
     namespace: String,
+    bm25: Bm25Options,
+    consistency: Option<Consistency>,
+    max_distance: Option<f32>,
+    timeout: Option<Duration>,
+    base_url: String,
 }
 
 impl TurbopufferStore {
@@ -55,21 +353,65 @@ impl TurbopufferStore {
             client: Client::new(),
             api_key,
             namespace,
+            bm25: Bm25Options::default(),
+            consistency: None,
+            max_distance: None,
+            timeout: None,
+            base_url: TURBOPUFFER_API_BASE.to_string(),
         }
     }
 
+    /// give up on a request (including reading the response) after `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// a request to `url` with the api key and configured timeout
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, url)
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        match self.timeout {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        }
+    }
+
+    pub fn with_bm25_options(mut self, options: Bm25Options) -> Self {
+        self.bm25 = options;
+        self
+    }
+
+    pub fn with_consistency(mut self, consistency: Option<Consistency>) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    /// similarity floor for vector queries, as a cosine distance
+    pub fn with_max_distance(mut self, max_distance: Option<f32>) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
     fn query_url(&self) -> String {
-        format!("{}/{}/query", TURBOPUFFER_API_BASE, self.namespace)
+        format!("{}/{}/query", self.base_url, self.namespace)
     }
 
     async fn execute_query(
         &self,
         request: serde_json::Value,
     ) -> Result<Vec<QueryRow>, VectorSearchError> {
+        let request = apply_consistency(request, self.consistency);
         let response = self
-            .client
-            .post(self.query_url())
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .request(reqwest::Method::POST, self.query_url())
             .json(&request)
             .send()
             .await?;
@@ -85,6 +427,11 @@ impl TurbopufferStore {
                         message: error_resp.error,
                     });
                 }
+                if error_resp.error.contains("dimension") {
+                    return Err(VectorSearchError::DimensionMismatch {
+                        message: error_resp.error,
+                    });
+                }
             }
 
             return Err(VectorSearchError::Api { status, body });
@@ -104,19 +451,23 @@ impl VectorStore for TurbopufferStore {
         &self,
         embedding: &[f32],
         top_k: usize,
+        options: &QueryOptions,
     ) -> Result<Vec<SearchResult>, VectorSearchError> {
-        let request = serde_json::json!({
-            "rank_by": ["vector", "ANN", embedding],
-            "top_k": top_k,
-            "include_attributes": ["url", "name", "filename"],
-        });
+        let request = apply_options(
+            serde_json::json!({
+                "rank_by": ["vector", "ANN", embedding],
+                "top_k": top_k,
+                "include_attributes": INCLUDE_ATTRIBUTES,
+            }),
+            options,
+        );
 
         log::debug!(
             "turbopuffer vector query: {}",
             serde_json::to_string_pretty(&request).unwrap_or_default()
         );
 
-        let rows = self.execute_query(request).await?;
+        let rows = within_max_distance(self.execute_query(request).await?, self.max_distance);
         Ok(rows.into_iter().map(SearchResult::from).collect())
     }
 
@@ -124,12 +475,26 @@ impl VectorStore for TurbopufferStore {
         &self,
         query: &str,
         top_k: usize,
+        options: &QueryOptions,
     ) -> Result<Vec<SearchResult>, VectorSearchError> {
-        let request = serde_json::json!({
-            "rank_by": ["name", "BM25", query],
-            "top_k": top_k,
-            "include_attributes": ["url", "name", "filename"],
-        });
+        self.search_by_keyword_field("name", query, top_k, options).await
+    }
+
+    async fn search_by_keyword_field(
+        &self,
+        field: &str,
+        query: &str,
+        top_k: usize,
+        options: &QueryOptions,
+    ) -> Result<Vec<SearchResult>, VectorSearchError> {
+        let request = apply_options(
+            serde_json::json!({
+                "rank_by": bm25_rank_by(field, query, &self.bm25),
+                "top_k": top_k,
+                "include_attributes": INCLUDE_ATTRIBUTES,
+            }),
+            options,
+        );
 
         log::debug!(
             "turbopuffer BM25 query: {}",
@@ -140,7 +505,8 @@ impl VectorStore for TurbopufferStore {
 
         if let Some(first) = rows.first() {
             log::info!(
-                "BM25 first result - id: {}, dist: {}, name: {:?}",
+                "BM25 first result ({}) - id: {}, dist: {}, name: {:?}",
+                field,
                 first.id,
                 first.dist,
                 first.attributes.get("name")
@@ -150,8 +516,254 @@ impl VectorStore for TurbopufferStore {
         Ok(rows.into_iter().map(SearchResult::from).collect())
     }
 
+    async fn get_by_id(&self, id: &str) -> Result<Option<SearchResult>, VectorSearchError> {
+        let request = serde_json::json!({
+            "filters": ["id", "Eq", id],
+            "top_k": 1,
+            "include_attributes": INCLUDE_ATTRIBUTES,
+        });
+
+        let rows = self.execute_query(request).await?;
+        Ok(rows.into_iter().next().map(SearchResult::from))
+    }
+
+    async fn list_all(&self, limit: usize) -> Result<Vec<SearchResult>, VectorSearchError> {
+        // no rank_by: turbopuffer returns rows in id order
+        let request = serde_json::json!({
+            "top_k": limit,
+            "include_attributes": INCLUDE_ATTRIBUTES,
+        });
+
+        let rows = self.execute_query(request).await?;
+        Ok(rows.into_iter().map(SearchResult::from).collect())
+    }
+
+    /// approximate number of rows in the namespace, from the namespace's HEAD headers
+    ///
+    /// `None` when turbopuffer doesn't report a count.
+    async fn approx_row_count(&self) -> Result<Option<u64>, VectorSearchError> {
+        let url = format!("{}/{}", self.base_url, self.namespace);
+        let response = self.request(reqwest::Method::HEAD, url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(VectorSearchError::Api {
+                status: response.status().as_u16(),
+                body: String::new(),
+            });
+        }
+
+        Ok(response
+            .headers()
+            .get(APPROX_COUNT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok()))
+    }
+
     fn name(&self) -> &'static str {
         "turbopuffer"
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_row_keeps_numeric_attributes() {
+        let row: QueryRow = serde_json::from_value(serde_json::json!({
+            "id": "a",
+            "dist": 0.5,
+            "attributes": { "name": "bufo-happy", "popularity": 42, "missing": null },
+        }))
+        .unwrap();
+
+        let result = SearchResult::from(row);
+        assert_eq!(result.attributes["name"], "bufo-happy");
+        assert_eq!(result.attributes["popularity"], "42");
+        assert!(!result.attributes.contains_key("missing"));
+    }
+
+    #[test]
+    fn test_bm25_rank_by_exact() {
+        let rank_by = bm25_rank_by("name", "jump", &Bm25Options::default());
+        assert_eq!(rank_by, serde_json::json!(["name", "BM25", "jump"]));
+    }
+
+    #[test]
+    fn test_bm25_rank_by_prefix() {
+        let options = Bm25Options {
+            last_as_prefix: true,
+            ..Default::default()
+        };
+        let rank_by = bm25_rank_by("name", "jump", &options);
+        assert_eq!(
+            rank_by,
+            serde_json::json!(["name", "BM25", "jump", { "last_as_prefix": true }])
+        );
+    }
+
+    #[test]
+    fn test_bm25_rank_by_hyphen_modes() {
+        let options = |hyphens| Bm25Options {
+            hyphens,
+            ..Default::default()
+        };
+
+        let rank_by = bm25_rank_by("name", "bufo-happy  dance", &options(HyphenMode::Keep));
+        assert_eq!(rank_by, serde_json::json!(["name", "BM25", "bufo-happy  dance"]));
+
+        let rank_by = bm25_rank_by("name", "bufo-happy  dance", &options(HyphenMode::Split));
+        assert_eq!(rank_by, serde_json::json!(["name", "BM25", "bufo happy dance"]));
+
+        let rank_by = bm25_rank_by("name", "-bufo--", &options(HyphenMode::Split));
+        assert_eq!(rank_by, serde_json::json!(["name", "BM25", "bufo"]));
+    }
+
+    #[test]
+    fn test_hyphen_mode_parsing() {
+        assert_eq!("split".parse::<HyphenMode>().unwrap(), HyphenMode::Split);
+        assert_eq!(" KEEP ".parse::<HyphenMode>().unwrap(), HyphenMode::Keep);
+        assert!("squash".parse::<HyphenMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_filters_equality() {
+        let filters = serde_json::json!(["tags", "Eq", "animal"]);
+        assert_eq!(parse_filters(&filters).unwrap(), filters);
+    }
+
+    #[test]
+    fn test_parse_filters_from_query_string() {
+        let raw =
+            Value::String(r#"["And", [["tags", "Eq", "animal"], ["width", "Gt", 200]]]"#.into());
+        let parsed = parse_filters(&raw).unwrap();
+        assert_eq!(parsed[0], "And");
+        assert_eq!(parsed[1][1], serde_json::json!(["width", "Gt", 200]));
+    }
+
+    #[test]
+    fn test_parse_filters_rejects_malformed() {
+        let cases = [
+            serde_json::json!({"tags": "animal"}),
+            serde_json::json!(["tags", "DropTable", "animal"]),
+            serde_json::json!(["tags", "Eq"]),
+            serde_json::json!(["", "Eq", "animal"]),
+            serde_json::json!(["tags", "Eq", {"nested": true}]),
+            serde_json::json!(["And", []]),
+            Value::String("not json".into()),
+        ];
+        for case in cases {
+            assert!(parse_filters(&case).is_err(), "accepted {}", case);
+        }
+    }
+
+    #[test]
+    fn test_added_after_combines_with_user_filters() {
+        let since = added_after(1_714_564_800);
+        assert_eq!(since, serde_json::json!(["added_at", "Gt", 1_714_564_800]));
+        assert!(since[2].is_i64());
+
+        let user = serde_json::json!(["tags", "Eq", "animal"]);
+        assert_eq!(all_of([]), None);
+        assert_eq!(all_of([since.clone()]), Some(since.clone()));
+        let combined = all_of([user.clone(), since.clone()]).unwrap();
+        assert_eq!(combined, serde_json::json!(["And", [user, since]]));
+        // what turbopuffer receives is still valid filter syntax
+        assert!(parse_filters(&combined).is_ok());
+    }
+
+    #[test]
+    fn test_apply_options_adds_filters() {
+        let options = QueryOptions {
+            filters: Some(serde_json::json!(["tags", "Eq", "animal"])),
+        };
+        let request = apply_options(serde_json::json!({ "top_k": 5 }), &options);
+        assert_eq!(request["filters"], serde_json::json!(["tags", "Eq", "animal"]));
+
+        let request = apply_options(serde_json::json!({ "top_k": 5 }), &QueryOptions::default());
+        assert!(request.get("filters").is_none());
+    }
+
+    #[test]
+    fn test_consistency_parsing() {
+        assert_eq!("strong".parse::<Consistency>().unwrap(), Consistency::Strong);
+        assert_eq!(" Eventual ".parse::<Consistency>().unwrap(), Consistency::Eventual);
+        assert!("linearizable".parse::<Consistency>().is_err());
+    }
+
+    #[test]
+    fn test_apply_consistency() {
+        let request = apply_consistency(serde_json::json!({ "top_k": 5 }), Some(Consistency::Strong));
+        assert_eq!(request["consistency"], serde_json::json!({ "level": "strong" }));
+
+        let request = apply_consistency(serde_json::json!({ "top_k": 5 }), None);
+        assert!(request.get("consistency").is_none());
+    }
+
+    #[test]
+    fn test_within_max_distance() {
+        let row = |id: &str, dist: f32| QueryRow {
+            id: id.to_string(),
+            dist,
+            attributes: serde_json::Map::new(),
+        };
+        let rows = vec![row("near", 0.2), row("edge", 0.8), row("far", 1.3)];
+
+        let kept: Vec<String> = within_max_distance(rows.clone(), Some(0.8))
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(kept, vec!["near", "edge"]);
+        assert_eq!(within_max_distance(rows, None).len(), 3);
+    }
+
+    /// a local server that answers every request with `body` after `delay`
+    async fn slow_server(delay: Duration, body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.read(&mut [0u8; 4096]).await;
+                    tokio::time::sleep(delay).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_queries_honor_the_timeout() {
+        let url = slow_server(
+            Duration::from_millis(300),
+            r#"[{"id":"a","dist":0.1,"attributes":{}}]"#,
+        )
+        .await;
+        let store = |timeout| {
+            TurbopufferStore::new("key".to_string(), "bufos".to_string())
+                .with_base_url(url.clone())
+                .with_timeout(Some(timeout))
+        };
+        let search = |store: TurbopufferStore| async move {
+            store
+                .search_by_vector(&[0.5], 1, &QueryOptions::default())
+                .await
+        };
+
+        match search(store(Duration::from_millis(50))).await {
+            Err(VectorSearchError::Request(e)) => assert!(e.is_timeout(), "{}", e),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        let results = search(store(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(results[0].id, "a");
+    }
+}
@@ -0,0 +1,111 @@
+//! search response envelope versions
+//!
+//! clients pin a response shape with `api_version=v1|v2` or an
+//! `Accept: application/vnd.bufo.v1+json` (or `v2`) header; the param wins when both are
+//! given, and v1 is the default. v1 is the flat `SearchResponse` object, unchanged as
+//! fields are added. v2 nests everything but `results` under `meta`, which is where
+//! richer additions (facets, score breakdowns) are meant to go.
+
+use crate::negotiate::accepts;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// a search response layout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// the flat `SearchResponse` object
+    #[default]
+    #[serde(alias = "1")]
+    V1,
+    /// `{"results": [...], "meta": {...}}`
+    #[serde(alias = "2")]
+    V2,
+}
+
+impl ApiVersion {
+    const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    fn media_type(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "application/vnd.bufo.v1+json",
+            ApiVersion::V2 => "application/vnd.bufo.v2+json",
+        }
+    }
+
+    /// the newest version `accept` lists, or `None` when it names none
+    pub fn negotiate(accept: Option<&str>) -> Option<Self> {
+        let accept = accept?;
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|version| accepts(accept, version.media_type()))
+    }
+}
+
+/// the v2 body for a flat response: its `results`, and every other field under `meta`
+///
+/// v1 is the response serialized as-is, so it has no counterpart here.
+pub fn v2_envelope<T: Serialize>(response: &T) -> Result<serde_json::Value, serde_json::Error> {
+    // through text rather than `to_value`, which widens f32 scores (0.9 -> 0.8999999761581421)
+    let mut meta: serde_json::Value = serde_json::from_str(&serde_json::to_string(response)?)?;
+    let results = meta
+        .as_object_mut()
+        .and_then(|fields| fields.remove("results"))
+        .unwrap_or_else(|| serde_json::Value::Array(Vec::new()));
+    Ok(serde_json::json!({ "results": results, "meta": meta }))
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate_from_accept() {
+        assert_eq!(ApiVersion::negotiate(None), None);
+        assert_eq!(ApiVersion::negotiate(Some("application/json")), None);
+        assert_eq!(
+            ApiVersion::negotiate(Some("application/vnd.bufo.v1+json")),
+            Some(ApiVersion::V1)
+        );
+        assert_eq!(
+            ApiVersion::negotiate(Some(
+                "application/vnd.bufo.v1+json, application/vnd.bufo.v2+json"
+            )),
+            Some(ApiVersion::V2)
+        );
+        assert_eq!(
+            ApiVersion::negotiate(Some("application/vnd.bufo.v2+json;q=0, application/json")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_param_spellings() {
+        let parse = |v: &str| serde_json::from_value::<ApiVersion>(json!(v)).ok();
+        assert_eq!(parse("v1"), Some(ApiVersion::V1));
+        assert_eq!(parse("2"), Some(ApiVersion::V2));
+        assert_eq!(parse("v3"), None);
+        assert_eq!(ApiVersion::V2.to_string(), "v2");
+    }
+
+    #[test]
+    fn test_v2_nests_everything_but_results() {
+        let flat = json!({"results": [{"id": "a"}], "suggestion": "happy", "relaxed": true});
+
+        assert_eq!(
+            v2_envelope(&flat).unwrap(),
+            json!({"results": [{"id": "a"}], "meta": {"suggestion": "happy", "relaxed": true}})
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! "did you mean" spelling suggestions from the bufo name vocabulary
+//!
+//! the vocabulary is every token that appears in a bufo name, loaded from the vector
+//! store at startup and refreshed periodically. query tokens missing from it are
+//! matched to the closest known token by edit distance. suggestions are advisory only;
+//! the search still runs on the original query.
+
+use crate::providers::{VectorSearchError, VectorStore};
+use crate::tokenize::tokenize;
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// upper bound on names fetched per refresh (well above the number of bufos)
+const LIST_LIMIT: usize = 10_000;
+
+/// tokens shorter than this are never corrected (too ambiguous)
+const MIN_CORRECTABLE_LEN: usize = 3;
+
+/// levenshtein edit distance between two strings (by chars)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// set of known tokens from bufo names
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    tokens: BTreeSet<String>,
+    /// full names, for prefix completion
+    names: BTreeSet<String>,
+}
+
+impl Vocabulary {
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names: BTreeSet<String> = names.into_iter().map(str::to_string).collect();
+        Self {
+            tokens: names.iter().flat_map(|name| tokenize(name)).collect(),
+            names,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// names whose terms start with `prefix`'s terms, with or without the `bufo` prefix
+    ///
+    /// "jumping on", "bufo-jump" and "Bufo Jumping" all match "bufo-jumping-on-the-bed".
+    pub fn names_with_prefix(&self, prefix: &str) -> Vec<&str> {
+        let prefix = tokenize(prefix).join(" ");
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        self.names
+            .iter()
+            .filter(|name| {
+                let phrase = tokenize(name).join(" ");
+                phrase.starts_with(&prefix)
+                    || phrase
+                        .strip_prefix("bufo ")
+                        .is_some_and(|rest| rest.starts_with(&prefix))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// closest known token within the allowed edit distance
+    ///
+    /// ties go to the alphabetically first token so suggestions are deterministic.
+    fn closest(&self, token: &str) -> Option<&str> {
+        // allow one edit for short words, two for longer ones
+        let max_distance = if token.chars().count() <= 4 { 1 } else { 2 };
+
+        self.tokens
+            .iter()
+            .map(|known| (edit_distance(token, known), known))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known.as_str())
+    }
+
+    /// suggest a corrected query, or `None` when every token is known or uncorrectable
+    pub fn suggest(&self, query: &str) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut changed = false;
+        let corrected: Vec<String> = tokenize(query)
+            .into_iter()
+            .map(|token| {
+                if token.chars().count() < MIN_CORRECTABLE_LEN || self.tokens.contains(&token) {
+                    return token;
+                }
+                match self.closest(&token) {
+                    Some(known) => {
+                        changed = true;
+                        known.to_string()
+                    }
+                    None => token,
+                }
+            })
+            .collect();
+
+        changed.then(|| corrected.join(" "))
+    }
+}
+
+/// shared, periodically refreshed vocabulary
+#[derive(Clone, Default)]
+pub struct VocabularyCache {
+    inner: Arc<RwLock<Arc<Vocabulary>>>,
+}
+
+impl VocabularyCache {
+    /// current vocabulary snapshot (empty until the first refresh succeeds)
+    pub fn current(&self) -> Arc<Vocabulary> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// rebuild the vocabulary from every name in the store, returning its size
+    pub async fn refresh<V: VectorStore>(&self, store: &V) -> Result<usize, VectorSearchError> {
+        let rows = store.list_all(LIST_LIMIT).await?;
+        let vocabulary = Vocabulary::from_names(
+            rows.iter()
+                .filter_map(|row| row.attributes.get("name").map(String::as_str)),
+        );
+        let size = vocabulary.len();
+
+        *self.inner.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(vocabulary);
+        Ok(size)
+    }
+}
+
+/// refresh the vocabulary now and then every `interval`, keeping the old one on failure
+pub async fn refresh_periodically<V: VectorStore>(
+    cache: VocabularyCache,
+    store: V,
+    interval: Duration,
+) {
+    loop {
+        match cache.refresh(&store).await {
+            Ok(size) => {
+                logfire::info!("vocabulary refreshed", tokens = size as i64);
+            }
+            Err(e) => {
+                let error = e.to_string();
+                logfire::warn!("vocabulary refresh failed", error = &error);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary() -> Vocabulary {
+        Vocabulary::from_names([
+            "bufo-jumping-on-the-bed",
+            "bufo-is-happy",
+            "bufo-party",
+            "bufo-apocalypse",
+        ])
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("happy", "happy"), 0);
+        assert_eq!(edit_distance("hapy", "happy"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_vocabulary_tokenizes_names() {
+        let vocab = vocabulary();
+        assert!(vocab.tokens.contains("jumping"));
+        assert!(vocab.tokens.contains("bufo"));
+        assert!(!vocab.tokens.contains("bufo-party"));
+    }
+
+    #[test]
+    fn test_names_with_prefix() {
+        let vocab = vocabulary();
+        assert_eq!(
+            vocab.names_with_prefix("Jumping On"),
+            vec!["bufo-jumping-on-the-bed"]
+        );
+        assert_eq!(vocab.names_with_prefix("bufo-pa"), vec!["bufo-party"]);
+        assert_eq!(
+            vocab.names_with_prefix("bufo"),
+            vec![
+                "bufo-apocalypse",
+                "bufo-is-happy",
+                "bufo-jumping-on-the-bed",
+                "bufo-party"
+            ]
+        );
+        assert!(vocab.names_with_prefix("happy").is_empty());
+        assert!(vocab.names_with_prefix(" - ").is_empty());
+    }
+
+    #[test]
+    fn test_suggest_corrects_misspelling() {
+        let vocab = vocabulary();
+        assert_eq!(vocab.suggest("hapy bufo"), Some("happy bufo".to_string()));
+        assert_eq!(vocab.suggest("jumpin"), Some("jumping".to_string()));
+    }
+
+    #[test]
+    fn test_suggest_none_when_all_known() {
+        let vocab = vocabulary();
+        assert_eq!(vocab.suggest("happy bufo"), None);
+        assert_eq!(vocab.suggest("Bufo-Party"), None);
+    }
+
+    #[test]
+    fn test_suggest_none_when_too_far() {
+        let vocab = vocabulary();
+        assert_eq!(vocab.suggest("xylophone"), None);
+    }
+
+    #[test]
+    fn test_suggest_none_with_empty_vocabulary() {
+        assert_eq!(Vocabulary::default().suggest("hapy"), None);
+    }
+}